@@ -0,0 +1,475 @@
+use actix_web::{HttpRequest, HttpResponse, delete, get, patch, post, put, web};
+use chrono::Utc;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, EntityTrait, IntoActiveModel, PaginatorTrait, QueryFilter,
+    QueryOrder, Set,
+};
+use uuid::Uuid;
+
+use crate::{
+    api::{
+        helpers::uuid_to_bytes,
+        permissions::PlatformAdmin,
+        validation::{self, Validator},
+        webhooks::functions::{build_webhook_uri, default_webhook_subscriptions},
+    },
+    app_state::AppState,
+    database::models::{channels, clients, companies, company_users, outgoing_webhooks},
+    errors::AppError,
+    services::wazzup_api::WebhookSubscriptionRequest,
+};
+
+use super::structures::{
+    ApiKeyStatus, CompanyList, CompanyStatus, CompanyView, CompanyWithWebhooks,
+    CreateCompanyRequest, RotateApiKeyRequest, TaskList, UpdateCompanyRequest, WebhookConnection,
+};
+
+const MAX_NAME_CHARS: usize = 255;
+const MAX_TIER_CHARS: usize = 64;
+
+/// Список всех компаний
+#[utoipa::path(
+    get,
+    path = "/api/admin/companies",
+    tag = "Admin",
+    responses(
+        (status = 200, description = "All companies", body = CompanyList),
+        (status = 403, description = "Platform administrator required"),
+    )
+)]
+#[get("")]
+pub async fn list_companies(
+    app_state: web::Data<AppState>,
+    _admin: PlatformAdmin,
+) -> Result<HttpResponse, AppError> {
+    let records = companies::Entity::find()
+        .order_by_asc(companies::Column::Name)
+        .all(&app_state.db)
+        .await?;
+
+    let data = records.iter().map(company_view).collect();
+
+    Ok(HttpResponse::Ok().json(CompanyList { data }))
+}
+
+/// Создаёт компанию; API ключ Wazzup проверяется до сохранения
+#[utoipa::path(
+    post,
+    path = "/api/admin/companies",
+    tag = "Admin",
+    request_body = CreateCompanyRequest,
+    responses(
+        (status = 201, description = "Company created", body = CompanyWithWebhooks),
+        (status = 400, description = "Invalid input or API key rejected by Wazzup"),
+        (status = 403, description = "Platform administrator required"),
+    )
+)]
+#[post("")]
+pub async fn create_company(
+    app_state: web::Data<AppState>,
+    _admin: PlatformAdmin,
+    req: HttpRequest,
+    body: validation::Json<CreateCompanyRequest>,
+) -> Result<HttpResponse, AppError> {
+    let request = body.into_inner();
+    let mut validator = Validator::new();
+    let name = validator.required_text("name", &request.name, MAX_NAME_CHARS);
+    let email = validator.email("email", request.email);
+    let phone = validator.phone("phone", request.phone);
+    let subscription_tier = validator.optional_text(
+        "subscriptionTier",
+        request.subscription_tier,
+        MAX_TIER_CHARS,
+    );
+    validator.finish()?;
+    let api_key = match normalize(request.wazzup_api_key) {
+        Some(key) => Some(verify_api_key(&app_state, &key).await.map(|_| key)?),
+        None => None,
+    };
+
+    let company_uuid = Uuid::new_v4();
+    let sealed_key = api_key
+        .as_deref()
+        .map(|key| app_state.api_keys.seal(company_uuid.as_bytes(), key))
+        .transpose()?;
+    let now = Utc::now();
+    let company = companies::ActiveModel {
+        id: Set(uuid_to_bytes(&company_uuid)),
+        name: Set(Some(name)),
+        description: Set(normalize(request.description)),
+        email: Set(email),
+        phone: Set(phone),
+        wazzup_api_key: Set(sealed_key),
+        is_active: Set(Some(bool_flag(request.is_active.unwrap_or(true)))),
+        subscription_tier: Set(subscription_tier),
+        created_at: Set(Some(now)),
+        updated_at: Set(Some(now)),
+        last_webhook_at: Set(None),
+    }
+    .insert(&app_state.db)
+    .await?;
+
+    let webhooks = match &api_key {
+        Some(key) => Some(connect_webhooks(&app_state, &req, &company_uuid, key).await),
+        None => None,
+    };
+
+    Ok(HttpResponse::Created().json(CompanyWithWebhooks {
+        company: company_view(&company),
+        webhooks,
+    }))
+}
+
+/// Данные компании
+#[utoipa::path(
+    get,
+    path = "/api/admin/companies/{companyId}",
+    tag = "Admin",
+    params(("companyId" = String, Path, description = "Company UUID")),
+    responses(
+        (status = 200, description = "Company", body = CompanyView),
+        (status = 403, description = "Platform administrator required"),
+        (status = 404, description = "Company not found"),
+    )
+)]
+#[get("/{companyId}")]
+pub async fn get_company(
+    app_state: web::Data<AppState>,
+    _admin: PlatformAdmin,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let company = load_company(&app_state, &path.into_inner()).await?;
+
+    Ok(HttpResponse::Ok().json(company_view(&company)))
+}
+
+/// Обновляет реквизиты, активность или тариф компании
+#[utoipa::path(
+    patch,
+    path = "/api/admin/companies/{companyId}",
+    tag = "Admin",
+    params(("companyId" = String, Path, description = "Company UUID")),
+    request_body = UpdateCompanyRequest,
+    responses(
+        (status = 200, description = "Company updated", body = CompanyView),
+        (status = 400, description = "Invalid input"),
+        (status = 403, description = "Platform administrator required"),
+        (status = 404, description = "Company not found"),
+    )
+)]
+#[patch("/{companyId}")]
+pub async fn update_company(
+    app_state: web::Data<AppState>,
+    _admin: PlatformAdmin,
+    path: web::Path<String>,
+    body: validation::Json<UpdateCompanyRequest>,
+) -> Result<HttpResponse, AppError> {
+    let company = load_company(&app_state, &path.into_inner()).await?;
+    let request = body.into_inner();
+    let mut active = company.into_active_model();
+    let mut validator = Validator::new();
+
+    if let Some(name) = request.name {
+        active.name = Set(Some(validator.required_text("name", &name, MAX_NAME_CHARS)));
+    }
+    if request.description.is_some() {
+        active.description = Set(normalize(request.description));
+    }
+    if request.email.is_some() {
+        active.email = Set(validator.email("email", request.email));
+    }
+    if request.phone.is_some() {
+        active.phone = Set(validator.phone("phone", request.phone));
+    }
+    if request.subscription_tier.is_some() {
+        active.subscription_tier = Set(validator.optional_text(
+            "subscriptionTier",
+            request.subscription_tier,
+            MAX_TIER_CHARS,
+        ));
+    }
+    validator.finish()?;
+    if let Some(is_active) = request.is_active {
+        active.is_active = Set(Some(bool_flag(is_active)));
+    }
+    active.updated_at = Set(Some(Utc::now()));
+
+    let company = active.update(&app_state.db).await?;
+
+    Ok(HttpResponse::Ok().json(company_view(&company)))
+}
+
+/// Удаляет компанию без каналов, клиентов, сотрудников и вебхуков
+#[utoipa::path(
+    delete,
+    path = "/api/admin/companies/{companyId}",
+    tag = "Admin",
+    params(("companyId" = String, Path, description = "Company UUID")),
+    responses(
+        (status = 204, description = "Company deleted"),
+        (status = 400, description = "Company still has data; deactivate it instead"),
+        (status = 403, description = "Platform administrator required"),
+        (status = 404, description = "Company not found"),
+    )
+)]
+#[delete("/{companyId}")]
+pub async fn delete_company(
+    app_state: web::Data<AppState>,
+    admin: PlatformAdmin,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let company = load_company(&app_state, &path.into_inner()).await?;
+    let db = &app_state.db;
+
+    let dependents = channels::Entity::find()
+        .filter(channels::Column::CompanyId.eq(company.id.clone()))
+        .count(db)
+        .await?
+        + clients::Entity::find()
+            .filter(clients::Column::CompanyId.eq(company.id.clone()))
+            .count(db)
+            .await?
+        + company_users::Entity::find()
+            .filter(company_users::Column::CompanyId.eq(company.id.clone()))
+            .count(db)
+            .await?
+        + outgoing_webhooks::Entity::find()
+            .filter(outgoing_webhooks::Column::CompanyId.eq(company.id.clone()))
+            .count(db)
+            .await?;
+    if dependents > 0 {
+        return Err(AppError::InvalidInput(
+            "Company still has channels, clients, members or webhooks; deactivate it instead"
+                .to_string(),
+        ));
+    }
+
+    companies::Entity::delete_by_id(company.id.clone())
+        .exec(db)
+        .await?;
+    log::info!(
+        "Company {} deleted by user {}",
+        uuid_string(&company.id),
+        uuid_string(&admin.principal.user_id)
+    );
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Меняет API ключ Wazzup и переподключает вебхуки
+#[utoipa::path(
+    put,
+    path = "/api/admin/companies/{companyId}/api-key",
+    tag = "Admin",
+    params(("companyId" = String, Path, description = "Company UUID")),
+    request_body = RotateApiKeyRequest,
+    responses(
+        (status = 200, description = "Key replaced; `webhooks` reports the reconnection", body = CompanyWithWebhooks),
+        (status = 400, description = "API key rejected by Wazzup"),
+        (status = 403, description = "Platform administrator required"),
+        (status = 404, description = "Company not found"),
+    )
+)]
+#[put("/{companyId}/api-key")]
+pub async fn rotate_api_key(
+    app_state: web::Data<AppState>,
+    admin: PlatformAdmin,
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: validation::Json<RotateApiKeyRequest>,
+) -> Result<HttpResponse, AppError> {
+    let company = load_company(&app_state, &path.into_inner()).await?;
+    let api_key = normalize(Some(body.into_inner().wazzup_api_key))
+        .ok_or_else(|| AppError::InvalidInput("wazzupApiKey must not be empty".to_string()))?;
+    verify_api_key(&app_state, &api_key).await?;
+
+    let company_uuid = Uuid::from_slice(&company.id).map_err(|_| AppError::Internal)?;
+    let sealed_key = app_state.api_keys.seal(&company.id, &api_key)?;
+    let mut active = company.into_active_model();
+    active.wazzup_api_key = Set(Some(sealed_key));
+    active.updated_at = Set(Some(Utc::now()));
+    let company = active.update(&app_state.db).await?;
+    log::info!(
+        "Wazzup API key of company {} rotated by user {}",
+        company_uuid,
+        uuid_string(&admin.principal.user_id)
+    );
+
+    let webhooks = connect_webhooks(&app_state, &req, &company_uuid, &api_key).await;
+
+    Ok(HttpResponse::Ok().json(CompanyWithWebhooks {
+        company: company_view(&company),
+        webhooks: Some(webhooks),
+    }))
+}
+
+/// Сводка по компании: каналы, последний вебхук, валидность ключа
+#[utoipa::path(
+    get,
+    path = "/api/admin/companies/{companyId}/status",
+    tag = "Admin",
+    params(("companyId" = String, Path, description = "Company UUID")),
+    responses(
+        (status = 200, description = "Company status", body = CompanyStatus),
+        (status = 403, description = "Platform administrator required"),
+        (status = 404, description = "Company not found"),
+    )
+)]
+#[get("/{companyId}/status")]
+pub async fn get_company_status(
+    app_state: web::Data<AppState>,
+    _admin: PlatformAdmin,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let company = load_company(&app_state, &path.into_inner()).await?;
+
+    let channel_count = channels::Entity::find()
+        .filter(channels::Column::CompanyId.eq(company.id.clone()))
+        .count(&app_state.db)
+        .await?;
+
+    let api_key = match app_state.api_keys.api_key_of(&company)? {
+        None => ApiKeyStatus::Missing,
+        Some(key) => match app_state.wazzup_api.check_api_key(&key).await {
+            Ok(true) => ApiKeyStatus::Valid,
+            Ok(false) => ApiKeyStatus::Invalid,
+            Err(err) => {
+                log::warn!("Unable to check the Wazzup API key: {}", err);
+                ApiKeyStatus::Unknown
+            }
+        },
+    };
+
+    Ok(HttpResponse::Ok().json(CompanyStatus {
+        company_id: uuid_string(&company.id),
+        is_active: company.is_active != Some(0),
+        channel_count,
+        last_webhook_at: company.last_webhook_at.map(|value| value.to_rfc3339()),
+        api_key,
+    }))
+}
+
+/// Состояние фоновых задач этого процесса
+#[utoipa::path(
+    get,
+    path = "/api/admin/tasks",
+    tag = "Admin",
+    responses(
+        (status = 200, description = "Background tasks of this process", body = TaskList),
+        (status = 403, description = "Platform administrator required"),
+    )
+)]
+#[get("/admin/tasks")]
+pub async fn list_tasks(
+    app_state: web::Data<AppState>,
+    _admin: PlatformAdmin,
+) -> Result<HttpResponse, AppError> {
+    Ok(HttpResponse::Ok().json(TaskList {
+        data: app_state.tasks.statuses(),
+    }))
+}
+
+pub fn init_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(list_tasks).service(
+        web::scope("/admin/companies")
+            .service(list_companies)
+            .service(create_company)
+            .service(get_company)
+            .service(update_company)
+            .service(delete_company)
+            .service(rotate_api_key)
+            .service(get_company_status),
+    );
+}
+
+async fn load_company(app_state: &AppState, raw_id: &str) -> Result<companies::Model, AppError> {
+    let company_uuid = Uuid::parse_str(raw_id)
+        .map_err(|_| AppError::InvalidInput("companyId must be a valid UUID".to_string()))?;
+
+    companies::Entity::find_by_id(uuid_to_bytes(&company_uuid))
+        .one(&app_state.db)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Company not found".to_string()))
+}
+
+async fn verify_api_key(app_state: &AppState, api_key: &str) -> Result<(), AppError> {
+    if app_state.wazzup_api.check_api_key(api_key).await? {
+        Ok(())
+    } else {
+        Err(AppError::InvalidInput(
+            "wazzupApiKey was rejected by Wazzup".to_string(),
+        ))
+    }
+}
+
+/// Subscribes Wazzup webhooks of the company to this service. A failure is
+/// reported in the response instead of failing the request: the key is
+/// already saved and the connection can be retried via `/api/webhook/{id}/connect`.
+async fn connect_webhooks(
+    app_state: &AppState,
+    req: &HttpRequest,
+    company_uuid: &Uuid,
+    api_key: &str,
+) -> WebhookConnection {
+    let webhooks_uri = build_webhook_uri(app_state, req, company_uuid);
+    let request = WebhookSubscriptionRequest {
+        webhooks_uri: webhooks_uri.clone(),
+        subscriptions: default_webhook_subscriptions(),
+    };
+
+    match app_state
+        .wazzup_api
+        .connect_webhooks(api_key, &request)
+        .await
+    {
+        Ok(_) => WebhookConnection {
+            connected: true,
+            webhooks_uri,
+            error: None,
+        },
+        Err(err) => {
+            log::warn!(
+                "Unable to connect webhooks for company {}: {}",
+                company_uuid,
+                err
+            );
+            WebhookConnection {
+                connected: false,
+                webhooks_uri,
+                error: Some(err.to_string()),
+            }
+        }
+    }
+}
+
+fn normalize(value: Option<String>) -> Option<String> {
+    value
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+fn bool_flag(value: bool) -> i8 {
+    if value { 1 } else { 0 }
+}
+
+fn uuid_string(bytes: &[u8]) -> String {
+    Uuid::from_slice(bytes).unwrap_or_default().to_string()
+}
+
+fn company_view(company: &companies::Model) -> CompanyView {
+    CompanyView {
+        id: uuid_string(&company.id),
+        name: company.name.clone(),
+        description: company.description.clone(),
+        email: company.email.clone(),
+        phone: company.phone.clone(),
+        is_active: company.is_active != Some(0),
+        subscription_tier: company.subscription_tier.clone(),
+        has_api_key: company
+            .wazzup_api_key
+            .as_deref()
+            .is_some_and(|key| !key.trim().is_empty()),
+        created_at: company.created_at.map(|value| value.to_rfc3339()),
+        updated_at: company.updated_at.map(|value| value.to_rfc3339()),
+    }
+}
@@ -0,0 +1,14 @@
+pub mod handlers;
+pub mod structures;
+
+pub use handlers::{
+    __path_create_company, __path_delete_company, __path_get_company, __path_get_company_status,
+    __path_list_companies, __path_list_tasks, __path_rotate_api_key, __path_update_company,
+    create_company, delete_company, get_company, get_company_status, init_routes, list_companies,
+    list_tasks, rotate_api_key, update_company,
+};
+
+pub use structures::{
+    ApiKeyStatus, CompanyList, CompanyStatus, CompanyView, CompanyWithWebhooks,
+    CreateCompanyRequest, RotateApiKeyRequest, TaskList, UpdateCompanyRequest, WebhookConnection,
+};
@@ -0,0 +1,114 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::services::background::TaskStatus;
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateCompanyRequest {
+    pub name: String,
+    pub description: Option<String>,
+    pub email: Option<String>,
+    pub phone: Option<String>,
+    /// Checked against Wazzup before the company is saved; webhooks are
+    /// connected right away when it is given.
+    pub wazzup_api_key: Option<String>,
+    pub subscription_tier: Option<String>,
+    /// Defaults to `true`.
+    pub is_active: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateCompanyRequest {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub email: Option<String>,
+    pub phone: Option<String>,
+    /// Empty string clears the tier.
+    pub subscription_tier: Option<String>,
+    /// Webhooks of inactive companies are acknowledged but not processed.
+    pub is_active: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RotateApiKeyRequest {
+    pub wazzup_api_key: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CompanyView {
+    pub id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub phone: Option<String>,
+    pub is_active: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subscription_tier: Option<String>,
+    /// The key itself is stored encrypted and never returned.
+    pub has_api_key: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CompanyList {
+    pub data: Vec<CompanyView>,
+}
+
+/// Фоновые задачи процесса: сначала активные, затем недавно завершённые.
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskList {
+    pub data: Vec<TaskStatus>,
+}
+
+/// Result of (re)connecting Wazzup webhooks after the API key was set.
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookConnection {
+    pub connected: bool,
+    pub webhooks_uri: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CompanyWithWebhooks {
+    pub company: CompanyView,
+    /// Missing when the company has no API key.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub webhooks: Option<WebhookConnection>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ApiKeyStatus {
+    Valid,
+    Invalid,
+    Missing,
+    /// Wazzup could not be reached.
+    Unknown,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CompanyStatus {
+    pub company_id: String,
+    pub is_active: bool,
+    pub channel_count: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_webhook_at: Option<String>,
+    pub api_key: ApiKeyStatus,
+}
@@ -5,10 +5,8 @@ use sea_orm::{
 use uuid::Uuid;
 
 use crate::{
-    api::helpers::get_company_api_key,
-    database::models::channels,
-    errors::AppError,
-    services::wazzup_api::ChannelListResponse,
+    api::helpers::get_company_api_key, app_state::AppState, database::models::channels,
+    errors::AppError, services::wazzup_api::ChannelListResponse,
 };
 
 pub use crate::api::context::bytes_to_uuid;
@@ -16,15 +14,19 @@ pub use crate::api::helpers::uuid_to_bytes;
 
 pub async fn get_company_api_key_by_uuid(
     company_uuid: &Uuid,
-    db: &DatabaseConnection,
+    app_state: &AppState,
 ) -> Result<String, AppError> {
-    get_company_api_key(company_uuid, db).await
+    get_company_api_key(company_uuid, app_state).await
 }
 
+/// Stores the company's Wazzup channels locally and marks them as owned by it.
 pub async fn sync_channels_to_db(
+    company_uuid: &Uuid,
     channel_response: &ChannelListResponse,
     db: &DatabaseConnection,
 ) -> Result<(), AppError> {
+    let company_id_bytes = uuid_to_bytes(company_uuid);
+
     if let Some(channels_list) = &channel_response.channels {
         for channel_info in channels_list {
             let guid = match &channel_info.guid {
@@ -45,11 +47,24 @@ pub async fn sync_channels_to_db(
                 .one(db)
                 .await?
             {
+                // The channel list comes from the company's own Wazzup account,
+                // so it is authoritative for ownership.
+                if let Some(owner) = existing.company_id.as_ref()
+                    && owner != &company_id_bytes
+                {
+                    log::warn!(
+                        "Channel {} moves to company {} from another company",
+                        uuid,
+                        company_uuid
+                    );
+                }
+
+                let mut active = existing.into_active_model();
                 if let Some(transport) = &channel_info.transport {
-                    let mut active = existing.into_active_model();
                     active.r#type = Set(transport.clone());
-                    active.update(db).await?;
                 }
+                active.company_id = Set(Some(company_id_bytes.clone()));
+                active.update(db).await?;
             } else {
                 let new_channel = channels::ActiveModel {
                     id: Set(channel_id_bytes),
@@ -57,6 +72,7 @@ pub async fn sync_channels_to_db(
                         .transport
                         .clone()
                         .unwrap_or_else(|| "unknown".to_string())),
+                    company_id: Set(Some(company_id_bytes.clone())),
                 };
                 new_channel.insert(db).await?;
             }
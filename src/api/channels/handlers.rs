@@ -6,17 +6,16 @@ use url::form_urlencoded;
 use uuid::Uuid;
 
 use crate::{
+    api::permissions::{ManageChannels, Require, ViewChats},
+    api::validation,
     app_state::AppState,
     database::models::{channel_settings, channels},
     errors::AppError,
-    services::wazzup_api::GenerateIframeLinkRequest,
+    services::{events::EventKind, outgoing_webhooks, wazzup_api::GenerateIframeLinkRequest},
 };
 
 use super::{
-    functions::{
-        get_company_api_key_by_uuid,
-        sync_channels_to_db, uuid_to_bytes,
-    },
+    functions::{get_company_api_key_by_uuid, sync_channels_to_db, uuid_to_bytes},
     structures::default_delete_chats,
     structures::{
         ChannelAddedNotification, ChannelDeletionResponse, ChannelView, ChannelsResponse,
@@ -39,12 +38,13 @@ use super::{
 #[get("/{companyId}")]
 pub async fn get_channels(
     app_state: web::Data<AppState>,
+    _access: Require<ViewChats>,
     path: web::Path<String>,
 ) -> Result<HttpResponse, AppError> {
     let company_uuid = Uuid::parse_str(&path.into_inner())
         .map_err(|_| AppError::InvalidInput("companyId must be a valid UUID".to_string()))?;
-    
-    let api_key = get_company_api_key_by_uuid(&company_uuid, &app_state.db).await?;
+
+    let api_key = get_company_api_key_by_uuid(&company_uuid, &app_state).await?;
     let channels_response = app_state.wazzup_api.get_channels(&api_key).await?;
 
     let data = channels_response
@@ -52,20 +52,18 @@ pub async fn get_channels(
         .as_ref()
         .map(|list| {
             list.iter()
-                .map(|info| {
-                    ChannelView {
-                        deleted: info.deleted,
-                        details: info.details.clone(),
-                        guid: info.guid.clone(),
-                        has_acecess: info.has_access,
-                        is_inbound: info.is_inbound,
-                        name: info.name.clone(),
-                        phone: info.phone.clone(),
-                        state: info.state.clone(),
-                        tier: info.tier.clone(),
-                        transport: info.transport.clone(),
-                        visible: info.visible,
-                    }
+                .map(|info| ChannelView {
+                    deleted: info.deleted,
+                    details: info.details.clone(),
+                    guid: info.guid.clone(),
+                    has_acecess: info.has_access,
+                    is_inbound: info.is_inbound,
+                    name: info.name.clone(),
+                    phone: info.phone.clone(),
+                    state: info.state.clone(),
+                    tier: info.tier.clone(),
+                    transport: info.transport.clone(),
+                    visible: info.visible,
                 })
                 .collect::<Vec<_>>()
         })
@@ -75,11 +73,11 @@ pub async fn get_channels(
 
     let db_clone = app_state.db.clone();
     let response_clone = channels_response.clone();
-    actix_web::rt::spawn(async move {
-        if let Err(err) = sync_channels_to_db(&response_clone, &db_clone).await {
-            log::error!("Failed to sync channels: {}", err);
-        }
-    });
+    app_state
+        .tasks
+        .spawn(format!("channels.sync {}", company_uuid), async move {
+            sync_channels_to_db(&company_uuid, &response_clone, &db_clone).await
+        });
 
     Ok(HttpResponse::Ok().json(response_body))
 }
@@ -100,14 +98,15 @@ pub async fn get_channels(
 #[post("/{companyId}/iframe-link")]
 pub async fn generate_wrapped_iframe_link(
     app_state: web::Data<AppState>,
+    _access: Require<ManageChannels>,
     req: HttpRequest,
     path: web::Path<String>,
-    body: web::Json<GenerateIframeLinkRequest>,
+    body: validation::Json<GenerateIframeLinkRequest>,
 ) -> Result<HttpResponse, AppError> {
     let company_uuid = Uuid::parse_str(&path.into_inner())
         .map_err(|_| AppError::InvalidInput("companyId must be a valid UUID".to_string()))?;
-    
-    let api_key = get_company_api_key_by_uuid(&company_uuid, &app_state.db).await?;
+
+    let api_key = get_company_api_key_by_uuid(&company_uuid, &app_state).await?;
     let payload = body.into_inner();
     let original_response = app_state
         .wazzup_api
@@ -156,8 +155,9 @@ pub async fn generate_wrapped_iframe_link(
 #[delete("/{companyId}/{channelId}")]
 pub async fn delete_channel(
     app_state: web::Data<AppState>,
+    _access: Require<ManageChannels>,
     path: web::Path<(String, String)>,
-    query: Option<web::Query<DeleteChannelQuery>>,
+    query: Option<validation::Query<DeleteChannelQuery>>,
 ) -> Result<HttpResponse, AppError> {
     let (company_id_raw, channel_id) = path.into_inner();
     let company_uuid = Uuid::parse_str(&company_id_raw)
@@ -165,17 +165,26 @@ pub async fn delete_channel(
     let channel_uuid = Uuid::parse_str(&channel_id)
         .map_err(|_| AppError::InvalidInput("channelId must be a valid UUID".to_string()))?;
 
-    let api_key = get_company_api_key_by_uuid(&company_uuid, &app_state.db).await?;
+    let api_key = get_company_api_key_by_uuid(&company_uuid, &app_state).await?;
 
     let delete_chats = query
         .map(|q| q.into_inner().delete_chats)
         .unwrap_or_else(default_delete_chats);
 
     let channel_bytes = uuid_to_bytes(&channel_uuid);
-    let mut transport = channels::Entity::find_by_id(channel_bytes.clone())
+    let local_channel = channels::Entity::find_by_id(channel_bytes.clone())
         .one(&app_state.db)
-        .await?
-        .map(|model| model.r#type);
+        .await?;
+
+    if let Some(owner) = local_channel
+        .as_ref()
+        .and_then(|model| model.company_id.as_ref())
+        && owner != &uuid_to_bytes(&company_uuid)
+    {
+        return Err(AppError::NotFound("Channel not found".to_string()));
+    }
+
+    let mut transport = local_channel.map(|model| model.r#type);
 
     if transport.is_none() {
         let response = app_state.wazzup_api.get_channels(&api_key).await?;
@@ -187,11 +196,11 @@ pub async fn delete_channel(
 
         let db_clone = app_state.db.clone();
         let response_clone = response.clone();
-        actix_web::rt::spawn(async move {
-            if let Err(err) = sync_channels_to_db(&response_clone, &db_clone).await {
-                log::error!("Failed to sync channels post-deletion lookup: {}", err);
-            }
-        });
+        app_state
+            .tasks
+            .spawn(format!("channels.sync {}", company_uuid), async move {
+                sync_channels_to_db(&company_uuid, &response_clone, &db_clone).await
+            });
     }
 
     let transport =
@@ -233,8 +242,9 @@ pub async fn delete_channel(
 #[post("/{companyId}/added/{transport}")]
 pub async fn handle_channel_added(
     app_state: web::Data<AppState>,
+    _access: Require<ManageChannels>,
     path: web::Path<(String, String)>,
-    body: web::Json<ChannelAddedNotification>,
+    body: validation::Json<ChannelAddedNotification>,
 ) -> Result<HttpResponse, AppError> {
     let (company_id_raw, transport) = path.into_inner();
     let company_uuid = Uuid::parse_str(&company_id_raw)
@@ -251,28 +261,31 @@ pub async fn handle_channel_added(
         payload.timestamp
     );
 
-    let api_key = get_company_api_key_by_uuid(&company_uuid, &app_state.db).await?;
+    let api_key = get_company_api_key_by_uuid(&company_uuid, &app_state).await?;
+
+    outgoing_webhooks::publish(
+        &app_state.db,
+        &app_state.events,
+        &company_uuid,
+        None,
+        EventKind::ChannelStateChanged,
+        json!({
+            "channelId": payload.channel_id,
+            "state": payload.state,
+            "transport": transport,
+        }),
+    )
+    .await;
+
     let api_clone = app_state.wazzup_api.clone();
     let db_clone = app_state.db.clone();
 
-    actix_web::rt::spawn(async move {
-        match api_clone.get_channels(&api_key).await {
-            Ok(response) => {
-                if let Err(err) = sync_channels_to_db(&response, &db_clone).await {
-                    log::error!(
-                        "Failed to sync channels after 'added' notification: {}",
-                        err
-                    );
-                }
-            }
-            Err(err) => {
-                log::error!(
-                    "Could not refresh channels after 'added' notification: {}",
-                    err
-                );
-            }
-        }
-    });
+    app_state
+        .tasks
+        .spawn(format!("channels.sync {}", company_uuid), async move {
+            let response = api_clone.get_channels(&api_key).await?;
+            sync_channels_to_db(&company_uuid, &response, &db_clone).await
+        });
 
     Ok(HttpResponse::Ok().json(json!({
         "status": "success",
@@ -300,13 +313,14 @@ pub async fn handle_channel_added(
 #[post("/{companyId}/{transport}/{channelId}/reinit")]
 pub async fn reinitialize_channel(
     app_state: web::Data<AppState>,
+    _access: Require<ManageChannels>,
     path: web::Path<(String, String, String)>,
 ) -> Result<HttpResponse, AppError> {
     let (company_id_raw, transport, channel_id) = path.into_inner();
     let company_uuid = Uuid::parse_str(&company_id_raw)
         .map_err(|_| AppError::InvalidInput("companyId must be a valid UUID".to_string()))?;
 
-    let api_key = get_company_api_key_by_uuid(&company_uuid, &app_state.db).await?;
+    let api_key = get_company_api_key_by_uuid(&company_uuid, &app_state).await?;
     app_state
         .wazzup_api
         .reinitialize_channel(&api_key, &transport, &channel_id)
@@ -0,0 +1,83 @@
+//! Opaque keyset cursors for chat message history.
+//!
+//! A cursor points at a message by `(created_at, id)`, which is the sort key
+//! of the history, so pages stay stable while new messages arrive. The time
+//! is kept to the nanosecond: SQLite stores it that precisely, and a rounded
+//! time would not match messages sent at the same moment.
+
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use chrono::{DateTime, Utc};
+use sea_orm::{ColumnTrait, Condition, prelude::DateTimeUtc};
+use uuid::Uuid;
+
+use crate::{database::models::messages, errors::AppError};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MessageCursor {
+    pub created_at: DateTimeUtc,
+    pub id: Vec<u8>,
+}
+
+impl MessageCursor {
+    pub fn from_message(message: &messages::Model) -> Self {
+        Self {
+            created_at: message.created_at,
+            id: message.id.clone(),
+        }
+    }
+
+    pub fn encode(&self) -> String {
+        let id = Uuid::from_slice(&self.id).unwrap_or_default();
+        URL_SAFE_NO_PAD.encode(format!(
+            "{}:{}",
+            self.created_at.timestamp_nanos_opt().unwrap_or(i64::MAX),
+            id.simple()
+        ))
+    }
+
+    pub fn decode(raw: &str) -> Result<Self, AppError> {
+        let invalid = || AppError::InvalidInput("Invalid cursor".to_string());
+
+        let decoded = URL_SAFE_NO_PAD.decode(raw.trim()).map_err(|_| invalid())?;
+        let decoded = String::from_utf8(decoded).map_err(|_| invalid())?;
+        let (nanos, id) = decoded.split_once(':').ok_or_else(invalid)?;
+
+        let created_at: DateTime<Utc> =
+            DateTime::from_timestamp_nanos(nanos.parse::<i64>().map_err(|_| invalid())?);
+        let id = Uuid::parse_str(id).map_err(|_| invalid())?;
+
+        Ok(Self {
+            created_at,
+            id: id.as_bytes().to_vec(),
+        })
+    }
+
+    /// Messages that come before the cursor in history.
+    pub fn older(&self) -> Condition {
+        Condition::any()
+            .add(messages::Column::CreatedAt.lt(self.created_at))
+            .add(
+                Condition::all()
+                    .add(messages::Column::CreatedAt.eq(self.created_at))
+                    .add(messages::Column::Id.lt(self.id.clone())),
+            )
+    }
+
+    /// Messages that come after the cursor in history.
+    pub fn newer(&self) -> Condition {
+        Condition::any()
+            .add(messages::Column::CreatedAt.gt(self.created_at))
+            .add(
+                Condition::all()
+                    .add(messages::Column::CreatedAt.eq(self.created_at))
+                    .add(messages::Column::Id.gt(self.id.clone())),
+            )
+    }
+
+    /// Extends `condition` to also match the cursor message itself.
+    pub fn including(&self, condition: Condition) -> Condition {
+        Condition::any()
+            .add(condition)
+            .add(messages::Column::Id.eq(self.id.clone()))
+    }
+}
@@ -1,42 +1,55 @@
 use std::collections::{HashMap, HashSet};
 
-use actix_web::{HttpRequest, HttpResponse, get, post, web};
+use actix_web::{HttpResponse, get, post, web};
 use chrono::{DateTime, Utc};
 use sea_orm::prelude::DateTimeUtc;
 use sea_orm::{
-    ColumnTrait, EntityTrait, FromQueryResult, JoinType, QueryFilter, QueryOrder, QuerySelect,
-    RelationTrait, sea_query::Expr, sea_query::extension::postgres::PgExpr,
+    ActiveModelTrait, ColumnTrait, Condition, EntityTrait, FromQueryResult, IntoActiveModel,
+    JoinType, QueryFilter, QueryOrder, QuerySelect, RelationTrait, Set,
+    sea_query::{Expr, Func, IntoCondition, OnConflict, Query, SimpleExpr},
 };
-use serde_json::Value as JsonValue;
+use serde_json::{Value as JsonValue, json};
 use uuid::Uuid;
 
 use crate::{
+    api::{
+        permissions::{ManageContacts, Require, SendMessages, ViewChats},
+        validation::{self, Validator, codes},
+    },
     app_state::AppState,
-    database::models::{channels, chats, clients, messages, users},
+    database::models::{
+        channels, chat_read_markers, chat_transfers, chats, clients, company_users, messages, users,
+    },
     errors::AppError,
-    services::wazzup_api::SendMessageRequest,
+    services::{
+        events::EventKind,
+        outgoing_webhooks,
+        search::{self, MessageSearch},
+        wazzup_api::SendMessageRequest,
+    },
 };
 
-use super::functions::{
-    option_i8_to_bool,
-    uuid_bytes_to_string, uuid_to_bytes,
-};
-use crate::api::helpers::get_company_api_key;
+use super::cursor::MessageCursor;
+use super::functions::{option_i8_to_bool, uuid_bytes_to_string, uuid_to_bytes};
+use super::previews::{self, LastMessage, PreviewQuery, PreviewRow};
 use super::structures::{
-    AssigneeSummary, ChannelSummary, ChatDetails, ChatInfoSummary, ChatMessagesResponse,
-    ChatPreview, ChatPreviewList, ChatPreviewsQuery, ClientSummary, MessageContentItem,
-    MessageSender, MessageView, MessagesQuery, OutgoingMessage, SendChatMessageRequest,
-    SendChatMessageResponse,
+    AssignChatRequest, AssigneeSummary, ChannelSummary, ChatDetails, ChatInfoSummary,
+    ChatMessagesResponse, ChatPreview, ChatPreviewList, ChatPreviewsQuery, ChatReadStateResponse,
+    ChatSearchQuery, ChatSearchResponse, ChatUnreadCount, ClientSearchHit, ClientSummary,
+    MarkChatReadRequest, MessageContentItem, MessageSearchHit, MessageSender, MessageView,
+    MessagesQuery, OutgoingMessage, SendChatMessageRequest, SendChatMessageResponse,
+    UnreadSummaryResponse,
 };
+use crate::api::helpers::get_company_api_key;
 
-#[derive(FromQueryResult)]
-struct LastMessageMeta {
-    chat_id: String,
-    last_created_at: Option<DateTimeUtc>,
-}
+const DEFAULT_MESSAGES_PAGE: u64 = 50;
+const MAX_MESSAGES_PAGE: u64 = 200;
+const DEFAULT_SEARCH_LIMIT: u64 = 20;
+const MAX_SEARCH_LIMIT: u64 = 100;
+const MIN_PHONE_SEARCH_DIGITS: usize = 3;
 
 #[derive(FromQueryResult)]
-struct InboundCountMeta {
+struct UnreadCountMeta {
     chat_id: String,
     unread_count: i64,
 }
@@ -60,77 +73,42 @@ struct InboundCountMeta {
 #[get("/{companyId}/previews")]
 pub async fn get_chat_previews(
     app_state: web::Data<AppState>,
+    access: Require<ViewChats>,
     path: web::Path<String>,
-    query: web::Query<ChatPreviewsQuery>,
+    query: validation::Query<ChatPreviewsQuery>,
 ) -> Result<HttpResponse, AppError> {
     let company_uuid = Uuid::parse_str(&path.into_inner())
         .map_err(|_| AppError::InvalidInput("companyId must be a valid UUID".to_string()))?;
     let company_id_bytes = uuid_to_bytes(&company_uuid);
 
     let params = query.into_inner();
-
-    let chat_records = load_company_chats(&company_id_bytes, &app_state, params.filter.clone()).await?;
-    let chat_ids: Vec<String> = chat_records
-        .iter()
-        .map(|record| record.chat.id.clone())
-        .collect();
-
-    let last_message_map = load_last_message_metadata(&app_state, &chat_ids).await?;
-    let unread_map = load_inbound_counts(&app_state, &chat_ids).await?;
-
-    let mut user_ids: HashSet<Vec<u8>> = HashSet::new();
-    for record in &chat_records {
-        if let Some(client) = &record.client {
-            user_ids.insert(client.responsible_user_id.clone());
-        }
-    }
-    let user_map = load_users(&app_state, &user_ids).await?;
-
-    let mut previews: Vec<(ChatPreview, Option<DateTimeUtc>)> = Vec::new();
-    for record in &chat_records {
-        let last_timestamp = last_message_map.get(record.chat.id.as_str()).cloned();
-        let unread_count = unread_map
-            .get(record.chat.id.as_str())
-            .copied()
-            .unwrap_or(0);
-
-        let preview = build_chat_preview(record, unread_count, &user_map)?;
-
-        if let Some(bot_only) = params.bot {
-            if bot_only != assigned_to_bot(&preview) {
-                continue;
-            }
-        }
-
-        previews.push((preview, last_timestamp));
-    }
-
-    previews.sort_by(|a, b| match (&a.1, &b.1) {
-        (Some(a_date), Some(b_date)) => b_date.cmp(a_date),
-        (Some(_), None) => std::cmp::Ordering::Less,
-        (None, Some(_)) => std::cmp::Ordering::Greater,
-        (None, None) => a.0.chat_info.name.cmp(&b.0.chat_info.name),
-    });
-
     let (offset, count) = normalize_pagination(params.offset, params.count)?;
-    let sliced = slice_previews(previews, offset, count);
 
-    let selected_ids: Vec<String> = sliced
+    let rows = previews::load_preview_page(
+        &app_state.db,
+        &PreviewQuery {
+            company_id: company_id_bytes,
+            filter: params.filter,
+            bot: params.bot,
+            reader_id: access.principal.user_id.clone(),
+            chat_owner: access.chat_owner(),
+            offset,
+            limit: count,
+        },
+    )
+    .await?;
+
+    let latest: Vec<String> = rows
         .iter()
-        .map(|(preview, _)| preview.id.clone())
+        .filter(|row| row.last_created_at.is_some())
+        .map(|row| row.chat_id.clone())
         .collect();
+    let mut last_messages = previews::load_last_messages(&app_state.db, &latest).await?;
 
-    let (last_messages, author_ids) = load_last_messages(&app_state, &selected_ids).await?;
-    let author_map = load_users(&app_state, &author_ids).await?;
-
-    let mut response_data = Vec::new();
-    for ((mut preview, _), chat_id_str) in sliced.into_iter().zip(selected_ids.into_iter()) {
-        if let Some(message) = last_messages.get(chat_id_str.as_str()) {
-            let sender =
-                resolve_message_sender(message, &author_map, &user_map, preview.client.as_ref());
-            preview.last_message = Some(build_message_view(message, sender)?);
-        }
-        response_data.push(preview);
+    let mut response_data = Vec::with_capacity(rows.len());
+    for row in rows {
+        let last_message = last_messages.remove(row.chat_id.as_str());
+        response_data.push(build_preview_from_row(row, last_message)?);
     }
 
     Ok(HttpResponse::Ok().json(ChatPreviewList {
@@ -144,7 +122,7 @@ pub async fn get_chat_previews(
     tag = "Chats",
     params(
         ("companyId" = String, Path, description = "Company UUID"),
-        ("chatId" = String, Path, description = "Chat identifier (UUID)")
+        ("chatId" = String, Path, description = "Chat identifier (UUID)"),
     ),
     responses(
         (status = 200, description = "Chat details", body = ChatDetails),
@@ -154,6 +132,7 @@ pub async fn get_chat_previews(
 #[get("/{companyId}/{chatId}")]
 pub async fn get_chat(
     app_state: web::Data<AppState>,
+    access: Require<ViewChats>,
     path: web::Path<(String, String)>,
 ) -> Result<HttpResponse, AppError> {
     let (company_id_raw, chat_id) = path.into_inner();
@@ -163,9 +142,20 @@ pub async fn get_chat(
 
     let chat_uuid = Uuid::parse_str(&chat_id)
         .map_err(|_| AppError::InvalidInput("Invalid chat id".to_string()))?;
-    let record = load_single_chat(&company_id_bytes, &app_state, &chat_uuid).await?;
+    let record = load_single_chat(
+        &company_id_bytes,
+        &app_state,
+        &chat_uuid,
+        access.chat_owner().as_deref(),
+    )
+    .await?;
 
-    let unread_map = load_inbound_counts(&app_state, &[record.chat.id.clone()]).await?;
+    let unread_map = load_unread_counts(
+        &app_state,
+        Condition::all().add(messages::Column::ChatId.eq(record.chat.id.clone())),
+        &access.principal.user_id,
+    )
+    .await?;
     let unread_count = unread_map
         .get(record.chat.id.as_str())
         .copied()
@@ -199,19 +189,24 @@ pub async fn get_chat(
     params(
         ("companyId" = String, Path, description = "Company UUID"),
         ("chatId" = String, Path, description = "Chat identifier (UUID)"),
-        ("offset" = Option<u64>, Query, description = "Number of messages to skip"),
-        ("count" = Option<u64>, Query, description = "Maximum number of messages to return"),
+        ("count" = Option<u64>, Query, description = "Maximum number of messages to return (default 50, max 200)"),
+        ("before" = Option<String>, Query, description = "Cursor from `nextCursor`: load older messages"),
+        ("after" = Option<String>, Query, description = "Cursor from `prevCursor`: load newer messages"),
+        ("around" = Option<String>, Query, description = "Message UUID to center the page on"),
+        ("offset" = Option<u64>, Query, deprecated, description = "Number of messages to skip; use cursors instead"),
     ),
     responses(
-        (status = 200, description = "Chat messages", body = ChatMessagesResponse),
-        (status = 404, description = "Chat not found"),
+        (status = 200, description = "Chat messages, newest first", body = ChatMessagesResponse),
+        (status = 400, description = "Invalid cursor or conflicting pagination parameters"),
+        (status = 404, description = "Chat or anchor message not found"),
     )
 )]
 #[get("/{companyId}/{chatId}/messages")]
 pub async fn get_chat_messages(
     app_state: web::Data<AppState>,
+    access: Require<ViewChats>,
     path: web::Path<(String, String)>,
-    query: web::Query<MessagesQuery>,
+    query: validation::Query<MessagesQuery>,
 ) -> Result<HttpResponse, AppError> {
     let (company_id_raw, chat_id) = path.into_inner();
     let company_uuid = Uuid::parse_str(&company_id_raw)
@@ -220,7 +215,13 @@ pub async fn get_chat_messages(
 
     let chat_uuid = Uuid::parse_str(&chat_id)
         .map_err(|_| AppError::InvalidInput("Invalid chat id".to_string()))?;
-    let record = load_single_chat(&company_id_bytes, &app_state, &chat_uuid).await?;
+    let record = load_single_chat(
+        &company_id_bytes,
+        &app_state,
+        &chat_uuid,
+        access.chat_owner().as_deref(),
+    )
+    .await?;
 
     let mut user_ids = HashSet::new();
     if let Some(client) = &record.client {
@@ -230,19 +231,24 @@ pub async fn get_chat_messages(
 
     let preview = build_chat_preview(&record, 0, &user_map)?;
 
-    let (offset, count) = normalize_pagination(query.offset, query.count)?;
-
-    let chat_id_str = chat_uuid.to_string();
-    let mut message_query = messages::Entity::find()
-        .filter(messages::Column::ChatId.eq(chat_id_str.clone()))
-        .order_by_desc(messages::Column::CreatedAt);
-
-    if let Some(limit) = count {
-        message_query = message_query.limit(limit);
-    }
-    message_query = message_query.offset(offset);
-
-    let message_models = message_query.all(&app_state.db).await?;
+    let page = load_message_page(&app_state, &chat_uuid.to_string(), query.into_inner()).await?;
+    let next_cursor = page
+        .has_more
+        .then(|| {
+            page.messages
+                .last()
+                .map(|m| MessageCursor::from_message(m).encode())
+        })
+        .flatten();
+    let prev_cursor = page
+        .has_newer
+        .then(|| {
+            page.messages
+                .first()
+                .map(|m| MessageCursor::from_message(m).encode())
+        })
+        .flatten();
+    let message_models = page.messages;
 
     let mut author_ids: HashSet<Vec<u8>> = HashSet::new();
     for message in &message_models {
@@ -259,7 +265,13 @@ pub async fn get_chat_messages(
         responses.push(build_message_view(&message, sender)?);
     }
 
-    Ok(HttpResponse::Ok().json(ChatMessagesResponse { data: responses }))
+    Ok(HttpResponse::Ok().json(ChatMessagesResponse {
+        data: responses,
+        next_cursor,
+        prev_cursor,
+        has_more: page.has_more,
+        has_newer: page.has_newer,
+    }))
 }
 
 #[utoipa::path(
@@ -274,14 +286,15 @@ pub async fn get_chat_messages(
     responses(
         (status = 200, description = "Message sent", body = SendChatMessageResponse),
         (status = 404, description = "Chat not found"),
-        (status = 422, description = "Invalid payload"),
+        (status = 400, description = "Invalid message parts, listed in `details`"),
     )
 )]
 #[post("/{companyId}/{chatId}/send")]
 pub async fn send_chat_message(
     app_state: web::Data<AppState>,
+    access: Require<SendMessages>,
     path: web::Path<(String, String)>,
-    body: web::Json<SendChatMessageRequest>,
+    body: validation::Json<SendChatMessageRequest>,
 ) -> Result<HttpResponse, AppError> {
     let (company_id_raw, chat_id) = path.into_inner();
     let company_uuid = Uuid::parse_str(&company_id_raw)
@@ -290,15 +303,15 @@ pub async fn send_chat_message(
 
     let chat_uuid = Uuid::parse_str(&chat_id)
         .map_err(|_| AppError::InvalidInput("Invalid chat id".to_string()))?;
-    let record = load_single_chat(&company_id_bytes, &app_state, &chat_uuid).await?;
+    let record = load_single_chat(
+        &company_id_bytes,
+        &app_state,
+        &chat_uuid,
+        access.chat_owner().as_deref(),
+    )
+    .await?;
 
     let payload = body.into_inner();
-    if payload.message.content.is_empty() {
-        return Err(AppError::InvalidInput(
-            "Message content cannot be empty".to_string(),
-        ));
-    }
-
     let (text_content, media_url) = extract_outgoing_content(&payload.message)?;
 
     let channel = record
@@ -313,11 +326,11 @@ pub async fn send_chat_message(
         sender_id: 0, // Legacy placeholder, CRM-side sender stored separately
         text: text_content.clone(),
         content_uri: media_url.clone(),
-        crm_user_id: None, // Internal service - no user context
+        crm_user_id: Some(uuid_bytes_to_string(&access.principal.user_id)?),
         crm_message_id: None,
     };
 
-    let api_key = get_company_api_key(&company_uuid, &app_state.db).await?;
+    let api_key = get_company_api_key(&company_uuid, &app_state).await?;
 
     let response = app_state
         .wazzup_api
@@ -334,103 +347,448 @@ pub async fn send_chat_message(
     }))
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/chats/{companyId}/{chatId}/read",
+    tag = "Chats",
+    params(
+        ("companyId" = String, Path, description = "Company UUID"),
+        ("chatId" = String, Path, description = "Chat identifier (UUID)")
+    ),
+    request_body = MarkChatReadRequest,
+    responses(
+        (status = 200, description = "Read marker updated", body = ChatReadStateResponse),
+        (status = 404, description = "Chat or message not found"),
+    )
+)]
+#[post("/{companyId}/{chatId}/read")]
+pub async fn mark_chat_read(
+    app_state: web::Data<AppState>,
+    access: Require<ViewChats>,
+    path: web::Path<(String, String)>,
+    body: validation::Json<MarkChatReadRequest>,
+) -> Result<HttpResponse, AppError> {
+    let (company_id_raw, chat_id) = path.into_inner();
+    let company_uuid = Uuid::parse_str(&company_id_raw)
+        .map_err(|_| AppError::InvalidInput("companyId must be a valid UUID".to_string()))?;
+    let company_id_bytes = uuid_to_bytes(&company_uuid);
+
+    let chat_uuid = Uuid::parse_str(&chat_id)
+        .map_err(|_| AppError::InvalidInput("Invalid chat id".to_string()))?;
+    let record = load_single_chat(
+        &company_id_bytes,
+        &app_state,
+        &chat_uuid,
+        access.chat_owner().as_deref(),
+    )
+    .await?;
+
+    let payload = body.into_inner();
+    let reader_id = access.principal.user_id.clone();
+
+    let read_message = match payload.message_id.as_deref() {
+        Some(raw) => {
+            let message_bytes = uuid_to_vec(raw)?;
+            let message = messages::Entity::find_by_id(message_bytes)
+                .one(&app_state.db)
+                .await?
+                .filter(|message| message.chat_id == record.chat.id)
+                .ok_or_else(|| AppError::NotFound("Message not found".to_string()))?;
+            Some(message)
+        }
+        None => {
+            messages::Entity::find()
+                .filter(messages::Column::ChatId.eq(record.chat.id.clone()))
+                .order_by_desc(messages::Column::CreatedAt)
+                .one(&app_state.db)
+                .await?
+        }
+    };
+
+    let (message_id, read_at) = match read_message {
+        Some(message) => (Some(message.id), message.created_at),
+        None => (None, Utc::now()),
+    };
+
+    let marker =
+        save_read_marker(&app_state, &record.chat.id, &reader_id, message_id, read_at).await?;
+
+    let unread_map = load_unread_counts(
+        &app_state,
+        Condition::all().add(messages::Column::ChatId.eq(record.chat.id.clone())),
+        &reader_id,
+    )
+    .await?;
+    let unread_count = unread_map
+        .get(record.chat.id.as_str())
+        .copied()
+        .unwrap_or(0);
+
+    let last_read_message_id = marker
+        .last_read_message_id
+        .as_deref()
+        .map(uuid_bytes_to_string)
+        .transpose()?;
+    let last_read_at: DateTime<Utc> = marker.last_read_at;
+
+    let response = ChatReadStateResponse {
+        chat_id: record.chat.id,
+        user_id: uuid_bytes_to_string(&reader_id)?,
+        last_read_message_id,
+        last_read_at: last_read_at.to_rfc3339(),
+        unread_count,
+    };
+
+    publish_chat_event(
+        &app_state,
+        &company_uuid,
+        &response.chat_id,
+        EventKind::ChatRead,
+        serde_json::to_value(&response)?,
+    )
+    .await;
+
+    Ok(HttpResponse::Ok().json(response))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/chats/{companyId}/{chatId}/assign",
+    tag = "Chats",
+    params(
+        ("companyId" = String, Path, description = "Company UUID"),
+        ("chatId" = String, Path, description = "Chat identifier (UUID)")
+    ),
+    request_body = AssignChatRequest,
+    responses(
+        (status = 200, description = "Chat reassigned", body = ChatDetails),
+        (status = 400, description = "Chat has no client to assign"),
+        (status = 404, description = "Chat or user not found"),
+    )
+)]
+#[post("/{companyId}/{chatId}/assign")]
+pub async fn assign_chat(
+    app_state: web::Data<AppState>,
+    access: Require<ManageContacts>,
+    path: web::Path<(String, String)>,
+    body: validation::Json<AssignChatRequest>,
+) -> Result<HttpResponse, AppError> {
+    let (company_id_raw, chat_id) = path.into_inner();
+    let company_uuid = Uuid::parse_str(&company_id_raw)
+        .map_err(|_| AppError::InvalidInput("companyId must be a valid UUID".to_string()))?;
+    let company_id_bytes = uuid_to_bytes(&company_uuid);
+
+    let chat_uuid = Uuid::parse_str(&chat_id)
+        .map_err(|_| AppError::InvalidInput("Invalid chat id".to_string()))?;
+    let mut record = load_single_chat(
+        &company_id_bytes,
+        &app_state,
+        &chat_uuid,
+        access.chat_owner().as_deref(),
+    )
+    .await?;
+
+    let assignee_id = resolve_company_user(&company_id_bytes, &app_state, &body.user_id).await?;
+
+    let client = record
+        .client
+        .take()
+        .ok_or_else(|| AppError::InvalidInput("Chat has no client to assign".to_string()))?;
+    let previous_assignee_id = client.responsible_user_id.clone();
+
+    let client = if previous_assignee_id != assignee_id {
+        let mut active = client.into_active_model();
+        active.responsible_user_id = Set(assignee_id.clone());
+        let updated = active.update(&app_state.db).await?;
+
+        chat_transfers::ActiveModel {
+            id: Set(uuid_to_bytes(&Uuid::new_v4())),
+            chat_id: Set(uuid_to_bytes(&chat_uuid)),
+            from_user_id: Set(previous_assignee_id.clone()),
+            to_user_id: Set(assignee_id.clone()),
+            created_at: Set(Utc::now()),
+        }
+        .insert(&app_state.db)
+        .await?;
+
+        updated
+    } else {
+        client
+    };
+    record.client = Some(client);
+
+    let user_ids = HashSet::from([assignee_id.clone()]);
+    let user_map = load_users(&app_state, &user_ids).await?;
+    let preview = build_chat_preview(&record, 0, &user_map)?;
+
+    if previous_assignee_id != assignee_id {
+        publish_chat_event(
+            &app_state,
+            &company_uuid,
+            &preview.id,
+            EventKind::ChatAssigned,
+            json!({
+                "chatId": preview.id,
+                "assignee": preview.assignee,
+                "previousAssigneeId": uuid_bytes_to_string(&previous_assignee_id).ok(),
+            }),
+        )
+        .await;
+    }
+
+    Ok(HttpResponse::Ok().json(preview))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/chats/{companyId}/unread",
+    tag = "Chats",
+    params(
+        ("companyId" = String, Path, description = "Company UUID"),
+    ),
+    responses(
+        (status = 200, description = "Unread counters for the company", body = UnreadSummaryResponse),
+        (status = 404, description = "User not found"),
+    )
+)]
+#[get("/{companyId}/unread")]
+pub async fn get_unread_summary(
+    app_state: web::Data<AppState>,
+    access: Require<ViewChats>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let company_uuid = Uuid::parse_str(&path.into_inner())
+        .map_err(|_| AppError::InvalidInput("companyId must be a valid UUID".to_string()))?;
+    let company_id_bytes = uuid_to_bytes(&company_uuid);
+
+    let mut scope = Condition::all().add(channels::Column::CompanyId.eq(company_id_bytes));
+    if let Some(owner) = access.chat_owner() {
+        scope = scope.add(owned_by(&owner));
+    }
+
+    let unread_map = load_unread_counts(&app_state, scope, &access.principal.user_id).await?;
+
+    let mut data: Vec<ChatUnreadCount> = unread_map
+        .into_iter()
+        .filter(|(_, unread_count)| *unread_count > 0)
+        .map(|(chat_id, unread_count)| ChatUnreadCount {
+            chat_id,
+            unread_count,
+        })
+        .collect();
+    data.sort_by(|a, b| {
+        b.unread_count
+            .cmp(&a.unread_count)
+            .then_with(|| a.chat_id.cmp(&b.chat_id))
+    });
+
+    Ok(HttpResponse::Ok().json(UnreadSummaryResponse {
+        total_unread: data.iter().map(|item| item.unread_count).sum(),
+        chats_with_unread: data.len() as i64,
+        data,
+    }))
+}
+
+/// Полнотекстовый поиск по тексту сообщений, именам и телефонам клиентов
+#[utoipa::path(
+    get,
+    path = "/api/chats/{companyId}/search",
+    tag = "Chats",
+    params(
+        ("companyId" = String, Path, description = "Company UUID"),
+        ("q" = String, Query, description = "Search text; every word must match (as a prefix)"),
+        ("channelId" = Option<String>, Query, description = "Only search chats of this channel"),
+        ("from" = Option<String>, Query, description = "RFC 3339 lower bound for message dates"),
+        ("to" = Option<String>, Query, description = "RFC 3339 upper bound for message dates"),
+        ("offset" = Option<u64>, Query, description = "Number of message hits to skip"),
+        ("count" = Option<u64>, Query, description = "Maximum number of hits to return (default 20, max 100)"),
+    ),
+    responses(
+        (status = 200, description = "Ranked search results; client matches are only returned on the first page", body = ChatSearchResponse),
+        (status = 400, description = "Invalid query"),
+    )
+)]
+#[get("/{companyId}/search")]
+pub async fn search_chats(
+    app_state: web::Data<AppState>,
+    access: Require<ViewChats>,
+    path: web::Path<String>,
+    query: validation::Query<ChatSearchQuery>,
+) -> Result<HttpResponse, AppError> {
+    let company_uuid = Uuid::parse_str(&path.into_inner())
+        .map_err(|_| AppError::InvalidInput("companyId must be a valid UUID".to_string()))?;
+    let company_id_bytes = uuid_to_bytes(&company_uuid);
+
+    let params = query.into_inner();
+    let terms = search::search_terms(&params.q);
+    if terms.is_empty() {
+        return Err(AppError::InvalidInput(
+            "q must contain at least one word".to_string(),
+        ));
+    }
+
+    let channel_id = params
+        .channel_id
+        .as_deref()
+        .map(|raw| {
+            Uuid::parse_str(raw)
+                .map(|uuid| uuid_to_bytes(&uuid))
+                .map_err(|_| AppError::InvalidInput("channelId must be a valid UUID".to_string()))
+        })
+        .transpose()?;
+    let from = parse_search_date(params.from.as_deref(), "from")?;
+    let to = parse_search_date(params.to.as_deref(), "to")?;
+    if let (Some(from), Some(to)) = (from, to)
+        && from > to
+    {
+        return Err(AppError::InvalidInput(
+            "from must not be later than to".to_string(),
+        ));
+    }
+
+    let (offset, count) = normalize_pagination(params.offset, params.count)?;
+    let limit = count.unwrap_or(DEFAULT_SEARCH_LIMIT).min(MAX_SEARCH_LIMIT);
+
+    let matches = search::search_messages(
+        &app_state.db,
+        &company_id_bytes,
+        &MessageSearch {
+            terms: terms.clone(),
+            channel_id: channel_id.clone(),
+            chat_owner: access.chat_owner(),
+            from,
+            to,
+            offset,
+            limit,
+        },
+    )
+    .await?;
+
+    let chat_ids: HashSet<String> = matches.iter().map(|hit| hit.chat_id.clone()).collect();
+    let chat_map: HashMap<String, chats::Model> = if chat_ids.is_empty() {
+        HashMap::new()
+    } else {
+        let mut chat_query = chats::Entity::find()
+            .inner_join(channels::Entity)
+            .filter(channels::Column::CompanyId.eq(company_id_bytes.clone()))
+            .filter(chats::Column::Id.is_in(chat_ids));
+        if let Some(owner) = access.chat_owner() {
+            chat_query = chat_query.filter(owned_by(&owner));
+        }
+        chat_query
+            .all(&app_state.db)
+            .await?
+            .into_iter()
+            .map(|chat| (chat.id.clone(), chat))
+            .collect()
+    };
+
+    let mut message_hits = Vec::with_capacity(matches.len());
+    for hit in matches {
+        let Some(chat) = chat_map.get(hit.chat_id.as_str()) else {
+            continue;
+        };
+        message_hits.push(MessageSearchHit {
+            message_id: uuid_bytes_to_string(&hit.message_id)?,
+            chat_name: chat.name.clone(),
+            channel_id: uuid_bytes_to_string(&chat.channel_id)?,
+            chat_id: hit.chat_id,
+            snippet: search::highlight_snippet(&hit.body, &terms),
+            score: hit.score,
+            created_at: hit.created_at.to_rfc3339(),
+        });
+    }
+
+    let client_hits = if offset == 0 {
+        search_client_chats(
+            &app_state,
+            &company_id_bytes,
+            &params.q,
+            &terms,
+            channel_id.as_deref(),
+            access.chat_owner().as_deref(),
+            limit,
+        )
+        .await?
+    } else {
+        Vec::new()
+    };
+
+    Ok(HttpResponse::Ok().json(ChatSearchResponse {
+        query: params.q,
+        messages: message_hits,
+        clients: client_hits,
+    }))
+}
+
 pub fn init_routes(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("/chats")
             .service(get_chat_previews)
+            .service(get_unread_summary)
+            .service(search_chats)
             .service(get_chat)
             .service(get_chat_messages)
-            .service(send_chat_message),
+            .service(send_chat_message)
+            .service(mark_chat_read)
+            .service(assign_chat),
     );
 }
 
+async fn publish_chat_event(
+    app_state: &AppState,
+    company_uuid: &Uuid,
+    chat_id: &str,
+    kind: EventKind,
+    payload: JsonValue,
+) {
+    outgoing_webhooks::publish(
+        &app_state.db,
+        &app_state.events,
+        company_uuid,
+        Some(chat_id.to_string()),
+        kind,
+        payload,
+    )
+    .await;
+}
+
 struct ChatRecord {
     chat: chats::Model,
     client: Option<clients::Model>,
     channel: Option<channels::Model>,
 }
 
-async fn load_company_chats(
-    company_id_bytes: &[u8],
-    app_state: &web::Data<AppState>,
-    filter: Option<String>,
-) -> Result<Vec<ChatRecord>, AppError> {
-    // TODO: Временно возвращаем все чаты, позже добавим правильную фильтрацию по компании
-    log::warn!(
-        "load_company_chats: Returning ALL chats (company filtering temporarily disabled)"
-    );
-    
-    // Load all chats
-    let mut query = chats::Entity::find();
-
-    if let Some(filter) = filter.filter(|value| !value.trim().is_empty()) {
-        let pattern = format!("%{}%", filter.trim());
-        query = query.filter(Expr::col(chats::Column::Name).ilike(pattern));
-    }
-
-    let chats_list = query
-        .find_with_related(channels::Entity)
-        .all(&app_state.db)
-        .await?;
-
-    // Load all clients for the company
-    let company_clients = clients::Entity::find()
-        .filter(clients::Column::CompanyId.eq(company_id_bytes.to_vec()))
-        .all(&app_state.db)
-        .await?;
-
-    let client_map: HashMap<Vec<u8>, clients::Model> = company_clients
-        .into_iter()
-        .map(|c| (c.id.clone(), c))
-        .collect();
-
-    Ok(chats_list
-        .into_iter()
-        .map(|(chat, channels)| {
-            let client = chat.client_id.as_ref().and_then(|id| client_map.get(id).cloned());
-            let channel = channels.into_iter().next();
-            ChatRecord {
-                chat,
-                client,
-                channel,
-            }
-        })
-        .collect())
-}
-
+/// Loads a chat of the company; chats of other companies (or, with
+/// `chat_owner`, of clients another user is responsible for) are reported as
+/// not found.
 async fn load_single_chat(
     company_id_bytes: &[u8],
     app_state: &web::Data<AppState>,
     chat_uuid: &Uuid,
+    chat_owner: Option<&[u8]>,
 ) -> Result<ChatRecord, AppError> {
-    let chat_id_str = chat_uuid.to_string();
+    let mut query = chats::Entity::find_by_id(chat_uuid.to_string())
+        .find_also_related(channels::Entity)
+        .filter(channels::Column::CompanyId.eq(company_id_bytes.to_vec()));
+    if let Some(owner) = chat_owner {
+        query = query.filter(owned_by(owner));
+    }
 
-    let chat = chats::Entity::find_by_id(chat_id_str.clone())
+    let (chat, channel) = query
         .one(&app_state.db)
         .await?
         .ok_or_else(|| AppError::NotFound("Chat not found".to_string()))?;
 
-    // Load client and verify it belongs to the company
-    let client = if let Some(ref client_id) = chat.client_id {
-        let client_opt = clients::Entity::find_by_id(client_id.clone())
-            .one(&app_state.db)
-            .await?;
-        
-        if let Some(ref client_model) = client_opt {
-            if client_model.company_id.as_ref() != Some(&company_id_bytes.to_vec()) {
-                return Err(AppError::NotFound("Chat not found".to_string()));
-            }
+    let client = match &chat.client_id {
+        Some(client_id) => {
+            clients::Entity::find_by_id(client_id.clone())
+                .one(&app_state.db)
+                .await?
         }
-        client_opt
-    } else {
-        None
+        None => None,
     };
 
-    // Load channel
-    let channel = channels::Entity::find_by_id(chat.channel_id.clone())
-        .one(&app_state.db)
-        .await?;
-
     Ok(ChatRecord {
         chat,
         client,
@@ -438,49 +796,127 @@ async fn load_single_chat(
     })
 }
 
-async fn load_last_message_metadata(
+/// Chats of clients the user is responsible for.
+fn owned_by(owner: &[u8]) -> SimpleExpr {
+    Expr::col((chats::Entity, chats::Column::ClientId)).in_subquery(
+        Query::select()
+            .column(clients::Column::Id)
+            .from(clients::Entity)
+            .and_where(clients::Column::ResponsibleUserId.eq(owner.to_vec()))
+            .to_owned(),
+    )
+}
+
+/// Resolves a user of the company.
+async fn resolve_company_user(
+    company_id_bytes: &[u8],
     app_state: &web::Data<AppState>,
-    chat_ids: &[String],
-) -> Result<HashMap<String, DateTimeUtc>, AppError> {
-    if chat_ids.is_empty() {
-        return Ok(HashMap::new());
-    }
+    raw_user_id: &str,
+) -> Result<Vec<u8>, AppError> {
+    let user_uuid = Uuid::parse_str(raw_user_id)
+        .map_err(|_| AppError::InvalidInput("userId must be a valid UUID".to_string()))?;
+    let user_id_bytes = uuid_to_bytes(&user_uuid);
 
-    let metas = messages::Entity::find()
-        .filter(messages::Column::ChatId.is_in(chat_ids.iter().cloned().collect::<Vec<_>>()))
-        .select_only()
-        .column(messages::Column::ChatId)
-        .column_as(
-            Expr::col(messages::Column::CreatedAt).max(),
-            "last_created_at",
+    company_users::Entity::find_by_id((company_id_bytes.to_vec(), user_id_bytes.clone()))
+        .one(&app_state.db)
+        .await?
+        .ok_or_else(|| AppError::NotFound("User not found in company".to_string()))?;
+
+    Ok(user_id_bytes)
+}
+
+/// Moves the user's read marker forward; an older position never overwrites a newer one.
+///
+/// The marker row is created if missing and then only updated when the new
+/// position is later, so concurrent requests neither collide on the key nor
+/// move the marker back.
+async fn save_read_marker(
+    app_state: &web::Data<AppState>,
+    chat_id: &str,
+    user_id_bytes: &[u8],
+    message_id: Option<Vec<u8>>,
+    read_at: DateTimeUtc,
+) -> Result<chat_read_markers::Model, AppError> {
+    let now = Utc::now();
+    chat_read_markers::Entity::insert(chat_read_markers::ActiveModel {
+        chat_id: Set(chat_id.to_string()),
+        user_id: Set(user_id_bytes.to_vec()),
+        last_read_message_id: Set(message_id.clone()),
+        last_read_at: Set(read_at),
+        updated_at: Set(now),
+    })
+    .on_conflict(
+        OnConflict::columns([
+            chat_read_markers::Column::ChatId,
+            chat_read_markers::Column::UserId,
+        ])
+        .do_nothing()
+        .to_owned(),
+    )
+    .exec_without_returning(&app_state.db)
+    .await?;
+
+    chat_read_markers::Entity::update_many()
+        .col_expr(
+            chat_read_markers::Column::LastReadMessageId,
+            Expr::value(message_id),
         )
-        .group_by(messages::Column::ChatId)
-        .into_model::<LastMessageMeta>()
-        .all(&app_state.db)
+        .col_expr(chat_read_markers::Column::LastReadAt, Expr::value(read_at))
+        .col_expr(chat_read_markers::Column::UpdatedAt, Expr::value(now))
+        .filter(chat_read_markers::Column::ChatId.eq(chat_id))
+        .filter(chat_read_markers::Column::UserId.eq(user_id_bytes.to_vec()))
+        .filter(chat_read_markers::Column::LastReadAt.lt(read_at))
+        .exec(&app_state.db)
         .await?;
 
-    Ok(metas
-        .into_iter()
-        .filter_map(|meta| meta.last_created_at.map(|ts| (meta.chat_id, ts)))
-        .collect())
+    chat_read_markers::Entity::find_by_id((chat_id.to_string(), user_id_bytes.to_vec()))
+        .one(&app_state.db)
+        .await?
+        .ok_or(AppError::Internal)
 }
 
-async fn load_inbound_counts(
+/// Counts inbound messages received after the reader's marker, per chat
+/// matching `scope` (a condition on messages, chats or their channels).
+async fn load_unread_counts(
     app_state: &web::Data<AppState>,
-    chat_ids: &[String],
+    scope: Condition,
+    reader_id: &[u8],
 ) -> Result<HashMap<String, i64>, AppError> {
-    if chat_ids.is_empty() {
-        return Ok(HashMap::new());
-    }
+    let reader_id = reader_id.to_vec();
+    let reader_marker =
+        chat_read_markers::Relation::Chats
+            .def()
+            .rev()
+            .on_condition(move |_, marker| {
+                Expr::col((marker, chat_read_markers::Column::UserId))
+                    .eq(reader_id.clone())
+                    .into_condition()
+            });
 
     let counts = messages::Entity::find()
-        .filter(messages::Column::ChatId.is_in(chat_ids.iter().cloned().collect::<Vec<_>>()))
+        .inner_join(chats::Entity)
+        .join(JoinType::InnerJoin, chats::Relation::Channels.def())
+        .join(JoinType::LeftJoin, reader_marker)
+        .filter(scope)
         .filter(messages::Column::IsInbound.eq(1))
+        .filter(
+            Condition::any()
+                .add(chat_read_markers::Column::LastReadAt.is_null())
+                .add(
+                    Expr::col((messages::Entity, messages::Column::CreatedAt)).gt(Expr::col((
+                        chat_read_markers::Entity,
+                        chat_read_markers::Column::LastReadAt,
+                    ))),
+                ),
+        )
         .select_only()
         .column(messages::Column::ChatId)
-        .column_as(Expr::col(messages::Column::Id).count(), "unread_count")
+        .column_as(
+            Expr::col((messages::Entity, messages::Column::Id)).count(),
+            "unread_count",
+        )
         .group_by(messages::Column::ChatId)
-        .into_model::<InboundCountMeta>()
+        .into_model::<UnreadCountMeta>()
         .all(&app_state.db)
         .await?;
 
@@ -553,6 +989,67 @@ fn build_chat_preview(
     Ok(preview)
 }
 
+fn build_preview_from_row(
+    row: PreviewRow,
+    last_message: Option<LastMessage>,
+) -> Result<ChatPreview, AppError> {
+    let client = match (&row.client_id, row.client_name) {
+        (Some(client_id), Some(name)) => Some(ClientSummary {
+            id: uuid_bytes_to_string(client_id)?,
+            name,
+            image_url: None,
+        }),
+        _ => None,
+    };
+
+    let assignee = match &row.assignee_id {
+        Some(assignee_id) => Some(AssigneeSummary {
+            id: uuid_bytes_to_string(assignee_id)?,
+            name: row.assignee_name.unwrap_or_else(|| "Unknown".to_string()),
+            image_url: None,
+            role: row.assignee_role.unwrap_or_else(|| "employee".to_string()),
+        }),
+        None => None,
+    };
+
+    let last_message = match last_message {
+        Some(last) => {
+            let sender = match (&last.author_id, &client) {
+                (Some(_), _) => MessageSender {
+                    name: last.author_name.unwrap_or_else(|| "Unknown".to_string()),
+                    image_url: None,
+                },
+                (None, Some(client)) => MessageSender {
+                    name: client.name.clone(),
+                    image_url: client.image_url.clone(),
+                },
+                (None, None) => MessageSender {
+                    name: "Unknown".to_string(),
+                    image_url: None,
+                },
+            };
+            Some(build_message_view(&last.message, sender)?)
+        }
+        None => None,
+    };
+
+    Ok(ChatPreview {
+        id: row.chat_id,
+        unread_count: row.unread_count,
+        channel: ChannelSummary {
+            id: uuid_bytes_to_string(&row.channel_id)?,
+            transport: Some(row.transport),
+        },
+        last_message,
+        chat_info: ChatInfoSummary {
+            name: row.chat_name,
+            image_url: None,
+        },
+        client,
+        assignee,
+    })
+}
+
 fn build_assignee_summary(
     client: Option<&clients::Model>,
     user_map: &HashMap<Vec<u8>, users::Model>,
@@ -576,12 +1073,257 @@ fn build_assignee_summary(
     Ok(None)
 }
 
-fn assigned_to_bot(preview: &ChatPreview) -> bool {
-    preview
-        .assignee
-        .as_ref()
-        .map(|assignee| assignee.role.eq_ignore_ascii_case("bot"))
-        .unwrap_or(false)
+/// Chats whose client name contains every term or whose phone contains the
+/// digits of the query, best matches first.
+async fn search_client_chats(
+    app_state: &web::Data<AppState>,
+    company_id_bytes: &[u8],
+    raw_query: &str,
+    terms: &[String],
+    channel_id: Option<&[u8]>,
+    chat_owner: Option<&[u8]>,
+    limit: u64,
+) -> Result<Vec<ClientSearchHit>, AppError> {
+    let mut name_condition = Condition::all();
+    for term in terms {
+        name_condition = name_condition.add(
+            Expr::expr(Func::lower(Expr::col((
+                clients::Entity,
+                clients::Column::FullName,
+            ))))
+            .like(search::contains_pattern(term)),
+        );
+    }
+
+    let digits: String = raw_query.chars().filter(|c| c.is_ascii_digit()).collect();
+    let search_phone = digits.len() >= MIN_PHONE_SEARCH_DIGITS;
+    let mut condition = Condition::any().add(name_condition);
+    if search_phone {
+        condition = condition.add(clients::Column::Phone.contains(digits.as_str()));
+    }
+
+    if let Some(owner) = chat_owner {
+        condition = Condition::all()
+            .add(condition)
+            .add(clients::Column::ResponsibleUserId.eq(owner.to_vec()));
+    }
+
+    let matched_clients: HashMap<Vec<u8>, clients::Model> = clients::Entity::find()
+        .filter(clients::Column::CompanyId.eq(company_id_bytes.to_vec()))
+        .filter(condition)
+        .limit(limit)
+        .all(&app_state.db)
+        .await?
+        .into_iter()
+        .map(|client| (client.id.clone(), client))
+        .collect();
+    if matched_clients.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut chat_query = chats::Entity::find()
+        .inner_join(channels::Entity)
+        .filter(channels::Column::CompanyId.eq(company_id_bytes.to_vec()))
+        .filter(chats::Column::ClientId.is_in(matched_clients.keys().cloned().collect::<Vec<_>>()));
+    if let Some(channel_id) = channel_id {
+        chat_query = chat_query.filter(chats::Column::ChannelId.eq(channel_id.to_vec()));
+    }
+    let chat_list = chat_query.all(&app_state.db).await?;
+
+    let first_term = terms.first().map(String::as_str).unwrap_or_default();
+    let mut hits = Vec::new();
+    for chat in chat_list {
+        let Some(client) = chat
+            .client_id
+            .as_ref()
+            .and_then(|client_id| matched_clients.get(client_id))
+        else {
+            continue;
+        };
+
+        let phone = client.phone.as_deref().unwrap_or_default();
+        let (matched_field, snippet, score) = if search_phone && phone.contains(digits.as_str()) {
+            let exact = phone.trim_start_matches('+') == digits;
+            (
+                "phone",
+                search::highlight_snippet(phone, std::slice::from_ref(&digits)),
+                if exact { 1.0 } else { 0.75 },
+            )
+        } else {
+            let prefix = client.full_name.to_lowercase().starts_with(first_term);
+            (
+                "name",
+                search::highlight_snippet(&client.full_name, terms),
+                if prefix { 0.6 } else { 0.4 },
+            )
+        };
+
+        hits.push(ClientSearchHit {
+            chat_id: chat.id.clone(),
+            chat_name: chat.name.clone(),
+            channel_id: uuid_bytes_to_string(&chat.channel_id)?,
+            client: ClientSummary {
+                id: uuid_bytes_to_string(&client.id)?,
+                name: client.full_name.clone(),
+                image_url: None,
+            },
+            matched_field: matched_field.to_string(),
+            snippet,
+            score,
+        });
+    }
+
+    hits.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then_with(|| a.chat_name.cmp(&b.chat_name))
+    });
+    hits.truncate(limit as usize);
+
+    Ok(hits)
+}
+
+fn parse_search_date(raw: Option<&str>, field: &str) -> Result<Option<DateTimeUtc>, AppError> {
+    raw.filter(|value| !value.trim().is_empty())
+        .map(|value| {
+            DateTime::parse_from_rfc3339(value.trim())
+                .map(|date| date.with_timezone(&Utc))
+                .map_err(|_| {
+                    AppError::InvalidInput(format!("{} must be an RFC 3339 date-time", field))
+                })
+        })
+        .transpose()
+}
+
+struct MessagePage {
+    /// Newest message first.
+    messages: Vec<messages::Model>,
+    has_more: bool,
+    has_newer: bool,
+}
+
+/// Loads one page of chat history using keyset pagination on
+/// `(created_at, id)`; the legacy `offset` mode is kept for old clients.
+async fn load_message_page(
+    app_state: &web::Data<AppState>,
+    chat_id: &str,
+    query: MessagesQuery,
+) -> Result<MessagePage, AppError> {
+    let modes = [
+        query.before.is_some(),
+        query.after.is_some(),
+        query.around.is_some(),
+        query.offset.is_some(),
+    ];
+    if modes.iter().filter(|set| **set).count() > 1 {
+        return Err(AppError::InvalidInput(
+            "Only one of before, after, around and offset can be used".to_string(),
+        ));
+    }
+
+    let (_, count) = normalize_pagination(None, query.count)?;
+    let limit = count
+        .unwrap_or(DEFAULT_MESSAGES_PAGE)
+        .min(MAX_MESSAGES_PAGE);
+    let in_chat = || messages::Entity::find().filter(messages::Column::ChatId.eq(chat_id));
+    let newest_first = |select: sea_orm::Select<messages::Entity>| {
+        select
+            .order_by_desc(messages::Column::CreatedAt)
+            .order_by_desc(messages::Column::Id)
+    };
+    let oldest_first = |select: sea_orm::Select<messages::Entity>| {
+        select
+            .order_by_asc(messages::Column::CreatedAt)
+            .order_by_asc(messages::Column::Id)
+    };
+
+    if let Some(raw) = query.around.as_deref() {
+        let anchor_uuid = Uuid::parse_str(raw)
+            .map_err(|_| AppError::InvalidInput("around must be a message UUID".to_string()))?;
+        let anchor = in_chat()
+            .filter(messages::Column::Id.eq(uuid_to_bytes(&anchor_uuid)))
+            .one(&app_state.db)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Message not found".to_string()))?;
+        let cursor = MessageCursor::from_message(&anchor);
+
+        let older_limit = (limit - 1) / 2;
+        let newer_limit = limit - 1 - older_limit;
+
+        let mut older = newest_first(in_chat().filter(cursor.older()))
+            .limit(older_limit + 1)
+            .all(&app_state.db)
+            .await?;
+        let has_more = older.len() as u64 > older_limit;
+        older.truncate(older_limit as usize);
+
+        let mut newer = oldest_first(in_chat().filter(cursor.newer()))
+            .limit(newer_limit + 1)
+            .all(&app_state.db)
+            .await?;
+        let has_newer = newer.len() as u64 > newer_limit;
+        newer.truncate(newer_limit as usize);
+        newer.reverse();
+
+        let mut page = newer;
+        page.push(anchor);
+        page.extend(older);
+
+        return Ok(MessagePage {
+            messages: page,
+            has_more,
+            has_newer,
+        });
+    }
+
+    if let Some(raw) = query.after.as_deref() {
+        let cursor = MessageCursor::decode(raw)?;
+        let mut page = oldest_first(in_chat().filter(cursor.newer()))
+            .limit(limit + 1)
+            .all(&app_state.db)
+            .await?;
+        let has_newer = page.len() as u64 > limit;
+        page.truncate(limit as usize);
+        page.reverse();
+
+        // With an empty page the cursor message itself is the older history.
+        let older = match page.last() {
+            Some(oldest) => MessageCursor::from_message(oldest).older(),
+            None => cursor.including(cursor.older()),
+        };
+        let has_more = in_chat().filter(older).one(&app_state.db).await?.is_some();
+
+        return Ok(MessagePage {
+            messages: page,
+            has_more,
+            has_newer,
+        });
+    }
+
+    let mut select = newest_first(in_chat());
+    let mut has_newer = false;
+    if let Some(raw) = query.before.as_deref() {
+        let cursor = MessageCursor::decode(raw)?;
+        has_newer = in_chat()
+            .filter(cursor.including(cursor.newer()))
+            .one(&app_state.db)
+            .await?
+            .is_some();
+        select = select.filter(cursor.older());
+    } else if let Some(offset) = query.offset {
+        has_newer = offset > 0;
+        select = select.offset(offset);
+    }
+
+    let mut page = select.limit(limit + 1).all(&app_state.db).await?;
+    let has_more = page.len() as u64 > limit;
+    page.truncate(limit as usize);
+
+    Ok(MessagePage {
+        messages: page,
+        has_more,
+        has_newer,
+    })
 }
 
 fn normalize_pagination(
@@ -599,22 +1341,6 @@ fn normalize_pagination(
     Ok((offset, count))
 }
 
-fn slice_previews(
-    previews: Vec<(ChatPreview, Option<DateTimeUtc>)>,
-    offset: u64,
-    count: Option<u64>,
-) -> Vec<(ChatPreview, Option<DateTimeUtc>)> {
-    let start = offset as usize;
-    if start >= previews.len() {
-        return Vec::new();
-    }
-
-    let end = count
-        .map(|c| usize::min(start + c as usize, previews.len()))
-        .unwrap_or(previews.len());
-    previews.into_iter().skip(start).take(end - start).collect()
-}
-
 async fn load_last_messages(
     app_state: &web::Data<AppState>,
     chat_ids: &[String],
@@ -710,10 +1436,11 @@ fn parse_message_content(value: &JsonValue) -> Vec<MessageContentItem> {
 fn extract_outgoing_content(
     message: &OutgoingMessage,
 ) -> Result<(Option<String>, Option<String>), AppError> {
+    let mut validator = Validator::new();
     let mut text: Option<String> = None;
     let mut media: Option<String> = None;
 
-    for item in &message.content {
+    for (index, item) in message.content.iter().enumerate() {
         match item.r#type.as_str() {
             "text" => {
                 let new_text = item.content.trim();
@@ -722,7 +1449,7 @@ fn extract_outgoing_content(
                 }
                 match &mut text {
                     Some(existing) => {
-                        existing.push_str("\n");
+                        existing.push('\n');
                         existing.push_str(new_text);
                     }
                     None => text = Some(new_text.to_string()),
@@ -730,26 +1457,30 @@ fn extract_outgoing_content(
             }
             "image" => {
                 if media.is_some() {
-                    return Err(AppError::InvalidInput(
-                        "Only a single image attachment is supported".to_string(),
-                    ));
+                    validator.add(
+                        format!("message.content[{}]", index),
+                        codes::TOO_MANY,
+                        "Only a single image attachment is supported",
+                    );
                 }
                 media = Some(item.content.clone());
             }
-            other => {
-                return Err(AppError::InvalidInput(format!(
-                    "Unsupported message part type: {}",
-                    other
-                )));
-            }
+            other => validator.add(
+                format!("message.content[{}].type", index),
+                codes::UNSUPPORTED,
+                format!("Unsupported message part type: {}", other),
+            ),
         }
     }
 
     if text.is_none() && media.is_none() {
-        return Err(AppError::InvalidInput(
-            "Message must contain at least text or an image".to_string(),
-        ));
+        validator.add(
+            "message.content",
+            codes::REQUIRED,
+            "Message must contain at least text or an image",
+        );
     }
+    validator.finish()?;
 
     Ok((text, media))
 }
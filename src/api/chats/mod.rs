@@ -1,15 +1,21 @@
+pub mod cursor;
 pub mod functions;
 pub mod handlers;
+pub mod previews;
 pub mod structures;
 
 pub use handlers::{
-    __path_get_chat, __path_get_chat_messages, __path_get_chat_previews, __path_send_chat_message,
-    get_chat, get_chat_messages, get_chat_previews, init_routes, send_chat_message,
+    __path_assign_chat, __path_get_chat, __path_get_chat_messages, __path_get_chat_previews,
+    __path_get_unread_summary, __path_mark_chat_read, __path_search_chats,
+    __path_send_chat_message, get_chat, get_chat_messages, get_chat_previews, get_unread_summary,
+    init_routes, mark_chat_read, search_chats, send_chat_message,
 };
 
 pub use structures::{
-    AssigneeSummary, ChannelSummary, ChatDetails, ChatInfoSummary, ChatMessagesResponse,
-    ChatPreview, ChatPreviewList, ChatPreviewsQuery, ClientSummary, MessageContentItem,
-    MessageSender, MessageView, MessagesQuery, OutgoingMessage, SendChatMessageRequest,
-    SendChatMessageResponse,
+    AssignChatRequest, AssigneeSummary, ChannelSummary, ChatDetails, ChatInfoSummary,
+    ChatMessagesResponse, ChatPreview, ChatPreviewList, ChatPreviewsQuery, ChatReadStateResponse,
+    ChatSearchQuery, ChatSearchResponse, ChatUnreadCount, ClientSearchHit, ClientSummary,
+    MarkChatReadRequest, MessageContentItem, MessageSearchHit, MessageSender, MessageView,
+    MessagesQuery, OutgoingMessage, SendChatMessageRequest, SendChatMessageResponse,
+    UnreadSummaryResponse,
 };
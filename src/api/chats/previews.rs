@@ -0,0 +1,374 @@
+//! SQL-side chat previews.
+//!
+//! A page of previews is produced by one query: company chats are joined with
+//! their channel, client, assignee and the time of their last message (one
+//! grouped aggregate over the company's messages), ordered by that time and
+//! paginated in SQL; unread counters are computed only for the rows of the
+//! page. The last messages of the page are then loaded by a second query that
+//! picks one row per chat with `ROW_NUMBER()`.
+
+use std::collections::HashMap;
+
+use sea_orm::{
+    ConnectionTrait, DbErr, FromQueryResult, Order, QueryResult,
+    prelude::DateTimeUtc,
+    sea_query::{
+        Alias, Asterisk, Cond, Expr, Func, IntoCondition, JoinType, Query, SelectStatement,
+        SimpleExpr, SubQueryStatement, WindowStatement,
+    },
+};
+
+use crate::{
+    database::models::{channels, chat_read_markers, chats, clients, messages, users},
+    services::search,
+};
+
+const COMPANY_CHATS: &str = "company_chats";
+const LATEST_MESSAGES: &str = "latest_messages";
+const PAGE: &str = "page";
+const RANKED_MESSAGES: &str = "ranked_messages";
+const ROW_RANK: &str = "row_rank";
+/// Upper bound used when only an offset is requested; MySQL and SQLite need
+/// a LIMIT in front of an OFFSET.
+const NO_LIMIT: u64 = i64::MAX as u64;
+
+#[derive(Debug, Default)]
+pub struct PreviewQuery {
+    pub company_id: Vec<u8>,
+    /// Case-insensitive substring of the chat name.
+    pub filter: Option<String>,
+    /// `Some(true)` keeps chats assigned to a bot, `Some(false)` the others.
+    pub bot: Option<bool>,
+    /// Reader whose markers define unread counters.
+    pub reader_id: Vec<u8>,
+    /// Only chats of clients this user is responsible for.
+    pub chat_owner: Option<Vec<u8>>,
+    pub offset: u64,
+    pub limit: Option<u64>,
+}
+
+#[derive(Debug, FromQueryResult)]
+pub struct PreviewRow {
+    pub chat_id: String,
+    pub chat_name: String,
+    pub channel_id: Vec<u8>,
+    pub transport: String,
+    pub client_id: Option<Vec<u8>>,
+    pub client_name: Option<String>,
+    pub assignee_id: Option<Vec<u8>>,
+    pub assignee_name: Option<String>,
+    pub assignee_role: Option<String>,
+    pub last_created_at: Option<DateTimeUtc>,
+    pub unread_count: i64,
+}
+
+#[derive(Debug)]
+pub struct LastMessage {
+    pub message: messages::Model,
+    /// Set when the author is a known user.
+    pub author_id: Option<Vec<u8>>,
+    pub author_name: Option<String>,
+}
+
+impl FromQueryResult for LastMessage {
+    fn from_query_result(res: &QueryResult, pre: &str) -> Result<Self, DbErr> {
+        Ok(Self {
+            message: messages::Model::from_query_result(res, pre)?,
+            author_id: res.try_get(pre, "author_id")?,
+            author_name: res.try_get(pre, "author_name")?,
+        })
+    }
+}
+
+/// One page of previews, most recently active chats first; chats without
+/// messages follow, ordered by name.
+pub async fn load_preview_page<C>(db: &C, query: &PreviewQuery) -> Result<Vec<PreviewRow>, DbErr>
+where
+    C: ConnectionTrait,
+{
+    let mut page = Query::select();
+    page.column(Asterisk)
+        .from_subquery(company_chats(query), Alias::new(COMPANY_CHATS));
+    order_previews(&mut page, COMPANY_CHATS);
+    if let Some(limit) = query.limit {
+        page.limit(limit);
+    } else if query.offset > 0 {
+        page.limit(NO_LIMIT);
+    }
+    if query.offset > 0 {
+        page.offset(query.offset);
+    }
+
+    let mut select = Query::select();
+    select
+        .column((Alias::new(PAGE), Asterisk))
+        .expr_as(
+            sub_query(unread_count(&query.reader_id)),
+            Alias::new("unread_count"),
+        )
+        .from_subquery(page, Alias::new(PAGE));
+    order_previews(&mut select, PAGE);
+
+    let backend = db.get_database_backend();
+    PreviewRow::find_by_statement(backend.build(&select))
+        .all(db)
+        .await
+}
+
+/// Last message of each of the chats; ties on the time go to the higher id.
+pub async fn load_last_messages<C>(
+    db: &C,
+    chat_ids: &[String],
+) -> Result<HashMap<String, LastMessage>, DbErr>
+where
+    C: ConnectionTrait,
+{
+    if chat_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    // Only rows at the last timestamp of each chat are ranked, so the window
+    // covers a handful of index lookups rather than whole chat histories.
+    let mut latest = Query::select();
+    latest
+        .column((messages::Entity, messages::Column::ChatId))
+        .expr_as(
+            Expr::col((messages::Entity, messages::Column::CreatedAt)).max(),
+            Alias::new("last_created_at"),
+        )
+        .from(messages::Entity)
+        .and_where(
+            Expr::col((messages::Entity, messages::Column::ChatId)).is_in(chat_ids.iter().cloned()),
+        )
+        .group_by_col((messages::Entity, messages::Column::ChatId));
+
+    let mut ranked = Query::select();
+    ranked
+        .column((messages::Entity, Asterisk))
+        .expr_as(
+            Expr::col((users::Entity, users::Column::Id)),
+            Alias::new("author_id"),
+        )
+        .expr_as(
+            Expr::col((users::Entity, users::Column::Name)),
+            Alias::new("author_name"),
+        )
+        .expr_window_as(
+            Expr::cust("ROW_NUMBER()"),
+            WindowStatement::partition_by((messages::Entity, messages::Column::ChatId))
+                .order_by((messages::Entity, messages::Column::CreatedAt), Order::Desc)
+                .order_by((messages::Entity, messages::Column::Id), Order::Desc)
+                .to_owned(),
+            Alias::new(ROW_RANK),
+        )
+        .from(messages::Entity)
+        .join_subquery(
+            JoinType::InnerJoin,
+            latest,
+            Alias::new(LATEST_MESSAGES),
+            Cond::all()
+                .add(
+                    Expr::col((Alias::new(LATEST_MESSAGES), Alias::new("chat_id")))
+                        .equals((messages::Entity, messages::Column::ChatId)),
+                )
+                .add(
+                    Expr::col((Alias::new(LATEST_MESSAGES), Alias::new("last_created_at")))
+                        .equals((messages::Entity, messages::Column::CreatedAt)),
+                ),
+        )
+        .join(
+            JoinType::LeftJoin,
+            users::Entity,
+            Expr::col((users::Entity, users::Column::Id))
+                .equals((messages::Entity, messages::Column::AuthorUserId)),
+        );
+
+    let mut select = Query::select();
+    select
+        .column(Asterisk)
+        .from_subquery(ranked, Alias::new(RANKED_MESSAGES))
+        .and_where(Expr::col((Alias::new(RANKED_MESSAGES), Alias::new(ROW_RANK))).eq(1));
+
+    let backend = db.get_database_backend();
+    Ok(LastMessage::find_by_statement(backend.build(&select))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|last| (last.message.chat_id.clone(), last))
+        .collect())
+}
+
+/// Chats of the company with everything a preview shows except counters.
+fn company_chats(query: &PreviewQuery) -> SelectStatement {
+    // One grouped pass over the company's messages instead of a lookup per chat.
+    let mut company_chat_ids = Query::select();
+    company_chat_ids
+        .column((chats::Entity, chats::Column::Id))
+        .from(chats::Entity)
+        .join(
+            JoinType::InnerJoin,
+            channels::Entity,
+            Expr::col((channels::Entity, channels::Column::Id))
+                .equals((chats::Entity, chats::Column::ChannelId)),
+        )
+        .and_where(
+            Expr::col((channels::Entity, channels::Column::CompanyId)).eq(query.company_id.clone()),
+        );
+    let mut latest = Query::select();
+    latest
+        .column((messages::Entity, messages::Column::ChatId))
+        .expr_as(
+            Expr::col((messages::Entity, messages::Column::CreatedAt)).max(),
+            Alias::new("last_created_at"),
+        )
+        .from(messages::Entity)
+        .and_where(
+            Expr::col((messages::Entity, messages::Column::ChatId)).in_subquery(company_chat_ids),
+        )
+        .group_by_col((messages::Entity, messages::Column::ChatId));
+
+    let mut select = Query::select();
+    select
+        .expr_as(
+            Expr::col((chats::Entity, chats::Column::Id)),
+            Alias::new("chat_id"),
+        )
+        .expr_as(
+            Expr::col((chats::Entity, chats::Column::Name)),
+            Alias::new("chat_name"),
+        )
+        .column((chats::Entity, chats::Column::ChannelId))
+        .expr_as(
+            Expr::col((channels::Entity, channels::Column::Type)),
+            Alias::new("transport"),
+        )
+        .expr_as(
+            Expr::col((clients::Entity, clients::Column::Id)),
+            Alias::new("client_id"),
+        )
+        .expr_as(
+            Expr::col((clients::Entity, clients::Column::FullName)),
+            Alias::new("client_name"),
+        )
+        .expr_as(
+            Expr::col((users::Entity, users::Column::Id)),
+            Alias::new("assignee_id"),
+        )
+        .expr_as(
+            Expr::col((users::Entity, users::Column::Name)),
+            Alias::new("assignee_name"),
+        )
+        .expr_as(
+            Expr::col((users::Entity, users::Column::Role)),
+            Alias::new("assignee_role"),
+        )
+        .column((Alias::new(LATEST_MESSAGES), Alias::new("last_created_at")))
+        .from(chats::Entity)
+        .join(
+            JoinType::InnerJoin,
+            channels::Entity,
+            Expr::col((channels::Entity, channels::Column::Id))
+                .equals((chats::Entity, chats::Column::ChannelId)),
+        )
+        .join(
+            JoinType::LeftJoin,
+            clients::Entity,
+            Expr::col((clients::Entity, clients::Column::Id))
+                .equals((chats::Entity, chats::Column::ClientId)),
+        )
+        .join(
+            JoinType::LeftJoin,
+            users::Entity,
+            Expr::col((users::Entity, users::Column::Id))
+                .equals((clients::Entity, clients::Column::ResponsibleUserId)),
+        )
+        .join_subquery(
+            JoinType::LeftJoin,
+            latest,
+            Alias::new(LATEST_MESSAGES),
+            Expr::col((Alias::new(LATEST_MESSAGES), Alias::new("chat_id")))
+                .equals((chats::Entity, chats::Column::Id)),
+        )
+        .and_where(
+            Expr::col((channels::Entity, channels::Column::CompanyId)).eq(query.company_id.clone()),
+        );
+
+    if let Some(filter) = query
+        .filter
+        .as_deref()
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+    {
+        select.and_where(
+            Expr::expr(Func::lower(Expr::col((chats::Entity, chats::Column::Name))))
+                .like(search::contains_pattern(&filter.to_lowercase())),
+        );
+    }
+
+    if let Some(owner) = &query.chat_owner {
+        select.and_where(
+            Expr::col((clients::Entity, clients::Column::ResponsibleUserId)).eq(owner.clone()),
+        );
+    }
+
+    if let Some(bot_only) = query.bot {
+        let is_bot =
+            Expr::expr(Func::lower(Expr::col((users::Entity, users::Column::Role)))).eq("bot");
+        select.cond_where(if bot_only {
+            is_bot.into_condition()
+        } else {
+            Cond::any()
+                .add(Expr::col((users::Entity, users::Column::Role)).is_null())
+                .add(is_bot.not())
+        });
+    }
+
+    select
+}
+
+/// Inbound messages of the page chat that the reader's marker does not cover yet.
+fn unread_count(reader_id: &[u8]) -> SelectStatement {
+    let mut read = Query::select();
+    read.expr(Expr::val(1))
+        .from(chat_read_markers::Entity)
+        .and_where(
+            Expr::col((chat_read_markers::Entity, chat_read_markers::Column::ChatId))
+                .equals((messages::Entity, messages::Column::ChatId)),
+        )
+        .and_where(
+            Expr::col((
+                chat_read_markers::Entity,
+                chat_read_markers::Column::LastReadAt,
+            ))
+            .gte(Expr::col((messages::Entity, messages::Column::CreatedAt))),
+        )
+        .and_where(
+            Expr::col((chat_read_markers::Entity, chat_read_markers::Column::UserId))
+                .eq(reader_id.to_vec()),
+        );
+
+    Query::select()
+        .expr(Expr::col((messages::Entity, messages::Column::Id)).count())
+        .from(messages::Entity)
+        .and_where(
+            Expr::col((messages::Entity, messages::Column::ChatId))
+                .equals((Alias::new(PAGE), Alias::new("chat_id"))),
+        )
+        .and_where(Expr::col((messages::Entity, messages::Column::IsInbound)).eq(1))
+        .and_where(Expr::exists(read).not())
+        .to_owned()
+}
+
+/// Chats with messages first, newest activity first; the rest by name.
+fn order_previews(select: &mut SelectStatement, table: &str) {
+    let column = |name: &str| Expr::col((Alias::new(table), Alias::new(name)));
+    select
+        .order_by_expr(column("last_created_at").is_null(), Order::Asc)
+        .order_by_expr(column("last_created_at").into(), Order::Desc)
+        .order_by_expr(column("chat_name").into(), Order::Asc)
+        .order_by_expr(column("chat_id").into(), Order::Asc);
+}
+
+fn sub_query(select: SelectStatement) -> SimpleExpr {
+    SimpleExpr::SubQuery(None, Box::new(SubQueryStatement::SelectStatement(select)))
+}
@@ -87,7 +87,18 @@ pub type ChatDetails = ChatPreview;
 #[derive(Debug, Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ChatMessagesResponse {
+    /// Newest message first.
     pub data: Vec<MessageView>,
+    /// Pass as `before` to load older messages.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+    /// Pass as `after` to load newer messages.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prev_cursor: Option<String>,
+    /// There are older messages than the last one in `data`.
+    pub has_more: bool,
+    /// There are newer messages than the first one in `data`.
+    pub has_newer: bool,
 }
 
 #[derive(Debug, Deserialize, ToSchema)]
@@ -102,8 +113,15 @@ pub struct ChatPreviewsQuery {
 #[derive(Debug, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct MessagesQuery {
+    /// Deprecated: use cursors instead.
     pub offset: Option<u64>,
     pub count: Option<u64>,
+    /// Cursor from `nextCursor`: messages older than it.
+    pub before: Option<String>,
+    /// Cursor from `prevCursor`: messages newer than it.
+    pub after: Option<String>,
+    /// Message id: the message with history on both sides of it.
+    pub around: Option<String>,
 }
 
 #[derive(Debug, Deserialize, ToSchema)]
@@ -124,3 +142,91 @@ pub struct SendChatMessageResponse {
     pub id: String,
     pub created_at: String,
 }
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AssignChatRequest {
+    pub user_id: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MarkChatReadRequest {
+    /// Last message the user has read. Defaults to the latest message in the chat.
+    pub message_id: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ChatReadStateResponse {
+    pub chat_id: String,
+    pub user_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_read_message_id: Option<String>,
+    pub last_read_at: String,
+    pub unread_count: i64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ChatUnreadCount {
+    pub chat_id: String,
+    pub unread_count: i64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UnreadSummaryResponse {
+    pub total_unread: i64,
+    pub chats_with_unread: i64,
+    pub data: Vec<ChatUnreadCount>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ChatSearchQuery {
+    /// Search text; words are matched as prefixes and all of them are required.
+    pub q: String,
+    pub channel_id: Option<String>,
+    /// RFC 3339 lower bound for message dates.
+    pub from: Option<String>,
+    /// RFC 3339 upper bound for message dates.
+    pub to: Option<String>,
+    pub offset: Option<u64>,
+    pub count: Option<u64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageSearchHit {
+    pub message_id: String,
+    pub chat_id: String,
+    pub chat_name: String,
+    pub channel_id: String,
+    /// HTML-escaped excerpt with matches wrapped in `<mark>`.
+    pub snippet: String,
+    pub score: f64,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ClientSearchHit {
+    pub chat_id: String,
+    pub chat_name: String,
+    pub channel_id: String,
+    pub client: ClientSummary,
+    /// `name` or `phone`
+    pub matched_field: String,
+    /// HTML-escaped matched value with matches wrapped in `<mark>`.
+    pub snippet: String,
+    pub score: f64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ChatSearchResponse {
+    pub query: String,
+    pub messages: Vec<MessageSearchHit>,
+    pub clients: Vec<ClientSearchHit>,
+}
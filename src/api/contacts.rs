@@ -8,16 +8,24 @@ use utoipa::ToSchema;
 use uuid::Uuid;
 
 use crate::{
-    api::{self, validation},
+    api::permissions::{ManageContacts, Require, ViewChats},
+    api::{
+        self,
+        validation::{self, Validator, codes},
+    },
     app_state::AppState,
     database::models::clients,
     errors::AppError,
+    services::events::{EventKind, client_payload},
+    services::outgoing_webhooks,
     services::wazzup_api::{self, WazzupContact, WazzupContactData},
 };
 
 use api::chats::functions::uuid_bytes_to_string;
 use api::helpers::{get_company_api_key, uuid_to_bytes};
 
+const MAX_FULL_NAME_CHARS: usize = 200;
+
 #[derive(Deserialize, Serialize, ToSchema, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct UpdateContactDto {
@@ -45,6 +53,16 @@ fn parse_uuid(value: &str, field: &str) -> Result<Uuid, AppError> {
         .map_err(|_| AppError::InvalidInput(format!("{} must be a valid UUID", field)))
 }
 
+/// Hides clients of other managers from a manager limited to their own chats.
+fn check_responsible(client: &clients::Model, owner: Option<Vec<u8>>) -> Result<(), AppError> {
+    match owner {
+        Some(owner) if client.responsible_user_id != owner => {
+            Err(AppError::NotFound("Contact not found".to_string()))
+        }
+        _ => Ok(()),
+    }
+}
+
 fn wazzup_chat_from_contact(contact: &WazzupContact) -> Option<String> {
     contact
         .contact_data
@@ -79,16 +97,6 @@ fn build_contact_view(
     })
 }
 
-fn sanitize_phone_input(phone: Option<String>) -> Result<Option<String>, AppError> {
-    match phone {
-        Some(ref raw) if !raw.is_empty() => validation::sanitize_phone(raw)
-            .ok_or_else(|| AppError::InvalidInput("Invalid phone".into()))
-            .map(Some),
-        Some(_) => Ok(None),
-        None => Ok(None),
-    }
-}
-
 fn local_client_to_wazzup_contact(
     client: &clients::Model,
     override_chat: Option<&str>,
@@ -175,16 +183,18 @@ async fn sync_client_to_wazzup(
 #[get("/{companyId}")]
 async fn get_contacts(
     app_state: web::Data<AppState>,
+    access: Require<ViewChats>,
     path: web::Path<String>,
 ) -> Result<HttpResponse, AppError> {
     let company_uuid = parse_uuid(&path.into_inner(), "companyId")?;
     let company_id_bytes = uuid_to_bytes(&company_uuid);
-    let api_key = get_company_api_key(&company_uuid, &app_state.db).await?;
+    let api_key = get_company_api_key(&company_uuid, &app_state).await?;
 
-    let clients = clients::Entity::find()
-        .filter(clients::Column::CompanyId.eq(company_id_bytes))
-        .all(&app_state.db)
-        .await?;
+    let mut query = clients::Entity::find().filter(clients::Column::CompanyId.eq(company_id_bytes));
+    if let Some(owner) = access.chat_owner() {
+        query = query.filter(clients::Column::ResponsibleUserId.eq(owner));
+    }
+    let clients = query.all(&app_state.db).await?;
 
     let wazzup_response = app_state
         .wazzup_api
@@ -230,6 +240,7 @@ async fn get_contacts(
 #[get("/{companyId}/{contactId}")]
 async fn get_contact_by_id(
     app_state: web::Data<AppState>,
+    access: Require<ViewChats>,
     path: web::Path<(String, String)>,
 ) -> Result<HttpResponse, AppError> {
     let (company_raw, contact_raw) = path.into_inner();
@@ -237,7 +248,7 @@ async fn get_contact_by_id(
     let contact_uuid = parse_uuid(&contact_raw, "contactId")?;
     let company_id_bytes = uuid_to_bytes(&company_uuid);
     let contact_id_bytes = uuid_to_bytes(&contact_uuid);
-    let api_key = get_company_api_key(&company_uuid, &app_state.db).await?;
+    let api_key = get_company_api_key(&company_uuid, &app_state).await?;
 
     let client = clients::Entity::find_by_id(contact_id_bytes.clone())
         .one(&app_state.db)
@@ -254,6 +265,7 @@ async fn get_contact_by_id(
             "Contact does not belong to the company".to_string(),
         ));
     }
+    check_responsible(&client, access.chat_owner())?;
 
     let client_id = uuid_bytes_to_string(&client.id)?;
     let wazzup_contact = app_state
@@ -278,14 +290,16 @@ async fn get_contact_by_id(
     request_body = UpdateContactDto,
     responses(
         (status = 200, description = "Contact updated successfully", body = ContactWithWazzupData),
+        (status = 400, description = "Invalid fields, listed in `details`"),
         (status = 404, description = "Company or contact not found")
     )
 )]
 #[put("/{companyId}/{contactId}")]
 async fn update_contact(
     app_state: web::Data<AppState>,
+    access: Require<ManageContacts>,
     path: web::Path<(String, String)>,
-    body: web::Json<UpdateContactDto>,
+    body: validation::Json<UpdateContactDto>,
 ) -> Result<HttpResponse, AppError> {
     let (company_raw, contact_raw) = path.into_inner();
     let company_uuid = parse_uuid(&company_raw, "companyId")?;
@@ -293,7 +307,7 @@ async fn update_contact(
     let company_id_bytes = uuid_to_bytes(&company_uuid);
     let contact_id_bytes = uuid_to_bytes(&contact_uuid);
 
-    let api_key = get_company_api_key(&company_uuid, &app_state.db).await?;
+    let api_key = get_company_api_key(&company_uuid, &app_state).await?;
 
     let existing_client = clients::Entity::find_by_id(contact_id_bytes.clone())
         .one(&app_state.db)
@@ -310,39 +324,50 @@ async fn update_contact(
             "Contact does not belong to the company".to_string(),
         ));
     }
+    check_responsible(&existing_client, access.chat_owner())?;
 
     let update_data = body.into_inner();
 
-    if !validation::ensure_max_len(&update_data.full_name, 200) {
-        return Err(AppError::InvalidInput("Full name too long".into()));
-    }
-
-    if !validation::validate_email_opt(&update_data.email) {
-        return Err(AppError::InvalidInput("Invalid email format".into()));
+    let mut validator = Validator::new();
+    validator.max_len("fullName", &update_data.full_name, MAX_FULL_NAME_CHARS);
+    let email = validator.email("email", Some(update_data.email.clone()));
+    if email.is_none() {
+        validator.add("email", codes::REQUIRED, "email must not be empty");
     }
-
-    let sanitized_phone = sanitize_phone_input(update_data.phone.clone())?;
+    let sanitized_phone = validator.phone("phone", update_data.phone.clone());
+    validator.finish()?;
+    let email = email.unwrap_or_default();
 
     let email_exists = clients::Entity::find()
         .filter(clients::Column::CompanyId.eq(company_id_bytes.clone()))
-        .filter(clients::Column::Email.eq(update_data.email.clone()))
+        .filter(clients::Column::Email.eq(email.clone()))
         .filter(clients::Column::Id.ne(contact_id_bytes.clone()))
         .one(&app_state.db)
         .await?;
 
     if email_exists.is_some() {
-        return Err(AppError::InvalidInput(format!(
-            "Contact with email {} already exists",
-            update_data.email
-        )));
+        return Err(validation::field_error(
+            "email",
+            codes::DUPLICATE,
+            format!("Contact with email {} already exists", email),
+        ));
     }
 
     let mut active_client: clients::ActiveModel = existing_client.clone().into();
     active_client.full_name = Set(update_data.full_name.clone());
-    active_client.email = Set(Some(update_data.email.clone()));
+    active_client.email = Set(Some(email));
     active_client.phone = Set(sanitized_phone);
 
     let updated_client = active_client.update(&app_state.db).await?;
+    outgoing_webhooks::publish(
+        &app_state.db,
+        &app_state.events,
+        &company_uuid,
+        None,
+        EventKind::ClientUpdated,
+        client_payload(&updated_client),
+    )
+    .await;
 
     if let Err(err) = sync_client_to_wazzup(
         &updated_client,
@@ -383,6 +408,7 @@ async fn update_contact(
 #[delete("/{companyId}/{contactId}")]
 async fn delete_contact(
     app_state: web::Data<AppState>,
+    access: Require<ManageContacts>,
     path: web::Path<(String, String)>,
 ) -> Result<HttpResponse, AppError> {
     let (company_raw, contact_raw) = path.into_inner();
@@ -391,7 +417,7 @@ async fn delete_contact(
     let company_id_bytes = uuid_to_bytes(&company_uuid);
     let contact_id_bytes = uuid_to_bytes(&contact_uuid);
 
-    let api_key = get_company_api_key(&company_uuid, &app_state.db).await?;
+    let api_key = get_company_api_key(&company_uuid, &app_state).await?;
 
     let client = clients::Entity::find_by_id(contact_id_bytes.clone())
         .one(&app_state.db)
@@ -408,6 +434,7 @@ async fn delete_contact(
             "Contact does not belong to the company".to_string(),
         ));
     }
+    check_responsible(&client, access.chat_owner())?;
 
     let client_id = uuid_bytes_to_string(&client.id)?;
 
@@ -426,6 +453,15 @@ async fn delete_contact(
     clients::Entity::delete_by_id(contact_id_bytes)
         .exec(&app_state.db)
         .await?;
+    outgoing_webhooks::publish(
+        &app_state.db,
+        &app_state.events,
+        &company_uuid,
+        None,
+        EventKind::ClientDeleted,
+        client_payload(&client),
+    )
+    .await;
 
     Ok(HttpResponse::NoContent().finish())
 }
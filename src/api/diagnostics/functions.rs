@@ -0,0 +1,132 @@
+use chrono::Utc;
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+use uuid::Uuid;
+
+use crate::{
+    api::{admin::ApiKeyStatus, helpers::uuid_to_bytes},
+    app_state::AppState,
+    database::models::{channels, companies},
+    errors::AppError,
+};
+
+use super::structures::{ChannelDiagnostics, CompanyDiagnostics, WebhookDiagnostics, WebhookState};
+
+/// Проверяет ключ API, вебхуки и каналы компании; `expected_uri` — адрес,
+/// на который вебхуки должны быть подписаны
+pub async fn company_diagnostics(
+    app_state: &AppState,
+    company_uuid: &Uuid,
+    expected_uri: String,
+) -> Result<CompanyDiagnostics, AppError> {
+    let company = companies::Entity::find_by_id(uuid_to_bytes(company_uuid))
+        .one(&app_state.db)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Company not found".to_string()))?;
+
+    let mut webhooks = WebhookDiagnostics {
+        state: WebhookState::Unknown,
+        expected_uri,
+        registered_uri: None,
+        error: None,
+    };
+    let mut channels = Vec::new();
+    let mut channels_error = None;
+
+    let api_key = app_state.api_keys.api_key_of(&company)?;
+    let api_key_status = match &api_key {
+        None => ApiKeyStatus::Missing,
+        Some(key) => match app_state.wazzup_api.check_api_key(key).await {
+            Ok(true) => ApiKeyStatus::Valid,
+            Ok(false) => ApiKeyStatus::Invalid,
+            Err(err) => {
+                log::warn!("Unable to check the Wazzup API key: {}", err);
+                ApiKeyStatus::Unknown
+            }
+        },
+    };
+
+    match (api_key_status, api_key) {
+        (ApiKeyStatus::Valid, Some(key)) => {
+            check_webhooks(app_state, &key, &mut webhooks).await;
+            match list_channels(app_state, &key, &company.id).await {
+                Ok(list) => channels = list,
+                Err(err) => channels_error = Some(err.to_string()),
+            }
+        }
+        (status, _) => {
+            let reason = match status {
+                ApiKeyStatus::Missing => "Company has no Wazzup API key",
+                ApiKeyStatus::Invalid => "Wazzup rejected the API key",
+                _ => "Wazzup could not be reached",
+            };
+            webhooks.error = Some(reason.to_string());
+            channels_error = Some(reason.to_string());
+        }
+    }
+
+    let now = Utc::now();
+    Ok(CompanyDiagnostics {
+        company_id: company_uuid.to_string(),
+        is_active: company.is_active != Some(0),
+        api_key: api_key_status,
+        webhooks,
+        channels,
+        channels_error,
+        last_webhook_at: company.last_webhook_at.map(|value| value.to_rfc3339()),
+        seconds_since_last_webhook: company
+            .last_webhook_at
+            .map(|value| (now - value).num_seconds().max(0)),
+    })
+}
+
+async fn check_webhooks(app_state: &AppState, api_key: &str, webhooks: &mut WebhookDiagnostics) {
+    match app_state.wazzup_api.get_webhooks(api_key).await {
+        Ok(settings) => {
+            let registered = settings.webhooks_uri.filter(|uri| !uri.trim().is_empty());
+            webhooks.state = match &registered {
+                None => WebhookState::NotRegistered,
+                Some(uri) if same_uri(uri, &webhooks.expected_uri) => WebhookState::Registered,
+                Some(_) => WebhookState::Mismatch,
+            };
+            webhooks.registered_uri = registered;
+        }
+        Err(err) => webhooks.error = Some(err.to_string()),
+    }
+}
+
+async fn list_channels(
+    app_state: &AppState,
+    api_key: &str,
+    company_id: &[u8],
+) -> Result<Vec<ChannelDiagnostics>, AppError> {
+    let response = app_state.wazzup_api.get_channels(api_key).await?;
+    let stored: Vec<String> = channels::Entity::find()
+        .filter(channels::Column::CompanyId.eq(company_id.to_vec()))
+        .all(&app_state.db)
+        .await?
+        .into_iter()
+        .filter_map(|channel| Uuid::from_slice(&channel.id).ok())
+        .map(|id| id.to_string())
+        .collect();
+
+    Ok(response
+        .channels
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|channel| !channel.deleted)
+        .filter_map(|channel| {
+            let channel_id = channel.guid?;
+            Some(ChannelDiagnostics {
+                known: stored.iter().any(|id| id.eq_ignore_ascii_case(&channel_id)),
+                channel_id,
+                transport: channel.transport,
+                name: channel.name,
+                state: channel.state,
+            })
+        })
+        .collect())
+}
+
+fn same_uri(left: &str, right: &str) -> bool {
+    left.trim().trim_end_matches('/') == right.trim().trim_end_matches('/')
+}
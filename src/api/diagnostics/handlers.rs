@@ -0,0 +1,45 @@
+use actix_web::{HttpRequest, HttpResponse, get, web};
+use uuid::Uuid;
+
+use crate::{
+    api::{
+        permissions::{Admin, Require},
+        webhooks::functions::build_webhook_uri,
+    },
+    app_state::AppState,
+    errors::AppError,
+};
+
+use super::functions::company_diagnostics;
+use super::structures::CompanyDiagnostics;
+
+/// Диагностика интеграции компании: ключ API, вебхуки, каналы, последний вебхук
+#[utoipa::path(
+    get,
+    path = "/api/diagnostics/{companyId}",
+    tag = "Diagnostics",
+    params(("companyId" = String, Path, description = "Company UUID")),
+    responses(
+        (status = 200, description = "Integration diagnostics", body = CompanyDiagnostics),
+        (status = 403, description = "Company administrator required"),
+        (status = 404, description = "Company not found"),
+    )
+)]
+#[get("/{companyId}")]
+pub async fn get_company_diagnostics(
+    app_state: web::Data<AppState>,
+    _access: Require<Admin>,
+    req: HttpRequest,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let company_uuid = Uuid::parse_str(&path.into_inner())
+        .map_err(|_| AppError::InvalidInput("companyId must be a valid UUID".to_string()))?;
+    let expected_uri = build_webhook_uri(&app_state, &req, &company_uuid);
+    let diagnostics = company_diagnostics(&app_state, &company_uuid, expected_uri).await?;
+
+    Ok(HttpResponse::Ok().json(diagnostics))
+}
+
+pub fn init_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::scope("/diagnostics").service(get_company_diagnostics));
+}
@@ -0,0 +1,9 @@
+pub mod functions;
+pub mod handlers;
+pub mod structures;
+
+pub use handlers::{__path_get_company_diagnostics, init_routes};
+
+pub use structures::{ChannelDiagnostics, CompanyDiagnostics, WebhookDiagnostics, WebhookState};
+
+pub use functions::company_diagnostics;
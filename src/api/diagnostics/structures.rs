@@ -0,0 +1,60 @@
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::api::admin::ApiKeyStatus;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum WebhookState {
+    /// Wazzup sends webhooks to this service.
+    Registered,
+    /// Wazzup sends webhooks somewhere else.
+    Mismatch,
+    NotRegistered,
+    /// The settings could not be read from Wazzup.
+    Unknown,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookDiagnostics {
+    pub state: WebhookState,
+    /// URI Wazzup should call for this company.
+    pub expected_uri: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub registered_uri: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ChannelDiagnostics {
+    pub channel_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transport: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Channel state reported by Wazzup, e.g. `active` or `qr`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub state: Option<String>,
+    /// Whether the channel is stored for this company.
+    pub known: bool,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CompanyDiagnostics {
+    pub company_id: String,
+    pub is_active: bool,
+    pub api_key: ApiKeyStatus,
+    pub webhooks: WebhookDiagnostics,
+    /// Channels of the Wazzup account; empty when they could not be read.
+    pub channels: Vec<ChannelDiagnostics>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channels_error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_webhook_at: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seconds_since_last_webhook: Option<i64>,
+}
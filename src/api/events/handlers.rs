@@ -0,0 +1,286 @@
+use std::collections::{HashSet, VecDeque};
+use std::sync::Arc;
+use std::time::Duration;
+
+use actix_web::{HttpRequest, HttpResponse, get, post, web, web::Bytes};
+use futures_util::stream;
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::time::{Instant, Interval, interval_at};
+use uuid::Uuid;
+
+use crate::{
+    api::helpers::uuid_to_bytes,
+    api::permissions::{Require, ViewChats},
+    api::validation,
+    app_state::AppState,
+    database::models::{chats, clients, companies},
+    errors::AppError,
+    services::{
+        events::{EventKind, ServiceEvent},
+        tokens,
+    },
+};
+
+use super::structures::{EventStreamQuery, StreamTicketResponse};
+
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+const RECONNECT_DELAY_MS: u64 = 3000;
+
+/// Поток событий компании в формате Server-Sent Events
+#[utoipa::path(
+    get,
+    path = "/api/events/{companyId}",
+    tag = "Events",
+    params(
+        ("companyId" = String, Path, description = "Company UUID"),
+        ("Last-Event-ID" = Option<String>, Header, description = "Id of the last event received before reconnecting"),
+        ("ticket" = Option<String>, Query, description = "Stream ticket from `POST /api/events/{companyId}/ticket`, for clients that cannot send the `Authorization` header"),
+        EventStreamQuery
+    ),
+    responses(
+        (status = 200, description = "Event stream", body = ServiceEvent, content_type = "text/event-stream"),
+        (status = 404, description = "Company not found"),
+    )
+)]
+#[get("/{companyId}")]
+pub async fn stream_events(
+    app_state: web::Data<AppState>,
+    access: Require<ViewChats>,
+    req: HttpRequest,
+    path: web::Path<String>,
+    query: validation::Query<EventStreamQuery>,
+) -> Result<HttpResponse, AppError> {
+    let company_uuid = Uuid::parse_str(&path.into_inner())
+        .map_err(|_| AppError::InvalidInput("companyId must be a valid UUID".to_string()))?;
+
+    companies::Entity::find_by_id(uuid_to_bytes(&company_uuid))
+        .one(&app_state.db)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Company not found".to_string()))?;
+
+    let params = query.into_inner();
+    let filter = EventFilter {
+        company_id: company_uuid.to_string(),
+        chat_ids: parse_chat_filter(params.chat_id.as_deref()),
+        chat_owner: access.chat_owner(),
+    };
+
+    let last_event_id = req
+        .headers()
+        .get("Last-Event-ID")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .or(params.last_event_id);
+
+    // Subscribe before reading the history so nothing published in between is lost;
+    // duplicates are dropped by comparing ids.
+    let receiver = app_state.events.subscribe();
+
+    let mut pending = VecDeque::new();
+    pending.push_back(Bytes::from(format!("retry: {}\n\n", RECONNECT_DELAY_MS)));
+    let mut last_sent_id = 0;
+
+    if let Some(last_event_id) = last_event_id {
+        let replay = app_state
+            .events
+            .replay_since(&filter.company_id, last_event_id);
+        if replay.reset {
+            pending.push_back(reset_frame());
+        } else {
+            last_sent_id = last_event_id;
+        }
+        for event in &replay.events {
+            if filter.matches(&app_state.db, event).await {
+                pending.push_back(event_frame(event));
+                last_sent_id = event.id;
+            }
+        }
+    }
+
+    let state = StreamState {
+        db: app_state.db.clone(),
+        receiver,
+        filter,
+        last_sent_id,
+        pending,
+        keep_alive: interval_at(Instant::now() + KEEP_ALIVE_INTERVAL, KEEP_ALIVE_INTERVAL),
+    };
+
+    let body = stream::unfold(state, |mut state| async move {
+        let frame = next_frame(&mut state).await?;
+        Some((Ok::<_, actix_web::Error>(frame), state))
+    });
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(("Cache-Control", "no-cache"))
+        .insert_header(("X-Accel-Buffering", "no"))
+        .streaming(body))
+}
+
+/// Выдаёт короткоживущий билет для подключения к потоку событий из `EventSource`
+#[utoipa::path(
+    post,
+    path = "/api/events/{companyId}/ticket",
+    tag = "Events",
+    params(
+        ("companyId" = String, Path, description = "Company UUID"),
+    ),
+    responses(
+        (status = 200, description = "Ticket to pass as `?ticket=` to the event stream", body = StreamTicketResponse),
+    )
+)]
+#[post("/{companyId}/ticket")]
+pub async fn create_stream_ticket(
+    app_state: web::Data<AppState>,
+    access: Require<ViewChats>,
+) -> Result<HttpResponse, AppError> {
+    let (ticket, expires_at) =
+        tokens::issue_stream_ticket(&app_state.db, &app_state.api_keys, &access.principal).await?;
+
+    Ok(HttpResponse::Ok().json(StreamTicketResponse {
+        ticket,
+        expires_at: expires_at.to_rfc3339(),
+    }))
+}
+
+pub fn init_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/events")
+            .service(create_stream_ticket)
+            .service(stream_events),
+    );
+}
+
+struct EventFilter {
+    company_id: String,
+    chat_ids: Option<HashSet<String>>,
+    /// Only events of chats and clients this user is responsible for.
+    chat_owner: Option<Vec<u8>>,
+}
+
+impl EventFilter {
+    async fn matches(&self, db: &DatabaseConnection, event: &ServiceEvent) -> bool {
+        if event.company_id != self.company_id {
+            return false;
+        }
+
+        if let (Some(chat_ids), Some(chat_id)) = (&self.chat_ids, &event.chat_id)
+            && !chat_ids.contains(chat_id)
+        {
+            return false;
+        }
+
+        match &self.chat_owner {
+            Some(owner) => is_owned_by(db, event, owner).await,
+            None => true,
+        }
+    }
+}
+
+/// Whether a restricted manager may see `event`: client events by the
+/// responsible user in the payload, chat events by the chat's client. A chat
+/// taken away from the manager is still announced to them.
+async fn is_owned_by(db: &DatabaseConnection, event: &ServiceEvent, owner: &[u8]) -> bool {
+    let owner_id = Uuid::from_slice(owner).unwrap_or_default().to_string();
+    let payload_user = |field: &str| event.payload.get(field).and_then(|value| value.as_str());
+
+    match event.r#type {
+        EventKind::ClientCreated | EventKind::ClientUpdated | EventKind::ClientDeleted => {
+            return payload_user("responsibleUserId") == Some(owner_id.as_str());
+        }
+        EventKind::ChatAssigned
+            if payload_user("previousAssigneeId") == Some(owner_id.as_str()) =>
+        {
+            return true;
+        }
+        _ => {}
+    }
+
+    let Some(chat_id) = &event.chat_id else {
+        return true;
+    };
+    let owned = chats::Entity::find_by_id(chat_id.clone())
+        .inner_join(clients::Entity)
+        .filter(clients::Column::ResponsibleUserId.eq(owner.to_vec()))
+        .one(db)
+        .await;
+    match owned {
+        Ok(chat) => chat.is_some(),
+        Err(err) => {
+            log::warn!("Could not check the owner of chat {}: {}", chat_id, err);
+            false
+        }
+    }
+}
+
+struct StreamState {
+    db: DatabaseConnection,
+    receiver: broadcast::Receiver<Arc<ServiceEvent>>,
+    filter: EventFilter,
+    last_sent_id: u64,
+    pending: VecDeque<Bytes>,
+    keep_alive: Interval,
+}
+
+async fn next_frame(state: &mut StreamState) -> Option<Bytes> {
+    if let Some(frame) = state.pending.pop_front() {
+        return Some(frame);
+    }
+
+    loop {
+        tokio::select! {
+            received = state.receiver.recv() => match received {
+                Ok(event) => {
+                    if event.id > state.last_sent_id && state.filter.matches(&state.db, &event).await {
+                        state.last_sent_id = event.id;
+                        return Some(event_frame(&event));
+                    }
+                }
+                Err(RecvError::Lagged(skipped)) => {
+                    log::warn!(
+                        "Event stream for company {} lagged behind by {} event(s)",
+                        state.filter.company_id,
+                        skipped
+                    );
+                    return Some(reset_frame());
+                }
+                Err(RecvError::Closed) => return None,
+            },
+            _ = state.keep_alive.tick() => {
+                return Some(Bytes::from_static(b": keep-alive\n\n"));
+            }
+        }
+    }
+}
+
+fn parse_chat_filter(raw: Option<&str>) -> Option<HashSet<String>> {
+    let chat_ids: HashSet<String> = raw?
+        .split(',')
+        .map(|value| value.trim())
+        .filter(|value| !value.is_empty())
+        .map(|value| value.to_string())
+        .collect();
+
+    if chat_ids.is_empty() {
+        None
+    } else {
+        Some(chat_ids)
+    }
+}
+
+fn event_frame(event: &ServiceEvent) -> Bytes {
+    let data = serde_json::to_string(event).unwrap_or_else(|_| "{}".to_string());
+    Bytes::from(format!(
+        "id: {}\nevent: {}\ndata: {}\n\n",
+        event.id,
+        event.r#type.as_str(),
+        data
+    ))
+}
+
+/// Tells the client that events were missed and its state must be refetched.
+fn reset_frame() -> Bytes {
+    Bytes::from_static(b"event: stream.reset\ndata: {}\n\n")
+}
@@ -0,0 +1,9 @@
+pub mod handlers;
+pub mod structures;
+
+pub use handlers::{
+    __path_create_stream_ticket, __path_stream_events, create_stream_ticket, init_routes,
+    stream_events,
+};
+
+pub use structures::StreamTicketResponse;
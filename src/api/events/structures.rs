@@ -0,0 +1,21 @@
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
+pub struct EventStreamQuery {
+    /// Comma-separated chat ids. Chat events of other chats are skipped;
+    /// company-wide events (channel state) are always delivered.
+    pub chat_id: Option<String>,
+    /// Resume after this event id. The `Last-Event-ID` header takes precedence.
+    pub last_event_id: Option<u64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct StreamTicketResponse {
+    /// Pass as `?ticket=` when opening the stream; valid for one minute.
+    pub ticket: String,
+    pub expires_at: String,
+}
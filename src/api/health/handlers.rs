@@ -0,0 +1,115 @@
+use actix_web::{HttpRequest, HttpResponse, get, http::header, web};
+
+use crate::{
+    app_state::AppState,
+    database::{self, migrations},
+    errors::AppError,
+    metrics,
+};
+
+use super::structures::{CheckStatus, HealthStatus, ReadinessReport};
+
+/// Проверка живости процесса; базу не трогает
+#[utoipa::path(
+    get,
+    path = "/healthz",
+    tag = "Health",
+    responses(
+        (status = 200, description = "The process is serving requests", body = HealthStatus),
+    ),
+    security(())
+)]
+#[get("/healthz")]
+pub async fn liveness() -> HttpResponse {
+    HttpResponse::Ok().json(HealthStatus {
+        status: "ok".to_string(),
+    })
+}
+
+/// Готовность принимать трафик: база отвечает и все миграции применены
+#[utoipa::path(
+    get,
+    path = "/readyz",
+    tag = "Health",
+    responses(
+        (status = 200, description = "Ready to serve traffic", body = ReadinessReport),
+        (status = 503, description = "Database unreachable or migrations pending", body = ReadinessReport),
+    ),
+    security(())
+)]
+#[get("/readyz")]
+pub async fn readiness(app_state: web::Data<AppState>) -> HttpResponse {
+    let mut report = ReadinessReport {
+        status: "unavailable".to_string(),
+        database: CheckStatus::Ok,
+        migrations: CheckStatus::Skipped,
+        pending_migrations: Vec::new(),
+    };
+
+    if let Err(err) = database::ping(&app_state.db).await {
+        log::warn!("Readiness check failed: {:#}", err);
+        report.database = CheckStatus::Failed;
+        return HttpResponse::ServiceUnavailable().json(report);
+    }
+
+    match migrations::pending(&app_state.db).await {
+        Ok(pending) if pending.is_empty() => report.migrations = CheckStatus::Ok,
+        Ok(pending) => {
+            report.migrations = CheckStatus::Failed;
+            report.pending_migrations = pending.iter().map(|migration| migration.version).collect();
+        }
+        Err(err) => {
+            log::warn!("Readiness check could not read migrations: {}", err);
+            report.migrations = CheckStatus::Failed;
+        }
+    }
+
+    if report.migrations != CheckStatus::Ok {
+        return HttpResponse::ServiceUnavailable().json(report);
+    }
+
+    report.status = "ready".to_string();
+    HttpResponse::Ok().json(report)
+}
+
+/// Метрики Prometheus; при заданном `METRICS_TOKEN` требуется этот токен
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    tag = "Health",
+    responses(
+        (status = 200, description = "Metrics in the Prometheus text format", body = String, content_type = "text/plain"),
+        (status = 401, description = "METRICS_TOKEN is set and was not presented"),
+    ),
+    security(())
+)]
+#[get("/metrics")]
+pub async fn get_metrics(
+    app_state: web::Data<AppState>,
+    req: HttpRequest,
+) -> Result<HttpResponse, AppError> {
+    if let Some(expected) = &app_state.config.metrics_token {
+        let presented = req
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+        if presented != Some(expected.expose()) {
+            return Err(AppError::Unauthorized("Metrics token required".to_string()));
+        }
+    }
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(metrics::render(&app_state.db).await))
+}
+
+/// Пробы для оркестратора; монтируются вне `/api` и не требуют токена
+pub fn init_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(liveness).service(readiness);
+}
+
+/// `/metrics` монтируется только на основном сервере: метрики общие для процесса
+pub fn init_metrics_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_metrics);
+}
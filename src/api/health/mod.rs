@@ -0,0 +1,9 @@
+pub mod handlers;
+pub mod structures;
+
+pub use handlers::{
+    __path_get_metrics, __path_liveness, __path_readiness, get_metrics, init_metrics_routes,
+    init_routes, liveness, readiness,
+};
+
+pub use structures::{CheckStatus, HealthStatus, ReadinessReport};
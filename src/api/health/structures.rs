@@ -0,0 +1,28 @@
+use serde::Serialize;
+use utoipa::ToSchema;
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct HealthStatus {
+    pub status: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Ok,
+    Failed,
+    /// Not checked because an earlier check failed.
+    Skipped,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadinessReport {
+    /// `ready` or `unavailable`.
+    pub status: String,
+    pub database: CheckStatus,
+    /// Failed while migrations are pending.
+    pub migrations: CheckStatus,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub pending_migrations: Vec<i32>,
+}
@@ -1,12 +1,72 @@
+use actix_cors::Cors;
 use actix_web::{
-    Error, HttpMessage,
+    Error, HttpMessage, ResponseError,
     dev::{Service, ServiceRequest, ServiceResponse, Transform},
+    error::InternalError,
+    http::{
+        Method,
+        header::{self, HeaderMap, HeaderName, HeaderValue},
+    },
+    web,
 };
 use futures_util::future::{LocalBoxFuture, Ready, ready};
-use std::rc::Rc;
+use opentelemetry::context::FutureExt;
+use serde::Deserialize;
+use std::{
+    rc::Rc,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 use uuid::Uuid;
 
-/// Middleware добавляющий trace/request id
+use crate::{
+    app_state::AppState,
+    config::{Config, ProxyNetwork, RateLimits},
+    errors::AppError,
+    logging, metrics,
+    services::{
+        rate_limit::{Decision, RateLimiter},
+        tokens,
+    },
+    telemetry,
+};
+
+/// CORS для API по настройкам `cors_*`: без списков разрешены любые origin, методы и заголовки
+pub fn cors(config: &Config) -> Cors {
+    let cors = Cors::default().max_age(config.cors_max_age_secs());
+
+    let origins = config.cors_origins();
+    let cors = if origins.is_empty() {
+        cors.allow_any_origin()
+    } else {
+        origins
+            .into_iter()
+            .fold(cors, |cors, origin| cors.allowed_origin(origin))
+    };
+
+    let methods = config.cors_methods();
+    let cors = if methods.is_empty() {
+        cors.allow_any_method()
+    } else {
+        cors.allowed_methods(methods)
+    };
+
+    let headers = config.cors_headers();
+    let cors = if headers.is_empty() {
+        cors.allow_any_header()
+    } else {
+        cors.allowed_headers(headers)
+    };
+
+    let exposed = config.cors_expose_headers();
+    if exposed.is_empty() {
+        cors
+    } else {
+        cors.expose_headers(exposed)
+    }
+}
+
+/// Middleware добавляющий request id в логи и ответ (`X-Request-Id`) и логирующий время ответа
 pub struct RequestId;
 
 impl<S, B> Transform<S, ServiceRequest> for RequestId
@@ -48,13 +108,455 @@ where
     }
 
     fn call(&self, req: ServiceRequest) -> Self::Future {
-        let id = Uuid::new_v4().to_string();
+        let id = incoming_request_id(&req).unwrap_or_else(|| Uuid::new_v4().to_string());
         req.extensions_mut().insert(id.clone());
-        log::debug!("request_id={} path={}", id, req.path());
-        let fut = self.service.call(req);
+        let method = req.method().to_string();
+        let path = req.path().to_string();
+        let route = req
+            .match_pattern()
+            .unwrap_or_else(|| metrics::UNMATCHED_ROUTE.to_string());
+        let span = telemetry::ServerSpan::start(req.headers(), &method, &route, &id);
+        let context = logging::RequestContext::new(id);
+        let started = Instant::now();
+
+        let fut = {
+            let _guard = span.context().attach();
+            logging::sync_scope(context.clone(), || self.service.call(req))
+        };
+        let fut = fut.with_context(span.context());
+        Box::pin(logging::scope(context.clone(), async move {
+            // Errors of inner middleware are rendered here, while the request
+            // id is still known, so their bodies carry `trace_id` too.
+            let result = fut.await.map_err(|err| {
+                let mut response = err.error_response();
+                if let Ok(value) = HeaderValue::from_str(context.request_id()) {
+                    response.headers_mut().insert(REQUEST_ID_HEADER, value);
+                }
+                Error::from(InternalError::from_response(err, response))
+            });
+            let status = match &result {
+                Ok(resp) => resp.status(),
+                Err(err) => err.as_response_error().status_code(),
+            };
+            // Orchestrator probes hit every few seconds; only failures are worth a line.
+            let level = if PROBE_PATHS.contains(&path.as_str()) && status.is_success() {
+                log::Level::Debug
+            } else {
+                log::Level::Info
+            };
+            log::log!(
+                level,
+                method = method.as_str(),
+                path = path.as_str(),
+                status = status.as_u16(),
+                latency_ms = started.elapsed().as_millis() as u64;
+                "Request completed"
+            );
+            metrics::observe_http(&method, &route, status.as_u16(), started.elapsed());
+            span.end(status.as_u16());
+
+            let mut resp = result?;
+            if let Ok(value) = HeaderValue::from_str(context.request_id()) {
+                resp.headers_mut().insert(REQUEST_ID_HEADER, value);
+            }
+            Ok(resp)
+        }))
+    }
+}
+
+const PROBE_PATHS: &[&str] = &["/healthz", "/readyz"];
+
+const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+/// Id passed by a proxy in `X-Request-Id`, if it looks like one.
+fn incoming_request_id(req: &ServiceRequest) -> Option<String> {
+    req.headers()
+        .get(&REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|id| {
+            !id.is_empty()
+                && id.len() <= 64
+                && id
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        })
+        .map(str::to_string)
+}
+
+/// Пути, доступные без токена: выдача токена по логину
+const PUBLIC_PATH_PREFIXES: &[&str] = &["/api/auth/"];
+/// Wazzup calls back on `/api/webhook/{id}`; the nested webhook management
+/// routes (`/connect`, `/test`) still require a token.
+const WEBHOOK_CALLBACK_PREFIX: &str = "/api/webhook/";
+/// Routes that are not scoped to a company.
+const UNSCOPED_PATH_PREFIXES: &[&str] = &["/api/admin/"];
+/// `EventSource` cannot send headers, so the event stream may pass a
+/// short-lived stream ticket as `?ticket=` instead of the token.
+const QUERY_TICKET_PATH_PREFIX: &str = "/api/events/";
+
+/// Middleware проверяющий bearer-токен и привязывающий запрос к пользователю и компании
+pub struct BearerAuth;
+
+impl<S, B> Transform<S, ServiceRequest> for BearerAuth
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = BearerAuthMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(BearerAuthMiddleware {
+            service: Rc::new(service),
+        }))
+    }
+}
+
+pub struct BearerAuthMiddleware<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for BearerAuthMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(
+        &self,
+        ctx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(ctx)
+    }
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = Rc::clone(&self.service);
+
+        Box::pin(async move {
+            let path = req.path().to_string();
+            if req.method() == Method::OPTIONS || is_public_path(&path) {
+                return service.call(req).await;
+            }
+
+            let app_state = req
+                .app_data::<web::Data<AppState>>()
+                .cloned()
+                .ok_or(AppError::Internal)?;
+
+            let requested_company = requested_company(&path)?;
+            let principal = match (bearer_token(&req), stream_ticket(&req)) {
+                (Some(token), _) => {
+                    tokens::authenticate(&app_state.db, &token, requested_company.as_deref())
+                        .await?
+                }
+                (None, Some(ticket)) => {
+                    tokens::authenticate_stream_ticket(
+                        &app_state.db,
+                        &app_state.api_keys,
+                        &ticket,
+                        requested_company.as_deref(),
+                    )
+                    .await?
+                }
+                (None, None) => {
+                    return Err(AppError::Unauthorized("Missing bearer token".to_string()).into());
+                }
+            };
+
+            logging::set_company_id(Uuid::from_slice(&principal.company_id).unwrap_or_default());
+            req.extensions_mut().insert(principal);
+            service.call(req).await
+        })
+    }
+}
+
+fn bearer_token(req: &ServiceRequest) -> Option<String> {
+    req.headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| {
+            value
+                .strip_prefix("Bearer ")
+                .or_else(|| value.strip_prefix("bearer "))
+        })
+        .map(|token| token.trim().to_string())
+        .filter(|token| !token.is_empty())
+}
+
+fn stream_ticket(req: &ServiceRequest) -> Option<String> {
+    if req.method() != Method::GET || !req.path().starts_with(QUERY_TICKET_PATH_PREFIX) {
+        return None;
+    }
+
+    web::Query::<TicketQuery>::from_query(req.query_string())
+        .ok()
+        .and_then(|query| query.into_inner().ticket)
+        .filter(|ticket| !ticket.is_empty())
+}
+
+#[derive(Deserialize)]
+struct TicketQuery {
+    ticket: Option<String>,
+}
+
+fn is_public_path(path: &str) -> bool {
+    if let Some(rest) = path.strip_prefix(WEBHOOK_CALLBACK_PREFIX) {
+        return !rest.trim_end_matches('/').contains('/');
+    }
+    PUBLIC_PATH_PREFIXES
+        .iter()
+        .any(|prefix| path.starts_with(prefix))
+}
+
+/// Company addressed by the request: every company-scoped route is
+/// `/api/<resource>/{companyId}/...`. Platform routes address no company and
+/// act for the company the token is bound to.
+fn requested_company(path: &str) -> Result<Option<Vec<u8>>, AppError> {
+    if UNSCOPED_PATH_PREFIXES
+        .iter()
+        .any(|prefix| path.starts_with(prefix))
+    {
+        return Ok(None);
+    }
+
+    let segment = path
+        .trim_start_matches('/')
+        .split('/')
+        .nth(2)
+        .filter(|segment| !segment.is_empty());
+
+    match segment {
+        Some(raw) => Uuid::parse_str(raw)
+            .map(|uuid| Some(uuid.as_bytes().to_vec()))
+            .map_err(|_| AppError::InvalidInput("companyId must be a valid UUID".to_string())),
+        None => Ok(None),
+    }
+}
+
+/// Routes that run heavy queries: chat previews and search.
+const EXPENSIVE_PATH_SUFFIXES: &[&str] = &["/previews", "/search"];
+
+const RATE_LIMIT_LIMIT_HEADER: HeaderName = HeaderName::from_static("x-ratelimit-limit");
+const RATE_LIMIT_REMAINING_HEADER: HeaderName = HeaderName::from_static("x-ratelimit-remaining");
+const RATE_LIMIT_RESET_HEADER: HeaderName = HeaderName::from_static("x-ratelimit-reset");
+
+#[derive(Clone, Copy)]
+enum RateLimitKeys {
+    Ip,
+    Principal,
+    WebhookIp,
+}
+
+/// Middleware ограничивающий частоту запросов: сверх лимита 429 с `Retry-After`,
+/// в ответах заголовки `X-RateLimit-Limit`, `X-RateLimit-Remaining`, `X-RateLimit-Reset`
+pub struct RateLimit {
+    limiter: RateLimiter,
+    limits: RateLimits,
+    keys: RateLimitKeys,
+    trusted_proxies: Arc<[ProxyNetwork]>,
+}
+
+impl RateLimit {
+    /// Лимит по IP клиента; ставится снаружи [`BearerAuth`], чтобы считать и запросы без токена
+    pub fn per_ip(limiter: RateLimiter, limits: RateLimits) -> Self {
+        Self {
+            limiter,
+            limits,
+            keys: RateLimitKeys::Ip,
+            trusted_proxies: Arc::from([]),
+        }
+    }
+
+    /// Лимиты по токену и компании, у тяжёлых маршрутов отдельные; ставится внутри [`BearerAuth`]
+    pub fn per_principal(limiter: RateLimiter, limits: RateLimits) -> Self {
+        Self {
+            limiter,
+            limits,
+            keys: RateLimitKeys::Principal,
+            trusted_proxies: Arc::from([]),
+        }
+    }
+
+    /// Лимит приёмника вебхуков по IP
+    pub fn webhooks(limiter: RateLimiter, limits: RateLimits) -> Self {
+        Self {
+            limiter,
+            limits,
+            keys: RateLimitKeys::WebhookIp,
+            trusted_proxies: Arc::from([]),
+        }
+    }
+
+    /// Прокси, от которых адрес клиента берётся из `X-Forwarded-For`
+    pub fn trusting(mut self, proxies: Vec<ProxyNetwork>) -> Self {
+        self.trusted_proxies = proxies.into();
+        self
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RateLimit
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RateLimitMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RateLimitMiddleware {
+            service: Rc::new(service),
+            limiter: self.limiter.clone(),
+            limits: self.limits,
+            keys: self.keys,
+            trusted_proxies: self.trusted_proxies.clone(),
+        }))
+    }
+}
+
+pub struct RateLimitMiddleware<S> {
+    service: Rc<S>,
+    limiter: RateLimiter,
+    limits: RateLimits,
+    keys: RateLimitKeys,
+    trusted_proxies: Arc<[ProxyNetwork]>,
+}
+
+impl<S> RateLimitMiddleware<S> {
+    /// `(bucket key, requests per minute)` the request counts against.
+    fn budgets(&self, req: &ServiceRequest) -> Vec<(String, u32)> {
+        let limits = &self.limits;
+        match self.keys {
+            RateLimitKeys::Ip => client_ip(req, &self.trusted_proxies)
+                .map(|ip| vec![(format!("ip:{}", ip), limits.ip)])
+                .unwrap_or_default(),
+            RateLimitKeys::WebhookIp => client_ip(req, &self.trusted_proxies)
+                .map(|ip| vec![(format!("webhook:ip:{}", ip), limits.webhook_ip)])
+                .unwrap_or_default(),
+            RateLimitKeys::Principal => {
+                let extensions = req.extensions();
+                let Some(principal) = extensions.get::<tokens::Principal>() else {
+                    return Vec::new();
+                };
+                let token = hex::encode(&principal.token_id);
+                let company = hex::encode(&principal.company_id);
+                let mut budgets = vec![
+                    (format!("token:{}", token), limits.token),
+                    (format!("company:{}", company), limits.company),
+                ];
+                if EXPENSIVE_PATH_SUFFIXES
+                    .iter()
+                    .any(|suffix| req.path().ends_with(suffix))
+                {
+                    budgets.push((format!("expensive:token:{}", token), limits.expensive_token));
+                    budgets.push((
+                        format!("expensive:company:{}", company),
+                        limits.expensive_company,
+                    ));
+                }
+                budgets
+            }
+        }
+    }
+}
+
+impl<S, B> Service<ServiceRequest> for RateLimitMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(
+        &self,
+        ctx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(ctx)
+    }
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let decision = self.limiter.check(&self.budgets(&req));
+
+        if let Some(decision) = decision.filter(|decision| !decision.allowed) {
+            log::info!(
+                path = req.path(),
+                limit = decision.limit;
+                "Request rejected by rate limit"
+            );
+            let err = AppError::RateLimited(whole_seconds(decision.retry_after).max(1));
+            let mut response = err.error_response();
+            insert_rate_limit_headers(response.headers_mut(), &decision);
+            return Box::pin(ready(Err(
+                InternalError::from_response(err, response).into()
+            )));
+        }
+
+        let service = Rc::clone(&self.service);
         Box::pin(async move {
-            let resp = fut.await?;
+            let mut resp = service.call(req).await?;
+            if let Some(decision) = decision {
+                insert_rate_limit_headers(resp.headers_mut(), &decision);
+            }
             Ok(resp)
         })
     }
 }
+
+/// Client address: the peer of the connection, or, while that is a trusted
+/// proxy, the hop it forwarded for. `X-Forwarded-For` is read from the right,
+/// so entries a client put there itself are never reached.
+fn client_ip(req: &ServiceRequest, trusted_proxies: &[ProxyNetwork]) -> Option<String> {
+    let mut client = req.peer_addr()?.ip();
+    let forwarded: Vec<&str> = req
+        .headers()
+        .get_all(header::X_FORWARDED_FOR)
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .collect();
+
+    for hop in forwarded.into_iter().rev() {
+        if !trusted_proxies.iter().any(|proxy| proxy.contains(client)) {
+            break;
+        }
+        match hop.trim().parse() {
+            Ok(ip) => client = ip,
+            Err(_) => break,
+        }
+    }
+    Some(client.to_string())
+}
+
+/// Headers of the most constrained budget: an outer limiter does not
+/// overwrite an inner one with more requests left.
+fn insert_rate_limit_headers(headers: &mut HeaderMap, decision: &Decision) {
+    let current = headers
+        .get(&RATE_LIMIT_REMAINING_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<u32>().ok());
+    if current.is_some_and(|remaining| remaining <= decision.remaining) {
+        return;
+    }
+    headers.insert(RATE_LIMIT_LIMIT_HEADER, HeaderValue::from(decision.limit));
+    headers.insert(
+        RATE_LIMIT_REMAINING_HEADER,
+        HeaderValue::from(decision.remaining),
+    );
+    headers.insert(
+        RATE_LIMIT_RESET_HEADER,
+        HeaderValue::from(whole_seconds(decision.reset_after)),
+    );
+}
+
+fn whole_seconds(duration: Duration) -> u64 {
+    duration.as_secs_f64().ceil() as u64
+}
@@ -1,8 +1,15 @@
+pub mod admin;
 pub mod channels;
 pub mod chats;
 pub mod contacts;
 pub mod context;
+pub mod diagnostics;
+pub mod events;
+pub mod health;
 pub mod helpers;
 pub mod middleware;
+pub mod outgoing_webhooks;
+pub mod permissions;
+pub mod tokens;
 pub mod validation;
 pub mod webhooks;
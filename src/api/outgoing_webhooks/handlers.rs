@@ -0,0 +1,442 @@
+use actix_web::{HttpResponse, delete, get, patch, post, web};
+use chrono::Utc;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, EntityTrait, IntoActiveModel, PaginatorTrait, QueryFilter,
+    QueryOrder, QuerySelect, Set,
+};
+use serde_json::Value as JsonValue;
+use uuid::Uuid;
+
+use crate::{
+    api::helpers::uuid_to_bytes,
+    api::permissions::{Admin, Require},
+    api::validation::{self, Validator, codes},
+    app_state::AppState,
+    database::models::{companies, outgoing_webhook_deliveries, outgoing_webhooks},
+    errors::AppError,
+    services::{
+        events::EventKind,
+        outgoing_webhooks::{STATUS_FAILED, STATUS_PENDING, STATUS_SUCCEEDED, event_types_of},
+    },
+};
+
+use super::structures::{
+    CreateOutgoingWebhookRequest, DeliveriesQuery, OutgoingWebhookList, OutgoingWebhookView,
+    UpdateOutgoingWebhookRequest, WebhookDeliveryList, WebhookDeliveryView,
+};
+
+const DEFAULT_DELIVERIES_PAGE: u64 = 50;
+const MAX_DELIVERIES_PAGE: u64 = 200;
+
+/// Список исходящих вебхуков компании
+#[utoipa::path(
+    get,
+    path = "/api/outgoing-webhooks/{companyId}",
+    tag = "Outgoing webhooks",
+    params(("companyId" = String, Path, description = "Company UUID")),
+    responses(
+        (status = 200, description = "Registered webhooks", body = OutgoingWebhookList),
+        (status = 404, description = "Company not found"),
+    )
+)]
+#[get("/{companyId}")]
+pub async fn list_outgoing_webhooks(
+    app_state: web::Data<AppState>,
+    _access: Require<Admin>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let company_uuid = parse_uuid(&path.into_inner(), "companyId")?;
+    ensure_company(&app_state, &company_uuid).await?;
+
+    let webhooks = outgoing_webhooks::Entity::find()
+        .filter(outgoing_webhooks::Column::CompanyId.eq(uuid_to_bytes(&company_uuid)))
+        .order_by_asc(outgoing_webhooks::Column::CreatedAt)
+        .all(&app_state.db)
+        .await?;
+
+    let data = webhooks
+        .iter()
+        .map(|webhook| webhook_view(webhook, false))
+        .collect();
+
+    Ok(HttpResponse::Ok().json(OutgoingWebhookList { data }))
+}
+
+/// Регистрирует исходящий вебхук; секрет подписи возвращается только в ответе
+#[utoipa::path(
+    post,
+    path = "/api/outgoing-webhooks/{companyId}",
+    tag = "Outgoing webhooks",
+    params(("companyId" = String, Path, description = "Company UUID")),
+    request_body = CreateOutgoingWebhookRequest,
+    responses(
+        (status = 201, description = "Webhook registered", body = OutgoingWebhookView),
+        (status = 400, description = "Invalid URL or event type"),
+        (status = 404, description = "Company not found"),
+    )
+)]
+#[post("/{companyId}")]
+pub async fn create_outgoing_webhook(
+    app_state: web::Data<AppState>,
+    _access: Require<Admin>,
+    path: web::Path<String>,
+    body: validation::Json<CreateOutgoingWebhookRequest>,
+) -> Result<HttpResponse, AppError> {
+    let company_uuid = parse_uuid(&path.into_inner(), "companyId")?;
+    ensure_company(&app_state, &company_uuid).await?;
+
+    let request = body.into_inner();
+    let mut validator = Validator::new();
+    let url = validator.http_url("url", &request.url);
+    check_target(&app_state, &mut validator, &url).await;
+    let event_types = validate_event_types(&mut validator, request.event_types.unwrap_or_default());
+    validator.finish()?;
+
+    let now = Utc::now();
+    let webhook = outgoing_webhooks::ActiveModel {
+        id: Set(uuid_to_bytes(&Uuid::new_v4())),
+        company_id: Set(uuid_to_bytes(&company_uuid)),
+        url: Set(url),
+        secret: Set(generate_secret()),
+        event_types: Set(event_types_json(&event_types)),
+        description: Set(normalize_description(request.description)),
+        is_active: Set(1),
+        created_at: Set(now),
+        updated_at: Set(now),
+    }
+    .insert(&app_state.db)
+    .await?;
+
+    Ok(HttpResponse::Created().json(webhook_view(&webhook, true)))
+}
+
+/// Обновляет адрес, фильтр событий или состояние вебхука
+#[utoipa::path(
+    patch,
+    path = "/api/outgoing-webhooks/{companyId}/{webhookId}",
+    tag = "Outgoing webhooks",
+    params(
+        ("companyId" = String, Path, description = "Company UUID"),
+        ("webhookId" = String, Path, description = "Webhook UUID")
+    ),
+    request_body = UpdateOutgoingWebhookRequest,
+    responses(
+        (status = 200, description = "Webhook updated", body = OutgoingWebhookView),
+        (status = 400, description = "Invalid URL or event type"),
+        (status = 404, description = "Webhook not found"),
+    )
+)]
+#[patch("/{companyId}/{webhookId}")]
+pub async fn update_outgoing_webhook(
+    app_state: web::Data<AppState>,
+    _access: Require<Admin>,
+    path: web::Path<(String, String)>,
+    body: validation::Json<UpdateOutgoingWebhookRequest>,
+) -> Result<HttpResponse, AppError> {
+    let (company_raw, webhook_raw) = path.into_inner();
+    let webhook = load_webhook(&app_state, &company_raw, &webhook_raw).await?;
+
+    let request = body.into_inner();
+    let rotate_secret = request.rotate_secret == Some(true);
+    let mut active = webhook.into_active_model();
+    let mut validator = Validator::new();
+
+    if let Some(url) = request.url {
+        let url = validator.http_url("url", &url);
+        check_target(&app_state, &mut validator, &url).await;
+        active.url = Set(url);
+    }
+    if let Some(event_types) = request.event_types {
+        active.event_types = Set(event_types_json(&validate_event_types(
+            &mut validator,
+            event_types,
+        )));
+    }
+    validator.finish()?;
+    if request.description.is_some() {
+        active.description = Set(normalize_description(request.description));
+    }
+    if let Some(is_active) = request.is_active {
+        active.is_active = Set(if is_active { 1 } else { 0 });
+    }
+    if rotate_secret {
+        active.secret = Set(generate_secret());
+    }
+    active.updated_at = Set(Utc::now());
+
+    let webhook = active.update(&app_state.db).await?;
+
+    Ok(HttpResponse::Ok().json(webhook_view(&webhook, rotate_secret)))
+}
+
+/// Удаляет вебхук вместе с журналом доставок
+#[utoipa::path(
+    delete,
+    path = "/api/outgoing-webhooks/{companyId}/{webhookId}",
+    tag = "Outgoing webhooks",
+    params(
+        ("companyId" = String, Path, description = "Company UUID"),
+        ("webhookId" = String, Path, description = "Webhook UUID")
+    ),
+    responses(
+        (status = 204, description = "Webhook deleted"),
+        (status = 404, description = "Webhook not found"),
+    )
+)]
+#[delete("/{companyId}/{webhookId}")]
+pub async fn delete_outgoing_webhook(
+    app_state: web::Data<AppState>,
+    _access: Require<Admin>,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse, AppError> {
+    let (company_raw, webhook_raw) = path.into_inner();
+    let webhook = load_webhook(&app_state, &company_raw, &webhook_raw).await?;
+
+    outgoing_webhook_deliveries::Entity::delete_many()
+        .filter(outgoing_webhook_deliveries::Column::WebhookId.eq(webhook.id.clone()))
+        .exec(&app_state.db)
+        .await?;
+    outgoing_webhooks::Entity::delete_by_id(webhook.id)
+        .exec(&app_state.db)
+        .await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Журнал доставок вебхука, новые записи первыми
+#[utoipa::path(
+    get,
+    path = "/api/outgoing-webhooks/{companyId}/{webhookId}/deliveries",
+    tag = "Outgoing webhooks",
+    params(
+        ("companyId" = String, Path, description = "Company UUID"),
+        ("webhookId" = String, Path, description = "Webhook UUID"),
+        DeliveriesQuery
+    ),
+    responses(
+        (status = 200, description = "Delivery log", body = WebhookDeliveryList),
+        (status = 404, description = "Webhook not found"),
+    )
+)]
+#[get("/{companyId}/{webhookId}/deliveries")]
+pub async fn list_webhook_deliveries(
+    app_state: web::Data<AppState>,
+    _access: Require<Admin>,
+    path: web::Path<(String, String)>,
+    query: validation::Query<DeliveriesQuery>,
+) -> Result<HttpResponse, AppError> {
+    let (company_raw, webhook_raw) = path.into_inner();
+    let webhook = load_webhook(&app_state, &company_raw, &webhook_raw).await?;
+    let params = query.into_inner();
+
+    let mut select = outgoing_webhook_deliveries::Entity::find()
+        .filter(outgoing_webhook_deliveries::Column::WebhookId.eq(webhook.id));
+
+    if let Some(status) = params.status.as_deref() {
+        if ![STATUS_PENDING, STATUS_SUCCEEDED, STATUS_FAILED].contains(&status) {
+            return Err(AppError::InvalidInput(
+                "status must be one of pending, succeeded, failed".to_string(),
+            ));
+        }
+        select = select.filter(outgoing_webhook_deliveries::Column::Status.eq(status));
+    }
+
+    let count = match params.count {
+        Some(0) => {
+            return Err(AppError::InvalidInput(
+                "count must be greater than zero".to_string(),
+            ));
+        }
+        Some(count) => count.min(MAX_DELIVERIES_PAGE),
+        None => DEFAULT_DELIVERIES_PAGE,
+    };
+
+    let total = select.clone().count(&app_state.db).await?;
+    let deliveries = select
+        .order_by_desc(outgoing_webhook_deliveries::Column::CreatedAt)
+        .offset(params.offset.unwrap_or(0))
+        .limit(count)
+        .all(&app_state.db)
+        .await?;
+
+    let data = deliveries.iter().map(delivery_view).collect();
+
+    Ok(HttpResponse::Ok().json(WebhookDeliveryList { data, total }))
+}
+
+/// Повторно отправляет доставку и возвращает результат попытки
+#[utoipa::path(
+    post,
+    path = "/api/outgoing-webhooks/{companyId}/{webhookId}/deliveries/{deliveryId}/redeliver",
+    tag = "Outgoing webhooks",
+    params(
+        ("companyId" = String, Path, description = "Company UUID"),
+        ("webhookId" = String, Path, description = "Webhook UUID"),
+        ("deliveryId" = String, Path, description = "Delivery UUID")
+    ),
+    responses(
+        (status = 200, description = "Delivery attempted", body = WebhookDeliveryView),
+        (status = 404, description = "Webhook or delivery not found"),
+    )
+)]
+#[post("/{companyId}/{webhookId}/deliveries/{deliveryId}/redeliver")]
+pub async fn redeliver_webhook_delivery(
+    app_state: web::Data<AppState>,
+    _access: Require<Admin>,
+    path: web::Path<(String, String, String)>,
+) -> Result<HttpResponse, AppError> {
+    let (company_raw, webhook_raw, delivery_raw) = path.into_inner();
+    let webhook = load_webhook(&app_state, &company_raw, &webhook_raw).await?;
+    let delivery_uuid = parse_uuid(&delivery_raw, "deliveryId")?;
+
+    let delivery = outgoing_webhook_deliveries::Entity::find_by_id(uuid_to_bytes(&delivery_uuid))
+        .one(&app_state.db)
+        .await?
+        .filter(|delivery| delivery.webhook_id == webhook.id)
+        .ok_or_else(|| AppError::NotFound("Delivery not found".to_string()))?;
+
+    let delivery = app_state
+        .outgoing_webhooks
+        .redeliver(&app_state.db, &webhook, delivery)
+        .await?;
+
+    Ok(HttpResponse::Ok().json(delivery_view(&delivery)))
+}
+
+pub fn init_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/outgoing-webhooks")
+            .service(list_outgoing_webhooks)
+            .service(create_outgoing_webhook)
+            .service(update_outgoing_webhook)
+            .service(delete_outgoing_webhook)
+            .service(list_webhook_deliveries)
+            .service(redeliver_webhook_delivery),
+    );
+}
+
+fn parse_uuid(value: &str, field: &str) -> Result<Uuid, AppError> {
+    Uuid::parse_str(value)
+        .map_err(|_| AppError::InvalidInput(format!("{} must be a valid UUID", field)))
+}
+
+async fn ensure_company(app_state: &AppState, company_uuid: &Uuid) -> Result<(), AppError> {
+    companies::Entity::find_by_id(uuid_to_bytes(company_uuid))
+        .one(&app_state.db)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Company not found".to_string()))?;
+    Ok(())
+}
+
+async fn load_webhook(
+    app_state: &AppState,
+    company_raw: &str,
+    webhook_raw: &str,
+) -> Result<outgoing_webhooks::Model, AppError> {
+    let company_uuid = parse_uuid(company_raw, "companyId")?;
+    let webhook_uuid = parse_uuid(webhook_raw, "webhookId")?;
+    let company_bytes = uuid_to_bytes(&company_uuid);
+
+    outgoing_webhooks::Entity::find_by_id(uuid_to_bytes(&webhook_uuid))
+        .one(&app_state.db)
+        .await?
+        .filter(|webhook| webhook.company_id == company_bytes)
+        .ok_or_else(|| AppError::NotFound("Webhook not found".to_string()))
+}
+
+/// Отклоняет адреса, которые ведут во внутреннюю сеть сервиса
+async fn check_target(app_state: &AppState, validator: &mut Validator, url: &str) {
+    if let Some(reason) = app_state.outgoing_webhooks.check_target(url).await {
+        validator.add(
+            "url",
+            codes::FORBIDDEN_HOST,
+            format!("url must point to a public host: {}", reason),
+        );
+    }
+}
+
+fn validate_event_types(validator: &mut Validator, raw: Vec<String>) -> Vec<EventKind> {
+    let mut kinds = Vec::new();
+    for (index, value) in raw.into_iter().enumerate() {
+        let Some(kind) = EventKind::parse(&value) else {
+            let known: Vec<&str> = EventKind::ALL.iter().map(|kind| kind.as_str()).collect();
+            validator.add(
+                format!("eventTypes[{}]", index),
+                codes::UNSUPPORTED,
+                format!(
+                    "Unknown event type '{}'; expected one of: {}",
+                    value,
+                    known.join(", ")
+                ),
+            );
+            continue;
+        };
+        if !kinds.contains(&kind) {
+            kinds.push(kind);
+        }
+    }
+    kinds
+}
+
+fn event_types_json(kinds: &[EventKind]) -> JsonValue {
+    JsonValue::Array(
+        kinds
+            .iter()
+            .map(|kind| JsonValue::String(kind.as_str().to_string()))
+            .collect(),
+    )
+}
+
+fn normalize_description(description: Option<String>) -> Option<String> {
+    description
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+fn generate_secret() -> String {
+    format!(
+        "whsec_{}{}",
+        Uuid::new_v4().simple(),
+        Uuid::new_v4().simple()
+    )
+}
+
+fn webhook_view(webhook: &outgoing_webhooks::Model, include_secret: bool) -> OutgoingWebhookView {
+    OutgoingWebhookView {
+        id: Uuid::from_slice(&webhook.id)
+            .unwrap_or_default()
+            .to_string(),
+        url: webhook.url.clone(),
+        event_types: event_types_of(webhook),
+        description: webhook.description.clone(),
+        is_active: webhook.is_active != 0,
+        secret: include_secret.then(|| webhook.secret.clone()),
+        created_at: webhook.created_at.to_rfc3339(),
+        updated_at: webhook.updated_at.to_rfc3339(),
+    }
+}
+
+fn delivery_view(delivery: &outgoing_webhook_deliveries::Model) -> WebhookDeliveryView {
+    WebhookDeliveryView {
+        id: Uuid::from_slice(&delivery.id)
+            .unwrap_or_default()
+            .to_string(),
+        webhook_id: Uuid::from_slice(&delivery.webhook_id)
+            .unwrap_or_default()
+            .to_string(),
+        event_id: delivery
+            .event_uid
+            .as_deref()
+            .and_then(|uid| Uuid::from_slice(uid).ok())
+            .map(|uid| uid.to_string()),
+        event_type: delivery.event_type.clone(),
+        status: delivery.status.clone(),
+        attempts: delivery.attempts,
+        response_status: delivery.response_status,
+        last_error: delivery.last_error.clone(),
+        next_attempt_at: delivery.next_attempt_at.map(|value| value.to_rfc3339()),
+        delivered_at: delivery.delivered_at.map(|value| value.to_rfc3339()),
+        created_at: delivery.created_at.to_rfc3339(),
+        payload: delivery.payload.clone(),
+    }
+}
@@ -0,0 +1,15 @@
+pub mod handlers;
+pub mod structures;
+
+pub use handlers::{
+    __path_create_outgoing_webhook, __path_delete_outgoing_webhook, __path_list_outgoing_webhooks,
+    __path_list_webhook_deliveries, __path_redeliver_webhook_delivery,
+    __path_update_outgoing_webhook, create_outgoing_webhook, delete_outgoing_webhook, init_routes,
+    list_outgoing_webhooks, list_webhook_deliveries, redeliver_webhook_delivery,
+    update_outgoing_webhook,
+};
+
+pub use structures::{
+    CreateOutgoingWebhookRequest, OutgoingWebhookList, OutgoingWebhookView,
+    UpdateOutgoingWebhookRequest, WebhookDeliveryList, WebhookDeliveryView,
+};
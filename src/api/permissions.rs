@@ -0,0 +1,194 @@
+//! Role-based access control.
+//!
+//! Each handler declares the permission it needs by taking a
+//! [`Require<P>`] argument, e.g. `access: Require<SendMessages>`. The
+//! extractor reads the [`Principal`] bound by
+//! [`crate::api::middleware::BearerAuth`] and checks the user's role in the
+//! company against the permission.
+//!
+//! Platform-wide administration (managing companies themselves) is not tied
+//! to a company role and uses [`PlatformAdmin`] instead.
+
+use std::marker::PhantomData;
+
+use actix_web::{FromRequest, HttpMessage, HttpRequest, dev::Payload, web};
+use futures_util::future::{Ready, ready};
+
+use crate::{app_state::AppState, errors::AppError, services::tokens::Principal};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Permission {
+    ViewChats,
+    SendMessages,
+    ManageChannels,
+    ManageContacts,
+    /// Issue, list and revoke one's own API tokens.
+    ManageOwnTokens,
+    Admin,
+}
+
+impl Permission {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Permission::ViewChats => "view chats",
+            Permission::SendMessages => "send messages",
+            Permission::ManageChannels => "manage channels",
+            Permission::ManageContacts => "manage contacts",
+            Permission::ManageOwnTokens => "manage own tokens",
+            Permission::Admin => "admin",
+        }
+    }
+}
+
+/// Role of a user in a company (`company_users.role`, falling back to `users.role`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    Admin,
+    Manager,
+    Employee,
+    Bot,
+    /// Missing or unrecognised role; grants nothing.
+    None,
+}
+
+impl Role {
+    pub fn parse(raw: Option<&str>) -> Self {
+        match raw.map(|role| role.trim().to_ascii_lowercase()).as_deref() {
+            Some("admin") => Role::Admin,
+            Some("manager") => Role::Manager,
+            Some("employee") => Role::Employee,
+            Some("bot") => Role::Bot,
+            _ => Role::None,
+        }
+    }
+
+    pub fn grants(self, permission: Permission) -> bool {
+        use Permission::*;
+
+        match self {
+            Role::Admin => true,
+            Role::Manager => matches!(
+                permission,
+                ViewChats | SendMessages | ManageChannels | ManageContacts | ManageOwnTokens
+            ),
+            Role::Employee | Role::Bot => {
+                matches!(permission, ViewChats | SendMessages | ManageOwnTokens)
+            }
+            Role::None => false,
+        }
+    }
+}
+
+/// Type-level permission marker used by [`Require`].
+pub trait RoutePermission {
+    const PERMISSION: Permission;
+}
+
+macro_rules! route_permission {
+    ($($name:ident),* $(,)?) => {
+        $(
+            pub struct $name;
+
+            impl RoutePermission for $name {
+                const PERMISSION: Permission = Permission::$name;
+            }
+        )*
+    };
+}
+
+route_permission!(
+    ViewChats,
+    SendMessages,
+    ManageChannels,
+    ManageContacts,
+    ManageOwnTokens,
+    Admin
+);
+
+/// Extractor that rejects the request with 403 unless the authenticated
+/// user's role grants `P`.
+pub struct Require<P> {
+    pub principal: Principal,
+    pub role: Role,
+    manager_own_chats_only: bool,
+    _permission: PhantomData<P>,
+}
+
+impl<P> Require<P> {
+    /// Responsible user the chats visible to this request are limited to.
+    ///
+    /// Set for managers when `MANAGER_OWN_CHATS_ONLY` is enabled: they only see
+    /// chats of clients they are responsible for.
+    pub fn chat_owner(&self) -> Option<Vec<u8>> {
+        (self.manager_own_chats_only && self.role == Role::Manager)
+            .then(|| self.principal.user_id.clone())
+    }
+}
+
+impl<P: RoutePermission> FromRequest for Require<P> {
+    type Error = AppError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(authorize(req))
+    }
+}
+
+fn authorize<P: RoutePermission>(req: &HttpRequest) -> Result<Require<P>, AppError> {
+    let principal = req
+        .extensions()
+        .get::<Principal>()
+        .cloned()
+        .ok_or_else(|| AppError::Unauthorized("Missing bearer token".to_string()))?;
+
+    let role = Role::parse(principal.role.as_deref());
+    if !role.grants(P::PERMISSION) {
+        return Err(AppError::Forbidden(format!(
+            "Permission `{}` required",
+            P::PERMISSION.as_str()
+        )));
+    }
+
+    let manager_own_chats_only = req
+        .app_data::<web::Data<AppState>>()
+        .map(|state| state.config.manager_own_chats_only())
+        .unwrap_or(false);
+
+    Ok(Require {
+        principal,
+        role,
+        manager_own_chats_only,
+        _permission: PhantomData,
+    })
+}
+
+/// Extractor for platform administrators: users whose own `users.role` is
+/// `admin`. Being an admin of a company is not enough.
+pub struct PlatformAdmin {
+    pub principal: Principal,
+}
+
+impl FromRequest for PlatformAdmin {
+    type Error = AppError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(authorize_platform_admin(req))
+    }
+}
+
+fn authorize_platform_admin(req: &HttpRequest) -> Result<PlatformAdmin, AppError> {
+    let principal = req
+        .extensions()
+        .get::<Principal>()
+        .cloned()
+        .ok_or_else(|| AppError::Unauthorized("Missing bearer token".to_string()))?;
+
+    if Role::parse(principal.user.role.as_deref()) != Role::Admin {
+        return Err(AppError::Forbidden(
+            "Platform administrator required".to_string(),
+        ));
+    }
+
+    Ok(PlatformAdmin { principal })
+}
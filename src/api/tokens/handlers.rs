@@ -0,0 +1,232 @@
+use actix_web::{HttpResponse, delete, get, post, web};
+use chrono::Duration;
+use sea_orm::{ColumnTrait, Condition, EntityTrait, QueryFilter, QueryOrder};
+use uuid::Uuid;
+
+use crate::{
+    api::{
+        helpers::uuid_to_bytes,
+        permissions::{ManageOwnTokens, Require},
+        validation::{self, Validator},
+    },
+    app_state::AppState,
+    database::models::{company_users, tokens},
+    errors::AppError,
+    services::tokens::{
+        self as token_service, DEFAULT_TOKEN_TTL_DAYS, MAX_TOKEN_TTL_DAYS, Principal,
+    },
+};
+
+use super::structures::{
+    CreateTokenRequest, IssuedTokenResponse, LoginTokenRequest, TokenList, TokenView,
+};
+
+const MAX_TOKEN_NAME_CHARS: usize = 255;
+
+/// Выдаёт токен по логину и паролю пользователя
+#[utoipa::path(
+    post,
+    path = "/api/auth/token",
+    tag = "Tokens",
+    request_body = LoginTokenRequest,
+    security(()),
+    responses(
+        (status = 201, description = "Token issued; the plain token is only returned once", body = IssuedTokenResponse),
+        (status = 400, description = "Invalid input"),
+        (status = 401, description = "Invalid login or password"),
+        (status = 403, description = "User is not a member of the company"),
+    )
+)]
+#[post("/token")]
+pub async fn login_token(
+    app_state: web::Data<AppState>,
+    body: validation::Json<LoginTokenRequest>,
+) -> Result<HttpResponse, AppError> {
+    let payload = body.into_inner();
+    let mut validator = Validator::new();
+    let company_uuid = validator.uuid("companyId", &payload.company_id);
+    let name = validator.optional_text("name", payload.name, MAX_TOKEN_NAME_CHARS);
+    let ttl = token_ttl(&mut validator, payload.expires_in_days);
+    validator.finish()?;
+    let company_id_bytes = uuid_to_bytes(&company_uuid);
+
+    let user =
+        token_service::verify_credentials(&app_state.db, &payload.login, &payload.password).await?;
+
+    company_users::Entity::find_by_id((company_id_bytes.clone(), user.id.clone()))
+        .one(&app_state.db)
+        .await?
+        .ok_or_else(|| AppError::Forbidden("User is not a member of the company".to_string()))?;
+
+    let issued =
+        token_service::issue_token(&app_state.db, &user.id, &company_id_bytes, name, ttl).await?;
+
+    Ok(HttpResponse::Created().json(IssuedTokenResponse {
+        token: issued.token,
+        details: token_view(&issued.model, None)?,
+    }))
+}
+
+/// Токены текущего пользователя, действующие для компании.
+/// Токены других участников здесь не видны никому, в том числе админу компании
+#[utoipa::path(
+    get,
+    path = "/api/tokens/{companyId}",
+    tag = "Tokens",
+    params(("companyId" = String, Path, description = "Company UUID")),
+    responses(
+        (status = 200, description = "Tokens of the authenticated user", body = TokenList),
+        (status = 401, description = "Missing, invalid or expired token"),
+        (status = 403, description = "The user has no role in the company"),
+    )
+)]
+#[get("/{companyId}")]
+pub async fn list_tokens(
+    app_state: web::Data<AppState>,
+    access: Require<ManageOwnTokens>,
+) -> Result<HttpResponse, AppError> {
+    let principal = access.principal;
+    let records = tokens::Entity::find()
+        .filter(tokens::Column::UserId.eq(principal.user_id.clone()))
+        .filter(
+            Condition::any()
+                .add(tokens::Column::CompanyId.eq(principal.company_id.clone()))
+                .add(tokens::Column::CompanyId.is_null()),
+        )
+        .order_by_desc(tokens::Column::CreatedAt)
+        .all(&app_state.db)
+        .await?;
+
+    let data = records
+        .iter()
+        .map(|record| token_view(record, Some(&principal)))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(HttpResponse::Ok().json(TokenList { data }))
+}
+
+/// Выдаёт текущему пользователю ещё один токен для компании
+#[utoipa::path(
+    post,
+    path = "/api/tokens/{companyId}",
+    tag = "Tokens",
+    params(("companyId" = String, Path, description = "Company UUID")),
+    request_body = CreateTokenRequest,
+    responses(
+        (status = 201, description = "Token issued; the plain token is only returned once", body = IssuedTokenResponse),
+        (status = 400, description = "Invalid input"),
+        (status = 401, description = "Missing, invalid or expired token"),
+        (status = 403, description = "The user has no role in the company"),
+    )
+)]
+#[post("/{companyId}")]
+pub async fn create_token(
+    app_state: web::Data<AppState>,
+    access: Require<ManageOwnTokens>,
+    body: validation::Json<CreateTokenRequest>,
+) -> Result<HttpResponse, AppError> {
+    let principal = access.principal;
+    let payload = body.into_inner();
+    let mut validator = Validator::new();
+    let name = validator.optional_text("name", payload.name, MAX_TOKEN_NAME_CHARS);
+    let ttl = token_ttl(&mut validator, payload.expires_in_days);
+    validator.finish()?;
+
+    let issued = token_service::issue_token(
+        &app_state.db,
+        &principal.user_id,
+        &principal.company_id,
+        name,
+        ttl,
+    )
+    .await?;
+
+    Ok(HttpResponse::Created().json(IssuedTokenResponse {
+        token: issued.token,
+        details: token_view(&issued.model, Some(&principal))?,
+    }))
+}
+
+/// Отзывает токен текущего пользователя; чужой токен не найдётся даже для админа компании,
+/// доступ участника закрывается его удалением из компании
+#[utoipa::path(
+    delete,
+    path = "/api/tokens/{companyId}/{tokenId}",
+    tag = "Tokens",
+    params(
+        ("companyId" = String, Path, description = "Company UUID"),
+        ("tokenId" = String, Path, description = "Token UUID"),
+    ),
+    responses(
+        (status = 204, description = "Token revoked"),
+        (status = 401, description = "Missing, invalid or expired token"),
+        (status = 403, description = "The user has no role in the company"),
+        (status = 404, description = "Token not found among the user's tokens"),
+    )
+)]
+#[delete("/{companyId}/{tokenId}")]
+pub async fn revoke_token(
+    app_state: web::Data<AppState>,
+    access: Require<ManageOwnTokens>,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse, AppError> {
+    let principal = access.principal;
+    let (_, token_id_raw) = path.into_inner();
+    let token_uuid = parse_uuid(&token_id_raw, "tokenId")?;
+
+    let result = tokens::Entity::delete_many()
+        .filter(tokens::Column::Id.eq(uuid_to_bytes(&token_uuid)))
+        .filter(tokens::Column::UserId.eq(principal.user_id.clone()))
+        .exec(&app_state.db)
+        .await?;
+
+    if result.rows_affected == 0 {
+        return Err(AppError::NotFound("Token not found".to_string()));
+    }
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+pub fn init_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::scope("/auth").service(login_token));
+    cfg.service(
+        web::scope("/tokens")
+            .service(list_tokens)
+            .service(create_token)
+            .service(revoke_token),
+    );
+}
+
+/// Срок жизни токена из `expiresInDays`, по умолчанию [`DEFAULT_TOKEN_TTL_DAYS`]
+fn token_ttl(validator: &mut Validator, days: Option<i64>) -> Duration {
+    let days = days.unwrap_or(DEFAULT_TOKEN_TTL_DAYS);
+    Duration::days(validator.in_range("expiresInDays", days, 1, MAX_TOKEN_TTL_DAYS))
+}
+
+fn parse_uuid(value: &str, field: &str) -> Result<Uuid, AppError> {
+    Uuid::parse_str(value)
+        .map_err(|_| AppError::InvalidInput(format!("{} must be a valid UUID", field)))
+}
+
+fn token_view(
+    record: &tokens::Model,
+    principal: Option<&Principal>,
+) -> Result<TokenView, AppError> {
+    let uuid_string = |bytes: &[u8]| Uuid::from_slice(bytes).map(|uuid| uuid.to_string());
+
+    Ok(TokenView {
+        id: uuid_string(&record.id).map_err(|_| AppError::Internal)?,
+        name: record.name.clone(),
+        user_id: uuid_string(&record.user_id).map_err(|_| AppError::Internal)?,
+        company_id: record
+            .company_id
+            .as_deref()
+            .map(uuid_string)
+            .transpose()
+            .map_err(|_| AppError::Internal)?,
+        created_at: record.created_at.to_rfc3339(),
+        last_used_at: record.last_used_at.to_rfc3339(),
+        expires_at: record.expires_at.to_rfc3339(),
+        current: principal.is_some_and(|principal| principal.token_id == record.id),
+    })
+}
@@ -0,0 +1,11 @@
+pub mod handlers;
+pub mod structures;
+
+pub use handlers::{
+    __path_create_token, __path_list_tokens, __path_login_token, __path_revoke_token, create_token,
+    init_routes, list_tokens, login_token, revoke_token,
+};
+
+pub use structures::{
+    CreateTokenRequest, IssuedTokenResponse, LoginTokenRequest, TokenList, TokenView,
+};
@@ -1,16 +1,294 @@
-//! Простые функции валидации для входных DTO.
-//! Позволяет раннее отбрасывание некорректных данных.
+//! Валидация входных DTO.
+//!
+//! [`Validator`] проверяет поля по одному и собирает все ошибки, чтобы клиент
+//! получил их разом в `details` ответа `VALIDATION_ERROR`. Проверки возвращают
+//! нормализованное значение (обрезанные пробелы, телефон только из цифр),
+//! которое имеет смысл только если [`Validator::finish`] вернул `Ok`.
+//!
+//! Экстракторы [`Json`] и [`Query`] заменяют `web::Json` и `web::Query`:
+//! поле неверного типа или отсутствующее поле попадает в `details` с путём к
+//! нему, например `message.content[1].type`.
 
+use std::{fmt, ops::Deref};
+
+use actix_web::{FromRequest, HttpRequest, dev::Payload, web};
+use futures_util::future::{LocalBoxFuture, Ready, ready};
 use regex::Regex;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::errors::{AppError, FieldError};
 
 lazy_static::lazy_static! {
     static ref EMAIL_RE: Regex = Regex::new(r"^[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}$").unwrap();
     static ref PHONE_RE: Regex = Regex::new(r"^[0-9+]{6,20}$").unwrap();
 }
 
-pub fn validate_email_opt(email: &str) -> bool {
+/// Коды правил в `FieldError.code`
+pub mod codes {
+    pub const REQUIRED: &str = "required";
+    pub const TOO_LONG: &str = "too_long";
+    pub const INVALID_EMAIL: &str = "invalid_email";
+    pub const INVALID_PHONE: &str = "invalid_phone";
+    pub const INVALID_URL: &str = "invalid_url";
+    pub const FORBIDDEN_HOST: &str = "forbidden_host";
+    pub const UNSUPPORTED: &str = "unsupported";
+    pub const TOO_MANY: &str = "too_many";
+    pub const DUPLICATE: &str = "duplicate";
+    pub const INVALID_UUID: &str = "invalid_uuid";
+    pub const OUT_OF_RANGE: &str = "out_of_range";
+    pub const INVALID_VALUE: &str = "invalid_value";
+}
+
+/// Собирает ошибки валидации полей запроса
+#[derive(Debug, Default)]
+pub struct Validator {
+    errors: Vec<FieldError>,
+}
+
+impl Validator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(
+        &mut self,
+        field: impl Into<String>,
+        code: &'static str,
+        message: impl Into<String>,
+    ) {
+        self.errors.push(FieldError {
+            field: field.into(),
+            code,
+            message: message.into(),
+        });
+    }
+
+    /// `Err(AppError::Validation)` со всеми собранными ошибками
+    pub fn finish(self) -> Result<(), AppError> {
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(AppError::Validation(self.errors))
+        }
+    }
+
+    /// Непустая строка длиной до `max` символов (после обрезки пробелов)
+    pub fn required_text(&mut self, field: &str, raw: &str, max: usize) -> String {
+        let value = raw.trim();
+        if value.is_empty() {
+            self.add(
+                field,
+                codes::REQUIRED,
+                format!("{} must not be empty", field),
+            );
+        } else {
+            self.max_len(field, value, max);
+        }
+        value.to_string()
+    }
+
+    /// Необязательная строка длиной до `max` символов; пустая считается отсутствующей
+    pub fn optional_text(
+        &mut self,
+        field: &str,
+        raw: Option<String>,
+        max: usize,
+    ) -> Option<String> {
+        let value = normalize(raw);
+        if let Some(value) = &value {
+            self.max_len(field, value, max);
+        }
+        value
+    }
+
+    pub fn max_len(&mut self, field: &str, value: &str, max: usize) {
+        if value.chars().count() > max {
+            self.add(
+                field,
+                codes::TOO_LONG,
+                format!("{} must be at most {} characters", field, max),
+            );
+        }
+    }
+
+    /// Адрес e-mail; пустой считается отсутствующим
+    pub fn email(&mut self, field: &str, raw: Option<String>) -> Option<String> {
+        let email = normalize(raw);
+        if let Some(email) = &email
+            && !is_valid_email(email)
+        {
+            self.add(
+                field,
+                codes::INVALID_EMAIL,
+                format!("{} must be a valid e-mail address", field),
+            );
+        }
+        email
+    }
+
+    /// Телефон, приведённый к цифрам и `+`; пустой считается отсутствующим
+    pub fn phone(&mut self, field: &str, raw: Option<String>) -> Option<String> {
+        let phone = normalize(raw)?;
+        let sanitized = sanitize_phone(&phone);
+        if sanitized.is_none() {
+            self.add(
+                field,
+                codes::INVALID_PHONE,
+                format!("{} must contain 6 to 20 digits", field),
+            );
+        }
+        sanitized
+    }
+
+    /// UUID в каноническом или компактном виде
+    pub fn uuid(&mut self, field: &str, raw: &str) -> Uuid {
+        Uuid::parse_str(raw.trim()).unwrap_or_else(|_| {
+            self.add(
+                field,
+                codes::INVALID_UUID,
+                format!("{} must be a valid UUID", field),
+            );
+            Uuid::nil()
+        })
+    }
+
+    /// Целое число от `min` до `max`; значение вне пределов приводится к ближайшей границе
+    pub fn in_range(&mut self, field: &str, value: i64, min: i64, max: i64) -> i64 {
+        if !(min..=max).contains(&value) {
+            self.add(
+                field,
+                codes::OUT_OF_RANGE,
+                format!("{} must be between {} and {}", field, min, max),
+            );
+        }
+        value.clamp(min, max)
+    }
+
+    /// Абсолютный http(s) URL
+    pub fn http_url(&mut self, field: &str, raw: &str) -> String {
+        let value = raw.trim();
+        let valid = url::Url::parse(value)
+            .map(|url| matches!(url.scheme(), "http" | "https") && url.host_str().is_some())
+            .unwrap_or(false);
+        if !valid {
+            self.add(
+                field,
+                codes::INVALID_URL,
+                format!("{} must be an http or https URL", field),
+            );
+        }
+        value.to_string()
+    }
+}
+
+/// Ошибка одного поля, обнаруженная вне [`Validator`] (например, дубликат в БД)
+pub fn field_error(field: &str, code: &'static str, message: impl Into<String>) -> AppError {
+    let mut validator = Validator::new();
+    validator.add(field, code, message);
+    AppError::Validation(validator.errors)
+}
+
+/// Тело запроса в JSON. Лимит размера и тип содержимого проверяет
+/// `web::JsonConfig`, ошибки полей отдаются как `VALIDATION_ERROR`.
+#[derive(Debug)]
+pub struct Json<T>(pub T);
+
+impl<T> Json<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for Json<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: DeserializeOwned + 'static> FromRequest for Json<T> {
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        // Сначала разбирается сам JSON, затем поля: так известен путь к полю.
+        let body = web::Json::<Value>::from_request(req, payload);
+        Box::pin(async move {
+            let value = body.await?.into_inner();
+            serde_path_to_error::deserialize(value)
+                .map(Json)
+                .map_err(|err| deserialize_error(err).into())
+        })
+    }
+}
+
+/// Параметры строки запроса; ошибки полей отдаются как `VALIDATION_ERROR`.
+#[derive(Debug)]
+pub struct Query<T>(pub T);
+
+impl<T> Query<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for Query<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: DeserializeOwned> FromRequest for Query<T> {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let params = url::form_urlencoded::parse(req.query_string().as_bytes());
+        ready(
+            serde_path_to_error::deserialize(serde_urlencoded::Deserializer::new(params))
+                .map(Query)
+                .map_err(|err| deserialize_error(err).into()),
+        )
+    }
+}
+
+/// Ошибка десериализации в виде ошибки поля. serde сообщает об
+/// отсутствующем или лишнем поле на уровне объекта, так что имя поля
+/// дописывается к пути; ошибка всего документа остаётся `INVALID_INPUT`.
+fn deserialize_error<E: fmt::Display>(err: serde_path_to_error::Error<E>) -> AppError {
+    let message = err.inner().to_string();
+    let path = err.path().to_string();
+    let parent = if path == "." { "" } else { path.as_str() };
+    let named = |prefix: &str, code: &'static str| {
+        let name = message.strip_prefix(prefix)?.split('`').next()?;
+        let field = if parent.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}.{}", parent, name)
+        };
+        Some(field_error(&field, code, message.clone()))
+    };
+
+    named("missing field `", codes::REQUIRED)
+        .or_else(|| named("unknown field `", codes::UNSUPPORTED))
+        .unwrap_or_else(|| {
+            if parent.is_empty() {
+                AppError::InvalidInput(message.clone())
+            } else {
+                field_error(parent, codes::INVALID_VALUE, message.clone())
+            }
+        })
+}
+
+pub fn is_valid_email(email: &str) -> bool {
     EMAIL_RE.is_match(email)
 }
+
 pub fn sanitize_phone(phone: &str) -> Option<String> {
     let digits: String = phone
         .chars()
@@ -22,6 +300,9 @@ pub fn sanitize_phone(phone: &str) -> Option<String> {
         None
     }
 }
-pub fn ensure_max_len(value: &str, max: usize) -> bool {
-    value.len() <= max
+
+fn normalize(value: Option<String>) -> Option<String> {
+    value
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
 }
@@ -1,19 +1,18 @@
 use actix_web::HttpRequest;
-use sea_orm::DatabaseConnection;
 use url::Url;
 use uuid::Uuid;
 
 use crate::{
-    api::helpers::get_company_api_key, app_state::AppState, errors::AppError,
+    api::helpers::get_company_api_key, app_state::AppState, config::Config, errors::AppError,
     services::wazzup_api::WebhookSubscriptions,
 };
 
 /// Получает API ключ компании из базы данных по UUID
 pub async fn get_company_api_key_by_uuid(
     company_uuid: &Uuid,
-    db: &DatabaseConnection,
+    app_state: &AppState,
 ) -> Result<String, AppError> {
-    match get_company_api_key(company_uuid, db).await {
+    match get_company_api_key(company_uuid, app_state).await {
         Ok(key) => Ok(key),
         Err(AppError::NotFound(_)) => Err(AppError::Unauthorized("Company not found".to_string())),
         Err(err) => Err(err),
@@ -27,34 +26,62 @@ pub fn parse_company_id(raw: &str) -> Result<Uuid, AppError> {
 
 /// Строит URI для webhook endpoint
 pub fn build_webhook_uri(app_state: &AppState, req: &HttpRequest, company_uuid: &Uuid) -> String {
-    let webhook_port = app_state.config.effective_webhook_port();
-
-    if let Some(public_url) = &app_state.config.public_url {
-        if let Ok(mut url) = Url::parse(public_url) {
-            let _ = url.set_port(Some(webhook_port));
-            url.set_path(&format!("/api/webhook/{}", company_uuid));
-            return url.to_string();
-        }
+    if let Some(uri) = public_webhook_uri(&app_state.config, company_uuid) {
+        return uri;
     }
 
     let conn_info = req.connection_info().clone();
-    let scheme = conn_info.scheme().to_owned();
+    // Приёмник с собственным сертификатом доступен только по HTTPS
+    let scheme = match app_state.config.webhook_tls() {
+        Some(_) => "https".to_string(),
+        None => conn_info.scheme().to_owned(),
+    };
     let host = conn_info.host().to_owned();
     let base = format!("{}://{}", scheme, host);
 
     match Url::parse(&base) {
-        Ok(mut url) => {
-            let _ = url.set_port(Some(webhook_port));
-            url.set_path(&format!("/api/webhook/{}", company_uuid));
-            url.to_string()
-        }
+        Ok(url) => webhook_uri_on(url, app_state.config.effective_webhook_port(), company_uuid),
         Err(_) => format!(
             "http://localhost:{}/api/webhook/{}",
-            webhook_port, company_uuid
+            app_state.config.effective_webhook_port(),
+            company_uuid
         ),
     }
 }
 
+/// URI вебхука на основе `public_url`; `None`, если он не задан
+pub fn public_webhook_uri(config: &Config, company_uuid: &Uuid) -> Option<String> {
+    let url = Url::parse(config.public_url.as_deref()?).ok()?;
+    Some(webhook_uri_on(
+        url,
+        config.effective_webhook_port(),
+        company_uuid,
+    ))
+}
+
+/// URI вебхука без входящего запроса (CLI): `public_url` или адрес слушателя
+pub fn configured_webhook_uri(config: &Config, company_uuid: &Uuid) -> String {
+    public_webhook_uri(config, company_uuid).unwrap_or_else(|| {
+        format!(
+            "{}://{}:{}/api/webhook/{}",
+            if config.webhook_tls().is_some() {
+                "https"
+            } else {
+                "http"
+            },
+            config.effective_webhook_host(),
+            config.effective_webhook_port(),
+            company_uuid
+        )
+    })
+}
+
+fn webhook_uri_on(mut url: Url, webhook_port: u16, company_uuid: &Uuid) -> String {
+    let _ = url.set_port(Some(webhook_port));
+    url.set_path(&format!("/api/webhook/{}", company_uuid));
+    url.to_string()
+}
+
 /// Создает дефолтные настройки подписок на вебхуки
 pub fn default_webhook_subscriptions() -> WebhookSubscriptions {
     WebhookSubscriptions {
@@ -3,6 +3,8 @@ use sea_orm::EntityTrait;
 
 use crate::{
     api::helpers::uuid_to_bytes,
+    api::permissions::{Admin, Require},
+    api::validation,
     app_state::AppState,
     database::models::companies,
     errors::AppError,
@@ -66,7 +68,7 @@ pub async fn validate_webhook(
 pub async fn handle_webhook(
     app_state: web::Data<AppState>,
     path: web::Path<String>,
-    body: web::Json<webhook_handler::WebhookRequest>,
+    body: validation::Json<webhook_handler::WebhookRequest>,
 ) -> Result<HttpResponse, AppError> {
     let company_uuid = parse_company_id(&path.into_inner())?;
 
@@ -84,6 +86,7 @@ pub async fn handle_webhook(
         &app_state.db,
         &app_state.bot_service,
         &app_state.wazzup_api,
+        &app_state.events,
     )
     .await?;
 
@@ -109,6 +112,7 @@ pub async fn handle_webhook(
 #[get("/{id}/connect")]
 pub async fn connect_webhooks(
     app_state: web::Data<AppState>,
+    _access: Require<Admin>,
     req: HttpRequest,
     path: web::Path<String>,
 ) -> Result<HttpResponse, AppError> {
@@ -119,7 +123,7 @@ pub async fn connect_webhooks(
     log::debug!("Parsed UUID: {}", company_uuid);
     log::debug!("UUID bytes: {:?}", uuid_to_bytes(&company_uuid));
 
-    let api_key = get_company_api_key_by_uuid(&company_uuid, &app_state.db).await?;
+    let api_key = get_company_api_key_by_uuid(&company_uuid, &app_state).await?;
 
     let webhooks_uri = build_webhook_uri(&app_state, &req, &company_uuid);
     let subscriptions = default_webhook_subscriptions();
@@ -157,6 +161,7 @@ pub async fn connect_webhooks(
 #[post("/{id}/test")]
 pub async fn test_webhook(
     app_state: web::Data<AppState>,
+    _access: Require<Admin>,
     path: web::Path<String>,
 ) -> Result<HttpResponse, AppError> {
     let company_uuid = parse_company_id(&path.into_inner())?;
@@ -164,7 +169,9 @@ pub async fn test_webhook(
     let test_webhook_request = webhook_handler::WebhookRequest {
         test: Some(true),
         messages: None,
+        statuses: None,
         contacts: None,
+        channels_updates: None,
     };
 
     webhook_handler::handle_webhook(
@@ -173,6 +180,7 @@ pub async fn test_webhook(
         &app_state.db,
         &app_state.bot_service,
         &app_state.wazzup_api,
+        &app_state.events,
     )
     .await?;
 
@@ -12,5 +12,6 @@ pub use structures::{
 };
 
 pub use functions::{
-    build_webhook_uri, default_webhook_subscriptions, get_company_api_key_by_uuid, parse_company_id,
+    build_webhook_uri, configured_webhook_uri, default_webhook_subscriptions,
+    get_company_api_key_by_uuid, parse_company_id, public_webhook_uri,
 };
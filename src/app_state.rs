@@ -1,5 +1,9 @@
 use crate::config::Config;
+use crate::services::api_keys::ApiKeyVault;
+use crate::services::background::TaskManager;
 use crate::services::bot_service::BotService;
+use crate::services::events::EventBus;
+use crate::services::outgoing_webhooks::OutgoingWebhookService;
 use crate::services::wazzup_api::WazzupApiService;
 use sea_orm::DatabaseConnection;
 
@@ -9,4 +13,8 @@ pub struct AppState {
     pub config: Config,
     pub wazzup_api: WazzupApiService,
     pub bot_service: BotService,
+    pub events: EventBus,
+    pub outgoing_webhooks: OutgoingWebhookService,
+    pub api_keys: ApiKeyVault,
+    pub tasks: TaskManager,
 }
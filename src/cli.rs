@@ -0,0 +1,157 @@
+use std::path::PathBuf;
+
+use clap::{ArgGroup, Args, Parser, Subcommand};
+use uuid::Uuid;
+
+/// Сервис интеграции с Wazzup
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Cli {
+    /// TOML-файл конфигурации; переменные окружения и флаги его переопределяют
+    #[arg(long, global = true, env = "WAZZUP_CONFIG", value_name = "FILE")]
+    pub config: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub overrides: ConfigOverrides,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Запускает API и приёмник вебхуков (по умолчанию)
+    Serve,
+    /// Запускает только API; потоки событий не получают событий входящих вебхуков
+    ServeApi,
+    /// Запускает только приёмник вебхуков
+    ServeWebhooks,
+    /// Применяет миграции схемы базы данных
+    Migrate {
+        /// Только показать применённые и ожидающие миграции
+        #[arg(long)]
+        status: bool,
+    },
+    /// Шифрует сохранённые API ключи Wazzup текущим мастер-ключом
+    EncryptKeys {
+        /// Только показать, сколько ключей будет зашифровано
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Проверяет конфигурацию и выводит итоговые настройки, не запуская сервер
+    CheckConfig,
+    /// Подписывает вебхуки Wazzup на этот сервис (нужен `public_url`)
+    #[command(group(ArgGroup::new("target").required(true).args(["company", "all"])))]
+    ConnectWebhooks {
+        /// UUID компании
+        #[arg(long)]
+        company: Option<Uuid>,
+        /// Все активные компании с API ключом
+        #[arg(long)]
+        all: bool,
+    },
+    /// Загружает каналы и контакты компании из Wazzup
+    Sync {
+        /// UUID компании
+        #[arg(long)]
+        company: Uuid,
+    },
+    /// Повторно обрабатывает сохранённое тело вебхука
+    ReplayWebhook {
+        /// UUID компании
+        #[arg(long)]
+        company: Uuid,
+        /// JSON-файл с телом вебхука
+        file: PathBuf,
+    },
+    /// Загружает историю сообщений чата из Wazzup
+    BackfillMessages {
+        /// UUID компании
+        #[arg(long)]
+        company: Uuid,
+        /// ID чата в Wazzup
+        #[arg(long)]
+        chat: String,
+    },
+    /// Индексирует для поиска сообщения, сохранённые до появления поискового индекса
+    IndexMessages,
+    /// Удаляет завершённые доставки исходящих вебхуков, задания очереди и истёкшие токены
+    Purge {
+        /// Удалять записи старше указанного числа дней
+        #[arg(long, default_value_t = 90, value_parser = clap::value_parser!(u32).range(1..))]
+        older_than_days: u32,
+        /// Удалять также сообщения
+        #[arg(long)]
+        messages: bool,
+        /// Только показать, сколько записей будет удалено
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Выводит диагностику интеграции компании в формате JSON
+    Diagnose {
+        /// UUID компании
+        #[arg(long)]
+        company: Uuid,
+    },
+}
+
+impl Command {
+    /// Разовые задачи обслуживания, которым не нужны слушатели
+    pub fn is_task(&self) -> bool {
+        matches!(
+            self,
+            Command::ConnectWebhooks { .. }
+                | Command::Sync { .. }
+                | Command::ReplayWebhook { .. }
+                | Command::BackfillMessages { .. }
+                | Command::IndexMessages
+                | Command::Purge { .. }
+                | Command::Diagnose { .. }
+        )
+    }
+}
+
+/// Флаги, переопределяющие файл и переменные окружения
+#[derive(Debug, Default, Args)]
+pub struct ConfigOverrides {
+    /// Адрес API
+    #[arg(long, global = true)]
+    pub host: Option<String>,
+    /// Порт API
+    #[arg(long, global = true)]
+    pub port: Option<u16>,
+    /// Адрес приёмника вебхуков
+    #[arg(long, global = true)]
+    pub webhook_host: Option<String>,
+    /// Порт приёмника вебхуков
+    #[arg(long, global = true)]
+    pub webhook_port: Option<u16>,
+    /// Адрес базы данных
+    #[arg(long, global = true, value_name = "URL")]
+    pub database_url: Option<String>,
+    /// Число воркеров каждого слушателя
+    #[arg(long, global = true)]
+    pub workers: Option<usize>,
+}
+
+impl ConfigOverrides {
+    /// Пары ключ-значение для [`crate::config::Config::load`]
+    pub fn pairs(&self) -> Vec<(&'static str, String)> {
+        let mut pairs = Vec::new();
+        let mut push = |key, value: Option<String>| {
+            if let Some(value) = value {
+                pairs.push((key, value));
+            }
+        };
+        push("host", self.host.clone());
+        push("port", self.port.map(|port| port.to_string()));
+        push("webhook_host", self.webhook_host.clone());
+        push(
+            "webhook_port",
+            self.webhook_port.map(|port| port.to_string()),
+        );
+        push("database_url", self.database_url.clone());
+        push("workers", self.workers.map(|workers| workers.to_string()));
+        pairs
+    }
+}
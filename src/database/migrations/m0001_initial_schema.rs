@@ -0,0 +1,1240 @@
+//! Every table of the service as it stood when versioned migrations were
+//! introduced. The statements are spelled out instead of being derived from
+//! the entities in `database::models`, so later changes to an entity cannot
+//! change what this migration creates; such changes need a migration of
+//! their own. Tables are listed so that foreign keys only point to tables
+//! created before them.
+
+use sea_orm::{
+    ConnectionTrait, DbErr,
+    sea_query::{
+        Alias, ColumnDef, ForeignKey, ForeignKeyAction, Index, Table, TableCreateStatement,
+    },
+};
+
+pub(super) async fn up<C>(db: &C) -> Result<(), DbErr>
+where
+    C: ConnectionTrait,
+{
+    let backend = db.get_database_backend();
+
+    let statements = [
+        companies(),
+        resource_roles(),
+        resources(),
+        users(),
+        company_users(),
+        tokens(),
+        clients(),
+        client_tags(),
+        client_tag_assignments(),
+        contact_types(),
+        contacts(),
+        channels(),
+        channel_settings(),
+        chats(),
+        messages(),
+        chat_transfers(),
+        chat_read_markers(),
+        message_search_index(),
+        services(),
+        service_required_roles(),
+        bookings(),
+        booking_resources(),
+        schedule_templates(),
+        availability_exceptions(),
+        projects(),
+        deals(),
+        task_statuses(),
+        tasks(),
+        task_assignments(),
+        outgoing_webhooks(),
+        outgoing_webhook_deliveries(),
+    ];
+
+    for mut statement in statements {
+        statement.if_not_exists();
+        db.execute(backend.build(&statement)).await?;
+    }
+
+    Ok(())
+}
+
+fn companies() -> TableCreateStatement {
+    Table::create()
+        .table(Alias::new("companies"))
+        .col(
+            ColumnDef::new(Alias::new("id"))
+                .binary_len(16)
+                .not_null()
+                .primary_key(),
+        )
+        .col(ColumnDef::new(Alias::new("name")).string())
+        .col(ColumnDef::new(Alias::new("description")).text())
+        .col(ColumnDef::new(Alias::new("email")).string())
+        .col(ColumnDef::new(Alias::new("phone")).string())
+        .col(ColumnDef::new(Alias::new("wazzup_api_key")).string())
+        .col(ColumnDef::new(Alias::new("is_active")).tiny_integer())
+        .col(ColumnDef::new(Alias::new("subscription_tier")).string())
+        .col(ColumnDef::new(Alias::new("created_at")).timestamp_with_time_zone())
+        .col(ColumnDef::new(Alias::new("updated_at")).timestamp_with_time_zone())
+        .col(ColumnDef::new(Alias::new("last_webhook_at")).timestamp_with_time_zone())
+        .to_owned()
+}
+
+fn resource_roles() -> TableCreateStatement {
+    Table::create()
+        .table(Alias::new("resource_roles"))
+        .col(
+            ColumnDef::new(Alias::new("id"))
+                .binary_len(16)
+                .not_null()
+                .primary_key(),
+        )
+        .col(
+            ColumnDef::new(Alias::new("name"))
+                .string()
+                .not_null()
+                .unique_key(),
+        )
+        .col(ColumnDef::new(Alias::new("description")).text())
+        .to_owned()
+}
+
+fn resources() -> TableCreateStatement {
+    Table::create()
+        .table(Alias::new("resources"))
+        .col(
+            ColumnDef::new(Alias::new("id"))
+                .binary_len(16)
+                .not_null()
+                .primary_key(),
+        )
+        .col(ColumnDef::new(Alias::new("name")).string().not_null())
+        .col(ColumnDef::new(Alias::new("type")).string().not_null())
+        .col(ColumnDef::new(Alias::new("role_id")).binary_len(16))
+        .col(ColumnDef::new(Alias::new("company_id")).binary_len(16))
+        .col(ColumnDef::new(Alias::new("quantity")).integer().not_null())
+        .col(ColumnDef::new(Alias::new("image_path")).string())
+        .col(
+            ColumnDef::new(Alias::new("created_at"))
+                .timestamp_with_time_zone()
+                .not_null(),
+        )
+        .foreign_key(
+            ForeignKey::create()
+                .name("fk-resources-company_id")
+                .from(Alias::new("resources"), Alias::new("company_id"))
+                .to(Alias::new("companies"), Alias::new("id"))
+                .on_delete(ForeignKeyAction::NoAction)
+                .on_update(ForeignKeyAction::NoAction),
+        )
+        .foreign_key(
+            ForeignKey::create()
+                .name("fk-resources-role_id")
+                .from(Alias::new("resources"), Alias::new("role_id"))
+                .to(Alias::new("resource_roles"), Alias::new("id"))
+                .on_delete(ForeignKeyAction::NoAction)
+                .on_update(ForeignKeyAction::NoAction),
+        )
+        .to_owned()
+}
+
+fn users() -> TableCreateStatement {
+    Table::create()
+        .table(Alias::new("users"))
+        .col(
+            ColumnDef::new(Alias::new("id"))
+                .binary_len(16)
+                .not_null()
+                .primary_key(),
+        )
+        .col(ColumnDef::new(Alias::new("name")).string())
+        .col(
+            ColumnDef::new(Alias::new("login"))
+                .string()
+                .not_null()
+                .unique_key(),
+        )
+        .col(
+            ColumnDef::new(Alias::new("email"))
+                .string()
+                .not_null()
+                .unique_key(),
+        )
+        .col(ColumnDef::new(Alias::new("password")).string().not_null())
+        .col(ColumnDef::new(Alias::new("role")).string())
+        .col(ColumnDef::new(Alias::new("resource_id")).binary_len(16))
+        .col(ColumnDef::new(Alias::new("bot_hook")).string())
+        .foreign_key(
+            ForeignKey::create()
+                .name("fk-users-resource_id")
+                .from(Alias::new("users"), Alias::new("resource_id"))
+                .to(Alias::new("resources"), Alias::new("id"))
+                .on_delete(ForeignKeyAction::NoAction)
+                .on_update(ForeignKeyAction::NoAction),
+        )
+        .to_owned()
+}
+
+fn company_users() -> TableCreateStatement {
+    Table::create()
+        .table(Alias::new("company_users"))
+        .col(
+            ColumnDef::new(Alias::new("company_id"))
+                .binary_len(16)
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(Alias::new("user_id"))
+                .binary_len(16)
+                .not_null(),
+        )
+        .col(ColumnDef::new(Alias::new("role")).string())
+        .primary_key(
+            Index::create()
+                .name("pk-company_users")
+                .col(Alias::new("company_id"))
+                .col(Alias::new("user_id"))
+                .primary(),
+        )
+        .foreign_key(
+            ForeignKey::create()
+                .name("fk-company_users-company_id")
+                .from(Alias::new("company_users"), Alias::new("company_id"))
+                .to(Alias::new("companies"), Alias::new("id"))
+                .on_delete(ForeignKeyAction::NoAction)
+                .on_update(ForeignKeyAction::NoAction),
+        )
+        .foreign_key(
+            ForeignKey::create()
+                .name("fk-company_users-user_id")
+                .from(Alias::new("company_users"), Alias::new("user_id"))
+                .to(Alias::new("users"), Alias::new("id"))
+                .on_delete(ForeignKeyAction::NoAction)
+                .on_update(ForeignKeyAction::NoAction),
+        )
+        .to_owned()
+}
+
+fn tokens() -> TableCreateStatement {
+    Table::create()
+        .table(Alias::new("tokens"))
+        .col(
+            ColumnDef::new(Alias::new("id"))
+                .binary_len(16)
+                .not_null()
+                .primary_key(),
+        )
+        .col(ColumnDef::new(Alias::new("name")).string())
+        .col(
+            ColumnDef::new(Alias::new("token_hash"))
+                .string()
+                .not_null()
+                .unique_key(),
+        )
+        .col(
+            ColumnDef::new(Alias::new("user_id"))
+                .binary_len(16)
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(Alias::new("created_at"))
+                .timestamp_with_time_zone()
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(Alias::new("last_used_at"))
+                .timestamp_with_time_zone()
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(Alias::new("expires_at"))
+                .timestamp_with_time_zone()
+                .not_null(),
+        )
+        .col(ColumnDef::new(Alias::new("company_id")).binary_len(16))
+        .foreign_key(
+            ForeignKey::create()
+                .name("fk-tokens-user_id")
+                .from(Alias::new("tokens"), Alias::new("user_id"))
+                .to(Alias::new("users"), Alias::new("id"))
+                .on_delete(ForeignKeyAction::NoAction)
+                .on_update(ForeignKeyAction::NoAction),
+        )
+        .to_owned()
+}
+
+fn clients() -> TableCreateStatement {
+    Table::create()
+        .table(Alias::new("clients"))
+        .col(
+            ColumnDef::new(Alias::new("id"))
+                .binary_len(16)
+                .not_null()
+                .primary_key(),
+        )
+        .col(ColumnDef::new(Alias::new("company_id")).binary_len(16))
+        .col(ColumnDef::new(Alias::new("full_name")).string().not_null())
+        .col(ColumnDef::new(Alias::new("email")).string())
+        .col(ColumnDef::new(Alias::new("phone")).string())
+        .col(
+            ColumnDef::new(Alias::new("responsible_user_id"))
+                .binary_len(16)
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(Alias::new("created_at"))
+                .timestamp_with_time_zone()
+                .not_null(),
+        )
+        .foreign_key(
+            ForeignKey::create()
+                .name("fk-clients-company_id")
+                .from(Alias::new("clients"), Alias::new("company_id"))
+                .to(Alias::new("companies"), Alias::new("id"))
+                .on_delete(ForeignKeyAction::NoAction)
+                .on_update(ForeignKeyAction::NoAction),
+        )
+        .foreign_key(
+            ForeignKey::create()
+                .name("fk-clients-responsible_user_id")
+                .from(Alias::new("clients"), Alias::new("responsible_user_id"))
+                .to(Alias::new("users"), Alias::new("id"))
+                .on_delete(ForeignKeyAction::NoAction)
+                .on_update(ForeignKeyAction::NoAction),
+        )
+        .to_owned()
+}
+
+fn client_tags() -> TableCreateStatement {
+    Table::create()
+        .table(Alias::new("client_tags"))
+        .col(
+            ColumnDef::new(Alias::new("id"))
+                .binary_len(16)
+                .not_null()
+                .primary_key(),
+        )
+        .col(
+            ColumnDef::new(Alias::new("value"))
+                .string()
+                .not_null()
+                .unique_key(),
+        )
+        .to_owned()
+}
+
+fn client_tag_assignments() -> TableCreateStatement {
+    Table::create()
+        .table(Alias::new("client_tag_assignments"))
+        .col(
+            ColumnDef::new(Alias::new("tag_id"))
+                .binary_len(16)
+                .not_null()
+                .primary_key(),
+        )
+        .col(
+            ColumnDef::new(Alias::new("client_id"))
+                .binary_len(16)
+                .not_null(),
+        )
+        .foreign_key(
+            ForeignKey::create()
+                .name("fk-client_tag_assignments-tag_id")
+                .from(Alias::new("client_tag_assignments"), Alias::new("tag_id"))
+                .to(Alias::new("client_tags"), Alias::new("id"))
+                .on_delete(ForeignKeyAction::NoAction)
+                .on_update(ForeignKeyAction::NoAction),
+        )
+        .foreign_key(
+            ForeignKey::create()
+                .name("fk-client_tag_assignments-client_id")
+                .from(
+                    Alias::new("client_tag_assignments"),
+                    Alias::new("client_id"),
+                )
+                .to(Alias::new("clients"), Alias::new("id"))
+                .on_delete(ForeignKeyAction::NoAction)
+                .on_update(ForeignKeyAction::NoAction),
+        )
+        .to_owned()
+}
+
+fn contact_types() -> TableCreateStatement {
+    Table::create()
+        .table(Alias::new("contact_types"))
+        .col(
+            ColumnDef::new(Alias::new("id"))
+                .binary_len(16)
+                .not_null()
+                .primary_key(),
+        )
+        .col(ColumnDef::new(Alias::new("value")).string().not_null())
+        .to_owned()
+}
+
+fn contacts() -> TableCreateStatement {
+    Table::create()
+        .table(Alias::new("contacts"))
+        .col(
+            ColumnDef::new(Alias::new("id"))
+                .binary_len(16)
+                .not_null()
+                .primary_key(),
+        )
+        .col(
+            ColumnDef::new(Alias::new("contact_type"))
+                .binary_len(16)
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(Alias::new("user_id"))
+                .binary_len(16)
+                .not_null(),
+        )
+        .col(ColumnDef::new(Alias::new("value")).string().not_null())
+        .foreign_key(
+            ForeignKey::create()
+                .name("fk-contacts-contact_type")
+                .from(Alias::new("contacts"), Alias::new("contact_type"))
+                .to(Alias::new("contact_types"), Alias::new("id"))
+                .on_delete(ForeignKeyAction::NoAction)
+                .on_update(ForeignKeyAction::NoAction),
+        )
+        .foreign_key(
+            ForeignKey::create()
+                .name("fk-contacts-user_id")
+                .from(Alias::new("contacts"), Alias::new("user_id"))
+                .to(Alias::new("users"), Alias::new("id"))
+                .on_delete(ForeignKeyAction::NoAction)
+                .on_update(ForeignKeyAction::NoAction),
+        )
+        .to_owned()
+}
+
+fn channels() -> TableCreateStatement {
+    Table::create()
+        .table(Alias::new("channels"))
+        .col(
+            ColumnDef::new(Alias::new("id"))
+                .binary_len(16)
+                .not_null()
+                .primary_key(),
+        )
+        .col(ColumnDef::new(Alias::new("type")).string().not_null())
+        .col(ColumnDef::new(Alias::new("company_id")).binary_len(16))
+        .foreign_key(
+            ForeignKey::create()
+                .name("fk-channels-company_id")
+                .from(Alias::new("channels"), Alias::new("company_id"))
+                .to(Alias::new("companies"), Alias::new("id"))
+                .on_delete(ForeignKeyAction::SetNull)
+                .on_update(ForeignKeyAction::NoAction),
+        )
+        .to_owned()
+}
+
+fn channel_settings() -> TableCreateStatement {
+    Table::create()
+        .table(Alias::new("channel_settings"))
+        .col(
+            ColumnDef::new(Alias::new("id"))
+                .binary_len(16)
+                .not_null()
+                .primary_key(),
+        )
+        .col(
+            ColumnDef::new(Alias::new("user_id"))
+                .binary_len(16)
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(Alias::new("channel_id"))
+                .binary_len(16)
+                .not_null(),
+        )
+        .col(ColumnDef::new(Alias::new("role")).string().not_null())
+        .col(
+            ColumnDef::new(Alias::new("receives_messages"))
+                .tiny_integer()
+                .not_null(),
+        )
+        .foreign_key(
+            ForeignKey::create()
+                .name("fk-channel_settings-channel_id")
+                .from(Alias::new("channel_settings"), Alias::new("channel_id"))
+                .to(Alias::new("channels"), Alias::new("id"))
+                .on_delete(ForeignKeyAction::NoAction)
+                .on_update(ForeignKeyAction::NoAction),
+        )
+        .foreign_key(
+            ForeignKey::create()
+                .name("fk-channel_settings-user_id")
+                .from(Alias::new("channel_settings"), Alias::new("user_id"))
+                .to(Alias::new("users"), Alias::new("id"))
+                .on_delete(ForeignKeyAction::NoAction)
+                .on_update(ForeignKeyAction::NoAction),
+        )
+        .to_owned()
+}
+
+fn chats() -> TableCreateStatement {
+    Table::create()
+        .table(Alias::new("chats"))
+        .col(
+            ColumnDef::new(Alias::new("id"))
+                .string()
+                .not_null()
+                .primary_key(),
+        )
+        .col(
+            ColumnDef::new(Alias::new("channel_id"))
+                .binary_len(16)
+                .not_null(),
+        )
+        .col(ColumnDef::new(Alias::new("client_id")).binary_len(16))
+        .col(ColumnDef::new(Alias::new("name")).string().not_null())
+        .foreign_key(
+            ForeignKey::create()
+                .name("fk-chats-channel_id")
+                .from(Alias::new("chats"), Alias::new("channel_id"))
+                .to(Alias::new("channels"), Alias::new("id"))
+                .on_delete(ForeignKeyAction::NoAction)
+                .on_update(ForeignKeyAction::NoAction),
+        )
+        .foreign_key(
+            ForeignKey::create()
+                .name("fk-chats-client_id")
+                .from(Alias::new("chats"), Alias::new("client_id"))
+                .to(Alias::new("clients"), Alias::new("id"))
+                .on_delete(ForeignKeyAction::NoAction)
+                .on_update(ForeignKeyAction::NoAction),
+        )
+        .to_owned()
+}
+
+fn messages() -> TableCreateStatement {
+    Table::create()
+        .table(Alias::new("messages"))
+        .col(
+            ColumnDef::new(Alias::new("id"))
+                .binary_len(16)
+                .not_null()
+                .primary_key(),
+        )
+        .col(ColumnDef::new(Alias::new("content")).json().not_null())
+        .col(ColumnDef::new(Alias::new("chat_id")).string().not_null())
+        .col(ColumnDef::new(Alias::new("is_inbound")).tiny_integer())
+        .col(ColumnDef::new(Alias::new("is_echo")).tiny_integer())
+        .col(ColumnDef::new(Alias::new("direction_status")).string())
+        .col(ColumnDef::new(Alias::new("author_user_id")).binary_len(16))
+        .col(
+            ColumnDef::new(Alias::new("created_at"))
+                .timestamp_with_time_zone()
+                .not_null(),
+        )
+        .foreign_key(
+            ForeignKey::create()
+                .name("fk-messages-chat_id")
+                .from(Alias::new("messages"), Alias::new("chat_id"))
+                .to(Alias::new("chats"), Alias::new("id"))
+                .on_delete(ForeignKeyAction::NoAction)
+                .on_update(ForeignKeyAction::NoAction),
+        )
+        .foreign_key(
+            ForeignKey::create()
+                .name("fk-messages-author_user_id")
+                .from(Alias::new("messages"), Alias::new("author_user_id"))
+                .to(Alias::new("users"), Alias::new("id"))
+                .on_delete(ForeignKeyAction::NoAction)
+                .on_update(ForeignKeyAction::NoAction),
+        )
+        .to_owned()
+}
+
+fn chat_transfers() -> TableCreateStatement {
+    Table::create()
+        .table(Alias::new("chat_transfers"))
+        .col(
+            ColumnDef::new(Alias::new("id"))
+                .binary_len(16)
+                .not_null()
+                .primary_key(),
+        )
+        .col(
+            ColumnDef::new(Alias::new("chat_id"))
+                .binary_len(16)
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(Alias::new("from_user_id"))
+                .binary_len(16)
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(Alias::new("to_user_id"))
+                .binary_len(16)
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(Alias::new("created_at"))
+                .timestamp_with_time_zone()
+                .not_null(),
+        )
+        .foreign_key(
+            ForeignKey::create()
+                .name("fk-chat_transfers-chat_id")
+                .from(Alias::new("chat_transfers"), Alias::new("chat_id"))
+                .to(Alias::new("chats"), Alias::new("id"))
+                .on_delete(ForeignKeyAction::NoAction)
+                .on_update(ForeignKeyAction::NoAction),
+        )
+        .foreign_key(
+            ForeignKey::create()
+                .name("fk-chat_transfers-from_user_id")
+                .from(Alias::new("chat_transfers"), Alias::new("from_user_id"))
+                .to(Alias::new("users"), Alias::new("id"))
+                .on_delete(ForeignKeyAction::NoAction)
+                .on_update(ForeignKeyAction::NoAction),
+        )
+        .foreign_key(
+            ForeignKey::create()
+                .name("fk-chat_transfers-to_user_id")
+                .from(Alias::new("chat_transfers"), Alias::new("to_user_id"))
+                .to(Alias::new("users"), Alias::new("id"))
+                .on_delete(ForeignKeyAction::NoAction)
+                .on_update(ForeignKeyAction::NoAction),
+        )
+        .to_owned()
+}
+
+fn chat_read_markers() -> TableCreateStatement {
+    Table::create()
+        .table(Alias::new("chat_read_markers"))
+        .col(ColumnDef::new(Alias::new("chat_id")).string().not_null())
+        .col(
+            ColumnDef::new(Alias::new("user_id"))
+                .binary_len(16)
+                .not_null(),
+        )
+        .col(ColumnDef::new(Alias::new("last_read_message_id")).binary_len(16))
+        .col(
+            ColumnDef::new(Alias::new("last_read_at"))
+                .timestamp_with_time_zone()
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(Alias::new("updated_at"))
+                .timestamp_with_time_zone()
+                .not_null(),
+        )
+        .primary_key(
+            Index::create()
+                .name("pk-chat_read_markers")
+                .col(Alias::new("chat_id"))
+                .col(Alias::new("user_id"))
+                .primary(),
+        )
+        .foreign_key(
+            ForeignKey::create()
+                .name("fk-chat_read_markers-chat_id")
+                .from(Alias::new("chat_read_markers"), Alias::new("chat_id"))
+                .to(Alias::new("chats"), Alias::new("id"))
+                .on_delete(ForeignKeyAction::Cascade)
+                .on_update(ForeignKeyAction::NoAction),
+        )
+        .foreign_key(
+            ForeignKey::create()
+                .name("fk-chat_read_markers-user_id")
+                .from(Alias::new("chat_read_markers"), Alias::new("user_id"))
+                .to(Alias::new("users"), Alias::new("id"))
+                .on_delete(ForeignKeyAction::Cascade)
+                .on_update(ForeignKeyAction::NoAction),
+        )
+        .to_owned()
+}
+
+fn message_search_index() -> TableCreateStatement {
+    Table::create()
+        .table(Alias::new("message_search_index"))
+        .col(
+            ColumnDef::new(Alias::new("message_id"))
+                .binary_len(16)
+                .not_null()
+                .primary_key(),
+        )
+        .col(ColumnDef::new(Alias::new("company_id")).binary_len(16))
+        .col(ColumnDef::new(Alias::new("chat_id")).string().not_null())
+        .col(ColumnDef::new(Alias::new("body")).text().not_null())
+        .col(
+            ColumnDef::new(Alias::new("created_at"))
+                .timestamp_with_time_zone()
+                .not_null(),
+        )
+        .foreign_key(
+            ForeignKey::create()
+                .name("fk-message_search_index-message_id")
+                .from(Alias::new("message_search_index"), Alias::new("message_id"))
+                .to(Alias::new("messages"), Alias::new("id"))
+                .on_delete(ForeignKeyAction::Cascade)
+                .on_update(ForeignKeyAction::NoAction),
+        )
+        .foreign_key(
+            ForeignKey::create()
+                .name("fk-message_search_index-chat_id")
+                .from(Alias::new("message_search_index"), Alias::new("chat_id"))
+                .to(Alias::new("chats"), Alias::new("id"))
+                .on_delete(ForeignKeyAction::Cascade)
+                .on_update(ForeignKeyAction::NoAction),
+        )
+        .to_owned()
+}
+
+fn services() -> TableCreateStatement {
+    Table::create()
+        .table(Alias::new("services"))
+        .col(
+            ColumnDef::new(Alias::new("id"))
+                .binary_len(16)
+                .not_null()
+                .primary_key(),
+        )
+        .col(ColumnDef::new(Alias::new("name")).string().not_null())
+        .col(ColumnDef::new(Alias::new("company_id")).binary_len(16))
+        .col(ColumnDef::new(Alias::new("duration")).integer().not_null())
+        .col(ColumnDef::new(Alias::new("price")).integer().not_null())
+        .col(ColumnDef::new(Alias::new("description")).text())
+        .col(ColumnDef::new(Alias::new("image_path")).string())
+        .col(
+            ColumnDef::new(Alias::new("is_active"))
+                .tiny_integer()
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(Alias::new("created_at"))
+                .timestamp_with_time_zone()
+                .not_null(),
+        )
+        .foreign_key(
+            ForeignKey::create()
+                .name("fk-services-company_id")
+                .from(Alias::new("services"), Alias::new("company_id"))
+                .to(Alias::new("companies"), Alias::new("id"))
+                .on_delete(ForeignKeyAction::NoAction)
+                .on_update(ForeignKeyAction::NoAction),
+        )
+        .to_owned()
+}
+
+fn service_required_roles() -> TableCreateStatement {
+    Table::create()
+        .table(Alias::new("service_required_roles"))
+        .col(
+            ColumnDef::new(Alias::new("id"))
+                .binary_len(16)
+                .not_null()
+                .primary_key(),
+        )
+        .col(
+            ColumnDef::new(Alias::new("service_id"))
+                .binary_len(16)
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(Alias::new("role_id"))
+                .binary_len(16)
+                .not_null(),
+        )
+        .col(ColumnDef::new(Alias::new("quantity")).integer().not_null())
+        .foreign_key(
+            ForeignKey::create()
+                .name("fk-service_required_roles-role_id")
+                .from(Alias::new("service_required_roles"), Alias::new("role_id"))
+                .to(Alias::new("resource_roles"), Alias::new("id"))
+                .on_delete(ForeignKeyAction::NoAction)
+                .on_update(ForeignKeyAction::NoAction),
+        )
+        .foreign_key(
+            ForeignKey::create()
+                .name("fk-service_required_roles-service_id")
+                .from(
+                    Alias::new("service_required_roles"),
+                    Alias::new("service_id"),
+                )
+                .to(Alias::new("services"), Alias::new("id"))
+                .on_delete(ForeignKeyAction::NoAction)
+                .on_update(ForeignKeyAction::NoAction),
+        )
+        .to_owned()
+}
+
+fn bookings() -> TableCreateStatement {
+    Table::create()
+        .table(Alias::new("bookings"))
+        .col(
+            ColumnDef::new(Alias::new("id"))
+                .binary_len(16)
+                .not_null()
+                .primary_key(),
+        )
+        .col(
+            ColumnDef::new(Alias::new("code"))
+                .string()
+                .not_null()
+                .unique_key(),
+        )
+        .col(
+            ColumnDef::new(Alias::new("service_id"))
+                .binary_len(16)
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(Alias::new("client_id"))
+                .binary_len(16)
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(Alias::new("start_datetime"))
+                .timestamp_with_time_zone()
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(Alias::new("end_datetime"))
+                .timestamp_with_time_zone()
+                .not_null(),
+        )
+        .col(ColumnDef::new(Alias::new("status")).string().not_null())
+        .col(ColumnDef::new(Alias::new("notes")).text())
+        .col(
+            ColumnDef::new(Alias::new("created_at"))
+                .timestamp_with_time_zone()
+                .not_null(),
+        )
+        .foreign_key(
+            ForeignKey::create()
+                .name("fk-bookings-client_id")
+                .from(Alias::new("bookings"), Alias::new("client_id"))
+                .to(Alias::new("clients"), Alias::new("id"))
+                .on_delete(ForeignKeyAction::NoAction)
+                .on_update(ForeignKeyAction::NoAction),
+        )
+        .foreign_key(
+            ForeignKey::create()
+                .name("fk-bookings-service_id")
+                .from(Alias::new("bookings"), Alias::new("service_id"))
+                .to(Alias::new("services"), Alias::new("id"))
+                .on_delete(ForeignKeyAction::NoAction)
+                .on_update(ForeignKeyAction::NoAction),
+        )
+        .to_owned()
+}
+
+fn booking_resources() -> TableCreateStatement {
+    Table::create()
+        .table(Alias::new("booking_resources"))
+        .col(
+            ColumnDef::new(Alias::new("booking_id"))
+                .binary_len(16)
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(Alias::new("resource_id"))
+                .binary_len(16)
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(Alias::new("quantity_used"))
+                .integer()
+                .not_null(),
+        )
+        .primary_key(
+            Index::create()
+                .name("pk-booking_resources")
+                .col(Alias::new("booking_id"))
+                .col(Alias::new("resource_id"))
+                .primary(),
+        )
+        .foreign_key(
+            ForeignKey::create()
+                .name("fk-booking_resources-booking_id")
+                .from(Alias::new("booking_resources"), Alias::new("booking_id"))
+                .to(Alias::new("bookings"), Alias::new("id"))
+                .on_delete(ForeignKeyAction::NoAction)
+                .on_update(ForeignKeyAction::NoAction),
+        )
+        .foreign_key(
+            ForeignKey::create()
+                .name("fk-booking_resources-resource_id")
+                .from(Alias::new("booking_resources"), Alias::new("resource_id"))
+                .to(Alias::new("resources"), Alias::new("id"))
+                .on_delete(ForeignKeyAction::NoAction)
+                .on_update(ForeignKeyAction::NoAction),
+        )
+        .to_owned()
+}
+
+fn schedule_templates() -> TableCreateStatement {
+    Table::create()
+        .table(Alias::new("schedule_templates"))
+        .col(
+            ColumnDef::new(Alias::new("id"))
+                .binary_len(16)
+                .not_null()
+                .primary_key(),
+        )
+        .col(
+            ColumnDef::new(Alias::new("resource_id"))
+                .binary_len(16)
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(Alias::new("day_of_week"))
+                .small_integer()
+                .not_null(),
+        )
+        .col(ColumnDef::new(Alias::new("start_time")).time().not_null())
+        .col(ColumnDef::new(Alias::new("end_time")).time().not_null())
+        .foreign_key(
+            ForeignKey::create()
+                .name("fk-schedule_templates-resource_id")
+                .from(Alias::new("schedule_templates"), Alias::new("resource_id"))
+                .to(Alias::new("resources"), Alias::new("id"))
+                .on_delete(ForeignKeyAction::NoAction)
+                .on_update(ForeignKeyAction::NoAction),
+        )
+        .to_owned()
+}
+
+fn availability_exceptions() -> TableCreateStatement {
+    Table::create()
+        .table(Alias::new("availability_exceptions"))
+        .col(
+            ColumnDef::new(Alias::new("id"))
+                .binary_len(16)
+                .not_null()
+                .primary_key(),
+        )
+        .col(ColumnDef::new(Alias::new("name")).string().not_null())
+        .col(
+            ColumnDef::new(Alias::new("resource_id"))
+                .binary_len(16)
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(Alias::new("start_datetime"))
+                .timestamp_with_time_zone()
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(Alias::new("end_datetime"))
+                .timestamp_with_time_zone()
+                .not_null(),
+        )
+        .col(ColumnDef::new(Alias::new("available")).tiny_integer())
+        .col(ColumnDef::new(Alias::new("reason")).text())
+        .foreign_key(
+            ForeignKey::create()
+                .name("fk-availability_exceptions-resource_id")
+                .from(
+                    Alias::new("availability_exceptions"),
+                    Alias::new("resource_id"),
+                )
+                .to(Alias::new("resources"), Alias::new("id"))
+                .on_delete(ForeignKeyAction::NoAction)
+                .on_update(ForeignKeyAction::NoAction),
+        )
+        .to_owned()
+}
+
+fn projects() -> TableCreateStatement {
+    Table::create()
+        .table(Alias::new("projects"))
+        .col(
+            ColumnDef::new(Alias::new("id"))
+                .binary_len(16)
+                .not_null()
+                .primary_key(),
+        )
+        .col(ColumnDef::new(Alias::new("name")).string().not_null())
+        .col(
+            ColumnDef::new(Alias::new("client_id"))
+                .binary_len(16)
+                .not_null(),
+        )
+        .foreign_key(
+            ForeignKey::create()
+                .name("fk-projects-client_id")
+                .from(Alias::new("projects"), Alias::new("client_id"))
+                .to(Alias::new("clients"), Alias::new("id"))
+                .on_delete(ForeignKeyAction::NoAction)
+                .on_update(ForeignKeyAction::NoAction),
+        )
+        .to_owned()
+}
+
+fn deals() -> TableCreateStatement {
+    Table::create()
+        .table(Alias::new("deals"))
+        .col(
+            ColumnDef::new(Alias::new("id"))
+                .binary_len(16)
+                .not_null()
+                .primary_key(),
+        )
+        .col(ColumnDef::new(Alias::new("deal_status")).string())
+        .col(ColumnDef::new(Alias::new("name")).string().not_null())
+        .col(ColumnDef::new(Alias::new("description")).json())
+        .col(ColumnDef::new(Alias::new("source_chat_id")).string())
+        .col(ColumnDef::new(Alias::new("assignee_id")).binary_len(16))
+        .col(ColumnDef::new(Alias::new("resulting_project_id")).binary_len(16))
+        .col(ColumnDef::new(Alias::new("previous_deal_id")).binary_len(16))
+        .foreign_key(
+            ForeignKey::create()
+                .name("fk-deals-source_chat_id")
+                .from(Alias::new("deals"), Alias::new("source_chat_id"))
+                .to(Alias::new("chats"), Alias::new("id"))
+                .on_delete(ForeignKeyAction::NoAction)
+                .on_update(ForeignKeyAction::NoAction),
+        )
+        .foreign_key(
+            ForeignKey::create()
+                .name("fk-deals-previous_deal_id")
+                .from(Alias::new("deals"), Alias::new("previous_deal_id"))
+                .to(Alias::new("deals"), Alias::new("id"))
+                .on_delete(ForeignKeyAction::NoAction)
+                .on_update(ForeignKeyAction::NoAction),
+        )
+        .foreign_key(
+            ForeignKey::create()
+                .name("fk-deals-resulting_project_id")
+                .from(Alias::new("deals"), Alias::new("resulting_project_id"))
+                .to(Alias::new("projects"), Alias::new("id"))
+                .on_delete(ForeignKeyAction::NoAction)
+                .on_update(ForeignKeyAction::NoAction),
+        )
+        .foreign_key(
+            ForeignKey::create()
+                .name("fk-deals-assignee_id")
+                .from(Alias::new("deals"), Alias::new("assignee_id"))
+                .to(Alias::new("users"), Alias::new("id"))
+                .on_delete(ForeignKeyAction::NoAction)
+                .on_update(ForeignKeyAction::NoAction),
+        )
+        .to_owned()
+}
+
+fn task_statuses() -> TableCreateStatement {
+    Table::create()
+        .table(Alias::new("task_statuses"))
+        .col(
+            ColumnDef::new(Alias::new("id"))
+                .binary_len(16)
+                .not_null()
+                .primary_key(),
+        )
+        .col(ColumnDef::new(Alias::new("value")).text())
+        .to_owned()
+}
+
+fn tasks() -> TableCreateStatement {
+    Table::create()
+        .table(Alias::new("tasks"))
+        .col(
+            ColumnDef::new(Alias::new("id"))
+                .binary_len(16)
+                .not_null()
+                .primary_key(),
+        )
+        .col(ColumnDef::new(Alias::new("name")).text().not_null())
+        .col(
+            ColumnDef::new(Alias::new("project_id"))
+                .binary_len(16)
+                .not_null(),
+        )
+        .col(ColumnDef::new(Alias::new("parent_task_id")).binary_len(16))
+        .col(
+            ColumnDef::new(Alias::new("created_at"))
+                .timestamp_with_time_zone()
+                .not_null(),
+        )
+        .col(ColumnDef::new(Alias::new("content")).json())
+        .col(
+            ColumnDef::new(Alias::new("status_id"))
+                .binary_len(16)
+                .not_null(),
+        )
+        .col(ColumnDef::new(Alias::new("previous_task_id")).binary_len(16))
+        .col(ColumnDef::new(Alias::new("route")).string())
+        .foreign_key(
+            ForeignKey::create()
+                .name("fk-tasks-project_id")
+                .from(Alias::new("tasks"), Alias::new("project_id"))
+                .to(Alias::new("projects"), Alias::new("id"))
+                .on_delete(ForeignKeyAction::NoAction)
+                .on_update(ForeignKeyAction::NoAction),
+        )
+        .foreign_key(
+            ForeignKey::create()
+                .name("fk-tasks-status_id")
+                .from(Alias::new("tasks"), Alias::new("status_id"))
+                .to(Alias::new("task_statuses"), Alias::new("id"))
+                .on_delete(ForeignKeyAction::NoAction)
+                .on_update(ForeignKeyAction::NoAction),
+        )
+        .foreign_key(
+            ForeignKey::create()
+                .name("fk-tasks-parent_task_id")
+                .from(Alias::new("tasks"), Alias::new("parent_task_id"))
+                .to(Alias::new("tasks"), Alias::new("id"))
+                .on_delete(ForeignKeyAction::NoAction)
+                .on_update(ForeignKeyAction::NoAction),
+        )
+        .foreign_key(
+            ForeignKey::create()
+                .name("fk-tasks-previous_task_id")
+                .from(Alias::new("tasks"), Alias::new("previous_task_id"))
+                .to(Alias::new("tasks"), Alias::new("id"))
+                .on_delete(ForeignKeyAction::NoAction)
+                .on_update(ForeignKeyAction::NoAction),
+        )
+        .to_owned()
+}
+
+fn task_assignments() -> TableCreateStatement {
+    Table::create()
+        .table(Alias::new("task_assignments"))
+        .col(
+            ColumnDef::new(Alias::new("id"))
+                .binary_len(16)
+                .not_null()
+                .primary_key(),
+        )
+        .col(
+            ColumnDef::new(Alias::new("task_id"))
+                .binary_len(16)
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(Alias::new("user_id"))
+                .binary_len(16)
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(Alias::new("can_edit"))
+                .tiny_integer()
+                .not_null(),
+        )
+        .foreign_key(
+            ForeignKey::create()
+                .name("fk-task_assignments-task_id")
+                .from(Alias::new("task_assignments"), Alias::new("task_id"))
+                .to(Alias::new("tasks"), Alias::new("id"))
+                .on_delete(ForeignKeyAction::NoAction)
+                .on_update(ForeignKeyAction::NoAction),
+        )
+        .foreign_key(
+            ForeignKey::create()
+                .name("fk-task_assignments-user_id")
+                .from(Alias::new("task_assignments"), Alias::new("user_id"))
+                .to(Alias::new("users"), Alias::new("id"))
+                .on_delete(ForeignKeyAction::NoAction)
+                .on_update(ForeignKeyAction::NoAction),
+        )
+        .to_owned()
+}
+
+fn outgoing_webhooks() -> TableCreateStatement {
+    Table::create()
+        .table(Alias::new("outgoing_webhooks"))
+        .col(
+            ColumnDef::new(Alias::new("id"))
+                .binary_len(16)
+                .not_null()
+                .primary_key(),
+        )
+        .col(
+            ColumnDef::new(Alias::new("company_id"))
+                .binary_len(16)
+                .not_null(),
+        )
+        .col(ColumnDef::new(Alias::new("url")).text().not_null())
+        .col(ColumnDef::new(Alias::new("secret")).string().not_null())
+        .col(ColumnDef::new(Alias::new("event_types")).json().not_null())
+        .col(ColumnDef::new(Alias::new("description")).string())
+        .col(
+            ColumnDef::new(Alias::new("is_active"))
+                .tiny_integer()
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(Alias::new("created_at"))
+                .timestamp_with_time_zone()
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(Alias::new("updated_at"))
+                .timestamp_with_time_zone()
+                .not_null(),
+        )
+        .foreign_key(
+            ForeignKey::create()
+                .name("fk-outgoing_webhooks-company_id")
+                .from(Alias::new("outgoing_webhooks"), Alias::new("company_id"))
+                .to(Alias::new("companies"), Alias::new("id"))
+                .on_delete(ForeignKeyAction::Cascade)
+                .on_update(ForeignKeyAction::NoAction),
+        )
+        .to_owned()
+}
+
+fn outgoing_webhook_deliveries() -> TableCreateStatement {
+    Table::create()
+        .table(Alias::new("outgoing_webhook_deliveries"))
+        .col(
+            ColumnDef::new(Alias::new("id"))
+                .binary_len(16)
+                .not_null()
+                .primary_key(),
+        )
+        .col(
+            ColumnDef::new(Alias::new("webhook_id"))
+                .binary_len(16)
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(Alias::new("company_id"))
+                .binary_len(16)
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(Alias::new("event_id"))
+                .big_integer()
+                .not_null(),
+        )
+        .col(ColumnDef::new(Alias::new("event_type")).string().not_null())
+        .col(ColumnDef::new(Alias::new("payload")).json().not_null())
+        .col(ColumnDef::new(Alias::new("status")).string().not_null())
+        .col(ColumnDef::new(Alias::new("attempts")).integer().not_null())
+        .col(ColumnDef::new(Alias::new("response_status")).integer())
+        .col(ColumnDef::new(Alias::new("last_error")).text())
+        .col(ColumnDef::new(Alias::new("next_attempt_at")).timestamp_with_time_zone())
+        .col(
+            ColumnDef::new(Alias::new("created_at"))
+                .timestamp_with_time_zone()
+                .not_null(),
+        )
+        .col(ColumnDef::new(Alias::new("delivered_at")).timestamp_with_time_zone())
+        .foreign_key(
+            ForeignKey::create()
+                .name("fk-outgoing_webhook_deliveries-webhook_id")
+                .from(
+                    Alias::new("outgoing_webhook_deliveries"),
+                    Alias::new("webhook_id"),
+                )
+                .to(Alias::new("outgoing_webhooks"), Alias::new("id"))
+                .on_delete(ForeignKeyAction::Cascade)
+                .on_update(ForeignKeyAction::NoAction),
+        )
+        .to_owned()
+}
@@ -0,0 +1,146 @@
+//! Columns and indexes the service added to the legacy schema. Fresh
+//! databases already get the columns in the first migration; databases
+//! restored from the dump get them here.
+
+use sea_orm::{
+    ConnectionTrait, DbBackend, DbErr, EntityName,
+    sea_query::{ColumnDef, Table},
+};
+
+use crate::database::{
+    models::{channels, companies, tokens},
+    schema::{add_missing_column, index_exists},
+};
+
+const MESSAGE_SEARCH_INDEX: &str = "ft_message_search_index_body";
+
+pub(super) async fn up<C>(db: &C) -> Result<(), DbErr>
+where
+    C: ConnectionTrait,
+{
+    ensure_channel_company(db).await?;
+    ensure_token_company(db).await?;
+    ensure_company_last_webhook(db).await?;
+    ensure_full_text_index(db).await?;
+
+    Ok(())
+}
+
+/// Adds `channels.company_id` and assigns existing channels to the company of
+/// the clients chatting through them.
+async fn ensure_channel_company<C>(db: &C) -> Result<(), DbErr>
+where
+    C: ConnectionTrait,
+{
+    let added = add_missing_column(
+        db,
+        channels::Entity.table_name(),
+        Table::alter()
+            .table(channels::Entity)
+            .add_column(
+                ColumnDef::new(channels::Column::CompanyId)
+                    .binary_len(16)
+                    .null(),
+            )
+            .to_owned(),
+        channels::Column::CompanyId,
+    )
+    .await?;
+    if !added {
+        return Ok(());
+    }
+
+    let updated = db
+        .execute_unprepared(
+            "UPDATE channels SET company_id = (\
+                SELECT clients.company_id FROM chats \
+                JOIN clients ON clients.id = chats.client_id \
+                WHERE chats.channel_id = channels.id AND clients.company_id IS NOT NULL \
+                LIMIT 1) \
+             WHERE company_id IS NULL",
+        )
+        .await?;
+    log::info!(
+        "Added channels.company_id, assigned {} existing channel(s)",
+        updated.rows_affected()
+    );
+
+    Ok(())
+}
+
+/// Adds `tokens.company_id`; tokens issued before stay unbound.
+async fn ensure_token_company<C>(db: &C) -> Result<(), DbErr>
+where
+    C: ConnectionTrait,
+{
+    add_missing_column(
+        db,
+        tokens::Entity.table_name(),
+        Table::alter()
+            .table(tokens::Entity)
+            .add_column(
+                ColumnDef::new(tokens::Column::CompanyId)
+                    .binary_len(16)
+                    .null(),
+            )
+            .to_owned(),
+        tokens::Column::CompanyId,
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Adds `companies.last_webhook_at`, set whenever Wazzup delivers a webhook.
+async fn ensure_company_last_webhook<C>(db: &C) -> Result<(), DbErr>
+where
+    C: ConnectionTrait,
+{
+    add_missing_column(
+        db,
+        companies::Entity.table_name(),
+        Table::alter()
+            .table(companies::Entity)
+            .add_column(
+                ColumnDef::new(companies::Column::LastWebhookAt)
+                    .timestamp_with_time_zone()
+                    .null(),
+            )
+            .to_owned(),
+        companies::Column::LastWebhookAt,
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Full-text index over `message_search_index.body`: FULLTEXT on MySQL,
+/// a GIN index over `to_tsvector('simple', body)` on Postgres.
+async fn ensure_full_text_index<C>(db: &C) -> Result<(), DbErr>
+where
+    C: ConnectionTrait,
+{
+    match db.get_database_backend() {
+        DbBackend::MySql => {
+            if index_exists(db, "message_search_index", MESSAGE_SEARCH_INDEX).await? {
+                return Ok(());
+            }
+            db.execute_unprepared(&format!(
+                "CREATE FULLTEXT INDEX {} ON message_search_index (body)",
+                MESSAGE_SEARCH_INDEX
+            ))
+            .await?;
+        }
+        DbBackend::Postgres => {
+            db.execute_unprepared(&format!(
+                "CREATE INDEX IF NOT EXISTS {} ON message_search_index \
+                 USING GIN (to_tsvector('simple', body))",
+                MESSAGE_SEARCH_INDEX
+            ))
+            .await?;
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
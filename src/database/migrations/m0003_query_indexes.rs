@@ -0,0 +1,144 @@
+//! Indexes and unique constraints behind the service's queries.
+
+use sea_orm::{ConnectionTrait, DbErr, EntityName, sea_query::Index};
+
+use crate::database::{
+    models::{
+        channels, chats, clients, message_search_index, messages, outgoing_webhook_deliveries,
+        outgoing_webhooks, tokens,
+    },
+    schema::ensure_index,
+};
+
+pub(super) async fn up<C>(db: &C) -> Result<(), DbErr>
+where
+    C: ConnectionTrait,
+{
+    let indexes = [
+        // Chat previews: company chats are found through `channels.company_id`,
+        // the last message and unread counters are looked up per chat ordered
+        // by `created_at`.
+        (
+            channels::Entity.table_name(),
+            "idx_channels_company_id",
+            Index::create()
+                .table(channels::Entity)
+                .col(channels::Column::CompanyId)
+                .to_owned(),
+        ),
+        (
+            chats::Entity.table_name(),
+            "idx_chats_channel_id",
+            Index::create()
+                .table(chats::Entity)
+                .col(chats::Column::ChannelId)
+                .to_owned(),
+        ),
+        (
+            chats::Entity.table_name(),
+            "idx_chats_client_id",
+            Index::create()
+                .table(chats::Entity)
+                .col(chats::Column::ClientId)
+                .to_owned(),
+        ),
+        (
+            messages::Entity.table_name(),
+            "idx_messages_chat_created_at",
+            Index::create()
+                .table(messages::Entity)
+                .col(messages::Column::ChatId)
+                .col(messages::Column::CreatedAt)
+                .to_owned(),
+        ),
+        (
+            messages::Entity.table_name(),
+            "idx_messages_chat_inbound_created_at",
+            Index::create()
+                .table(messages::Entity)
+                .col(messages::Column::ChatId)
+                .col(messages::Column::IsInbound)
+                .col(messages::Column::CreatedAt)
+                .to_owned(),
+        ),
+        // Contacts: emails are unique within a company, incoming webhooks
+        // match clients by phone, managers see the clients they handle.
+        (
+            clients::Entity.table_name(),
+            "uq_clients_company_email",
+            Index::create()
+                .table(clients::Entity)
+                .col(clients::Column::CompanyId)
+                .col(clients::Column::Email)
+                .unique()
+                .to_owned(),
+        ),
+        (
+            clients::Entity.table_name(),
+            "idx_clients_phone",
+            Index::create()
+                .table(clients::Entity)
+                .col(clients::Column::Phone)
+                .to_owned(),
+        ),
+        (
+            clients::Entity.table_name(),
+            "idx_clients_responsible_user_id",
+            Index::create()
+                .table(clients::Entity)
+                .col(clients::Column::ResponsibleUserId)
+                .to_owned(),
+        ),
+        (
+            tokens::Entity.table_name(),
+            "idx_tokens_user_id",
+            Index::create()
+                .table(tokens::Entity)
+                .col(tokens::Column::UserId)
+                .to_owned(),
+        ),
+        // Message search is always scoped to a company and a date range.
+        (
+            message_search_index::Entity.table_name(),
+            "idx_message_search_index_company_created_at",
+            Index::create()
+                .table(message_search_index::Entity)
+                .col(message_search_index::Column::CompanyId)
+                .col(message_search_index::Column::CreatedAt)
+                .to_owned(),
+        ),
+        // Outgoing webhooks: subscriptions per company and the retry loop
+        // over due deliveries.
+        (
+            outgoing_webhooks::Entity.table_name(),
+            "idx_outgoing_webhooks_company_id",
+            Index::create()
+                .table(outgoing_webhooks::Entity)
+                .col(outgoing_webhooks::Column::CompanyId)
+                .to_owned(),
+        ),
+        (
+            outgoing_webhook_deliveries::Entity.table_name(),
+            "idx_outgoing_webhook_deliveries_webhook_id",
+            Index::create()
+                .table(outgoing_webhook_deliveries::Entity)
+                .col(outgoing_webhook_deliveries::Column::WebhookId)
+                .to_owned(),
+        ),
+        (
+            outgoing_webhook_deliveries::Entity.table_name(),
+            "idx_outgoing_webhook_deliveries_status_next_attempt_at",
+            Index::create()
+                .table(outgoing_webhook_deliveries::Entity)
+                .col(outgoing_webhook_deliveries::Column::Status)
+                .col(outgoing_webhook_deliveries::Column::NextAttemptAt)
+                .to_owned(),
+        ),
+    ];
+
+    for (table, name, index) in indexes {
+        ensure_index(db, table, name, index).await?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,114 @@
+//! Tables of the persistent job queue and the indexes its workers poll.
+//! Like the first migration, the tables are spelled out rather than derived
+//! from the entities.
+
+use sea_orm::{
+    ConnectionTrait, DbErr, EntityName,
+    sea_query::{Alias, ColumnDef, Index, Table, TableCreateStatement},
+};
+
+use crate::database::{models::jobs, schema::ensure_index};
+
+pub(super) async fn up<C>(db: &C) -> Result<(), DbErr>
+where
+    C: ConnectionTrait,
+{
+    let backend = db.get_database_backend();
+
+    for mut statement in [jobs(), recurring_jobs()] {
+        statement.if_not_exists();
+        db.execute(backend.build(&statement)).await?;
+    }
+
+    let indexes = [
+        // Workers look for due jobs by status and time; a job with a unique
+        // key is enqueued at most once until it finishes.
+        (
+            "idx_jobs_status_run_at",
+            Index::create()
+                .table(jobs::Entity)
+                .col(jobs::Column::Status)
+                .col(jobs::Column::RunAt)
+                .to_owned(),
+        ),
+        (
+            "uq_jobs_unique_key",
+            Index::create()
+                .table(jobs::Entity)
+                .col(jobs::Column::UniqueKey)
+                .unique()
+                .to_owned(),
+        ),
+    ];
+    for (name, index) in indexes {
+        ensure_index(db, jobs::Entity.table_name(), name, index).await?;
+    }
+
+    Ok(())
+}
+
+fn jobs() -> TableCreateStatement {
+    Table::create()
+        .table(Alias::new("jobs"))
+        .col(
+            ColumnDef::new(Alias::new("id"))
+                .binary_len(16)
+                .not_null()
+                .primary_key(),
+        )
+        .col(ColumnDef::new(Alias::new("job_type")).string().not_null())
+        .col(ColumnDef::new(Alias::new("payload")).json().not_null())
+        .col(ColumnDef::new(Alias::new("status")).string().not_null())
+        .col(ColumnDef::new(Alias::new("unique_key")).string())
+        .col(ColumnDef::new(Alias::new("attempts")).integer().not_null())
+        .col(
+            ColumnDef::new(Alias::new("max_attempts"))
+                .integer()
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(Alias::new("run_at"))
+                .timestamp_with_time_zone()
+                .not_null(),
+        )
+        .col(ColumnDef::new(Alias::new("locked_until")).timestamp_with_time_zone())
+        .col(ColumnDef::new(Alias::new("last_error")).text())
+        .col(
+            ColumnDef::new(Alias::new("created_at"))
+                .timestamp_with_time_zone()
+                .not_null(),
+        )
+        .col(ColumnDef::new(Alias::new("finished_at")).timestamp_with_time_zone())
+        .to_owned()
+}
+
+fn recurring_jobs() -> TableCreateStatement {
+    Table::create()
+        .table(Alias::new("recurring_jobs"))
+        .col(
+            ColumnDef::new(Alias::new("name"))
+                .string()
+                .not_null()
+                .primary_key(),
+        )
+        .col(ColumnDef::new(Alias::new("job_type")).string().not_null())
+        .col(ColumnDef::new(Alias::new("payload")).json().not_null())
+        .col(ColumnDef::new(Alias::new("schedule")).string().not_null())
+        .col(
+            ColumnDef::new(Alias::new("next_run_at"))
+                .timestamp_with_time_zone()
+                .not_null(),
+        )
+        .col(ColumnDef::new(Alias::new("last_run_at")).timestamp_with_time_zone())
+        .col(
+            ColumnDef::new(Alias::new("is_active"))
+                .tiny_integer()
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(Alias::new("updated_at"))
+                .timestamp_with_time_zone()
+                .not_null(),
+        )
+        .to_owned()
+}
@@ -0,0 +1,60 @@
+//! Outgoing webhook deliveries refer to their event by its UUID. The numeric
+//! ids they carried before were positions in the event stream of a single
+//! process, so they repeat across restarts and between processes.
+
+use sea_orm::{
+    ConnectionTrait, DbErr, EntityName,
+    sea_query::{Alias, ColumnDef, Index, Table},
+};
+
+use crate::database::{
+    models::outgoing_webhook_deliveries,
+    schema::{add_missing_column, drop_existing_column, ensure_index},
+};
+
+pub(super) async fn up<C>(db: &C) -> Result<(), DbErr>
+where
+    C: ConnectionTrait,
+{
+    let table = outgoing_webhook_deliveries::Entity.table_name();
+
+    // Deliveries recorded before keep a null event UUID.
+    add_missing_column(
+        db,
+        table,
+        Table::alter()
+            .table(outgoing_webhook_deliveries::Entity)
+            .add_column(
+                ColumnDef::new(outgoing_webhook_deliveries::Column::EventUid)
+                    .binary_len(16)
+                    .null(),
+            )
+            .to_owned(),
+        outgoing_webhook_deliveries::Column::EventUid,
+    )
+    .await?;
+    drop_existing_column(
+        db,
+        table,
+        Table::alter()
+            .table(outgoing_webhook_deliveries::Entity)
+            .drop_column(Alias::new("event_id"))
+            .to_owned(),
+        Alias::new("event_id"),
+    )
+    .await?;
+
+    // The dispatcher looks up the deliveries of every event it sees.
+    ensure_index(
+        db,
+        table,
+        "idx_outgoing_webhook_deliveries_event_uid",
+        Index::create()
+            .table(outgoing_webhook_deliveries::Entity)
+            .col(outgoing_webhook_deliveries::Column::EventUid)
+            .to_owned(),
+    )
+    .await?;
+
+    Ok(())
+}
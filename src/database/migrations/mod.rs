@@ -0,0 +1,179 @@
+//! Versioned schema migrations.
+//!
+//! Every table the service reads or writes is created here, so an empty
+//! database can be bootstrapped from this repository alone. Migrations only
+//! add what is missing: databases restored from the legacy dump pass through
+//! them unchanged apart from the service-owned tables, columns and indexes.
+//! Applied versions are recorded in `schema_migrations`. On Postgres and
+//! SQLite a migration and its record are committed together, so a failed
+//! migration leaves nothing behind and is retried as a whole; MySQL commits
+//! every DDL statement on its own, so there a failed migration may have to be
+//! completed by hand.
+//!
+//! Run them with `wazzup migrate`; with `MIGRATE_ON_STARTUP` (the default)
+//! the server applies pending migrations itself before it starts.
+
+mod m0001_initial_schema;
+mod m0002_service_columns;
+mod m0003_query_indexes;
+mod m0004_job_queue;
+mod m0005_delivery_event_uids;
+
+use chrono::Utc;
+use sea_orm::{
+    ActiveModelTrait, ConnectionTrait, DbBackend, DbErr, EntityTrait, QueryOrder, Set,
+    TransactionTrait,
+    sea_query::{Alias, ColumnDef, Table, TableCreateStatement},
+};
+
+use crate::database::models::schema_migrations;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Migration {
+    pub version: i32,
+    pub name: &'static str,
+}
+
+/// All migrations, oldest first. Never reorder or edit applied entries; add
+/// a new one instead.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "initial_schema",
+    },
+    Migration {
+        version: 2,
+        name: "service_columns",
+    },
+    Migration {
+        version: 3,
+        name: "query_indexes",
+    },
+    Migration {
+        version: 4,
+        name: "job_queue",
+    },
+    Migration {
+        version: 5,
+        name: "delivery_event_uids",
+    },
+];
+
+/// Applies pending migrations in order and returns them.
+pub async fn run<C>(db: &C) -> Result<Vec<Migration>, DbErr>
+where
+    C: ConnectionTrait + TransactionTrait,
+{
+    let pending = pending(db).await?;
+
+    for migration in &pending {
+        log::info!(
+            "Applying migration {:04}_{}",
+            migration.version,
+            migration.name
+        );
+        if db.get_database_backend() == DbBackend::MySql {
+            apply_and_record(db, migration).await?;
+        } else {
+            let txn = db.begin().await?;
+            apply_and_record(&txn, migration).await?;
+            txn.commit().await?;
+        }
+    }
+
+    Ok(pending)
+}
+
+/// Migrations that have not been applied yet.
+pub async fn pending<C>(db: &C) -> Result<Vec<Migration>, DbErr>
+where
+    C: ConnectionTrait,
+{
+    let applied = applied(db).await?;
+
+    Ok(MIGRATIONS
+        .iter()
+        .filter(|migration| {
+            !applied
+                .iter()
+                .any(|record| record.version == migration.version)
+        })
+        .copied()
+        .collect())
+}
+
+/// Migrations recorded as applied, oldest first.
+pub async fn applied<C>(db: &C) -> Result<Vec<schema_migrations::Model>, DbErr>
+where
+    C: ConnectionTrait,
+{
+    ensure_migrations_table(db).await?;
+
+    schema_migrations::Entity::find()
+        .order_by_asc(schema_migrations::Column::Version)
+        .all(db)
+        .await
+}
+
+async fn apply_and_record<C>(db: &C, migration: &Migration) -> Result<(), DbErr>
+where
+    C: ConnectionTrait,
+{
+    apply(db, migration).await?;
+
+    schema_migrations::ActiveModel {
+        version: Set(migration.version),
+        name: Set(migration.name.to_string()),
+        applied_at: Set(Utc::now()),
+    }
+    .insert(db)
+    .await?;
+
+    Ok(())
+}
+
+async fn apply<C>(db: &C, migration: &Migration) -> Result<(), DbErr>
+where
+    C: ConnectionTrait,
+{
+    match migration.version {
+        1 => m0001_initial_schema::up(db).await,
+        2 => m0002_service_columns::up(db).await,
+        3 => m0003_query_indexes::up(db).await,
+        4 => m0004_job_queue::up(db).await,
+        5 => m0005_delivery_event_uids::up(db).await,
+        version => Err(DbErr::Migration(format!(
+            "Migration {} has no implementation",
+            version
+        ))),
+    }
+}
+
+async fn ensure_migrations_table<C>(db: &C) -> Result<(), DbErr>
+where
+    C: ConnectionTrait,
+{
+    let backend = db.get_database_backend();
+    let mut statement = schema_migrations();
+    statement.if_not_exists();
+    db.execute(backend.build(&statement)).await?;
+    Ok(())
+}
+
+fn schema_migrations() -> TableCreateStatement {
+    Table::create()
+        .table(Alias::new("schema_migrations"))
+        .col(
+            ColumnDef::new(Alias::new("version"))
+                .integer()
+                .not_null()
+                .primary_key(),
+        )
+        .col(ColumnDef::new(Alias::new("name")).string().not_null())
+        .col(
+            ColumnDef::new(Alias::new("applied_at"))
+                .timestamp_with_time_zone()
+                .not_null(),
+        )
+        .to_owned()
+}
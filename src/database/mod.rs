@@ -1,5 +1,7 @@
 pub mod connector;
+pub mod migrations;
 pub mod models;
+pub mod schema;
 
 // Re-export the primary DB types and connect helper for convenient access as `database::connect()`
 #[allow(unused_imports)]
@@ -8,16 +8,33 @@ pub struct Model {
     #[sea_orm(primary_key, auto_increment = false, column_type = "Binary(16)")]
     pub id: Vec<u8>,
     pub r#type: String,
+    /// Company that owns the channel; chats are scoped to companies through it.
+    #[sea_orm(column_type = "Binary(16)", nullable)]
+    pub company_id: Option<Vec<u8>>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::companies::Entity",
+        from = "Column::CompanyId",
+        to = "super::companies::Column::Id",
+        on_update = "NoAction",
+        on_delete = "SetNull"
+    )]
+    Companies,
     #[sea_orm(has_many = "super::channel_settings::Entity")]
     ChannelSettings,
     #[sea_orm(has_many = "super::chats::Entity")]
     Chats,
 }
 
+impl Related<super::companies::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Companies.def()
+    }
+}
+
 impl Related<super::channel_settings::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::ChannelSettings.def()
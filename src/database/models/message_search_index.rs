@@ -0,0 +1,50 @@
+//! Plain-text copy of message content used for full-text search.
+
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq)]
+#[sea_orm(table_name = "message_search_index")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false, column_type = "Binary(16)")]
+    pub message_id: Vec<u8>,
+    #[sea_orm(column_type = "Binary(16)", nullable)]
+    pub company_id: Option<Vec<u8>>,
+    pub chat_id: String,
+    #[sea_orm(column_type = "Text")]
+    pub body: String,
+    pub created_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::messages::Entity",
+        from = "Column::MessageId",
+        to = "super::messages::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    Messages,
+    #[sea_orm(
+        belongs_to = "super::chats::Entity",
+        from = "Column::ChatId",
+        to = "super::chats::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    Chats,
+}
+
+impl Related<super::messages::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Messages.def()
+    }
+}
+
+impl Related<super::chats::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Chats.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
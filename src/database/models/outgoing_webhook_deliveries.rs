@@ -0,0 +1,46 @@
+//! Delivery log of events sent to outgoing webhooks, including pending retries.
+
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq)]
+#[sea_orm(table_name = "outgoing_webhook_deliveries")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false, column_type = "Binary(16)")]
+    pub id: Vec<u8>,
+    #[sea_orm(column_type = "Binary(16)")]
+    pub webhook_id: Vec<u8>,
+    #[sea_orm(column_type = "Binary(16)")]
+    pub company_id: Vec<u8>,
+    #[sea_orm(column_type = "Binary(16)", nullable)]
+    pub event_uid: Option<Vec<u8>>,
+    pub event_type: String,
+    pub payload: Json,
+    pub status: String,
+    pub attempts: i32,
+    pub response_status: Option<i32>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub last_error: Option<String>,
+    pub next_attempt_at: Option<DateTimeUtc>,
+    pub created_at: DateTimeUtc,
+    pub delivered_at: Option<DateTimeUtc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::outgoing_webhooks::Entity",
+        from = "Column::WebhookId",
+        to = "super::outgoing_webhooks::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    OutgoingWebhooks,
+}
+
+impl Related<super::outgoing_webhooks::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::OutgoingWebhooks.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
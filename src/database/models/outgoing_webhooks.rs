@@ -0,0 +1,49 @@
+//! Downstream endpoint that receives normalized service events of a company.
+
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq)]
+#[sea_orm(table_name = "outgoing_webhooks")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false, column_type = "Binary(16)")]
+    pub id: Vec<u8>,
+    #[sea_orm(column_type = "Binary(16)")]
+    pub company_id: Vec<u8>,
+    #[sea_orm(column_type = "Text")]
+    pub url: String,
+    pub secret: String,
+    /// JSON array of event types; an empty array subscribes to every event.
+    pub event_types: Json,
+    pub description: Option<String>,
+    pub is_active: i8,
+    pub created_at: DateTimeUtc,
+    pub updated_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::companies::Entity",
+        from = "Column::CompanyId",
+        to = "super::companies::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    Companies,
+    #[sea_orm(has_many = "super::outgoing_webhook_deliveries::Entity")]
+    OutgoingWebhookDeliveries,
+}
+
+impl Related<super::companies::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Companies.def()
+    }
+}
+
+impl Related<super::outgoing_webhook_deliveries::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::OutgoingWebhookDeliveries.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,23 @@
+//! Cron schedules that enqueue a job every time they come due.
+
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq)]
+#[sea_orm(table_name = "recurring_jobs")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub name: String,
+    pub job_type: String,
+    pub payload: Json,
+    /// Five-field cron expression, evaluated in UTC.
+    pub schedule: String,
+    pub next_run_at: DateTimeUtc,
+    pub last_run_at: Option<DateTimeUtc>,
+    pub is_active: i8,
+    pub updated_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -15,6 +15,10 @@ pub struct Model {
     pub created_at: DateTimeUtc,
     pub last_used_at: DateTimeUtc,
     pub expires_at: DateTimeUtc,
+    /// Company the token is issued for; added by this service, so tokens
+    /// created elsewhere have none and work for any company of the user.
+    #[sea_orm(column_type = "Binary(16)", nullable)]
+    pub company_id: Option<Vec<u8>>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
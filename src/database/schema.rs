@@ -0,0 +1,120 @@
+//! Idempotent schema helpers for `database::migrations`.
+//!
+//! Databases restored from the legacy dump already have most tables, so
+//! migrations add columns and indexes only when they are missing.
+
+use sea_orm::{
+    ConnectionTrait, DbBackend, DbErr, Iden, Statement,
+    sea_query::{IndexCreateStatement, TableAlterStatement},
+};
+
+/// Creates `index` named `name` on `table` unless it already exists.
+pub(crate) async fn ensure_index<C>(
+    db: &C,
+    table: &str,
+    name: &str,
+    mut index: IndexCreateStatement,
+) -> Result<(), DbErr>
+where
+    C: ConnectionTrait,
+{
+    let backend = db.get_database_backend();
+    if backend == DbBackend::MySql && index_exists(db, table, name).await? {
+        return Ok(());
+    }
+
+    index.name(name).if_not_exists();
+    db.execute(backend.build(&index)).await?;
+    Ok(())
+}
+
+/// MySQL has no `CREATE INDEX IF NOT EXISTS`, so existence is checked first.
+pub(crate) async fn index_exists<C>(db: &C, table: &str, index: &str) -> Result<bool, DbErr>
+where
+    C: ConnectionTrait,
+{
+    let count = db
+        .query_one(Statement::from_sql_and_values(
+            DbBackend::MySql,
+            "SELECT COUNT(*) AS cnt FROM information_schema.statistics \
+             WHERE table_schema = DATABASE() AND table_name = ? AND index_name = ?",
+            [table.into(), index.into()],
+        ))
+        .await?
+        .map(|row| row.try_get::<i64>("", "cnt"))
+        .transpose()?
+        .unwrap_or(0);
+
+    Ok(count > 0)
+}
+
+/// Runs `statement` unless `column` already exists; returns whether it ran.
+pub(crate) async fn add_missing_column<C, I>(
+    db: &C,
+    table: &str,
+    statement: TableAlterStatement,
+    column: I,
+) -> Result<bool, DbErr>
+where
+    C: ConnectionTrait,
+    I: Iden,
+{
+    if column_exists(db, table, &column.to_string()).await? {
+        return Ok(false);
+    }
+
+    db.execute(db.get_database_backend().build(&statement))
+        .await?;
+    Ok(true)
+}
+
+/// Runs `statement` if `column` exists; returns whether it ran.
+pub(crate) async fn drop_existing_column<C, I>(
+    db: &C,
+    table: &str,
+    statement: TableAlterStatement,
+    column: I,
+) -> Result<bool, DbErr>
+where
+    C: ConnectionTrait,
+    I: Iden,
+{
+    if !column_exists(db, table, &column.to_string()).await? {
+        return Ok(false);
+    }
+
+    db.execute(db.get_database_backend().build(&statement))
+        .await?;
+    Ok(true)
+}
+
+async fn column_exists<C>(db: &C, table: &str, column: &str) -> Result<bool, DbErr>
+where
+    C: ConnectionTrait,
+{
+    let backend = db.get_database_backend();
+    let sql = match backend {
+        DbBackend::MySql => {
+            "SELECT COUNT(*) AS cnt FROM information_schema.columns \
+             WHERE table_schema = DATABASE() AND table_name = ? AND column_name = ?"
+        }
+        DbBackend::Postgres => {
+            "SELECT COUNT(*) AS cnt FROM information_schema.columns \
+             WHERE table_schema = current_schema() AND table_name = $1 AND column_name = $2"
+        }
+        DbBackend::Sqlite => "SELECT COUNT(*) AS cnt FROM pragma_table_info(?) WHERE name = ?",
+    };
+
+    let count = db
+        .query_one(Statement::from_sql_and_values(
+            backend,
+            sql,
+            [table.into(), column.into()],
+        ))
+        .await?
+        .map(|row| row.try_get::<i64>("", "cnt"))
+        .transpose()?
+        .unwrap_or(0);
+
+    Ok(count > 0)
+}
@@ -1,19 +1,37 @@
-use actix_web::{HttpResponse, ResponseError, http::StatusCode};
+use actix_web::{
+    HttpResponse, ResponseError,
+    error::{JsonPayloadError, PathError, QueryPayloadError},
+    http::{StatusCode, header},
+    web,
+};
 use sea_orm::DbErr;
 use serde::Serialize;
 use thiserror::Error;
 
+use crate::logging;
+
 /// Унифицированная структура ответа об ошибке
 #[derive(Serialize)]
 pub struct ErrorResponse<'a> {
     pub code: &'a str,
     pub message: String,
+    /// Ошибки по полям запроса (для `VALIDATION_ERROR`)
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub details: Option<String>,
+    pub details: Option<Vec<FieldError>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub trace_id: Option<String>,
 }
 
+/// Ошибка валидации одного поля запроса
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct FieldError {
+    /// Путь к полю в JSON, например `message.content[1].type`
+    pub field: String,
+    /// Машиночитаемый код правила, например `too_long`
+    pub code: &'static str,
+    pub message: String,
+}
+
 #[derive(Error, Debug)]
 pub enum AppError {
     #[error("Database error: {0}")]
@@ -31,6 +49,9 @@ pub enum AppError {
     #[error("Invalid input: {0}")]
     InvalidInput(String),
 
+    #[error("Validation failed: {}", summarize(.0))]
+    Validation(Vec<FieldError>),
+
     #[error("Forbidden: {0}")]
     Forbidden(String),
 
@@ -40,6 +61,15 @@ pub enum AppError {
     #[error("External API error: {0}")]
     ExternalApiError(String),
 
+    #[error("Payload too large: {0}")]
+    PayloadTooLarge(String),
+
+    #[error("Unsupported media type: {0}")]
+    UnsupportedMediaType(String),
+
+    #[error("Too many requests, retry in {0} s")]
+    RateLimited(u64),
+
     #[error("Internal server error")]
     Internal,
 }
@@ -53,23 +83,32 @@ impl ResponseError for AppError {
             | AppError::Internal
             | AppError::ExternalApiError(_) => StatusCode::INTERNAL_SERVER_ERROR,
             AppError::NotFound(_) => StatusCode::NOT_FOUND,
-            AppError::InvalidInput(_) => StatusCode::BAD_REQUEST,
+            AppError::InvalidInput(_) | AppError::Validation(_) => StatusCode::BAD_REQUEST,
             AppError::Forbidden(_) => StatusCode::FORBIDDEN,
             AppError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            AppError::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            AppError::UnsupportedMediaType(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            AppError::RateLimited(_) => StatusCode::TOO_MANY_REQUESTS,
         }
     }
 
     fn error_response(&self) -> HttpResponse {
         let code = self.code();
         let message = self.to_string();
-        // trace_id можно внедрить позже через middleware (корреляция)
         let body = ErrorResponse {
             code,
             message,
-            details: None,
-            trace_id: None,
+            details: match self {
+                AppError::Validation(errors) => Some(errors.clone()),
+                _ => None,
+            },
+            trace_id: logging::current_request_id(),
         };
-        HttpResponse::build(self.status_code()).json(body)
+        let mut response = HttpResponse::build(self.status_code());
+        if let AppError::RateLimited(retry_after) = self {
+            response.insert_header((header::RETRY_AFTER, retry_after.to_string()));
+        }
+        response.json(body)
     }
 }
 
@@ -81,10 +120,62 @@ impl AppError {
             AppError::JsonError(_) => "JSON_ERROR",
             AppError::NotFound(_) => "NOT_FOUND",
             AppError::InvalidInput(_) => "INVALID_INPUT",
+            AppError::Validation(_) => "VALIDATION_ERROR",
             AppError::Forbidden(_) => "FORBIDDEN",
             AppError::Unauthorized(_) => "UNAUTHORIZED",
             AppError::ExternalApiError(_) => "EXTERNAL_API_ERROR",
+            AppError::PayloadTooLarge(_) => "PAYLOAD_TOO_LARGE",
+            AppError::UnsupportedMediaType(_) => "UNSUPPORTED_MEDIA_TYPE",
+            AppError::RateLimited(_) => "RATE_LIMITED",
             AppError::Internal => "INTERNAL",
         }
     }
 }
+
+impl From<JsonPayloadError> for AppError {
+    fn from(err: JsonPayloadError) -> Self {
+        match err {
+            JsonPayloadError::OverflowKnownLength { .. } | JsonPayloadError::Overflow { .. } => {
+                AppError::PayloadTooLarge(err.to_string())
+            }
+            JsonPayloadError::ContentType => AppError::UnsupportedMediaType(err.to_string()),
+            _ => AppError::InvalidInput(err.to_string()),
+        }
+    }
+}
+
+impl From<QueryPayloadError> for AppError {
+    fn from(err: QueryPayloadError) -> Self {
+        AppError::InvalidInput(err.to_string())
+    }
+}
+
+impl From<PathError> for AppError {
+    fn from(err: PathError) -> Self {
+        AppError::InvalidInput(err.to_string())
+    }
+}
+
+/// Настройки `web::Json`: ошибки разбора тела отдаются как [`AppError`], в
+/// общем формате и с `trace_id`.
+pub fn json_config() -> web::JsonConfig {
+    web::JsonConfig::default().error_handler(|err, _| AppError::from(err).into())
+}
+
+/// Настройки `web::Query`, ошибки которых отдаются как [`AppError`].
+pub fn query_config() -> web::QueryConfig {
+    web::QueryConfig::default().error_handler(|err, _| AppError::from(err).into())
+}
+
+/// Настройки `web::Path`, ошибки которых отдаются как [`AppError`].
+pub fn path_config() -> web::PathConfig {
+    web::PathConfig::default().error_handler(|err, _| AppError::from(err).into())
+}
+
+fn summarize(errors: &[FieldError]) -> String {
+    errors
+        .iter()
+        .map(|error| format!("{}: {}", error.field, error.message))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
@@ -3,4 +3,10 @@ pub mod app_state;
 pub mod config;
 pub mod database;
 pub mod errors;
+pub mod logging;
+pub mod metrics;
 pub mod services;
+pub mod telemetry;
+#[cfg(feature = "testing")]
+pub mod testing;
+pub mod tls;
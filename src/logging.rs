@@ -0,0 +1,324 @@
+//! Structured logging and redaction of personal data.
+//!
+//! Every record is written as one JSON object (or a plain text line with
+//! `LOG_FORMAT=text`) carrying the request id and company id of the request
+//! being served, plus any `key = value` fields of the log call.
+//!
+//! Phones, emails, bearer tokens and stream tickets are masked in every record, and bodies
+//! passed through [`redact_body`] lose message text, names and contact data.
+//! `LOG_REDACT=false` turns redaction off for local debugging; never set it in
+//! production.
+
+use std::{
+    borrow::Cow,
+    cell::RefCell,
+    fmt::Display,
+    future::Future,
+    io::Write,
+    rc::Rc,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use log::kv::{Error as KvError, Key, Value as KvValue, VisitSource};
+use regex::{Captures, Regex};
+use serde_json::{Map, Value, json};
+
+use crate::config::Config;
+
+const MASK: &str = "***";
+
+/// Keys whose values are never logged, compared without case and underscores.
+const SENSITIVE_KEYS: &[&str] = &[
+    "text",
+    "content",
+    "caption",
+    "name",
+    "fullname",
+    "firstname",
+    "lastname",
+    "username",
+    "clientname",
+    "phone",
+    "clientphone",
+    "email",
+    "chatid",
+    "contenturi",
+    "avataruri",
+    "apikey",
+    "wazzupapikey",
+    "token",
+    "accesstoken",
+    "password",
+    "authorization",
+];
+
+static REDACT: AtomicBool = AtomicBool::new(true);
+
+lazy_static::lazy_static! {
+    static ref EMAIL_RE: Regex =
+        Regex::new(r"[A-Za-z0-9._%+-]+@([A-Za-z0-9-]+(?:\.[A-Za-z0-9-]+)*\.[A-Za-z]{2,})").unwrap();
+    static ref PHONE_RE: Regex = Regex::new(r"\+?\d{10,15}").unwrap();
+    static ref BEARER_RE: Regex =
+        Regex::new(r"(?i)(bearer\s+|access_token=|ticket=)[A-Za-z0-9._~+/=-]+").unwrap();
+    static ref TOKEN_RE: Regex = Regex::new(r"(wz[ts]_)[A-Za-z0-9_.-]+").unwrap();
+}
+
+tokio::task_local! {
+    static REQUEST_CONTEXT: Rc<RequestContext>;
+}
+
+/// Ids attached to every record logged while a request is served.
+#[derive(Debug)]
+pub struct RequestContext {
+    request_id: String,
+    company_id: RefCell<Option<String>>,
+}
+
+impl RequestContext {
+    pub fn new(request_id: String) -> Rc<Self> {
+        Rc::new(Self {
+            request_id,
+            company_id: RefCell::new(None),
+        })
+    }
+
+    pub fn request_id(&self) -> &str {
+        &self.request_id
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    Json,
+    Text,
+}
+
+/// Installs the global logger. `RUST_LOG` still selects levels (default `info`).
+pub fn init(config: &Config) {
+    let format = config.log_format();
+    set_redaction(config.log_redact());
+
+    env_logger::Builder::from_env(env_logger::Env::new().default_filter_or("info"))
+        .format(move |buf, record| {
+            let line = match format {
+                LogFormat::Json => json_line(record),
+                LogFormat::Text => text_line(record),
+            };
+            writeln!(buf, "{}", line)
+        })
+        .init();
+
+    if !redaction_enabled() {
+        log::warn!("Log redaction is disabled; logs may contain personal data");
+    }
+}
+
+pub fn set_redaction(enabled: bool) {
+    REDACT.store(enabled, Ordering::Relaxed);
+}
+
+pub fn redaction_enabled() -> bool {
+    REDACT.load(Ordering::Relaxed)
+}
+
+/// Runs `future` with `context` attached to its log records.
+pub fn scope<F: Future>(context: Rc<RequestContext>, future: F) -> impl Future<Output = F::Output> {
+    REQUEST_CONTEXT.scope(context, future)
+}
+
+/// Runs `f` with `context` attached to its log records.
+pub fn sync_scope<R>(context: Rc<RequestContext>, f: impl FnOnce() -> R) -> R {
+    REQUEST_CONTEXT.sync_scope(context, f)
+}
+
+/// Records the company the current request acts for; no-op outside a request.
+pub fn set_company_id(company_id: impl Display) {
+    let _ = REQUEST_CONTEXT.try_with(|context| {
+        *context.company_id.borrow_mut() = Some(company_id.to_string());
+    });
+}
+
+/// Id of the request being served; `None` outside a request.
+pub fn current_request_id() -> Option<String> {
+    REQUEST_CONTEXT
+        .try_with(|context| context.request_id.clone())
+        .ok()
+}
+
+fn current_context() -> (Option<String>, Option<String>) {
+    REQUEST_CONTEXT
+        .try_with(|context| {
+            (
+                Some(context.request_id.clone()),
+                context.company_id.borrow().clone(),
+            )
+        })
+        .unwrap_or_default()
+}
+
+/// Masks phones, emails and tokens in free text.
+pub fn redact(text: &str) -> Cow<'_, str> {
+    if !redaction_enabled() {
+        return Cow::Borrowed(text);
+    }
+
+    let text = BEARER_RE.replace_all(text, |caps: &Captures| format!("{}{}", &caps[1], MASK));
+    let text = TOKEN_RE
+        .replace_all(&text, |caps: &Captures| format!("{}{}", &caps[1], MASK))
+        .into_owned();
+    let text = EMAIL_RE
+        .replace_all(&text, |caps: &Captures| format!("{}@{}", MASK, &caps[1]))
+        .into_owned();
+    let masked = PHONE_RE
+        .replace_all(&text, |caps: &Captures| {
+            let found = caps.get(0).unwrap();
+            if is_part_of_word(&text, found.start(), found.end()) {
+                found.as_str().to_string()
+            } else {
+                mask_phone(found.as_str())
+            }
+        })
+        .into_owned();
+
+    Cow::Owned(masked)
+}
+
+/// Redacts a request or response body: sensitive JSON fields are masked
+/// entirely, anything else goes through [`redact`].
+pub fn redact_body(body: &str) -> String {
+    if !redaction_enabled() {
+        return body.to_string();
+    }
+
+    match serde_json::from_str::<Value>(body) {
+        Ok(mut value) => {
+            redact_json(&mut value);
+            value.to_string()
+        }
+        Err(_) => redact(body).into_owned(),
+    }
+}
+
+/// Masks sensitive fields of a JSON value in place.
+pub fn redact_json(value: &mut Value) {
+    if !redaction_enabled() {
+        return;
+    }
+
+    match value {
+        Value::Object(fields) => {
+            for (key, field) in fields.iter_mut() {
+                if is_sensitive_key(key) && !(field.is_object() || field.is_array()) {
+                    if !field.is_null() {
+                        *field = Value::String(MASK.to_string());
+                    }
+                } else {
+                    redact_json(field);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(redact_json),
+        Value::String(text) => {
+            let masked = redact(text).into_owned();
+            *text = masked;
+        }
+        _ => {}
+    }
+}
+
+fn is_sensitive_key(key: &str) -> bool {
+    let normalized: String = key
+        .chars()
+        .filter(|c| *c != '_' && *c != '-')
+        .flat_map(char::to_lowercase)
+        .collect();
+    SENSITIVE_KEYS.contains(&normalized.as_str())
+}
+
+/// Digits glued to letters or dashes belong to ids (UUIDs, hashes), not phones.
+fn is_part_of_word(text: &str, start: usize, end: usize) -> bool {
+    let glued = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_';
+    text[..start].chars().next_back().is_some_and(glued)
+        || text[end..].chars().next().is_some_and(glued)
+}
+
+fn mask_phone(phone: &str) -> String {
+    let digits = phone.trim_start_matches('+');
+    let (hidden, shown) = digits.split_at(digits.len() - 2);
+    format!(
+        "{}{}{}",
+        if phone.starts_with('+') { "+" } else { "" },
+        "*".repeat(hidden.len()),
+        shown
+    )
+}
+
+fn json_line(record: &log::Record<'_>) -> String {
+    let (request_id, company_id) = current_context();
+    let mut line = Map::new();
+    line.insert("ts".into(), json!(chrono::Utc::now().to_rfc3339()));
+    line.insert("level".into(), json!(record.level().as_str()));
+    line.insert("target".into(), json!(record.target()));
+    line.insert("msg".into(), json!(redact(&record.args().to_string())));
+    if let Some(request_id) = request_id {
+        line.insert("request_id".into(), json!(request_id));
+    }
+    if let Some(company_id) = company_id {
+        line.insert("company_id".into(), json!(company_id));
+    }
+
+    let mut fields = Fields(Vec::new());
+    let _ = record.key_values().visit(&mut fields);
+    for (key, value) in fields.0 {
+        line.entry(key).or_insert(value);
+    }
+
+    Value::Object(line).to_string()
+}
+
+fn text_line(record: &log::Record<'_>) -> String {
+    let (request_id, company_id) = current_context();
+    let mut line = format!(
+        "[{} {} {}",
+        chrono::Utc::now().to_rfc3339(),
+        record.level(),
+        record.target()
+    );
+    if let Some(request_id) = request_id {
+        line.push_str(&format!(" request_id={}", request_id));
+    }
+    if let Some(company_id) = company_id {
+        line.push_str(&format!(" company_id={}", company_id));
+    }
+    line.push_str(&format!("] {}", redact(&record.args().to_string())));
+
+    let mut fields = Fields(Vec::new());
+    let _ = record.key_values().visit(&mut fields);
+    for (key, value) in fields.0 {
+        match value {
+            Value::String(text) => line.push_str(&format!(" {}={}", key, text)),
+            other => line.push_str(&format!(" {}={}", key, other)),
+        }
+    }
+
+    line
+}
+
+/// Collects `key = value` fields of a record.
+struct Fields(Vec<(String, Value)>);
+
+impl<'kvs> VisitSource<'kvs> for Fields {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: KvValue<'kvs>) -> Result<(), KvError> {
+        let value = if let Some(number) = value.to_u64() {
+            json!(number)
+        } else if let Some(number) = value.to_i64() {
+            json!(number)
+        } else if let Some(flag) = value.to_bool() {
+            json!(flag)
+        } else {
+            json!(redact(&value.to_string()))
+        };
+        self.0.push((key.as_str().to_string(), value));
+        Ok(())
+    }
+}
@@ -1,193 +1,676 @@
-use actix_cors::Cors;
+use std::sync::Arc;
+
 use actix_files as fs;
+use actix_web::dev::Server;
 use actix_web::{App, HttpServer, middleware, web};
-use dotenvy::dotenv;
-use sea_orm::Database;
-use std::env;
-use utoipa::OpenApi;
+use clap::Parser;
+use utoipa::{
+    Modify, OpenApi,
+    openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme},
+};
 use utoipa_swagger_ui::SwaggerUi;
 
 mod api;
 mod app_state;
+mod cli;
 mod config;
 mod database;
 mod errors;
+mod logging;
+mod metrics;
 mod services; // ensure app_state visible to crate::* imports
+mod tasks;
+mod telemetry;
+mod tls;
 
-use crate::api::{channels, chats, contacts, webhooks};
+use crate::api::{
+    admin, channels, chats, contacts, diagnostics, events, health, outgoing_webhooks, tokens,
+    webhooks,
+};
 use crate::app_state::AppState;
+use crate::cli::{Cli, Command};
 use crate::config::Config;
-use crate::services::{bot_service, wazzup_api};
+use crate::services::{
+    api_keys::ApiKeyVault,
+    background::TaskManager,
+    bot_service,
+    events::EventBus,
+    jobs::{self, JobQueue},
+    outgoing_webhooks::{OutgoingWebhookService, SWEEP_INTERVAL},
+    rate_limit::{self, RateLimiter},
+    wazzup_api,
+};
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
-    dotenv().ok();
-    env_logger::init_from_env(env_logger::Env::new().default_filter_or("info"));
+    let cli = Cli::parse();
+    let config = Config::load(cli.config.as_deref(), &cli.overrides.pairs())
+        .map_err(|err| std::io::Error::other(format!("Invalid configuration: {}", err)))?;
+    logging::init(&config);
+    let command = cli.command.unwrap_or(Command::Serve);
+
+    if let Command::CheckConfig = command {
+        return check_config(&config);
+    }
 
-    let config = Config::from_env().expect("Failed to load configuration");
-    let db_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
-    let db = Database::connect(&db_url)
+    let db_settings = config
+        .database_settings()
+        .map_err(|err| std::io::Error::other(format!("Invalid configuration: {}", err)))?;
+    let db = database::connect_with_settings(&db_settings)
         .await
         .expect("Failed to connect to database");
+    if let Command::Migrate { status } = command {
+        return migrate(&db, status).await;
+    }
+    if config.migrate_on_startup() {
+        database::migrations::run(&db)
+            .await
+            .expect("Failed to apply database migrations");
+    } else {
+        let pending = database::migrations::pending(&db)
+            .await
+            .expect("Failed to read applied database migrations");
+        if !pending.is_empty() {
+            log::warn!(
+                "{} database migration(s) pending; run `wazzup migrate`",
+                pending.len()
+            );
+        }
+    }
+
+    let api_keys = ApiKeyVault::from_config(&config).expect("Failed to load API key master keys");
+    if let Command::EncryptKeys { dry_run } = command {
+        return encrypt_keys(&db, &api_keys, dry_run).await;
+    }
+    let outgoing = OutgoingWebhookService::from_config(&config)
+        .map_err(|err| std::io::Error::other(format!("Invalid configuration: {}", err)))?;
+    if command.is_task() {
+        let shared = Shared {
+            db,
+            config,
+            events: EventBus::new(),
+            outgoing,
+            api_keys,
+            tasks: TaskManager::new(),
+            rate_limiter: RateLimiter::new(),
+        };
+        return tasks::run(command, shared.app_state()).await;
+    }
+    let telemetry = telemetry::init(&config);
+    if !api_keys.is_configured() {
+        log::warn!(
+            "No API key master key configured; only unencrypted Wazzup API keys can be read \
+             and stream tickets are only accepted by the process that issued them"
+        );
+    }
+
+    let tasks = TaskManager::new();
 
-    #[derive(OpenApi)]
-    #[openapi(
-        paths(
-            // Channels
-            channels::get_channels,
-            channels::delete_channel,
-            channels::generate_wrapped_iframe_link,
-            channels::handle_channel_added,
-            channels::reinitialize_channel,
-            // Chats
-            chats::get_chat_previews,
-            chats::get_chat,
-            chats::get_chat_messages,
-            chats::send_chat_message,
-            // Contacts
-            contacts::get_contacts,
-            contacts::get_contact_by_id,
-            contacts::update_contact,
-            contacts::delete_contact,
-            // Webhooks
-            webhooks::validate_webhook,
-            webhooks::handle_webhook,
-            webhooks::connect_webhooks,
-            webhooks::test_webhook,
-        ),
-        components(
-            schemas(
-                contacts::UpdateContactDto,
-                contacts::ContactWithWazzupData,
-                channels::WrappedIframeLinkResponse,
-                channels::ChannelAddedNotification,
-                channels::ChannelsResponse,
-                channels::ChannelView,
-                webhooks::ConnectWebhooksResponse,
-
-                // --- Chats API Schemas ---
-                chats::ChatPreview,
-                chats::ChatPreviewList,
-                chats::ChatDetails,
-                chats::ChatMessagesResponse,
-                chats::ChatPreviewsQuery,
-                chats::MessagesQuery,
-                chats::MessageView,
-                chats::MessageSender,
-                chats::MessageContentItem,
-                chats::OutgoingMessage,
-                chats::SendChatMessageRequest,
-                chats::SendChatMessageResponse,
-
-                // --- Wazzup API Schemas ---
-                wazzup_api::ChannelListResponse,
-                wazzup_api::ChannelInfo,
-                wazzup_api::GenerateIframeLinkRequest,
-                wazzup_api::SendMessageRequest,
-                wazzup_api::SendMessageResponse,
-                wazzup_api::MessageListResponse,
-                wazzup_api::Message,
-                wazzup_api::UnreadCountResponse,
-                wazzup_api::UserSettings,
-                wazzup_api::UserRole,
-                wazzup_api::UpdateUserSettingsRequest
+    // Both listeners publish events (API actions and incoming webhooks), so
+    // each process runs its own dispatcher.
+    let events = EventBus::new();
+    // The bus is in-process: outgoing webhooks are recorded in the database
+    // and reach every receiver, but event streams only carry what this
+    // process publishes.
+    if matches!(command, Command::ServeApi | Command::ServeWebhooks) {
+        log::warn!(
+            "Event streams (/api/events) only carry events of this process; \
+             incoming Wazzup webhooks handled by serve-webhooks do not reach them. \
+             Run `serve` to stream every event, or use outgoing webhooks"
+        );
+    }
+    {
+        let (outgoing, db, events) = (outgoing.clone(), db.clone(), events.clone());
+        tasks.spawn_service("outgoing-webhooks.dispatch", move |shutdown| {
+            outgoing
+                .clone()
+                .listen(db.clone(), events.clone(), shutdown)
+        });
+    }
+    {
+        let (outgoing, db) = (outgoing.clone(), db.clone());
+        tasks.spawn_periodic("outgoing-webhooks.retry", SWEEP_INTERVAL, move || {
+            let (outgoing, db) = (outgoing.clone(), db.clone());
+            async move { outgoing.deliver_due(&db).await }
+        });
+    }
+
+    let sync_schedule = match &config.company_sync_schedule {
+        Some(schedule) => {
+            jobs::set_recurring(
+                &db,
+                jobs::COMPANY_SYNC_SCHEDULE,
+                schedule,
+                jobs::SYNC_ALL_COMPANIES,
+                serde_json::json!({}),
             )
-        ),
-        tags(
-            (name = "Channels", description = "Channel management endpoints"),
-            (name = "Chats", description = "Chat management endpoints"),
-            (name = "Contacts", description = "Contact management endpoints (synced with Wazzup)"),
-            (name = "Webhooks", description = "Endpoints for receiving Wazzup webhooks"),
+            .await
+        }
+        None => jobs::remove_recurring(&db, jobs::COMPANY_SYNC_SCHEDULE).await,
+    };
+    if let Err(err) = sync_schedule {
+        log::error!("Failed to update the company sync schedule: {}", err);
+    }
+
+    // Only the certificates of the listeners this process runs are needed.
+    let api_tls = match command {
+        Command::ServeWebhooks => None,
+        _ => config.api_tls().map(tls::Certificate::load).transpose()?,
+    };
+    let webhook_tls = match command {
+        Command::ServeApi => None,
+        _ => config
+            .webhook_tls()
+            .map(tls::Certificate::load)
+            .transpose()?,
+    };
+    #[cfg(unix)]
+    {
+        let certificates: Vec<_> = api_tls.iter().chain(&webhook_tls).cloned().collect();
+        if !certificates.is_empty() {
+            tasks.spawn_service("tls.reload", move |shutdown| {
+                tls::reload_on_hangup(certificates.clone(), shutdown)
+            });
+        }
+    }
+
+    // Both listeners of a process share the buckets.
+    let rate_limiter = RateLimiter::new();
+    {
+        let rate_limiter = rate_limiter.clone();
+        tasks.spawn_periodic("rate-limit.sweep", rate_limit::SWEEP_INTERVAL, move || {
+            rate_limiter.sweep();
+            async { Ok(()) }
+        });
+    }
+
+    let shutdown_timeout = config.shutdown_timeout();
+    let shared = Shared {
+        db,
+        config,
+        events,
+        outgoing,
+        api_keys,
+        tasks: tasks.clone(),
+        rate_limiter,
+    };
+    {
+        // Every process works the queue; claims keep instances apart.
+        let (queue, shared) = (JobQueue::standard(), shared.clone());
+        tasks.spawn_service("jobs.worker", move |shutdown| {
+            queue.clone().work(shared.app_state(), shutdown)
+        });
+    }
+    // The servers stop on SIGINT/SIGTERM after finishing in-flight requests;
+    // background tasks are drained afterwards.
+    let served = match command {
+        Command::ServeApi => api_server(shared, api_tls)?.await,
+        Command::ServeWebhooks => webhook_server(shared, webhook_tls)?.await,
+        _ => tokio::try_join!(
+            api_server(shared.clone(), api_tls)?,
+            webhook_server(shared, webhook_tls)?
         )
-    )]
-    struct ApiDoc;
+        .map(|_| ()),
+    };
+    tasks.shutdown(shutdown_timeout).await;
+    telemetry.shutdown();
 
-    let host = config.host.clone();
-    let port = config.port;
-    let webhook_port = config.effective_webhook_port();
+    served
+}
 
-    log::info!("Starting server at http://{}:{}", host, port);
-    log::info!(
-        "Swagger UI available at http://{}:{}/swagger-ui/",
-        host,
-        port
-    );
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        // Channels
+        channels::get_channels,
+        channels::delete_channel,
+        channels::generate_wrapped_iframe_link,
+        channels::handle_channel_added,
+        channels::reinitialize_channel,
+        // Chats
+        chats::get_chat_previews,
+        chats::search_chats,
+        chats::get_chat,
+        chats::get_chat_messages,
+        chats::send_chat_message,
+        chats::mark_chat_read,
+        chats::get_unread_summary,
+        chats::assign_chat,
+        // Contacts
+        contacts::get_contacts,
+        contacts::get_contact_by_id,
+        contacts::update_contact,
+        contacts::delete_contact,
+        // Webhooks
+        webhooks::validate_webhook,
+        webhooks::handle_webhook,
+        webhooks::connect_webhooks,
+        webhooks::test_webhook,
+        // Events
+        events::create_stream_ticket,
+        events::stream_events,
+        // Outgoing webhooks
+        outgoing_webhooks::list_outgoing_webhooks,
+        outgoing_webhooks::create_outgoing_webhook,
+        outgoing_webhooks::update_outgoing_webhook,
+        outgoing_webhooks::delete_outgoing_webhook,
+        outgoing_webhooks::list_webhook_deliveries,
+        outgoing_webhooks::redeliver_webhook_delivery,
+        // Tokens
+        tokens::login_token,
+        tokens::list_tokens,
+        tokens::create_token,
+        tokens::revoke_token,
+        // Admin
+        admin::list_companies,
+        admin::create_company,
+        admin::get_company,
+        admin::update_company,
+        admin::delete_company,
+        admin::rotate_api_key,
+        admin::get_company_status,
+        admin::list_tasks,
+        // Diagnostics
+        diagnostics::get_company_diagnostics,
+        // Health
+        health::liveness,
+        health::readiness,
+        health::get_metrics,
+    ),
+    components(
+        schemas(
+            contacts::UpdateContactDto,
+            contacts::ContactWithWazzupData,
+            channels::WrappedIframeLinkResponse,
+            channels::ChannelAddedNotification,
+            channels::ChannelsResponse,
+            channels::ChannelView,
+            webhooks::ConnectWebhooksResponse,
+
+            // --- Chats API Schemas ---
+            chats::ChatPreview,
+            chats::ChatPreviewList,
+            chats::ChatDetails,
+            chats::ChatMessagesResponse,
+            chats::ChatPreviewsQuery,
+            chats::MessagesQuery,
+            chats::MessageView,
+            chats::MessageSender,
+            chats::MessageContentItem,
+            chats::OutgoingMessage,
+            chats::SendChatMessageRequest,
+            chats::SendChatMessageResponse,
+            chats::MarkChatReadRequest,
+            chats::ChatReadStateResponse,
+            chats::ChatUnreadCount,
+            chats::UnreadSummaryResponse,
+            chats::AssignChatRequest,
+            chats::ChatSearchQuery,
+            chats::ChatSearchResponse,
+            chats::MessageSearchHit,
+            chats::ClientSearchHit,
+
+            // --- Events API Schemas ---
+            crate::services::events::ServiceEvent,
+            crate::services::events::EventKind,
+            events::StreamTicketResponse,
+
+            // --- Outgoing Webhooks API Schemas ---
+            outgoing_webhooks::CreateOutgoingWebhookRequest,
+            outgoing_webhooks::UpdateOutgoingWebhookRequest,
+            outgoing_webhooks::OutgoingWebhookView,
+            outgoing_webhooks::OutgoingWebhookList,
+            outgoing_webhooks::WebhookDeliveryView,
+            outgoing_webhooks::WebhookDeliveryList,
 
+            // --- Tokens API Schemas ---
+            tokens::LoginTokenRequest,
+            tokens::CreateTokenRequest,
+            tokens::TokenView,
+            tokens::IssuedTokenResponse,
+            tokens::TokenList,
+
+            // --- Admin API Schemas ---
+            admin::CreateCompanyRequest,
+            admin::UpdateCompanyRequest,
+            admin::RotateApiKeyRequest,
+            admin::CompanyView,
+            admin::CompanyList,
+            admin::CompanyWithWebhooks,
+            admin::WebhookConnection,
+            admin::CompanyStatus,
+            admin::ApiKeyStatus,
+            admin::TaskList,
+            crate::services::background::TaskStatus,
+            crate::services::background::TaskKind,
+            crate::services::background::TaskState,
+
+            // --- Diagnostics API Schemas ---
+            diagnostics::CompanyDiagnostics,
+            diagnostics::WebhookDiagnostics,
+            diagnostics::WebhookState,
+            diagnostics::ChannelDiagnostics,
+
+            // --- Health Schemas ---
+            health::HealthStatus,
+            health::ReadinessReport,
+            health::CheckStatus,
+
+            // --- Wazzup API Schemas ---
+            wazzup_api::ChannelListResponse,
+            wazzup_api::ChannelInfo,
+            wazzup_api::GenerateIframeLinkRequest,
+            wazzup_api::SendMessageRequest,
+            wazzup_api::SendMessageResponse,
+            wazzup_api::MessageListResponse,
+            wazzup_api::Message,
+            wazzup_api::UnreadCountResponse,
+            wazzup_api::UserSettings,
+            wazzup_api::UserRole,
+            wazzup_api::UpdateUserSettingsRequest
+        )
+    ),
+    tags(
+        (name = "Channels", description = "Channel management endpoints"),
+        (name = "Chats", description = "Chat management endpoints"),
+        (name = "Contacts", description = "Contact management endpoints (synced with Wazzup)"),
+        (name = "Webhooks", description = "Endpoints for receiving Wazzup webhooks"),
+        (name = "Events", description = "Real-time event stream (Server-Sent Events)"),
+        (name = "Outgoing webhooks", description = "Signed delivery of service events to downstream endpoints"),
+        (name = "Tokens", description = "Bearer tokens for API access"),
+        (name = "Admin", description = "Platform administration of companies"),
+        (name = "Diagnostics", description = "Health of a company's Wazzup integration"),
+        (name = "Health", description = "Probes of both listeners and Prometheus metrics"),
+    ),
+    modifiers(&BearerSecurity),
+    security(("bearer" = []))
+)]
+struct ApiDoc;
+
+struct BearerSecurity;
+
+impl Modify for BearerSecurity {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "bearer",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+        );
+    }
+}
+
+/// Everything the workers of both listeners share.
+#[derive(Clone)]
+struct Shared {
+    db: sea_orm::DatabaseConnection,
+    config: Config,
+    events: EventBus,
+    outgoing: OutgoingWebhookService,
+    api_keys: ApiKeyVault,
+    tasks: TaskManager,
+    rate_limiter: RateLimiter,
+}
+
+impl Shared {
+    fn app_state(&self) -> AppState {
+        AppState {
+            db: self.db.clone(),
+            config: self.config.clone(),
+            wazzup_api: wazzup_api::WazzupApiService::from_config(&self.config),
+            bot_service: bot_service::BotService::new(),
+            events: self.events.clone(),
+            outgoing_webhooks: self.outgoing.clone(),
+            api_keys: self.api_keys.clone(),
+            tasks: self.tasks.clone(),
+        }
+    }
+}
+
+/// API, Swagger UI and static files on `host:port`, over HTTPS with `tls`.
+fn api_server(shared: Shared, tls: Option<Arc<tls::Certificate>>) -> std::io::Result<Server> {
+    let host = shared.config.host.clone();
+    let port = shared.config.port;
+    let workers = shared.config.workers;
+    let shutdown_timeout = shared.config.shutdown_timeout().as_secs();
+    let scheme = if tls.is_some() { "https" } else { "http" };
+
+    log::info!("Starting server at {}://{}:{}", scheme, host, port);
     log::info!(
-        "Webhook listener starting at http://{}:{}",
+        "Swagger UI available at {}://{}:{}/swagger-ui/",
+        scheme,
         host,
-        webhook_port
+        port
     );
-    let api_db = db.clone();
-    let api_config = config.clone();
-    let api_host = host.clone();
 
-    let api_server = HttpServer::new(move || {
+    let server = HttpServer::new(move || {
         App::new()
-            .app_data(web::Data::new(AppState {
-                db: api_db.clone(),
-                config: api_config.clone(),
-                wazzup_api: wazzup_api::WazzupApiService::new(),
-                bot_service: bot_service::BotService::new(),
-            }))
+            .app_data(web::Data::new(shared.app_state()))
             .app_data(actix_web::web::PayloadConfig::new(
-                api_config.effective_max_body_bytes(),
+                shared.config.effective_max_body_bytes(),
             ))
+            .app_data(errors::json_config())
+            .app_data(errors::query_config())
+            .app_data(errors::path_config())
             .wrap(api::middleware::RequestId)
-            .wrap(
-                Cors::default()
-                    .allow_any_origin()
-                    .allow_any_method()
-                    .allow_any_header()
-                    .max_age(3600),
-            )
+            .wrap(api::middleware::cors(&shared.config))
             .service(
                 fs::Files::new("/static", "./static")
                     .show_files_listing()
                     .use_last_modified(true),
             )
+            .configure(health::init_routes)
+            .configure(health::init_metrics_routes)
             .service(
                 web::scope("/api")
+                    .wrap(api::middleware::RateLimit::per_principal(
+                        shared.rate_limiter.clone(),
+                        shared.config.rate_limits(),
+                    ))
+                    .wrap(api::middleware::BearerAuth)
+                    .wrap(
+                        api::middleware::RateLimit::per_ip(
+                            shared.rate_limiter.clone(),
+                            shared.config.rate_limits(),
+                        )
+                        .trusting(shared.config.trusted_proxies()),
+                    )
                     .wrap(middleware::NormalizePath::trim())
                     .configure(channels::init_routes)
                     .configure(chats::init_routes)
                     .configure(contacts::init_routes)
+                    .configure(events::init_routes)
+                    .configure(outgoing_webhooks::init_routes)
+                    .configure(tokens::init_routes)
+                    .configure(admin::init_routes)
+                    .configure(diagnostics::init_routes)
                     .configure(webhooks::init_routes),
             )
             .service(web::redirect("/swagger", "/swagger/"))
             .service(
                 SwaggerUi::new("/swagger/{_:.*}").url("/api-docs/openapi.json", ApiDoc::openapi()),
             )
-    })
-    .bind((api_host, port))?
-    .run();
+    });
+    let server = match workers {
+        Some(workers) => server.workers(workers),
+        None => server,
+    }
+    .shutdown_timeout(shutdown_timeout);
+    let server = match tls {
+        Some(certificate) => server.bind_rustls_0_23((host, port), certificate.server_config()?)?,
+        None => server.bind((host, port))?,
+    };
+    Ok(server.run())
+}
+
+/// Wazzup webhook callbacks on `webhook_host:webhook_port`, over HTTPS with `tls`.
+fn webhook_server(shared: Shared, tls: Option<Arc<tls::Certificate>>) -> std::io::Result<Server> {
+    let host = shared.config.effective_webhook_host().to_string();
+    let port = shared.config.effective_webhook_port();
+    let workers = shared.config.workers;
+    let shutdown_timeout = shared.config.shutdown_timeout().as_secs();
 
-    let webhook_db = db.clone();
-    let webhook_config = config.clone();
-    let webhook_host = host.clone();
+    log::info!(
+        "Webhook listener starting at {}://{}:{}",
+        if tls.is_some() { "https" } else { "http" },
+        host,
+        port
+    );
 
-    let webhook_server = HttpServer::new(move || {
+    let server = HttpServer::new(move || {
         App::new()
-            .app_data(web::Data::new(AppState {
-                db: webhook_db.clone(),
-                config: webhook_config.clone(),
-                wazzup_api: wazzup_api::WazzupApiService::new(),
-                bot_service: bot_service::BotService::new(),
-            }))
+            .app_data(web::Data::new(shared.app_state()))
             .app_data(actix_web::web::PayloadConfig::new(
-                webhook_config.effective_max_body_bytes(),
+                shared.config.effective_max_body_bytes(),
             ))
+            .app_data(errors::json_config())
+            .app_data(errors::query_config())
+            .app_data(errors::path_config())
             .wrap(api::middleware::RequestId)
+            .configure(health::init_routes)
             .service(
                 web::scope("/api")
+                    .wrap(
+                        api::middleware::RateLimit::webhooks(
+                            shared.rate_limiter.clone(),
+                            shared.config.rate_limits(),
+                        )
+                        .trusting(shared.config.trusted_proxies()),
+                    )
                     .wrap(middleware::NormalizePath::trim())
                     .configure(webhooks::init_routes),
             )
-    })
-    .bind((webhook_host, webhook_port))?
-    .run();
+    });
+    let server = match workers {
+        Some(workers) => server.workers(workers),
+        None => server,
+    }
+    .shutdown_timeout(shutdown_timeout);
+    let server = match tls {
+        Some(certificate) => server.bind_rustls_0_23((host, port), certificate.server_config()?)?,
+        None => server.bind((host, port))?,
+    };
+    Ok(server.run())
+}
 
-    tokio::try_join!(api_server, webhook_server)?;
+/// `wazzup check-config`: validates the configuration and master keys and
+/// logs the effective settings, without connecting anywhere.
+fn check_config(config: &Config) -> std::io::Result<()> {
+    let invalid = |err: ::config::ConfigError| {
+        std::io::Error::other(format!("Invalid configuration: {}", err))
+    };
+    let database = config.database_settings().map_err(invalid)?;
+    let api_keys = ApiKeyVault::from_config(config).map_err(invalid)?;
 
+    log::info!("API listener: {}:{}", config.host, config.port);
+    log::info!(
+        "Webhook listener: {}:{}",
+        config.effective_webhook_host(),
+        config.effective_webhook_port()
+    );
+    log::info!(
+        "Database: {} (pool max {}, min {})",
+        database::connector::without_password(&database.url),
+        display_or_default(database.max_connections),
+        display_or_default(database.min_connections)
+    );
+    log::info!(
+        "Wazzup API: {}, channels API: {}",
+        display_or_default(config.wazzup_api_url.as_deref()),
+        display_or_default(config.wazzup_tech_url.as_deref())
+    );
+    let any_or = |items: Vec<&str>| match items.is_empty() {
+        true => "any".to_string(),
+        false => items.join(", "),
+    };
+    log::info!(
+        "CORS: origins {}; methods {}; headers {}",
+        any_or(config.cors_origins()),
+        any_or(config.cors_methods()),
+        any_or(config.cors_headers())
+    );
+    let limits = config.rate_limits();
+    let per_minute = |limit: u32| match limit {
+        0 => "unlimited".to_string(),
+        limit => format!("{}/min", limit),
+    };
+    log::info!(
+        "Rate limits: token {}, company {}, IP {}; expensive routes: token {}, company {}; webhooks: IP {}",
+        per_minute(limits.token),
+        per_minute(limits.company),
+        per_minute(limits.ip),
+        per_minute(limits.expensive_token),
+        per_minute(limits.expensive_company),
+        per_minute(limits.webhook_ip)
+    );
+    for (listener, files) in [("API", config.api_tls()), ("Webhook", config.webhook_tls())] {
+        match files {
+            Some(files) => {
+                let certificate = tls::Certificate::load(files.clone())?;
+                log::info!(
+                    "{} TLS: {} ({} certificate(s) in chain)",
+                    listener,
+                    files.cert.display(),
+                    certificate.chain().len()
+                );
+            }
+            None => log::info!("{} TLS: off", listener),
+        }
+    }
+    if !api_keys.is_configured() {
+        log::warn!("No API key master key configured");
+    }
+    log::info!("Configuration is valid");
+    Ok(())
+}
+
+fn display_or_default(value: Option<impl std::fmt::Display>) -> String {
+    value
+        .map(|value| value.to_string())
+        .unwrap_or_else(|| "default".to_string())
+}
+
+/// `wazzup migrate`: applies pending migrations, or lists them with `--status`.
+async fn migrate(db: &sea_orm::DatabaseConnection, status: bool) -> std::io::Result<()> {
+    let to_io = |err: sea_orm::DbErr| std::io::Error::other(err.to_string());
+
+    if status {
+        for record in database::migrations::applied(db).await.map_err(to_io)? {
+            log::info!(
+                "Applied {:04}_{} at {}",
+                record.version,
+                record.name,
+                record.applied_at.to_rfc3339()
+            );
+        }
+        for migration in database::migrations::pending(db).await.map_err(to_io)? {
+            log::info!("Pending {:04}_{}", migration.version, migration.name);
+        }
+        return Ok(());
+    }
+
+    let applied = database::migrations::run(db).await.map_err(to_io)?;
+    log::info!("Applied {} migration(s)", applied.len());
+    Ok(())
+}
+
+/// `wazzup encrypt-keys`: seals plain API keys and re-wraps keys of older
+/// master key versions.
+async fn encrypt_keys(
+    db: &sea_orm::DatabaseConnection,
+    api_keys: &ApiKeyVault,
+    dry_run: bool,
+) -> std::io::Result<()> {
+    let report = api_keys
+        .seal_stored_keys(db, dry_run)
+        .await
+        .map_err(|err| std::io::Error::other(err.to_string()))?;
+
+    log::info!(
+        "{}{} key(s) encrypted, {} re-wrapped with the current master key, {} unchanged",
+        if dry_run { "Dry run: " } else { "" },
+        report.sealed,
+        report.rewrapped,
+        report.unchanged
+    );
     Ok(())
 }
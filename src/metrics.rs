@@ -0,0 +1,233 @@
+//! Prometheus metrics, served as text on `/metrics` of the API listener.
+//!
+//! Label values are kept bounded: routes are the matched route patterns, not
+//! raw paths; Wazzup endpoints have ids and transports replaced by `{param}`;
+//! webhook counters only carry ids of companies that exist. Gauges for the
+//! database pool and background queues are refreshed on every scrape.
+
+use std::time::Duration;
+
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGaugeVec, Opts, Registry, TextEncoder,
+    core::Collector,
+};
+use sea_orm::{
+    ColumnTrait, ConnectionTrait, DatabaseBackend, DatabaseConnection, EntityTrait, PaginatorTrait,
+    QueryFilter,
+};
+use uuid::Uuid;
+
+use crate::{
+    database::models::{jobs, outgoing_webhook_deliveries},
+    services::{jobs as jobs_queue, outgoing_webhooks::STATUS_PENDING},
+};
+
+/// Route label of requests that matched no route (404s, probes for random paths).
+pub const UNMATCHED_ROUTE: &str = "unmatched";
+
+/// Path segments of Wazzup endpoints kept as is; anything else is an id.
+const WAZZUP_PATH_SEGMENTS: &[&str] = &[
+    "v3",
+    "iframe",
+    "generate-channels-link",
+    "channels",
+    "list",
+    "reinit",
+    "settings",
+    "contacts",
+    "message",
+    "messages",
+    "unanswered",
+    "placeholder",
+    "webhooks",
+];
+
+const HTTP_METHODS: &[&str] = &["GET", "POST", "PUT", "PATCH", "DELETE", "HEAD", "OPTIONS"];
+
+const LATENCY_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0,
+];
+
+lazy_static::lazy_static! {
+    static ref REGISTRY: Registry = Registry::new();
+
+    static ref HTTP_REQUESTS: IntCounterVec = register(IntCounterVec::new(
+        Opts::new("http_requests_total", "HTTP requests served, by route and status"),
+        &["method", "route", "status"],
+    ));
+    static ref HTTP_DURATION: HistogramVec = register(HistogramVec::new(
+        HistogramOpts::new("http_request_duration_seconds", "HTTP request latency")
+            .buckets(LATENCY_BUCKETS.to_vec()),
+        &["method", "route"],
+    ));
+
+    static ref WAZZUP_REQUESTS: IntCounterVec = register(IntCounterVec::new(
+        Opts::new(
+            "wazzup_api_requests_total",
+            "Calls to the Wazzup API; outcome is 2xx, 4xx, 5xx or error (no response)",
+        ),
+        &["method", "endpoint", "outcome"],
+    ));
+    static ref WAZZUP_DURATION: HistogramVec = register(HistogramVec::new(
+        HistogramOpts::new("wazzup_api_request_duration_seconds", "Wazzup API call latency")
+            .buckets(LATENCY_BUCKETS.to_vec()),
+        &["method", "endpoint"],
+    ));
+
+    static ref WEBHOOK_MESSAGES: IntCounterVec = register(IntCounterVec::new(
+        Opts::new(
+            "webhook_messages_total",
+            "Messages received from Wazzup webhooks, by company and outcome",
+        ),
+        &["company_id", "outcome"],
+    ));
+
+    static ref DB_POOL: IntGaugeVec = register(IntGaugeVec::new(
+        Opts::new(
+            "db_pool_connections",
+            "Database pool connections; state is idle, in_use or max",
+        ),
+        &["state"],
+    ));
+    static ref QUEUE_DEPTH: IntGaugeVec = register(IntGaugeVec::new(
+        Opts::new("background_queue_depth", "Jobs waiting in background queues"),
+        &["queue"],
+    ));
+}
+
+fn register<C: Collector + Clone + 'static>(collector: prometheus::Result<C>) -> C {
+    let collector = collector.expect("invalid metric definition");
+    REGISTRY
+        .register(Box::new(collector.clone()))
+        .expect("metric registered twice");
+    collector
+}
+
+/// Records a served HTTP request; `route` is the matched pattern, e.g.
+/// `/api/chats/{companyId}/previews`.
+pub fn observe_http(method: &str, route: &str, status: u16, elapsed: Duration) {
+    let method = method_label(method);
+    HTTP_REQUESTS
+        .with_label_values(&[method, route, &status.to_string()])
+        .inc();
+    HTTP_DURATION
+        .with_label_values(&[method, route])
+        .observe(elapsed.as_secs_f64());
+}
+
+/// Records a Wazzup API call to `path` (ids and query are stripped here);
+/// `status` is `None` when no response arrived.
+pub fn observe_wazzup(method: &str, path: &str, status: Option<u16>, elapsed: Duration) {
+    let method = method_label(method);
+    let endpoint = wazzup_endpoint(path);
+    let outcome = match status {
+        Some(code) if code < 300 => "2xx",
+        Some(code) if code < 500 => "4xx",
+        Some(_) => "5xx",
+        None => "error",
+    };
+    WAZZUP_REQUESTS
+        .with_label_values(&[method, &endpoint, outcome])
+        .inc();
+    WAZZUP_DURATION
+        .with_label_values(&[method, &endpoint])
+        .observe(elapsed.as_secs_f64());
+}
+
+/// Counts a webhook message of an existing company.
+pub fn record_webhook_message(company: &Uuid, processed: bool) {
+    let outcome = if processed { "processed" } else { "failed" };
+    WEBHOOK_MESSAGES
+        .with_label_values(&[&company.to_string(), outcome])
+        .inc();
+}
+
+/// Refreshes the gauges and renders every metric in the text format.
+pub async fn render(db: &DatabaseConnection) -> String {
+    update_pool_gauges(db);
+    match outgoing_webhook_deliveries::Entity::find()
+        .filter(outgoing_webhook_deliveries::Column::Status.eq(STATUS_PENDING))
+        .count(db)
+        .await
+    {
+        Ok(pending) => QUEUE_DEPTH
+            .with_label_values(&["outgoing_webhooks"])
+            .set(pending as i64),
+        Err(err) => log::warn!("Unable to count pending webhook deliveries: {}", err),
+    }
+    match jobs::Entity::find()
+        .filter(jobs::Column::Status.eq(jobs_queue::STATUS_PENDING))
+        .count(db)
+        .await
+    {
+        Ok(pending) => QUEUE_DEPTH.with_label_values(&["jobs"]).set(pending as i64),
+        Err(err) => log::warn!("Unable to count pending jobs: {}", err),
+    }
+
+    let mut buffer = Vec::new();
+    if let Err(err) = TextEncoder::new().encode(&REGISTRY.gather(), &mut buffer) {
+        log::error!("Failed to encode metrics: {}", err);
+    }
+    String::from_utf8(buffer).unwrap_or_default()
+}
+
+fn update_pool_gauges(db: &DatabaseConnection) {
+    let (size, idle, max) = match db.get_database_backend() {
+        DatabaseBackend::MySql => {
+            let pool = db.get_mysql_connection_pool();
+            (
+                pool.size(),
+                pool.num_idle(),
+                pool.options().get_max_connections(),
+            )
+        }
+        DatabaseBackend::Postgres => {
+            let pool = db.get_postgres_connection_pool();
+            (
+                pool.size(),
+                pool.num_idle(),
+                pool.options().get_max_connections(),
+            )
+        }
+        #[cfg(feature = "sqlite")]
+        DatabaseBackend::Sqlite => {
+            let pool = db.get_sqlite_connection_pool();
+            (
+                pool.size(),
+                pool.num_idle(),
+                pool.options().get_max_connections(),
+            )
+        }
+        #[allow(unreachable_patterns)]
+        _ => return,
+    };
+
+    DB_POOL.with_label_values(&["idle"]).set(idle as i64);
+    DB_POOL
+        .with_label_values(&["in_use"])
+        .set(size.saturating_sub(idle as u32) as i64);
+    DB_POOL.with_label_values(&["max"]).set(max as i64);
+}
+
+fn method_label(method: &str) -> &'static str {
+    HTTP_METHODS
+        .iter()
+        .find(|known| known.eq_ignore_ascii_case(method))
+        .copied()
+        .unwrap_or("OTHER")
+}
+
+/// `/v3/contacts/42?x=1` -> `/v3/contacts/{param}`.
+pub fn wazzup_endpoint(path: &str) -> String {
+    let path = path.split('?').next().unwrap_or_default();
+    path.split('/')
+        .map(|segment| {
+            if segment.is_empty() || WAZZUP_PATH_SEGMENTS.contains(&segment) {
+                segment
+            } else {
+                "{param}"
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
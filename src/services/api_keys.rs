@@ -0,0 +1,404 @@
+//! Envelope encryption of Wazzup API keys stored in `companies.wazzup_api_key`.
+//!
+//! Every key is encrypted with its own random data key (AES-256-GCM); the data
+//! key is in turn encrypted ("wrapped") with a versioned master key from the
+//! configuration. The company id is bound to both ciphertexts as associated
+//! data, so a sealed value cannot be copied to another company.
+//!
+//! Stored format: `wzk:v<version>:<wrapped data key>:<encrypted API key>`,
+//! both parts base64 with the 12-byte nonce in front. Rows that do not start
+//! with `wzk:` are plain keys from before encryption; they keep working until
+//! `wazzup encrypt-keys` seals them.
+//!
+//! This is the only module that decrypts API keys. Plain keys must never be
+//! logged; log the company id instead.
+//!
+//! The vault also holds the key that signs stream tickets (see
+//! [`crate::services::tokens`]). It is derived from the current master key,
+//! so every instance accepts the tickets of the others; without master keys
+//! it is random and tickets only work on the process that issued them.
+
+use std::{collections::HashMap, fmt, fs, sync::Arc};
+
+use aes_gcm::{
+    Aes256Gcm, Key, Nonce,
+    aead::{Aead, AeadCore, KeyInit, OsRng, Payload},
+};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use hmac::{Hmac, Mac};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, EntityTrait, IntoActiveModel, QueryFilter, Set,
+};
+use sha2::Sha256;
+use uuid::Uuid;
+
+use crate::{config::Config, database::models::companies, errors::AppError};
+
+const SEALED_PREFIX: &str = "wzk:";
+const NONCE_LEN: usize = 12;
+const MASTER_KEY_LEN: usize = 32;
+/// Context of the stream ticket key derived from a master key.
+const TICKET_KEY_CONTEXT: &[u8] = b"wazzup stream tickets";
+
+/// Master keys by version; new values are sealed with `current`.
+#[derive(Clone)]
+pub struct ApiKeyVault {
+    inner: Arc<KeyRing>,
+}
+
+struct KeyRing {
+    current: Option<u32>,
+    keys: HashMap<u32, Key<Aes256Gcm>>,
+    ticket_key: Vec<u8>,
+}
+
+impl fmt::Debug for ApiKeyVault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut versions: Vec<_> = self.inner.keys.keys().collect();
+        versions.sort();
+        f.debug_struct("ApiKeyVault")
+            .field("current", &self.inner.current)
+            .field("versions", &versions)
+            .finish()
+    }
+}
+
+/// Outcome of [`ApiKeyVault::seal_stored_keys`].
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SealReport {
+    /// Plain keys that were encrypted.
+    pub sealed: u64,
+    /// Keys re-wrapped from an older master key version.
+    pub rewrapped: u64,
+    /// Keys already sealed with the current master key.
+    pub unchanged: u64,
+}
+
+impl ApiKeyVault {
+    /// Loads master keys from `API_KEY_MASTER_KEYS` and `API_KEY_MASTER_KEY_FILE`.
+    ///
+    /// Both hold `version:base64-key` entries (comma- or line-separated) with
+    /// 32-byte keys. `API_KEY_MASTER_KEY_VERSION` selects the key new values
+    /// are sealed with; it defaults to the highest version.
+    pub fn from_config(config: &Config) -> Result<Self, config::ConfigError> {
+        let mut keys = HashMap::new();
+
+        if let Some(raw) = &config.api_key_master_keys {
+            parse_master_keys(raw.expose(), &mut keys)?;
+        }
+        if let Some(path) = &config.api_key_master_key_file {
+            let contents = fs::read_to_string(path).map_err(|err| {
+                config::ConfigError::Message(format!(
+                    "Unable to read master key file {}: {}",
+                    path, err
+                ))
+            })?;
+            parse_master_keys(&contents, &mut keys)?;
+        }
+
+        let current = match config.api_key_master_key_version {
+            Some(version) if !keys.contains_key(&version) => {
+                return Err(config::ConfigError::Message(format!(
+                    "Master key version {} is not configured",
+                    version
+                )));
+            }
+            Some(version) => Some(version),
+            None => keys.keys().max().copied(),
+        };
+
+        let ticket_key = match current {
+            Some(version) => <Hmac<Sha256> as Mac>::new_from_slice(&keys[&version])
+                .expect("HMAC accepts keys of any length")
+                .chain_update(TICKET_KEY_CONTEXT)
+                .finalize()
+                .into_bytes()
+                .to_vec(),
+            None => Aes256Gcm::generate_key(&mut OsRng).to_vec(),
+        };
+
+        Ok(Self {
+            inner: Arc::new(KeyRing {
+                current,
+                keys,
+                ticket_key,
+            }),
+        })
+    }
+
+    pub fn is_configured(&self) -> bool {
+        self.inner.current.is_some()
+    }
+
+    /// Server-side secret that stream tickets are signed with.
+    pub fn ticket_key(&self) -> &[u8] {
+        &self.inner.ticket_key
+    }
+
+    /// Encrypts `api_key` of the company for storage.
+    pub fn seal(&self, company_id: &[u8], api_key: &str) -> Result<String, AppError> {
+        let (version, master_key) = self.current_key()?;
+
+        let data_key = Aes256Gcm::generate_key(&mut OsRng);
+        let payload = encrypt(&data_key, api_key.as_bytes(), company_id)?;
+        let wrapped = encrypt(master_key, &data_key, company_id)?;
+
+        Ok(format!(
+            "{}v{}:{}:{}",
+            SEALED_PREFIX,
+            version,
+            STANDARD.encode(wrapped),
+            STANDARD.encode(payload)
+        ))
+    }
+
+    /// API key of the company, decrypted.
+    pub async fn company_api_key<C>(&self, db: &C, company_uuid: &Uuid) -> Result<String, AppError>
+    where
+        C: ConnectionTrait,
+    {
+        let company = companies::Entity::find_by_id(company_uuid.as_bytes().to_vec())
+            .one(db)
+            .await?
+            .ok_or_else(|| {
+                log::warn!("Company not found for UUID: {}", company_uuid);
+                AppError::NotFound("Company not found".to_string())
+            })?;
+
+        self.api_key_of(&company)?
+            .ok_or_else(|| AppError::InvalidInput("Company API key not configured".to_string()))
+    }
+
+    /// Decrypted API key of an already loaded company, if it has one.
+    pub fn api_key_of(&self, company: &companies::Model) -> Result<Option<String>, AppError> {
+        match company
+            .wazzup_api_key
+            .as_deref()
+            .map(str::trim)
+            .filter(|stored| !stored.is_empty())
+        {
+            Some(stored) => self.open(&company.id, stored).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Seals plain keys and re-wraps keys sealed with an older master key.
+    ///
+    /// Only the data key is re-wrapped on rotation; the encrypted API key
+    /// itself is left as is. With `dry_run` nothing is written.
+    pub async fn seal_stored_keys<C>(&self, db: &C, dry_run: bool) -> Result<SealReport, AppError>
+    where
+        C: ConnectionTrait,
+    {
+        let (current, _) = self.current_key()?;
+        let mut report = SealReport::default();
+
+        let stored = companies::Entity::find()
+            .filter(companies::Column::WazzupApiKey.is_not_null())
+            .all(db)
+            .await?;
+
+        for company in stored {
+            let Some(value) = company
+                .wazzup_api_key
+                .as_deref()
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+            else {
+                continue;
+            };
+
+            let resealed = match parse_sealed(value) {
+                None => {
+                    report.sealed += 1;
+                    self.seal(&company.id, value)?
+                }
+                Some(sealed) if sealed.version == current => {
+                    report.unchanged += 1;
+                    continue;
+                }
+                Some(sealed) => {
+                    report.rewrapped += 1;
+                    self.rewrap(&company.id, &sealed)?
+                }
+            };
+
+            log::info!(
+                "Sealing API key of company {}",
+                Uuid::from_slice(&company.id).unwrap_or_default()
+            );
+            if !dry_run {
+                let mut active = company.into_active_model();
+                active.wazzup_api_key = Set(Some(resealed));
+                active.update(db).await?;
+            }
+        }
+
+        Ok(report)
+    }
+
+    fn open(&self, company_id: &[u8], stored: &str) -> Result<String, AppError> {
+        let Some(sealed) = parse_sealed(stored) else {
+            // Plain key from before encryption was introduced.
+            return Ok(stored.to_string());
+        };
+        let company = Uuid::from_slice(company_id).unwrap_or_default();
+
+        let data_key = self.unwrap_data_key(company_id, &sealed)?;
+        let payload = decode(&sealed.payload, company)?;
+        let plain = decrypt(&data_key, &payload, company_id).inspect_err(|_| {
+            log::error!("Unable to decrypt the API key of company {}", company);
+        })?;
+
+        String::from_utf8(plain).map_err(|_| {
+            log::error!("Decrypted API key of company {} is not UTF-8", company);
+            AppError::Internal
+        })
+    }
+
+    fn rewrap(&self, company_id: &[u8], sealed: &Sealed) -> Result<String, AppError> {
+        let (version, master_key) = self.current_key()?;
+        let data_key = self.unwrap_data_key(company_id, sealed)?;
+        let wrapped = encrypt(master_key, &data_key, company_id)?;
+
+        Ok(format!(
+            "{}v{}:{}:{}",
+            SEALED_PREFIX,
+            version,
+            STANDARD.encode(wrapped),
+            sealed.payload
+        ))
+    }
+
+    fn unwrap_data_key(
+        &self,
+        company_id: &[u8],
+        sealed: &Sealed,
+    ) -> Result<Key<Aes256Gcm>, AppError> {
+        let company = Uuid::from_slice(company_id).unwrap_or_default();
+        let master_key = self.inner.keys.get(&sealed.version).ok_or_else(|| {
+            log::error!(
+                "API key of company {} is sealed with unknown master key version {}",
+                company,
+                sealed.version
+            );
+            AppError::Internal
+        })?;
+
+        let wrapped = decode(&sealed.wrapped_key, company)?;
+        let data_key = decrypt(master_key, &wrapped, company_id).inspect_err(|_| {
+            log::error!(
+                "Unable to unwrap the data key of company {} with master key version {}",
+                company,
+                sealed.version
+            );
+        })?;
+        if data_key.len() != MASTER_KEY_LEN {
+            return Err(AppError::Internal);
+        }
+
+        Ok(*Key::<Aes256Gcm>::from_slice(&data_key))
+    }
+
+    fn current_key(&self) -> Result<(u32, &Key<Aes256Gcm>), AppError> {
+        self.inner
+            .current
+            .and_then(|version| self.inner.keys.get(&version).map(|key| (version, key)))
+            .ok_or_else(|| {
+                log::error!("No master key configured for API key encryption");
+                AppError::Internal
+            })
+    }
+}
+
+struct Sealed {
+    version: u32,
+    wrapped_key: String,
+    payload: String,
+}
+
+fn parse_sealed(stored: &str) -> Option<Sealed> {
+    let mut parts = stored.strip_prefix(SEALED_PREFIX)?.split(':');
+    let version = parts.next()?.strip_prefix('v')?.parse().ok()?;
+    let wrapped_key = parts.next()?.to_string();
+    let payload = parts.next()?.to_string();
+    if parts.next().is_some() {
+        return None;
+    }
+
+    Some(Sealed {
+        version,
+        wrapped_key,
+        payload,
+    })
+}
+
+fn parse_master_keys(
+    raw: &str,
+    keys: &mut HashMap<u32, Key<Aes256Gcm>>,
+) -> Result<(), config::ConfigError> {
+    let entries = raw
+        .split([',', '\n'])
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty() && !entry.starts_with('#'));
+
+    for entry in entries {
+        let invalid = || {
+            config::ConfigError::Message(
+                "Master keys must be `version:base64-key` entries with 32-byte keys".to_string(),
+            )
+        };
+        let (version, encoded) = entry.split_once(':').ok_or_else(invalid)?;
+        let version: u32 = version.trim().parse().map_err(|_| invalid())?;
+        let bytes = STANDARD.decode(encoded.trim()).map_err(|_| invalid())?;
+        if bytes.len() != MASTER_KEY_LEN {
+            return Err(invalid());
+        }
+        if keys
+            .insert(version, *Key::<Aes256Gcm>::from_slice(&bytes))
+            .is_some()
+        {
+            return Err(config::ConfigError::Message(format!(
+                "Master key version {} is configured twice",
+                version
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+fn encrypt(key: &Key<Aes256Gcm>, plain: &[u8], aad: &[u8]) -> Result<Vec<u8>, AppError> {
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = Aes256Gcm::new(key)
+        .encrypt(&nonce, Payload { msg: plain, aad })
+        .map_err(|_| AppError::Internal)?;
+
+    let mut sealed = nonce.to_vec();
+    sealed.extend(ciphertext);
+    Ok(sealed)
+}
+
+fn decrypt(key: &Key<Aes256Gcm>, sealed: &[u8], aad: &[u8]) -> Result<Vec<u8>, AppError> {
+    if sealed.len() < NONCE_LEN {
+        return Err(AppError::Internal);
+    }
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+
+    Aes256Gcm::new(key)
+        .decrypt(
+            Nonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad,
+            },
+        )
+        .map_err(|_| AppError::Internal)
+}
+
+fn decode(encoded: &str, company: Uuid) -> Result<Vec<u8>, AppError> {
+    STANDARD.decode(encoded).map_err(|_| {
+        log::error!("Sealed API key of company {} is malformed", company);
+        AppError::Internal
+    })
+}
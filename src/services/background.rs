@@ -0,0 +1,403 @@
+//! Supervised background tasks.
+//!
+//! Work that outlives a request goes through [`TaskManager`] instead of a bare
+//! `spawn`. Tasks run on the runtime that created the manager (the main thread
+//! in production), so they survive the HTTP worker that started them. Every
+//! task has a name and a status that the admin API reports.
+//!
+//! - one-off tasks ([`TaskManager::spawn`]) run once and are drained on
+//!   shutdown;
+//! - services ([`TaskManager::spawn_service`]) run until shutdown and are
+//!   restarted with a backoff when they fail or panic;
+//! - periodic tasks ([`TaskManager::spawn_periodic`]) are services that run a
+//!   job on a fixed interval.
+//!
+//! [`TaskManager::shutdown`] asks services to stop, waits for all tasks up to a
+//! timeout and aborts whatever is still running.
+
+use std::{
+    any::Any,
+    collections::{BTreeMap, VecDeque},
+    future::Future,
+    panic::AssertUnwindSafe,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Utc};
+use futures_util::FutureExt;
+use serde::Serialize;
+use tokio::{runtime::Handle, sync::watch, task::JoinHandle};
+use utoipa::ToSchema;
+
+use crate::errors::AppError;
+
+/// Finished tasks kept for the status report.
+const FINISHED_HISTORY: usize = 100;
+const MIN_RESTART_DELAY: Duration = Duration::from_secs(1);
+const MAX_RESTART_DELAY: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum TaskKind {
+    OneOff,
+    Service,
+    Periodic,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum TaskState {
+    Running,
+    /// Waiting for the restart delay after a failure.
+    Restarting,
+    Succeeded,
+    Failed,
+    /// Service stopped at shutdown.
+    Stopped,
+    /// Still running when the shutdown timeout expired.
+    Aborted,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskStatus {
+    pub id: u64,
+    pub name: String,
+    pub kind: TaskKind,
+    pub state: TaskState,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    /// Times a service was restarted after failing.
+    pub restarts: u32,
+    /// Error or panic message of the last failure.
+    pub last_error: Option<String>,
+}
+
+/// Resolves once shutdown is requested; handed to services.
+#[derive(Clone)]
+pub struct Shutdown(watch::Receiver<bool>);
+
+impl Shutdown {
+    pub async fn requested(&mut self) {
+        // A dropped manager counts as a shutdown too.
+        let _ = self.0.wait_for(|stop| *stop).await;
+    }
+
+    pub fn is_requested(&self) -> bool {
+        *self.0.borrow()
+    }
+}
+
+#[derive(Clone)]
+pub struct TaskManager {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    runtime: Handle,
+    shutdown: watch::Sender<bool>,
+    next_id: AtomicU64,
+    tasks: Mutex<Tasks>,
+}
+
+#[derive(Default)]
+struct Tasks {
+    active: BTreeMap<u64, ActiveTask>,
+    finished: VecDeque<TaskStatus>,
+}
+
+struct ActiveTask {
+    status: TaskStatus,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl TaskManager {
+    /// Tasks will run on the current Tokio runtime.
+    ///
+    /// # Panics
+    ///
+    /// Outside of a Tokio runtime.
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                runtime: Handle::current(),
+                shutdown: watch::Sender::new(false),
+                next_id: AtomicU64::new(1),
+                tasks: Mutex::new(Tasks::default()),
+            }),
+        }
+    }
+
+    /// Runs `task` once. An error or panic is logged and reported as
+    /// [`TaskState::Failed`].
+    pub fn spawn<F>(&self, name: impl Into<String>, task: F)
+    where
+        F: Future<Output = Result<(), AppError>> + Send + 'static,
+    {
+        let name = name.into();
+        let Some(id) = self.register(&name, TaskKind::OneOff) else {
+            return;
+        };
+
+        let manager = self.clone();
+        let handle = self.inner.runtime.spawn(async move {
+            match run_guarded(task).await {
+                Ok(()) => manager.finish(id, TaskState::Succeeded, None),
+                Err(message) => {
+                    log::error!("Background task {} failed: {}", name, message);
+                    manager.finish(id, TaskState::Failed, Some(message));
+                }
+            }
+        });
+        self.attach(id, handle);
+    }
+
+    /// Runs `service` until shutdown. When it fails, panics or returns early
+    /// it is started again after a delay that doubles with every consecutive
+    /// failure, up to a minute. The service should return once
+    /// [`Shutdown::requested`] resolves.
+    pub fn spawn_service<F, Fut>(&self, name: impl Into<String>, service: F)
+    where
+        F: Fn(Shutdown) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), AppError>> + Send + 'static,
+    {
+        self.supervise(name.into(), TaskKind::Service, service);
+    }
+
+    /// Runs `job` every `period`, the first time right away. A failed run is
+    /// logged and the next one happens on schedule; a panic restarts the task.
+    pub fn spawn_periodic<F, Fut>(&self, name: impl Into<String>, period: Duration, job: F)
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), AppError>> + Send + 'static,
+    {
+        let name = name.into();
+        let job = Arc::new(job);
+        let label = name.clone();
+        self.supervise(name, TaskKind::Periodic, move |mut shutdown| {
+            let job = job.clone();
+            let label = label.clone();
+            async move {
+                let mut ticker = tokio::time::interval(period);
+                loop {
+                    tokio::select! {
+                        _ = ticker.tick() => {
+                            if let Err(err) = job().await {
+                                log::error!("Periodic task {} failed: {}", label, err);
+                            }
+                        }
+                        _ = shutdown.requested() => return Ok(()),
+                    }
+                }
+            }
+        });
+    }
+
+    /// Active tasks first, then recently finished ones, newest first.
+    pub fn statuses(&self) -> Vec<TaskStatus> {
+        let tasks = self.inner.tasks.lock().unwrap();
+        tasks
+            .active
+            .values()
+            .map(|task| task.status.clone())
+            .chain(tasks.finished.iter().rev().cloned())
+            .collect()
+    }
+
+    pub fn is_shutting_down(&self) -> bool {
+        *self.inner.shutdown.borrow()
+    }
+
+    /// Stops services, waits up to `timeout` for every task and aborts the
+    /// ones still running. New tasks are refused from now on.
+    pub async fn shutdown(&self, timeout: Duration) {
+        self.inner.shutdown.send_replace(true);
+
+        let handles: Vec<(u64, String, JoinHandle<()>)> = {
+            let mut tasks = self.inner.tasks.lock().unwrap();
+            tasks
+                .active
+                .iter_mut()
+                .filter_map(|(id, task)| {
+                    let handle = task.handle.take()?;
+                    Some((*id, task.status.name.clone(), handle))
+                })
+                .collect()
+        };
+        if handles.is_empty() {
+            return;
+        }
+        log::info!(
+            "Waiting up to {}s for {} background task(s)",
+            timeout.as_secs(),
+            handles.len()
+        );
+
+        let deadline = tokio::time::Instant::now() + timeout;
+        for (id, name, mut handle) in handles {
+            if tokio::time::timeout_at(deadline, &mut handle)
+                .await
+                .is_err()
+            {
+                handle.abort();
+                log::warn!("Background task {} aborted at shutdown", name);
+                self.finish(
+                    id,
+                    TaskState::Aborted,
+                    Some("Still running when the shutdown timeout expired".to_string()),
+                );
+            }
+        }
+    }
+
+    fn supervise<F, Fut>(&self, name: String, kind: TaskKind, service: F)
+    where
+        F: Fn(Shutdown) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), AppError>> + Send + 'static,
+    {
+        let Some(id) = self.register(&name, kind) else {
+            return;
+        };
+
+        let manager = self.clone();
+        let shutdown = Shutdown(self.inner.shutdown.subscribe());
+        let handle = self.inner.runtime.spawn(async move {
+            let mut failures = 0u32;
+            loop {
+                let started = Instant::now();
+                let outcome = run_guarded(service(shutdown.clone())).await;
+                if shutdown.is_requested() {
+                    manager.finish(id, TaskState::Stopped, outcome.err());
+                    return;
+                }
+                let message = match outcome {
+                    Ok(()) => "Exited before shutdown".to_string(),
+                    Err(message) => message,
+                };
+
+                // A service that ran for a while before failing starts over
+                // with the shortest delay.
+                if started.elapsed() > MAX_RESTART_DELAY {
+                    failures = 0;
+                }
+                let delay = restart_delay(failures);
+                failures += 1;
+                log::error!(
+                    "Background task {} failed: {}; restarting in {}s",
+                    name,
+                    message,
+                    delay.as_secs()
+                );
+                manager.update(id, |status| {
+                    status.state = TaskState::Restarting;
+                    status.restarts += 1;
+                    status.last_error = Some(message);
+                });
+
+                let mut stop = shutdown.clone();
+                tokio::select! {
+                    _ = tokio::time::sleep(delay) => {}
+                    _ = stop.requested() => {
+                        manager.finish(id, TaskState::Stopped, None);
+                        return;
+                    }
+                }
+                manager.update(id, |status| status.state = TaskState::Running);
+            }
+        });
+        self.attach(id, handle);
+    }
+
+    fn register(&self, name: &str, kind: TaskKind) -> Option<u64> {
+        if self.is_shutting_down() {
+            log::warn!("Not starting background task {} during shutdown", name);
+            return None;
+        }
+        let id = self.inner.next_id.fetch_add(1, Ordering::Relaxed);
+        let status = TaskStatus {
+            id,
+            name: name.to_string(),
+            kind,
+            state: TaskState::Running,
+            started_at: Utc::now(),
+            finished_at: None,
+            restarts: 0,
+            last_error: None,
+        };
+        self.inner.tasks.lock().unwrap().active.insert(
+            id,
+            ActiveTask {
+                status,
+                handle: None,
+            },
+        );
+        Some(id)
+    }
+
+    /// Keeps the handle for [`TaskManager::shutdown`] unless the task is
+    /// already done.
+    fn attach(&self, id: u64, handle: JoinHandle<()>) {
+        if let Some(task) = self.inner.tasks.lock().unwrap().active.get_mut(&id) {
+            task.handle = Some(handle);
+        }
+    }
+
+    fn update(&self, id: u64, change: impl FnOnce(&mut TaskStatus)) {
+        if let Some(task) = self.inner.tasks.lock().unwrap().active.get_mut(&id) {
+            change(&mut task.status);
+        }
+    }
+
+    fn finish(&self, id: u64, state: TaskState, error: Option<String>) {
+        let mut tasks = self.inner.tasks.lock().unwrap();
+        let Some(task) = tasks.active.remove(&id) else {
+            return;
+        };
+        let mut status = task.status;
+        status.state = state;
+        status.finished_at = Some(Utc::now());
+        if error.is_some() {
+            status.last_error = error;
+        }
+        if tasks.finished.len() == FINISHED_HISTORY {
+            tasks.finished.pop_front();
+        }
+        tasks.finished.push_back(status);
+    }
+}
+
+impl Default for TaskManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The task's error or panic message.
+pub(crate) async fn run_guarded<F>(task: F) -> Result<(), String>
+where
+    F: Future<Output = Result<(), AppError>>,
+{
+    match AssertUnwindSafe(task).catch_unwind().await {
+        Ok(Ok(())) => Ok(()),
+        Ok(Err(err)) => Err(err.to_string()),
+        Err(panic) => Err(format!("panicked: {}", panic_message(panic.as_ref()))),
+    }
+}
+
+fn panic_message(panic: &(dyn Any + Send)) -> &str {
+    panic
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| panic.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown panic")
+}
+
+fn restart_delay(failures: u32) -> Duration {
+    MIN_RESTART_DELAY
+        .saturating_mul(2u32.saturating_pow(failures))
+        .min(MAX_RESTART_DELAY)
+}
@@ -1,5 +1,6 @@
 use crate::database::models::users;
 use crate::errors::AppError;
+use crate::telemetry;
 use reqwest::Client;
 use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
 use serde::{Deserialize, Serialize};
@@ -36,13 +37,24 @@ impl BotService {
         hook_url: &str,
         request: &BotHookRequest,
     ) -> Result<BotHookResponse, AppError> {
-        let response = self
+        let mut http_request = self
             .client
             .post(hook_url)
             .json(request)
             .timeout(std::time::Duration::from_secs(30))
-            .send()
-            .await
+            .build()
+            .map_err(|e| AppError::ExternalApiError(format!("Bot hook request failed: {}", e)))?;
+        let span =
+            telemetry::ClientSpan::start("bot hook POST".to_string(), "POST", http_request.url());
+        span.propagate(http_request.headers_mut());
+        let response = self.client.execute(http_request).await;
+        span.end(
+            response
+                .as_ref()
+                .ok()
+                .map(|response| response.status().as_u16()),
+        );
+        let response = response
             .map_err(|e| AppError::ExternalApiError(format!("Bot hook request failed: {}", e)))?;
 
         if !response.status().is_success() {
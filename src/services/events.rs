@@ -0,0 +1,234 @@
+//! In-process event bus for real-time updates.
+//!
+//! Events are published by the webhook processor and the chat and contact
+//! handlers and fanned out to every subscriber of the process (both HTTP
+//! listeners share a single bus). A bounded history allows clients to resume
+//! after a reconnect. Publishers go through
+//! [`crate::services::outgoing_webhooks::publish`], which records the
+//! outgoing webhook deliveries of the event before it reaches the bus.
+//!
+//! The bus and its history live in memory, so streaming assumes a single
+//! instance: events published by another instance never reach the
+//! subscribers of this one, and a restart empties the history. Resuming only
+//! works with an id this process handed out and still remembers; for any
+//! other `Last-Event-ID` (one from before a restart or from another instance)
+//! [`EventBus::replay_since`] asks for a reset and the client has to refetch
+//! its state. Outgoing webhooks do not depend on the bus, their deliveries are
+//! stored in the database.
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::{Value, json};
+use tokio::sync::broadcast;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::database::models::clients;
+
+const CHANNEL_CAPACITY: usize = 1024;
+const HISTORY_CAPACITY: usize = 2048;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+pub enum EventKind {
+    #[serde(rename = "message.created")]
+    MessageCreated,
+    #[serde(rename = "message.status")]
+    MessageStatusChanged,
+    #[serde(rename = "chat.assigned")]
+    ChatAssigned,
+    #[serde(rename = "chat.read")]
+    ChatRead,
+    #[serde(rename = "channel.state")]
+    ChannelStateChanged,
+    #[serde(rename = "client.created")]
+    ClientCreated,
+    #[serde(rename = "client.updated")]
+    ClientUpdated,
+    #[serde(rename = "client.deleted")]
+    ClientDeleted,
+}
+
+impl EventKind {
+    pub const ALL: [EventKind; 8] = [
+        EventKind::MessageCreated,
+        EventKind::MessageStatusChanged,
+        EventKind::ChatAssigned,
+        EventKind::ChatRead,
+        EventKind::ChannelStateChanged,
+        EventKind::ClientCreated,
+        EventKind::ClientUpdated,
+        EventKind::ClientDeleted,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EventKind::MessageCreated => "message.created",
+            EventKind::MessageStatusChanged => "message.status",
+            EventKind::ChatAssigned => "chat.assigned",
+            EventKind::ChatRead => "chat.read",
+            EventKind::ChannelStateChanged => "channel.state",
+            EventKind::ClientCreated => "client.created",
+            EventKind::ClientUpdated => "client.updated",
+            EventKind::ClientDeleted => "client.deleted",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|kind| kind.as_str() == value.trim())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ServiceEvent {
+    /// Position in the stream of this process, used to resume it.
+    pub id: u64,
+    /// Identifies the event across processes and restarts; outgoing webhook
+    /// deliveries refer to it.
+    #[schema(value_type = String)]
+    pub uid: Uuid,
+    pub r#type: EventKind,
+    pub company_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chat_id: Option<String>,
+    pub payload: Value,
+    pub created_at: String,
+}
+
+/// Payload of `client.*` events.
+pub fn client_payload(client: &clients::Model) -> Value {
+    let client_uuid = Uuid::from_slice(&client.id).unwrap_or_default();
+    let responsible_uuid = Uuid::from_slice(&client.responsible_user_id).unwrap_or_default();
+
+    json!({
+        "clientId": client_uuid.to_string(),
+        "fullName": client.full_name,
+        "email": client.email,
+        "phone": client.phone,
+        "responsibleUserId": responsible_uuid.to_string(),
+        "createdAt": client.created_at.to_rfc3339(),
+    })
+}
+
+/// Result of looking up buffered events for a reconnecting client.
+pub struct Replay {
+    pub events: Vec<Arc<ServiceEvent>>,
+    /// Events after the requested position cannot all be replayed: it is
+    /// older than the buffered history, or it is not an id this process
+    /// handed out. The client has to refetch its state.
+    pub reset: bool,
+}
+
+#[derive(Clone)]
+pub struct EventBus {
+    inner: Arc<EventBusInner>,
+}
+
+struct EventBusInner {
+    sender: broadcast::Sender<Arc<ServiceEvent>>,
+    history: Mutex<VecDeque<Arc<ServiceEvent>>>,
+    first_id: u64,
+    next_id: AtomicU64,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(CHANNEL_CAPACITY);
+        // Ids start from the current time so that ids of an earlier run fall
+        // below the ids of this one and are recognised as unknown.
+        let first_id = (Utc::now().timestamp_millis().max(0) as u64) * 1000;
+
+        Self {
+            inner: Arc::new(EventBusInner {
+                sender,
+                history: Mutex::new(VecDeque::with_capacity(HISTORY_CAPACITY)),
+                first_id,
+                next_id: AtomicU64::new(first_id),
+            }),
+        }
+    }
+
+    /// Publishes an event. Its `uid` and creation time are picked by the
+    /// caller, which may have recorded its outgoing webhook deliveries first.
+    pub fn publish(
+        &self,
+        uid: Uuid,
+        created_at: DateTime<Utc>,
+        company_uuid: &Uuid,
+        chat_id: Option<String>,
+        kind: EventKind,
+        payload: Value,
+    ) -> Arc<ServiceEvent> {
+        let mut history = self
+            .inner
+            .history
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        // Ids are assigned under the history lock to keep the buffer ordered.
+        let event = Arc::new(ServiceEvent {
+            id: self.inner.next_id.fetch_add(1, Ordering::Relaxed),
+            uid,
+            r#type: kind,
+            company_id: company_uuid.to_string(),
+            chat_id,
+            payload,
+            created_at: created_at.to_rfc3339(),
+        });
+
+        if history.len() == HISTORY_CAPACITY {
+            history.pop_front();
+        }
+        history.push_back(event.clone());
+
+        // Sending only fails when nobody is subscribed, which is fine.
+        let _ = self.inner.sender.send(event.clone());
+
+        event
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<Arc<ServiceEvent>> {
+        self.inner.sender.subscribe()
+    }
+
+    /// Returns buffered events of the company published after `last_event_id`.
+    pub fn replay_since(&self, company_id: &str, last_event_id: u64) -> Replay {
+        let history = self
+            .inner
+            .history
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        let next_id = self.inner.next_id.load(Ordering::Relaxed);
+        if !(self.inner.first_id..next_id).contains(&last_event_id) {
+            return Replay {
+                events: Vec::new(),
+                reset: true,
+            };
+        }
+
+        let oldest_id = history.front().map(|oldest| oldest.id).unwrap_or(next_id);
+        let events = history
+            .iter()
+            .filter(|event| event.id > last_event_id && event.company_id == company_id)
+            .cloned()
+            .collect();
+
+        Replay {
+            events,
+            reset: last_event_id.saturating_add(1) < oldest_id,
+        }
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
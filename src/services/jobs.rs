@@ -0,0 +1,510 @@
+//! Persistent job queue.
+//!
+//! Jobs are rows of the `jobs` table with a type, a JSON payload and the
+//! time they become due. Workers run in every server process and claim due
+//! jobs with `SELECT ... FOR UPDATE SKIP LOCKED` (SQLite has no row locks and
+//! serializes writers instead) followed by a conditional update, so several
+//! instances can share one queue without running a job twice. A claim is a
+//! lease: a job whose worker died is picked up again once it expires.
+//!
+//! Failed jobs are retried with exponential backoff until `max_attempts` is
+//! reached. A job enqueued with a unique key is stored only if no unfinished
+//! job holds that key. Recurring jobs (`recurring_jobs`) enqueue a job each
+//! time their cron schedule comes due.
+
+use std::{collections::HashMap, future::Future, sync::Arc, time::Duration};
+
+use chrono::{DateTime, Utc};
+use futures_util::future::{self, BoxFuture};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, Condition, ConnectionTrait, DatabaseConnection, EntityTrait,
+    IntoActiveModel, QueryFilter, QueryOrder, QuerySelect, Set, TransactionTrait,
+    sea_query::{Expr, LockBehavior, LockType},
+};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use uuid::Uuid;
+
+use crate::{
+    app_state::AppState,
+    database::models::{companies, jobs, recurring_jobs},
+    errors::AppError,
+    services::{
+        background::{Shutdown, run_guarded},
+        cron::CronSchedule,
+        maintenance,
+    },
+};
+
+pub const STATUS_PENDING: &str = "pending";
+pub const STATUS_RUNNING: &str = "running";
+pub const STATUS_SUCCEEDED: &str = "succeeded";
+pub const STATUS_FAILED: &str = "failed";
+
+/// Pulls one company's channels and contacts from Wazzup; payload
+/// `{"companyId": "<uuid>"}`.
+pub const SYNC_COMPANY: &str = "company.sync";
+/// Enqueues [`SYNC_COMPANY`] for every active company with an API key.
+pub const SYNC_ALL_COMPANIES: &str = "companies.sync";
+/// Recurring job behind `company_sync_schedule`.
+pub const COMPANY_SYNC_SCHEDULE: &str = "company-sync";
+
+pub const DEFAULT_MAX_ATTEMPTS: i32 = 5;
+/// How long a worker waits for new jobs when the queue is drained.
+pub const POLL_INTERVAL: Duration = Duration::from_secs(1);
+const BATCH_SIZE: u64 = 10;
+/// A claimed job is not picked up by another worker until the lease expires.
+const CLAIM_LEASE_SECS: i64 = 5 * 60;
+const BASE_RETRY_DELAY_SECS: i64 = 30;
+const MAX_RETRY_DELAY_SECS: i64 = 60 * 60;
+const MAX_ERROR_LEN: usize = 1000;
+
+/// A job to enqueue. [`NewJob::new`] makes it due right away and tried
+/// [`DEFAULT_MAX_ATTEMPTS`] times; set the fields to change that.
+#[derive(Debug, Clone)]
+pub struct NewJob {
+    pub job_type: String,
+    pub payload: Value,
+    pub run_at: Option<DateTime<Utc>>,
+    pub unique_key: Option<String>,
+    pub max_attempts: i32,
+}
+
+impl NewJob {
+    pub fn new(job_type: impl Into<String>, payload: Value) -> Self {
+        Self {
+            job_type: job_type.into(),
+            payload,
+            run_at: None,
+            unique_key: None,
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+        }
+    }
+
+    pub fn unique_key(mut self, key: impl Into<String>) -> Self {
+        self.unique_key = Some(key.into());
+        self
+    }
+}
+
+/// Stores `job` and returns its id. When an unfinished job already holds
+/// the unique key, nothing is stored and that job's id is returned.
+pub async fn enqueue<C>(db: &C, job: NewJob) -> Result<Uuid, AppError>
+where
+    C: ConnectionTrait,
+{
+    if let Some(existing) = holder_of(db, job.unique_key.as_deref()).await? {
+        return Ok(existing);
+    }
+
+    let id = Uuid::new_v4();
+    let now = Utc::now();
+    let inserted = jobs::ActiveModel {
+        id: Set(id.as_bytes().to_vec()),
+        job_type: Set(job.job_type),
+        payload: Set(job.payload),
+        status: Set(STATUS_PENDING.to_string()),
+        unique_key: Set(job.unique_key.clone()),
+        attempts: Set(0),
+        max_attempts: Set(job.max_attempts.max(1)),
+        run_at: Set(job.run_at.unwrap_or(now)),
+        locked_until: Set(None),
+        last_error: Set(None),
+        created_at: Set(now),
+        finished_at: Set(None),
+    }
+    .insert(db)
+    .await;
+
+    match inserted {
+        Ok(_) => Ok(id),
+        // Lost a race for the unique key to another enqueue.
+        Err(err) => match holder_of(db, job.unique_key.as_deref()).await? {
+            Some(existing) => Ok(existing),
+            None => Err(err.into()),
+        },
+    }
+}
+
+async fn holder_of<C>(db: &C, unique_key: Option<&str>) -> Result<Option<Uuid>, AppError>
+where
+    C: ConnectionTrait,
+{
+    let Some(key) = unique_key else {
+        return Ok(None);
+    };
+    Ok(jobs::Entity::find()
+        .filter(jobs::Column::UniqueKey.eq(key))
+        .one(db)
+        .await?
+        .and_then(|job| Uuid::from_slice(&job.id).ok()))
+}
+
+/// Creates or updates the recurring job `name`. Its next run is recomputed
+/// only when the schedule or the job changed.
+pub async fn set_recurring(
+    db: &DatabaseConnection,
+    name: &str,
+    schedule: &str,
+    job_type: &str,
+    payload: Value,
+) -> Result<(), AppError> {
+    let cron = CronSchedule::parse(schedule)
+        .map_err(|err| AppError::InvalidInput(format!("{}: {}", name, err)))?;
+    let now = Utc::now();
+    let next_run_at = cron.next_after(now).ok_or_else(|| {
+        AppError::InvalidInput(format!("{}: schedule '{}' never runs", name, schedule))
+    })?;
+
+    match recurring_jobs::Entity::find_by_id(name.to_string())
+        .one(db)
+        .await?
+    {
+        Some(existing)
+            if existing.schedule == schedule
+                && existing.job_type == job_type
+                && existing.payload == payload
+                && existing.is_active != 0 => {}
+        Some(existing) => {
+            let mut active = existing.into_active_model();
+            active.job_type = Set(job_type.to_string());
+            active.payload = Set(payload);
+            active.schedule = Set(schedule.to_string());
+            active.next_run_at = Set(next_run_at);
+            active.is_active = Set(1);
+            active.updated_at = Set(now);
+            active.update(db).await?;
+        }
+        None => {
+            recurring_jobs::ActiveModel {
+                name: Set(name.to_string()),
+                job_type: Set(job_type.to_string()),
+                payload: Set(payload),
+                schedule: Set(schedule.to_string()),
+                next_run_at: Set(next_run_at),
+                last_run_at: Set(None),
+                is_active: Set(1),
+                updated_at: Set(now),
+            }
+            .insert(db)
+            .await?;
+        }
+    }
+    Ok(())
+}
+
+/// Stops the recurring job `name`; jobs it already enqueued still run.
+pub async fn remove_recurring(db: &DatabaseConnection, name: &str) -> Result<(), AppError> {
+    recurring_jobs::Entity::delete_by_id(name.to_string())
+        .exec(db)
+        .await?;
+    Ok(())
+}
+
+/// Enqueues a job for every recurring job that came due and moves it to its
+/// next run. Runs missed while no worker was up are coalesced into one.
+pub async fn enqueue_recurring(db: &DatabaseConnection) -> Result<usize, AppError> {
+    let now = Utc::now();
+    let due = recurring_jobs::Entity::find()
+        .filter(recurring_jobs::Column::IsActive.ne(0))
+        .filter(recurring_jobs::Column::NextRunAt.lte(now))
+        .all(db)
+        .await?;
+
+    let mut enqueued = 0;
+    for recurring in due {
+        let next_run_at = CronSchedule::parse(&recurring.schedule)
+            .ok()
+            .and_then(|cron| cron.next_after(now));
+
+        // Claim this run so that other workers skip it.
+        let mut claim = recurring_jobs::Entity::update_many()
+            .col_expr(recurring_jobs::Column::LastRunAt, Expr::value(Some(now)))
+            .filter(recurring_jobs::Column::Name.eq(recurring.name.clone()))
+            .filter(recurring_jobs::Column::NextRunAt.eq(recurring.next_run_at));
+        claim = match next_run_at {
+            Some(next_run_at) => {
+                claim.col_expr(recurring_jobs::Column::NextRunAt, Expr::value(next_run_at))
+            }
+            None => {
+                log::error!(
+                    "Recurring job {} has an unusable schedule '{}'; disabling it",
+                    recurring.name,
+                    recurring.schedule
+                );
+                claim.col_expr(recurring_jobs::Column::IsActive, Expr::value(0))
+            }
+        };
+        if claim.exec(db).await?.rows_affected == 0 || next_run_at.is_none() {
+            continue;
+        }
+
+        enqueue(
+            db,
+            NewJob::new(recurring.job_type, recurring.payload).unique_key(format!(
+                "{}@{}",
+                recurring.name,
+                recurring.next_run_at.timestamp()
+            )),
+        )
+        .await?;
+        enqueued += 1;
+    }
+    Ok(enqueued)
+}
+
+type Handler =
+    Arc<dyn Fn(AppState, Value) -> BoxFuture<'static, Result<(), AppError>> + Send + Sync>;
+
+/// Job handlers by type, and the worker that runs them.
+#[derive(Clone, Default)]
+pub struct JobQueue {
+    handlers: HashMap<String, Handler>,
+}
+
+impl JobQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue with the service's own job types.
+    pub fn standard() -> Self {
+        Self::new()
+            .register(SYNC_COMPANY, |app_state, payload| async move {
+                let CompanyPayload { company_id } = serde_json::from_value(payload)?;
+                maintenance::sync_company(&app_state, &company_id).await?;
+                Ok(())
+            })
+            .register(SYNC_ALL_COMPANIES, |app_state, _| async move {
+                enqueue_company_syncs(&app_state.db).await
+            })
+    }
+
+    /// Runs jobs of `job_type` with `handler`, which gets the job payload.
+    /// An error or panic counts as a failed attempt.
+    pub fn register<F, Fut>(mut self, job_type: &str, handler: F) -> Self
+    where
+        F: Fn(AppState, Value) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), AppError>> + Send + 'static,
+    {
+        let handler: Handler =
+            Arc::new(move |app_state, payload| Box::pin(handler(app_state, payload)));
+        self.handlers.insert(job_type.to_string(), handler);
+        self
+    }
+
+    /// Claims a batch of due jobs, runs them concurrently and returns how
+    /// many were claimed.
+    pub async fn run_due(&self, app_state: &AppState) -> Result<usize, AppError> {
+        let claimed = claim(&app_state.db, BATCH_SIZE).await?;
+        let count = claimed.len();
+
+        let results =
+            future::join_all(claimed.into_iter().map(|job| self.execute(app_state, job))).await;
+        for result in results {
+            result?;
+        }
+        Ok(count)
+    }
+
+    /// Runs due jobs and recurring schedules until shutdown. Jobs in
+    /// progress are finished first; the task manager aborts them when the
+    /// shutdown timeout runs out, and their leases bring them back later.
+    pub async fn work(self, app_state: AppState, mut shutdown: Shutdown) -> Result<(), AppError> {
+        loop {
+            enqueue_recurring(&app_state.db).await?;
+            let claimed = self.run_due(&app_state).await?;
+            if shutdown.is_requested() {
+                return Ok(());
+            }
+            if claimed as u64 == BATCH_SIZE {
+                continue;
+            }
+            tokio::select! {
+                _ = tokio::time::sleep(POLL_INTERVAL) => {}
+                _ = shutdown.requested() => return Ok(()),
+            }
+        }
+    }
+
+    async fn execute(&self, app_state: &AppState, job: jobs::Model) -> Result<(), AppError> {
+        let outcome = match self.handlers.get(&job.job_type) {
+            Some(handler) => run_guarded(handler(app_state.clone(), job.payload.clone())).await,
+            None => Err(format!("No handler for job type {}", job.job_type)),
+        };
+        if let Err(error) = &outcome {
+            log::warn!(
+                "Job {} ({}, attempt {}) failed: {}",
+                Uuid::from_slice(&job.id).unwrap_or_default(),
+                job.job_type,
+                job.attempts,
+                error
+            );
+        }
+        finish(&app_state.db, job, outcome).await
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CompanyPayload {
+    company_id: Uuid,
+}
+
+async fn enqueue_company_syncs(db: &DatabaseConnection) -> Result<(), AppError> {
+    let companies = companies::Entity::find()
+        .filter(companies::Column::WazzupApiKey.is_not_null())
+        .all(db)
+        .await?;
+
+    for company in companies {
+        if company.is_active == Some(0) {
+            continue;
+        }
+        let Ok(company_uuid) = Uuid::from_slice(&company.id) else {
+            continue;
+        };
+        enqueue(
+            db,
+            NewJob::new(SYNC_COMPANY, json!({ "companyId": company_uuid }))
+                .unique_key(format!("{}:{}", SYNC_COMPANY, company_uuid)),
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+/// Marks up to `limit` due jobs as running under a fresh lease. Jobs whose
+/// lease expired after their last attempt are failed instead.
+async fn claim(db: &DatabaseConnection, limit: u64) -> Result<Vec<jobs::Model>, AppError> {
+    let now = Utc::now();
+    let lease = now + chrono::Duration::seconds(CLAIM_LEASE_SECS);
+    let txn = db.begin().await?;
+
+    let due = jobs::Entity::find()
+        .filter(
+            Condition::any()
+                .add(
+                    Condition::all()
+                        .add(jobs::Column::Status.eq(STATUS_PENDING))
+                        .add(jobs::Column::RunAt.lte(now)),
+                )
+                .add(
+                    Condition::all()
+                        .add(jobs::Column::Status.eq(STATUS_RUNNING))
+                        .add(jobs::Column::LockedUntil.lt(now)),
+                ),
+        )
+        .order_by_asc(jobs::Column::RunAt)
+        .limit(limit)
+        .lock_with_behavior(LockType::Update, LockBehavior::SkipLocked)
+        .all(&txn)
+        .await?;
+
+    let mut claimed = Vec::with_capacity(due.len());
+    for job in due {
+        let expired = job.status == STATUS_RUNNING;
+        let update = jobs::Entity::update_many()
+            .filter(jobs::Column::Id.eq(job.id.clone()))
+            .filter(jobs::Column::Status.eq(job.status.clone()))
+            .filter(jobs::Column::Attempts.eq(job.attempts));
+
+        if expired && job.attempts >= job.max_attempts {
+            update
+                .col_expr(jobs::Column::Status, Expr::value(STATUS_FAILED))
+                .col_expr(jobs::Column::UniqueKey, Expr::value(Option::<String>::None))
+                .col_expr(
+                    jobs::Column::LockedUntil,
+                    Expr::value(Option::<DateTime<Utc>>::None),
+                )
+                .col_expr(
+                    jobs::Column::LastError,
+                    Expr::value("Worker stopped during the last attempt"),
+                )
+                .col_expr(jobs::Column::FinishedAt, Expr::value(Some(now)))
+                .exec(&txn)
+                .await?;
+            continue;
+        }
+
+        let result = update
+            .col_expr(jobs::Column::Status, Expr::value(STATUS_RUNNING))
+            .col_expr(jobs::Column::Attempts, Expr::value(job.attempts + 1))
+            .col_expr(jobs::Column::LockedUntil, Expr::value(Some(lease)))
+            .exec(&txn)
+            .await?;
+        if result.rows_affected == 1 {
+            claimed.push(jobs::Model {
+                status: STATUS_RUNNING.to_string(),
+                attempts: job.attempts + 1,
+                locked_until: Some(lease),
+                ..job
+            });
+        }
+    }
+
+    txn.commit().await?;
+    Ok(claimed)
+}
+
+/// Stores the outcome of a claimed job unless its lease was taken over.
+async fn finish(
+    db: &DatabaseConnection,
+    job: jobs::Model,
+    outcome: Result<(), String>,
+) -> Result<(), AppError> {
+    let now = Utc::now();
+    let update = jobs::Entity::update_many()
+        .filter(jobs::Column::Id.eq(job.id.clone()))
+        .filter(jobs::Column::Status.eq(STATUS_RUNNING))
+        .filter(jobs::Column::Attempts.eq(job.attempts))
+        .col_expr(
+            jobs::Column::LockedUntil,
+            Expr::value(Option::<DateTime<Utc>>::None),
+        );
+
+    let update = match outcome {
+        Ok(()) => update
+            .col_expr(jobs::Column::Status, Expr::value(STATUS_SUCCEEDED))
+            .col_expr(jobs::Column::UniqueKey, Expr::value(Option::<String>::None))
+            .col_expr(jobs::Column::LastError, Expr::value(Option::<String>::None))
+            .col_expr(jobs::Column::FinishedAt, Expr::value(Some(now))),
+        Err(error) if job.attempts >= job.max_attempts => update
+            .col_expr(jobs::Column::Status, Expr::value(STATUS_FAILED))
+            .col_expr(jobs::Column::UniqueKey, Expr::value(Option::<String>::None))
+            .col_expr(
+                jobs::Column::LastError,
+                Expr::value(truncate(error, MAX_ERROR_LEN)),
+            )
+            .col_expr(jobs::Column::FinishedAt, Expr::value(Some(now))),
+        Err(error) => update
+            .col_expr(jobs::Column::Status, Expr::value(STATUS_PENDING))
+            .col_expr(
+                jobs::Column::LastError,
+                Expr::value(truncate(error, MAX_ERROR_LEN)),
+            )
+            .col_expr(
+                jobs::Column::RunAt,
+                Expr::value(now + chrono::Duration::seconds(retry_delay_secs(job.attempts))),
+            ),
+    };
+
+    update.exec(db).await?;
+    Ok(())
+}
+
+fn retry_delay_secs(attempts: i32) -> i64 {
+    let exponent = (attempts - 1).clamp(0, 16) as u32;
+    (BASE_RETRY_DELAY_SECS * 2_i64.pow(exponent)).min(MAX_RETRY_DELAY_SECS)
+}
+
+fn truncate(mut value: String, max_len: usize) -> String {
+    if value.len() > max_len {
+        let mut end = max_len;
+        while !value.is_char_boundary(end) {
+            end -= 1;
+        }
+        value.truncate(end);
+    }
+    value
+}
@@ -0,0 +1,384 @@
+//! Operator tasks run from the command line against the configured database.
+//!
+//! Every task goes through the same code paths as the HTTP API and the
+//! webhook listener, so data fixed here looks exactly like data that arrived
+//! normally. Events published by these tasks reach only subscribers of this
+//! process: outgoing webhooks are not delivered for them.
+
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use sea_orm::{
+    ColumnTrait, Condition, DatabaseConnection, EntityTrait, PaginatorTrait, QueryFilter,
+    QueryOrder,
+};
+use uuid::Uuid;
+
+use crate::{
+    api::{
+        channels::functions::sync_channels_to_db,
+        helpers::{get_company_api_key, uuid_to_bytes},
+        webhooks::functions::{default_webhook_subscriptions, public_webhook_uri},
+    },
+    app_state::AppState,
+    database::models::{
+        channels, chats, companies, jobs, message_search_index, messages,
+        outgoing_webhook_deliveries, tokens,
+    },
+    errors::AppError,
+    services::{
+        jobs as jobs_queue,
+        outgoing_webhooks::{STATUS_FAILED, STATUS_SUCCEEDED},
+        wazzup_api::{Message, WazzupContact, WebhookSubscriptionRequest},
+        webhook_handler::{self, WebhookContactEvent, WebhookMessage, WebhookRequest},
+    },
+};
+
+/// Outcome of a [`sync_company`] run.
+#[derive(Debug, Default)]
+pub struct SyncReport {
+    /// Channels listed by Wazzup.
+    pub channels: usize,
+    /// Contacts listed by Wazzup.
+    pub contacts: usize,
+}
+
+/// Outcome of a [`backfill_chat`] run.
+#[derive(Debug, Default)]
+pub struct BackfillReport {
+    /// Messages Wazzup returned for the chat.
+    pub fetched: usize,
+    /// Messages that were not stored before.
+    pub stored: u64,
+}
+
+/// Rows removed (or, on a dry run, that would be removed) by [`purge`].
+#[derive(Debug, Default)]
+pub struct PurgeReport {
+    /// Finished outgoing webhook deliveries.
+    pub deliveries: u64,
+    /// Finished queue jobs.
+    pub jobs: u64,
+    /// Expired bearer tokens.
+    pub tokens: u64,
+    /// Messages, counted only when messages are purged.
+    pub messages: u64,
+}
+
+/// Subscribes the company's Wazzup webhooks to this service and returns the
+/// registered URI. Needs `public_url`: without a request there is no other
+/// way to know the address Wazzup can reach.
+pub async fn connect_webhooks(app_state: &AppState, company: &Uuid) -> Result<String, AppError> {
+    let webhooks_uri = public_webhook_uri(&app_state.config, company).ok_or_else(|| {
+        AppError::InvalidInput("public_url must be set to connect webhooks".to_string())
+    })?;
+    let api_key = get_company_api_key(company, app_state).await?;
+    let request = WebhookSubscriptionRequest {
+        webhooks_uri: webhooks_uri.clone(),
+        subscriptions: default_webhook_subscriptions(),
+    };
+    app_state
+        .wazzup_api
+        .connect_webhooks(&api_key, &request)
+        .await?;
+    Ok(webhooks_uri)
+}
+
+/// [`connect_webhooks`] for every active company with an API key. One
+/// company failing does not stop the others.
+pub async fn connect_all_webhooks(
+    app_state: &AppState,
+) -> Result<Vec<(Uuid, Result<String, AppError>)>, AppError> {
+    let companies = companies::Entity::find()
+        .filter(companies::Column::WazzupApiKey.is_not_null())
+        .order_by_asc(companies::Column::Name)
+        .all(&app_state.db)
+        .await?;
+
+    let mut results = Vec::new();
+    for company in companies {
+        if company.is_active == Some(0) {
+            continue;
+        }
+        let Ok(company_uuid) = Uuid::from_slice(&company.id) else {
+            continue;
+        };
+        let result = connect_webhooks(app_state, &company_uuid).await;
+        results.push((company_uuid, result));
+    }
+    Ok(results)
+}
+
+/// Pulls the company's channels and contacts from Wazzup into the database.
+pub async fn sync_company(app_state: &AppState, company: &Uuid) -> Result<SyncReport, AppError> {
+    let api_key = get_company_api_key(company, app_state).await?;
+
+    let channel_list = app_state.wazzup_api.get_channels(&api_key).await?;
+    sync_channels_to_db(company, &channel_list, &app_state.db).await?;
+
+    let mut contacts = Vec::new();
+    loop {
+        let page = app_state
+            .wazzup_api
+            .get_contacts_with_offset(&api_key, contacts.len() as i32)
+            .await?;
+        if page.data.is_empty() {
+            break;
+        }
+        contacts.extend(page.data.into_iter().map(contact_event));
+        if contacts.len() >= page.count.max(0) as usize {
+            break;
+        }
+    }
+
+    let report = SyncReport {
+        channels: channel_list.channels.as_ref().map_or(0, Vec::len),
+        contacts: contacts.len(),
+    };
+    process(
+        app_state,
+        company,
+        WebhookRequest {
+            contacts: Some(contacts),
+            ..empty_webhook()
+        },
+    )
+    .await?;
+    Ok(report)
+}
+
+/// Processes a saved webhook body as if Wazzup had sent it again. The
+/// company's last webhook time is left alone.
+pub async fn replay_webhook(
+    app_state: &AppState,
+    company: &Uuid,
+    file: &Path,
+) -> Result<(), AppError> {
+    let body = std::fs::read(file).map_err(|err| {
+        AppError::InvalidInput(format!("Unable to read {}: {}", file.display(), err))
+    })?;
+    let webhook: WebhookRequest = serde_json::from_slice(&body)?;
+    process(app_state, company, webhook).await
+}
+
+/// Stores the messages Wazzup has for a chat of the company. `chat_id` is
+/// the Wazzup chat id; the stored chat is looked up on every channel of the
+/// company. Messages that are already stored are only updated, so the task
+/// can be repeated.
+pub async fn backfill_chat(
+    app_state: &AppState,
+    company: &Uuid,
+    chat_id: &str,
+) -> Result<BackfillReport, AppError> {
+    let company_channels = channels::Entity::find()
+        .filter(channels::Column::CompanyId.eq(uuid_to_bytes(company)))
+        .all(&app_state.db)
+        .await?;
+    // Chats stored before ids were scoped by channel keep the plain id.
+    let mut candidates = vec![webhook_handler::parse_flexible_uuid(chat_id).to_string()];
+    candidates.extend(company_channels.iter().filter_map(|channel| {
+        let channel_uuid = Uuid::from_slice(&channel.id).ok()?;
+        Some(webhook_handler::chat_uuid(&channel_uuid, chat_id).to_string())
+    }));
+    let chat = chats::Entity::find()
+        .filter(chats::Column::Id.is_in(candidates))
+        .filter(
+            chats::Column::ChannelId
+                .is_in(company_channels.iter().map(|channel| channel.id.clone())),
+        )
+        .one(&app_state.db)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Chat not found".to_string()))?;
+    let stored_id = chat.id.clone();
+    let channel = company_channels
+        .into_iter()
+        .find(|channel| channel.id == chat.channel_id)
+        .ok_or_else(|| AppError::NotFound("Chat not found".to_string()))?;
+    let channel_uuid = Uuid::from_slice(&channel.id)
+        .map_err(|_| AppError::InvalidInput("Chat has an invalid channel id".to_string()))?;
+
+    let api_key = get_company_api_key(company, app_state).await?;
+    let response = app_state.wazzup_api.get_messages(&api_key, chat_id).await?;
+    let history: Vec<WebhookMessage> = response
+        .messages
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|message| history_message(message, chat_id, &channel_uuid, &channel.r#type))
+        .collect();
+    let fetched = history.len();
+
+    let before = messages::Entity::find()
+        .filter(messages::Column::ChatId.eq(stored_id))
+        .count(&app_state.db)
+        .await?;
+    process(
+        app_state,
+        company,
+        WebhookRequest {
+            messages: Some(history),
+            ..empty_webhook()
+        },
+    )
+    .await?;
+    let after = messages::Entity::find()
+        .filter(messages::Column::ChatId.eq(chat.id))
+        .count(&app_state.db)
+        .await?;
+
+    Ok(BackfillReport {
+        fetched,
+        stored: after.saturating_sub(before),
+    })
+}
+
+/// Deletes finished outgoing webhook deliveries and queue jobs created
+/// before `cutoff`, tokens that expired before it, and with `include_messages` messages created before it.
+/// A dry run only counts the rows.
+pub async fn purge(
+    db: &DatabaseConnection,
+    cutoff: DateTime<Utc>,
+    include_messages: bool,
+    dry_run: bool,
+) -> Result<PurgeReport, AppError> {
+    let finished_deliveries = Condition::all()
+        .add(outgoing_webhook_deliveries::Column::Status.is_in([STATUS_SUCCEEDED, STATUS_FAILED]))
+        .add(outgoing_webhook_deliveries::Column::CreatedAt.lt(cutoff));
+    let finished_jobs = Condition::all()
+        .add(jobs::Column::Status.is_in([jobs_queue::STATUS_SUCCEEDED, jobs_queue::STATUS_FAILED]))
+        .add(jobs::Column::CreatedAt.lt(cutoff));
+    let expired_tokens = tokens::Column::ExpiresAt.lt(cutoff);
+    let old_messages = messages::Column::CreatedAt.lt(cutoff);
+
+    let mut report = PurgeReport {
+        deliveries: outgoing_webhook_deliveries::Entity::find()
+            .filter(finished_deliveries.clone())
+            .count(db)
+            .await?,
+        jobs: jobs::Entity::find()
+            .filter(finished_jobs.clone())
+            .count(db)
+            .await?,
+        tokens: tokens::Entity::find()
+            .filter(expired_tokens.clone())
+            .count(db)
+            .await?,
+        messages: 0,
+    };
+    if include_messages {
+        report.messages = messages::Entity::find()
+            .filter(old_messages.clone())
+            .count(db)
+            .await?;
+    }
+    if dry_run {
+        return Ok(report);
+    }
+
+    outgoing_webhook_deliveries::Entity::delete_many()
+        .filter(finished_deliveries)
+        .exec(db)
+        .await?;
+    jobs::Entity::delete_many()
+        .filter(finished_jobs)
+        .exec(db)
+        .await?;
+    tokens::Entity::delete_many()
+        .filter(expired_tokens)
+        .exec(db)
+        .await?;
+    if include_messages {
+        // Index rows first: they reference the messages.
+        message_search_index::Entity::delete_many()
+            .filter(message_search_index::Column::CreatedAt.lt(cutoff))
+            .exec(db)
+            .await?;
+        messages::Entity::delete_many()
+            .filter(old_messages)
+            .exec(db)
+            .await?;
+    }
+    Ok(report)
+}
+
+async fn process(
+    app_state: &AppState,
+    company: &Uuid,
+    webhook: WebhookRequest,
+) -> Result<(), AppError> {
+    companies::Entity::find_by_id(uuid_to_bytes(company))
+        .one(&app_state.db)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Company not found".to_string()))?;
+
+    webhook_handler::process_webhook(
+        *company,
+        webhook,
+        &app_state.db,
+        &app_state.bot_service,
+        &app_state.wazzup_api,
+        &app_state.events,
+    )
+    .await
+}
+
+fn empty_webhook() -> WebhookRequest {
+    WebhookRequest {
+        test: None,
+        messages: None,
+        statuses: None,
+        contacts: None,
+        channels_updates: None,
+    }
+}
+
+/// A CRM contact as Wazzup lists it, in the shape of a contact webhook.
+fn contact_event(contact: WazzupContact) -> WebhookContactEvent {
+    let phone = contact.contact_data.iter().find_map(|data| {
+        data.phone
+            .clone()
+            .or_else(|| (data.chat_type == "whatsapp").then(|| data.chat_id.clone()))
+    });
+    WebhookContactEvent {
+        contact_id: contact.id,
+        name: Some(contact.name),
+        phone,
+        email: None,
+        chat_id: None,
+        channel_id: None,
+        responsible_user_id: Some(contact.responsible_user_id),
+    }
+}
+
+/// A message from the history API in the shape of a message webhook.
+fn history_message(
+    message: Message,
+    chat_id: &str,
+    channel: &Uuid,
+    transport: &str,
+) -> Option<WebhookMessage> {
+    Some(WebhookMessage {
+        message_id: message.id?,
+        channel_id: message.channel_id.unwrap_or_else(|| channel.to_string()),
+        chat_type: transport.to_string(),
+        chat_id: chat_id.to_string(),
+        r#type: message.content_type.unwrap_or_else(|| "text".to_string()),
+        text: message.text,
+        content_uri: None,
+        client_name: None,
+        client_phone: None,
+        date_time: message.created_at.map(|value| value.to_rfc3339()),
+        is_echo: message
+            .is_inbound
+            .or(match message.direction.as_deref() {
+                Some("inbound" | "incoming") => Some(true),
+                Some("outbound" | "outgoing") => Some(false),
+                _ => None,
+            })
+            .map(|inbound| !inbound),
+        // History carries no delivery status; keep the stored one.
+        status: None,
+        contact: None,
+        author_name: None,
+        author_id: None,
+    })
+}
@@ -1,3 +1,13 @@
+pub mod api_keys;
+pub mod background;
 pub mod bot_service;
+pub mod cron;
+pub mod events;
+pub mod jobs;
+pub mod maintenance;
+pub mod outgoing_webhooks;
+pub mod rate_limit;
+pub mod search;
+pub mod tokens;
 pub mod wazzup_api;
 pub mod webhook_handler;
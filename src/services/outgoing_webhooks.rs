@@ -0,0 +1,530 @@
+//! Delivery of service events to downstream webhooks registered by companies.
+//!
+//! Events are published through [`publish`], which matches them against the
+//! active webhooks of the company and records the deliveries in
+//! `outgoing_webhook_deliveries` before the event goes on the [`EventBus`].
+//! Deliveries refer to the event by [`ServiceEvent::uid`], which is also the
+//! `id` of the body sent. The dispatcher listening on the bus sends them
+//! right away; a periodic sweep
+//! retries failures with exponential backoff and sends whatever the dispatcher
+//! missed (a lagging bus, a restart, events of CLI tasks). Deliveries are
+//! signed with HMAC-SHA256.
+//!
+//! Webhook URLs come from tenants, so unless
+//! `outgoing_webhooks_allow_private_networks` is set the service only talks to
+//! public addresses: hosts are resolved by [`PublicResolver`], literal IPs are
+//! checked before sending, and redirects are not followed.
+
+use std::{
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+    time::Duration,
+};
+
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use reqwest::{
+    Client,
+    dns::{Addrs, Name, Resolve, Resolving},
+    redirect,
+};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, IntoActiveModel, QueryFilter,
+    QueryOrder, QuerySelect, Set, prelude::DateTimeUtc,
+};
+use serde::Serialize;
+use serde_json::Value;
+use sha2::Sha256;
+use tokio::{sync::broadcast::error::RecvError, task::JoinSet};
+use uuid::Uuid;
+
+use crate::{
+    api::helpers::uuid_to_bytes,
+    config::Config,
+    database::models::{outgoing_webhook_deliveries, outgoing_webhooks},
+    errors::AppError,
+    services::{
+        background::Shutdown,
+        events::{EventBus, EventKind, ServiceEvent},
+    },
+};
+
+pub const SIGNATURE_HEADER: &str = "X-Webhook-Signature";
+pub const EVENT_HEADER: &str = "X-Webhook-Event";
+pub const DELIVERY_HEADER: &str = "X-Webhook-Delivery";
+
+pub const STATUS_PENDING: &str = "pending";
+pub const STATUS_SUCCEEDED: &str = "succeeded";
+pub const STATUS_FAILED: &str = "failed";
+
+pub const MAX_ATTEMPTS: i32 = 8;
+const BASE_RETRY_DELAY_SECS: i64 = 30;
+const MAX_RETRY_DELAY_SECS: i64 = 60 * 60;
+/// A claimed delivery is not picked up by the sweep again until the lease expires.
+const CLAIM_LEASE_SECS: i64 = 60;
+/// How often [`OutgoingWebhookService::deliver_due`] should run.
+pub const SWEEP_INTERVAL: Duration = Duration::from_secs(15);
+const SWEEP_BATCH_SIZE: u64 = 100;
+const MAX_ERROR_LEN: usize = 1000;
+
+#[derive(Clone)]
+pub struct OutgoingWebhookService {
+    client: Client,
+    allow_private_networks: bool,
+}
+
+impl OutgoingWebhookService {
+    pub fn from_config(config: &Config) -> Result<Self, AppError> {
+        let allow_private_networks = config.outgoing_webhooks_allow_private_networks();
+        let mut builder = Client::builder()
+            .timeout(Duration::from_secs(10))
+            .redirect(redirect::Policy::none());
+        if !allow_private_networks {
+            // A proxy would resolve the host itself, past the address check.
+            builder = builder.no_proxy().dns_resolver(Arc::new(PublicResolver));
+        }
+
+        Ok(Self {
+            client: builder.build()?,
+            allow_private_networks,
+        })
+    }
+
+    /// Why the service refuses to send to `url`, if it does: the host is or
+    /// resolves to a loopback, private or link-local address. Hosts that do
+    /// not resolve are let through; sending to them just fails.
+    pub async fn check_target(&self, url: &str) -> Option<String> {
+        if self.allow_private_networks {
+            return None;
+        }
+        let url = url::Url::parse(url)
+            .ok()
+            .filter(|url| matches!(url.scheme(), "http" | "https"))?;
+        let addresses: Vec<IpAddr> = match url.host()? {
+            url::Host::Ipv4(ip) => vec![IpAddr::V4(ip)],
+            url::Host::Ipv6(ip) => vec![IpAddr::V6(ip)],
+            url::Host::Domain(domain) => tokio::net::lookup_host((domain, 0))
+                .await
+                .ok()?
+                .map(|address| address.ip())
+                .collect(),
+        };
+
+        addresses
+            .into_iter()
+            .find(|ip| !is_public_address(*ip))
+            .map(|ip| format!("{} is not a public address", ip))
+    }
+
+    /// Sends the deliveries recorded for events on the bus until shutdown or
+    /// until the bus is closed, then waits for the sends in flight. Missed
+    /// events and retries are left to [`Self::deliver_due`].
+    pub async fn listen(
+        self,
+        db: DatabaseConnection,
+        events: EventBus,
+        mut shutdown: Shutdown,
+    ) -> Result<(), AppError> {
+        let mut receiver = events.subscribe();
+        let mut in_flight = JoinSet::new();
+
+        loop {
+            tokio::select! {
+                received = receiver.recv() => match received {
+                    Ok(event) => {
+                        if let Err(err) = self.deliver_event(&db, &event, &mut in_flight).await {
+                            log::error!(
+                                "Failed to deliver outgoing webhooks for event {}: {}",
+                                event.id,
+                                err
+                            );
+                        }
+                    }
+                    Err(RecvError::Lagged(skipped)) => {
+                        log::warn!(
+                            "Outgoing webhook dispatcher skipped {} event(s); the sweep will send them",
+                            skipped
+                        );
+                    }
+                    Err(RecvError::Closed) => break,
+                },
+                Some(_) = in_flight.join_next(), if !in_flight.is_empty() => {}
+                _ = shutdown.requested() => break,
+            }
+        }
+
+        // Aborted along with the service when the shutdown timeout expires.
+        while in_flight.join_next().await.is_some() {}
+        Ok(())
+    }
+
+    /// Sends the not yet attempted deliveries of an event, each in its own
+    /// task of `in_flight`.
+    async fn deliver_event(
+        &self,
+        db: &DatabaseConnection,
+        event: &ServiceEvent,
+        in_flight: &mut JoinSet<()>,
+    ) -> Result<(), AppError> {
+        let deliveries = outgoing_webhook_deliveries::Entity::find()
+            .filter(outgoing_webhook_deliveries::Column::EventUid.eq(uuid_to_bytes(&event.uid)))
+            .filter(outgoing_webhook_deliveries::Column::Status.eq(STATUS_PENDING))
+            .filter(outgoing_webhook_deliveries::Column::Attempts.eq(0))
+            .all(db)
+            .await?;
+
+        for delivery in deliveries {
+            let service = self.clone();
+            let db = db.clone();
+            in_flight.spawn(async move {
+                if let Err(err) = service.claim_and_send(&db, delivery, Utc::now()).await {
+                    log::error!("Outgoing webhook delivery failed to persist: {}", err);
+                }
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Retries pending deliveries whose backoff (or claim lease) has expired.
+    pub async fn deliver_due(&self, db: &DatabaseConnection) -> Result<(), AppError> {
+        let now = Utc::now();
+        let due = outgoing_webhook_deliveries::Entity::find()
+            .filter(outgoing_webhook_deliveries::Column::Status.eq(STATUS_PENDING))
+            .filter(outgoing_webhook_deliveries::Column::NextAttemptAt.lte(now))
+            .order_by_asc(outgoing_webhook_deliveries::Column::NextAttemptAt)
+            .limit(SWEEP_BATCH_SIZE)
+            .all(db)
+            .await?;
+
+        for delivery in due {
+            self.claim_and_send(db, delivery, now).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Claims a due delivery so that a concurrent sweep or dispatcher does not
+    /// send it twice, then sends it unless the webhook is gone or disabled.
+    async fn claim_and_send(
+        &self,
+        db: &DatabaseConnection,
+        delivery: outgoing_webhook_deliveries::Model,
+        now: DateTimeUtc,
+    ) -> Result<(), AppError> {
+        let claimed = outgoing_webhook_deliveries::Entity::update_many()
+            .col_expr(
+                outgoing_webhook_deliveries::Column::NextAttemptAt,
+                sea_orm::sea_query::Expr::value(lease_until(now)),
+            )
+            .filter(outgoing_webhook_deliveries::Column::Id.eq(delivery.id.clone()))
+            .filter(outgoing_webhook_deliveries::Column::NextAttemptAt.eq(delivery.next_attempt_at))
+            .exec(db)
+            .await?;
+        if claimed.rows_affected == 0 {
+            return Ok(());
+        }
+
+        let webhook = match outgoing_webhooks::Entity::find_by_id(delivery.webhook_id.clone())
+            .one(db)
+            .await?
+        {
+            Some(webhook) if webhook.is_active != 0 => webhook,
+            _ => {
+                let mut active = delivery.into_active_model();
+                active.status = Set(STATUS_FAILED.to_string());
+                active.next_attempt_at = Set(None);
+                active.last_error = Set(Some("Webhook is disabled or removed".to_string()));
+                active.update(db).await?;
+                return Ok(());
+            }
+        };
+
+        self.attempt(db, &webhook, delivery).await?;
+        Ok(())
+    }
+
+    /// Sends a delivery again on request, regardless of its current state.
+    pub async fn redeliver(
+        &self,
+        db: &DatabaseConnection,
+        webhook: &outgoing_webhooks::Model,
+        delivery: outgoing_webhook_deliveries::Model,
+    ) -> Result<outgoing_webhook_deliveries::Model, AppError> {
+        let mut active = delivery.into_active_model();
+        active.status = Set(STATUS_PENDING.to_string());
+        active.next_attempt_at = Set(Some(lease_until(Utc::now())));
+        let delivery = active.update(db).await?;
+
+        self.attempt(db, webhook, delivery).await
+    }
+
+    /// Sends one delivery and stores the outcome, scheduling a retry on failure.
+    async fn attempt(
+        &self,
+        db: &DatabaseConnection,
+        webhook: &outgoing_webhooks::Model,
+        delivery: outgoing_webhook_deliveries::Model,
+    ) -> Result<outgoing_webhook_deliveries::Model, AppError> {
+        let body = serde_json::to_vec(&delivery.payload)?;
+        let timestamp = Utc::now().timestamp();
+        let signature = sign_payload(&webhook.secret, timestamp, &body);
+        let delivery_id = Uuid::from_slice(&delivery.id).unwrap_or_default();
+
+        let attempts = delivery.attempts + 1;
+        let (response_status, error) = match self.check_target(&webhook.url).await {
+            Some(reason) => (None, Some(format!("Target refused: {}", reason))),
+            None => {
+                let result = self
+                    .client
+                    .post(&webhook.url)
+                    .header("Content-Type", "application/json")
+                    .header(SIGNATURE_HEADER, signature)
+                    .header(EVENT_HEADER, delivery.event_type.as_str())
+                    .header(DELIVERY_HEADER, delivery_id.to_string())
+                    .body(body)
+                    .send()
+                    .await;
+                match result {
+                    Ok(response) if response.status().is_success() => {
+                        (Some(response.status().as_u16() as i32), None)
+                    }
+                    Ok(response) => {
+                        let status = response.status();
+                        let text = response.text().await.unwrap_or_default();
+                        (
+                            Some(status.as_u16() as i32),
+                            Some(format!("Endpoint responded with {}: {}", status, text)),
+                        )
+                    }
+                    Err(err) => (None, Some(format!("Request failed: {}", err))),
+                }
+            }
+        };
+
+        let now = Utc::now();
+        let mut active = delivery.into_active_model();
+        active.attempts = Set(attempts);
+        active.response_status = Set(response_status);
+
+        match error {
+            None => {
+                active.status = Set(STATUS_SUCCEEDED.to_string());
+                active.last_error = Set(None);
+                active.next_attempt_at = Set(None);
+                active.delivered_at = Set(Some(now));
+            }
+            Some(error) => {
+                log::warn!(
+                    "Outgoing webhook delivery {} (attempt {}) failed: {}",
+                    delivery_id,
+                    attempts,
+                    error
+                );
+                active.last_error = Set(Some(truncate(error, MAX_ERROR_LEN)));
+                if attempts >= MAX_ATTEMPTS {
+                    active.status = Set(STATUS_FAILED.to_string());
+                    active.next_attempt_at = Set(None);
+                } else {
+                    active.status = Set(STATUS_PENDING.to_string());
+                    active.next_attempt_at = Set(Some(
+                        now + chrono::Duration::seconds(retry_delay_secs(attempts)),
+                    ));
+                }
+            }
+        }
+
+        Ok(active.update(db).await?)
+    }
+}
+
+/// Body of a delivery. It carries the stable id of the event rather than its
+/// position in the stream of the process that published it.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DeliveryBody<'a> {
+    id: Uuid,
+    r#type: EventKind,
+    company_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    chat_id: Option<&'a str>,
+    payload: &'a Value,
+    created_at: String,
+}
+
+/// Records a pending delivery for every active webhook of the company
+/// subscribed to the event, then publishes it on the bus. Recorded first, the
+/// deliveries are there when the dispatcher looks for them and survive a
+/// dispatcher that misses the event or a restart.
+///
+/// Callers publish once their change is committed, so a failure to record
+/// the deliveries is logged rather than failing the request.
+pub async fn publish(
+    db: &DatabaseConnection,
+    events: &EventBus,
+    company_uuid: &Uuid,
+    chat_id: Option<String>,
+    kind: EventKind,
+    payload: Value,
+) -> Arc<ServiceEvent> {
+    let uid = Uuid::new_v4();
+    let created_at = Utc::now();
+    let body = DeliveryBody {
+        id: uid,
+        r#type: kind,
+        company_id: company_uuid.to_string(),
+        chat_id: chat_id.as_deref(),
+        payload: &payload,
+        created_at: created_at.to_rfc3339(),
+    };
+    if let Err(err) = record_deliveries(db, company_uuid, &body).await {
+        log::error!(
+            "Failed to record outgoing webhook deliveries of {} event {}: {}",
+            kind.as_str(),
+            uid,
+            err
+        );
+    }
+
+    events.publish(uid, created_at, company_uuid, chat_id, kind, payload)
+}
+
+async fn record_deliveries(
+    db: &DatabaseConnection,
+    company_uuid: &Uuid,
+    body: &DeliveryBody<'_>,
+) -> Result<(), AppError> {
+    let company_bytes = uuid_to_bytes(company_uuid);
+    let webhooks = outgoing_webhooks::Entity::find()
+        .filter(outgoing_webhooks::Column::CompanyId.eq(company_bytes.clone()))
+        .filter(outgoing_webhooks::Column::IsActive.eq(1))
+        .all(db)
+        .await?;
+
+    let payload = serde_json::to_value(body)?;
+    let now = Utc::now();
+    let deliveries: Vec<_> = webhooks
+        .into_iter()
+        .filter(|webhook| subscribes_to(webhook, body.r#type))
+        .map(|webhook| outgoing_webhook_deliveries::ActiveModel {
+            id: Set(uuid_to_bytes(&Uuid::new_v4())),
+            webhook_id: Set(webhook.id),
+            company_id: Set(company_bytes.clone()),
+            event_uid: Set(Some(uuid_to_bytes(&body.id))),
+            event_type: Set(body.r#type.as_str().to_string()),
+            payload: Set(payload.clone()),
+            status: Set(STATUS_PENDING.to_string()),
+            attempts: Set(0),
+            response_status: Set(None),
+            last_error: Set(None),
+            next_attempt_at: Set(Some(now)),
+            created_at: Set(now),
+            delivered_at: Set(None),
+        })
+        .collect();
+    if !deliveries.is_empty() {
+        outgoing_webhook_deliveries::Entity::insert_many(deliveries)
+            .exec(db)
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Resolves webhook hosts to their public addresses only, so that a name
+/// pointing at the internal network fails instead of being connected to.
+struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let host = name.as_str();
+            let addresses: Vec<SocketAddr> = tokio::net::lookup_host((host, 0))
+                .await?
+                .filter(|address| is_public_address(address.ip()))
+                .collect();
+            if addresses.is_empty() {
+                return Err(format!("{} does not resolve to a public address", host).into());
+            }
+            Ok(Box::new(addresses.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// Whether `ip` is routable on the internet: not loopback, private (RFC 1918,
+/// unique-local), link-local, carrier-grade NAT, unspecified or multicast.
+pub fn is_public_address(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                || a == 0
+                || (a == 100 && (64..128).contains(&b)))
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(mapped) => is_public_address(IpAddr::V4(mapped)),
+            None => {
+                !(ip.is_loopback()
+                    || ip.is_unspecified()
+                    || ip.is_multicast()
+                    || ip.is_unique_local()
+                    || ip.is_unicast_link_local())
+            }
+        },
+    }
+}
+
+/// Signature header value: `t=<unix seconds>,v1=<hex HMAC-SHA256 of "<t>.<body>">`.
+pub fn sign_payload(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    format!(
+        "t={},v1={}",
+        timestamp,
+        hex::encode(mac.finalize().into_bytes())
+    )
+}
+
+/// Event types the webhook is subscribed to; empty means every event.
+pub fn event_types_of(webhook: &outgoing_webhooks::Model) -> Vec<String> {
+    match &webhook.event_types {
+        Value::Array(items) => items
+            .iter()
+            .filter_map(|item| item.as_str().map(|value| value.to_string()))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn subscribes_to(webhook: &outgoing_webhooks::Model, kind: EventKind) -> bool {
+    let event_types = event_types_of(webhook);
+    event_types.is_empty() || event_types.iter().any(|value| value == kind.as_str())
+}
+
+fn retry_delay_secs(attempts: i32) -> i64 {
+    let exponent = (attempts - 1).clamp(0, 16) as u32;
+    (BASE_RETRY_DELAY_SECS * 2_i64.pow(exponent)).min(MAX_RETRY_DELAY_SECS)
+}
+
+fn lease_until(now: DateTimeUtc) -> DateTimeUtc {
+    now + chrono::Duration::seconds(CLAIM_LEASE_SECS)
+}
+
+fn truncate(mut value: String, max_len: usize) -> String {
+    if value.len() > max_len {
+        let mut end = max_len;
+        while !value.is_char_boundary(end) {
+            end -= 1;
+        }
+        value.truncate(end);
+    }
+    value
+}
@@ -0,0 +1,135 @@
+//! In-memory token buckets for inbound request rate limits.
+//!
+//! Every budget is a number of requests per minute: a bucket holds up to that
+//! many tokens and refills continuously, so short bursts up to the budget are
+//! fine while the sustained rate is capped. A request usually counts against
+//! several buckets at once (IP, token, company); it is let through only when
+//! all of them have a token left, and only then are the tokens taken.
+//!
+//! Buckets live in the process, so each instance enforces its own limits.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// How often idle buckets are dropped, see [`RateLimiter::sweep`].
+pub const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Clone, Default)]
+pub struct RateLimiter {
+    buckets: Arc<Mutex<HashMap<String, Bucket>>>,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+    per_minute: u32,
+}
+
+impl Bucket {
+    fn refill(&mut self, per_minute: u32, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * rate(per_minute)).min(per_minute as f64);
+        self.updated = now;
+        self.per_minute = per_minute;
+    }
+
+    fn is_full(&self, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens + elapsed * rate(self.per_minute) >= self.per_minute as f64
+    }
+}
+
+/// Outcome of [`RateLimiter::check`], for the most constrained bucket.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Decision {
+    pub allowed: bool,
+    /// Budget of the bucket, requests per minute.
+    pub limit: u32,
+    /// Requests left in the bucket right now.
+    pub remaining: u32,
+    /// Time until the bucket is full again.
+    pub reset_after: Duration,
+    /// Time until the next request would pass; zero when allowed.
+    pub retry_after: Duration,
+}
+
+impl RateLimiter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Takes one token from every `(key, requests per minute)` bucket, or
+    /// none if any of them is empty. Budgets of zero are not limited.
+    pub fn check(&self, budgets: &[(String, u32)]) -> Option<Decision> {
+        let now = Instant::now();
+        let budgets: Vec<_> = budgets
+            .iter()
+            .filter(|(_, per_minute)| *per_minute > 0)
+            .collect();
+        if budgets.is_empty() {
+            return None;
+        }
+
+        let mut buckets = self.buckets.lock().unwrap();
+        for (key, per_minute) in &budgets {
+            buckets
+                .entry(key.clone())
+                .or_insert_with(|| Bucket {
+                    tokens: *per_minute as f64,
+                    updated: now,
+                    per_minute: *per_minute,
+                })
+                .refill(*per_minute, now);
+        }
+
+        let allowed = budgets.iter().all(|(key, _)| buckets[key].tokens >= 1.0);
+        let decisions = budgets.iter().map(|(key, per_minute)| {
+            let bucket = buckets.get_mut(key).unwrap();
+            if allowed {
+                bucket.tokens -= 1.0;
+            }
+            decide(bucket, *per_minute, allowed)
+        });
+        // A rejection reports the bucket that takes longest to let a request
+        // through; otherwise the one closest to running out.
+        if allowed {
+            decisions.min_by_key(|decision| (decision.remaining, decision.limit))
+        } else {
+            decisions.max_by_key(|decision| decision.retry_after)
+        }
+    }
+
+    /// Drops buckets that have refilled completely, so keys of clients that
+    /// went away do not pile up.
+    pub fn sweep(&self) {
+        let now = Instant::now();
+        self.buckets
+            .lock()
+            .unwrap()
+            .retain(|_, bucket| !bucket.is_full(now));
+    }
+}
+
+fn decide(bucket: &Bucket, per_minute: u32, allowed: bool) -> Decision {
+    let rate = rate(per_minute);
+    let missing = |tokens: f64| Duration::from_secs_f64(tokens.max(0.0) / rate);
+    Decision {
+        allowed,
+        limit: per_minute,
+        remaining: bucket.tokens.floor().max(0.0) as u32,
+        reset_after: missing(per_minute as f64 - bucket.tokens),
+        retry_after: if allowed {
+            Duration::ZERO
+        } else {
+            missing(1.0 - bucket.tokens)
+        },
+    }
+}
+
+/// Tokens per second.
+fn rate(per_minute: u32) -> f64 {
+    per_minute as f64 / 60.0
+}
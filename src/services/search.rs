@@ -0,0 +1,391 @@
+//! Full-text search over message text.
+//!
+//! Message content is stored as JSON, so the text parts are copied into
+//! `message_search_index` when a message is stored; messages stored before
+//! are indexed by `wazzup index-messages`. Queries use MySQL `MATCH ... AGAINST` or Postgres `tsvector`
+//! depending on the backend; other backends fall back to `LIKE`, which only
+//! folds the case of ASCII letters.
+
+use std::collections::{HashMap, HashSet};
+
+use sea_orm::{
+    ColumnTrait, ConnectionTrait, DbBackend, DbErr, EntityTrait, FromQueryResult, JoinType, Order,
+    QueryFilter, QueryOrder, QuerySelect, RelationTrait,
+    prelude::DateTimeUtc,
+    sea_query::{Alias, Expr, Func, LikeExpr, OnConflict, Query},
+};
+use serde_json::Value;
+
+use crate::database::models::{channels, chats, clients, message_search_index, messages};
+
+const BACKFILL_BATCH_SIZE: u64 = 500;
+const SNIPPET_CONTEXT_CHARS: usize = 60;
+const HIGHLIGHT_START: &str = "<mark>";
+const HIGHLIGHT_END: &str = "</mark>";
+
+#[derive(Debug, FromQueryResult)]
+pub struct MessageMatch {
+    pub message_id: Vec<u8>,
+    pub chat_id: String,
+    pub body: String,
+    pub created_at: DateTimeUtc,
+    pub score: f64,
+}
+
+#[derive(Debug, Default)]
+pub struct MessageSearch {
+    pub terms: Vec<String>,
+    pub channel_id: Option<Vec<u8>>,
+    /// Only chats of clients this user is responsible for.
+    pub chat_owner: Option<Vec<u8>>,
+    pub from: Option<DateTimeUtc>,
+    pub to: Option<DateTimeUtc>,
+    pub offset: u64,
+    pub limit: u64,
+}
+
+/// Text parts of a message joined into one searchable string.
+pub fn message_text(content: &Value) -> String {
+    match content {
+        Value::Array(parts) => parts
+            .iter()
+            .filter(|part| part.get("type").and_then(Value::as_str) == Some("text"))
+            .filter_map(|part| part.get("content").and_then(Value::as_str))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Value::String(text) => text.clone(),
+        _ => String::new(),
+    }
+}
+
+/// Splits a user query into lowercase word terms; punctuation and search
+/// operators are treated as separators.
+pub fn search_terms(query: &str) -> Vec<String> {
+    let mut terms: Vec<String> = Vec::new();
+    for term in query
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|term| !term.is_empty())
+        .map(|term| term.to_lowercase())
+    {
+        if !terms.contains(&term) {
+            terms.push(term);
+        }
+    }
+    terms
+}
+
+/// `LIKE` pattern matching `text` anywhere; `%`, `_` and `\` in the text
+/// are matched literally.
+pub fn contains_pattern(text: &str) -> LikeExpr {
+    let mut pattern = String::with_capacity(text.len() + 2);
+    pattern.push('%');
+    for c in text.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            pattern.push('\\');
+        }
+        pattern.push(c);
+    }
+    pattern.push('%');
+    LikeExpr::new(pattern).escape('\\')
+}
+
+/// Stores (or refreshes) the searchable text of a message.
+pub async fn index_message<C>(
+    db: &C,
+    company_id: Option<Vec<u8>>,
+    message: &messages::Model,
+) -> Result<(), DbErr>
+where
+    C: ConnectionTrait,
+{
+    let record = message_search_index::ActiveModel {
+        message_id: sea_orm::Set(message.id.clone()),
+        company_id: sea_orm::Set(company_id),
+        chat_id: sea_orm::Set(message.chat_id.clone()),
+        body: sea_orm::Set(message_text(&message.content)),
+        created_at: sea_orm::Set(message.created_at),
+    };
+
+    message_search_index::Entity::insert(record)
+        .on_conflict(
+            OnConflict::column(message_search_index::Column::MessageId)
+                .update_columns([
+                    message_search_index::Column::CompanyId,
+                    message_search_index::Column::Body,
+                ])
+                .to_owned(),
+        )
+        .exec_without_returning(db)
+        .await?;
+
+    Ok(())
+}
+
+/// Indexes messages stored before the search index existed. Messages are
+/// walked in id order one batch at a time, so every query stays bounded.
+pub async fn backfill<C>(db: &C) -> Result<u64, DbErr>
+where
+    C: ConnectionTrait,
+{
+    let mut indexed = 0;
+    let mut after: Option<Vec<u8>> = None;
+
+    loop {
+        let mut query = messages::Entity::find()
+            .order_by_asc(messages::Column::Id)
+            .limit(BACKFILL_BATCH_SIZE);
+        if let Some(last) = &after {
+            query = query.filter(messages::Column::Id.gt(last.clone()));
+        }
+        let batch = query.all(db).await?;
+        let Some(last) = batch.last() else {
+            break;
+        };
+        after = Some(last.id.clone());
+
+        let known: HashSet<Vec<u8>> = message_search_index::Entity::find()
+            .select_only()
+            .column(message_search_index::Column::MessageId)
+            .filter(
+                message_search_index::Column::MessageId
+                    .is_in(batch.iter().map(|message| message.id.clone())),
+            )
+            .into_tuple::<Vec<u8>>()
+            .all(db)
+            .await?
+            .into_iter()
+            .collect();
+        let missing: Vec<&messages::Model> = batch
+            .iter()
+            .filter(|message| !known.contains(&message.id))
+            .collect();
+
+        let companies = load_chat_companies(db, &missing).await?;
+        for message in &missing {
+            let company_id = companies.get(message.chat_id.as_str()).cloned();
+            index_message(db, company_id, message).await?;
+        }
+
+        indexed += missing.len() as u64;
+        if (batch.len() as u64) < BACKFILL_BATCH_SIZE {
+            break;
+        }
+    }
+
+    Ok(indexed)
+}
+
+/// Company of each chat, resolved through the channel that owns it.
+async fn load_chat_companies<C>(
+    db: &C,
+    batch: &[&messages::Model],
+) -> Result<HashMap<String, Vec<u8>>, DbErr>
+where
+    C: ConnectionTrait,
+{
+    if batch.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let chat_ids: HashSet<String> = batch
+        .iter()
+        .map(|message| message.chat_id.clone())
+        .collect();
+
+    Ok(chats::Entity::find()
+        .find_also_related(channels::Entity)
+        .filter(chats::Column::Id.is_in(chat_ids))
+        .all(db)
+        .await?
+        .into_iter()
+        .filter_map(|(chat, channel)| Some((chat.id, channel?.company_id?)))
+        .collect())
+}
+
+/// Messages of the company matching every term, best matches first.
+pub async fn search_messages<C>(
+    db: &C,
+    company_id: &[u8],
+    search: &MessageSearch,
+) -> Result<Vec<MessageMatch>, DbErr>
+where
+    C: ConnectionTrait,
+{
+    if search.terms.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut select = message_search_index::Entity::find()
+        .select_only()
+        .column(message_search_index::Column::MessageId)
+        .column(message_search_index::Column::ChatId)
+        .column(message_search_index::Column::Body)
+        .column(message_search_index::Column::CreatedAt)
+        .filter(message_search_index::Column::CompanyId.eq(company_id.to_vec()));
+
+    match db.get_database_backend() {
+        DbBackend::MySql => {
+            // Boolean mode: every term is required and matched as a prefix.
+            let against = search
+                .terms
+                .iter()
+                .map(|term| format!("+{}*", term))
+                .collect::<Vec<_>>()
+                .join(" ");
+            let matcher = "MATCH(`message_search_index`.`body`) AGAINST (? IN BOOLEAN MODE)";
+            select = select
+                .column_as(Expr::cust_with_values(matcher, [against.clone()]), "score")
+                .filter(Expr::cust_with_values(matcher, [against]));
+        }
+        DbBackend::Postgres => {
+            let tsquery = search
+                .terms
+                .iter()
+                .map(|term| format!("{}:*", term))
+                .collect::<Vec<_>>()
+                .join(" & ");
+            select = select
+                .column_as(
+                    Expr::cust_with_values(
+                        "CAST(ts_rank(to_tsvector('simple', \"message_search_index\".\"body\"), \
+                         to_tsquery('simple', $1)) AS DOUBLE PRECISION)",
+                        [tsquery.clone()],
+                    ),
+                    "score",
+                )
+                .filter(Expr::cust_with_values(
+                    "to_tsvector('simple', \"message_search_index\".\"body\") @@ to_tsquery('simple', $1)",
+                    [tsquery],
+                ));
+        }
+        _ => {
+            select = select.column_as(Expr::val(0.0_f64), "score");
+            for term in &search.terms {
+                select = select.filter(
+                    Expr::expr(Func::lower(Expr::col((
+                        message_search_index::Entity,
+                        message_search_index::Column::Body,
+                    ))))
+                    .like(contains_pattern(term)),
+                );
+            }
+        }
+    }
+
+    if let Some(channel_id) = &search.channel_id {
+        select = select
+            .join(
+                JoinType::InnerJoin,
+                message_search_index::Relation::Chats.def(),
+            )
+            .filter(chats::Column::ChannelId.eq(channel_id.clone()));
+    }
+    if let Some(owner) = &search.chat_owner {
+        select = select.filter(
+            Expr::col((
+                message_search_index::Entity,
+                message_search_index::Column::ChatId,
+            ))
+            .in_subquery(
+                Query::select()
+                    .column((chats::Entity, chats::Column::Id))
+                    .from(chats::Entity)
+                    .inner_join(
+                        clients::Entity,
+                        Expr::col((clients::Entity, clients::Column::Id))
+                            .equals((chats::Entity, chats::Column::ClientId)),
+                    )
+                    .and_where(
+                        Expr::col((clients::Entity, clients::Column::ResponsibleUserId))
+                            .eq(owner.clone()),
+                    )
+                    .to_owned(),
+            ),
+        );
+    }
+    if let Some(from) = search.from {
+        select = select.filter(message_search_index::Column::CreatedAt.gte(from));
+    }
+    if let Some(to) = search.to {
+        select = select.filter(message_search_index::Column::CreatedAt.lte(to));
+    }
+
+    select
+        .order_by(Expr::col(Alias::new("score")), Order::Desc)
+        .order_by_desc(message_search_index::Column::CreatedAt)
+        .offset(search.offset)
+        .limit(search.limit)
+        .into_model::<MessageMatch>()
+        .all(db)
+        .await
+}
+
+/// Cuts a window of `text` around the first matched term and wraps every
+/// term occurrence in `<mark>`. The text itself is HTML-escaped.
+pub fn highlight_snippet(text: &str, terms: &[String]) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let lower: Vec<char> = chars
+        .iter()
+        .map(|c| c.to_lowercase().next().unwrap_or(*c))
+        .collect();
+    let term_chars: Vec<Vec<char>> = terms.iter().map(|term| term.chars().collect()).collect();
+
+    let mut matches: Vec<(usize, usize)> = Vec::new();
+    let mut index = 0;
+    while index < lower.len() {
+        let found = term_chars
+            .iter()
+            .filter(|term| !term.is_empty() && lower[index..].starts_with(term))
+            .map(|term| term.len())
+            .max();
+        match found {
+            Some(len) => {
+                matches.push((index, index + len));
+                index += len;
+            }
+            None => index += 1,
+        }
+    }
+
+    let (start, end) = match matches.first() {
+        Some(&(first_start, _)) => (
+            first_start.saturating_sub(SNIPPET_CONTEXT_CHARS),
+            (first_start + SNIPPET_CONTEXT_CHARS * 2).min(chars.len()),
+        ),
+        None => (0, (SNIPPET_CONTEXT_CHARS * 2).min(chars.len())),
+    };
+
+    let mut snippet = String::new();
+    if start > 0 {
+        snippet.push('…');
+    }
+
+    let mut position = start;
+    for &(match_start, match_end) in matches
+        .iter()
+        .filter(|(match_start, match_end)| *match_start >= start && *match_end <= end)
+    {
+        push_escaped(&mut snippet, &chars[position..match_start]);
+        snippet.push_str(HIGHLIGHT_START);
+        push_escaped(&mut snippet, &chars[match_start..match_end]);
+        snippet.push_str(HIGHLIGHT_END);
+        position = match_end;
+    }
+    push_escaped(&mut snippet, &chars[position..end]);
+
+    if end < chars.len() {
+        snippet.push('…');
+    }
+    snippet
+}
+
+fn push_escaped(target: &mut String, chars: &[char]) {
+    for c in chars {
+        match c {
+            '&' => target.push_str("&amp;"),
+            '<' => target.push_str("&lt;"),
+            '>' => target.push_str("&gt;"),
+            '"' => target.push_str("&quot;"),
+            _ => target.push(*c),
+        }
+    }
+}
@@ -0,0 +1,328 @@
+//! API tokens.
+//!
+//! Only the SHA-256 hash of a token is stored in `tokens.token_hash`; the
+//! plain token is shown once when it is issued. A token belongs to a user and
+//! (when issued by this service) to one company of that user.
+
+use chrono::{Duration, Utc};
+use hmac::{Hmac, Mac};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, Condition, ConnectionTrait, DbErr, EntityTrait, QueryFilter,
+    Set, prelude::DateTimeUtc, sea_query::Expr,
+};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::{
+    database::models::{companies, company_users, tokens, users},
+    errors::AppError,
+    services::api_keys::ApiKeyVault,
+};
+
+pub const TOKEN_PREFIX: &str = "wzt_";
+pub const STREAM_TICKET_PREFIX: &str = "wzs_";
+/// Lifetime of a stream ticket; it only has to outlive opening the stream.
+pub const STREAM_TICKET_TTL_SECS: i64 = 60;
+pub const DEFAULT_TOKEN_TTL_DAYS: i64 = 90;
+pub const MAX_TOKEN_TTL_DAYS: i64 = 365;
+/// `last_used_at` is only written when it is older than this, so busy
+/// clients do not turn every request into a write.
+const LAST_USED_RESOLUTION_SECS: i64 = 60;
+
+lazy_static::lazy_static! {
+    /// Hash that logins of unknown users are verified against.
+    static ref DUMMY_PASSWORD_HASH: String =
+        bcrypt::hash(Uuid::new_v4().to_string(), bcrypt::DEFAULT_COST)
+            .expect("bcrypt hashes a random password");
+}
+
+/// Identity a request is bound to after its token has been validated.
+#[derive(Debug, Clone)]
+pub struct Principal {
+    pub token_id: Vec<u8>,
+    pub user_id: Vec<u8>,
+    pub company_id: Vec<u8>,
+    pub user: users::Model,
+    /// Role of the user in the company, falling back to the user's own role.
+    pub role: Option<String>,
+}
+
+pub struct IssuedToken {
+    pub token: String,
+    pub model: tokens::Model,
+}
+
+pub fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+fn generate_token() -> String {
+    format!(
+        "{}{}{}",
+        TOKEN_PREFIX,
+        Uuid::new_v4().simple(),
+        Uuid::new_v4().simple()
+    )
+}
+
+/// Creates a token for a member of the company.
+pub async fn issue_token<C>(
+    db: &C,
+    user_id: &[u8],
+    company_id: &[u8],
+    name: Option<String>,
+    ttl: Duration,
+) -> Result<IssuedToken, DbErr>
+where
+    C: ConnectionTrait,
+{
+    let token = generate_token();
+    let now = Utc::now();
+
+    let model = tokens::ActiveModel {
+        id: Set(Uuid::new_v4().as_bytes().to_vec()),
+        name: Set(name),
+        token_hash: Set(hash_token(&token)),
+        user_id: Set(user_id.to_vec()),
+        created_at: Set(now),
+        last_used_at: Set(now),
+        expires_at: Set(now + ttl),
+        company_id: Set(Some(company_id.to_vec())),
+    }
+    .insert(db)
+    .await?;
+
+    Ok(IssuedToken { token, model })
+}
+
+/// Resolves a bearer token to the user and company it acts for.
+///
+/// `requested_company` is the company addressed by the request. A token bound
+/// to a company may only address that company; an unbound token may address
+/// any company the user is a member of.
+pub async fn authenticate<C>(
+    db: &C,
+    token: &str,
+    requested_company: Option<&[u8]>,
+) -> Result<Principal, AppError>
+where
+    C: ConnectionTrait,
+{
+    let record = tokens::Entity::find()
+        .filter(tokens::Column::TokenHash.eq(hash_token(token)))
+        .one(db)
+        .await?
+        .ok_or_else(|| AppError::Unauthorized("Invalid token".to_string()))?;
+
+    principal_of(db, record, requested_company).await
+}
+
+/// Creates a stream ticket: a short-lived stand-in for the token of
+/// `principal` that may be put into the URL of an event stream, where
+/// `EventSource` cannot send headers.
+///
+/// The ticket names the token and the company and is signed with the ticket
+/// key of the vault, which never leaves the server. It needs no storage of its
+/// own and stops working when the token is revoked; neither a leaked ticket
+/// nor a leaked `tokens` row is enough to forge one.
+pub async fn issue_stream_ticket<C>(
+    db: &C,
+    vault: &ApiKeyVault,
+    principal: &Principal,
+) -> Result<(String, DateTimeUtc), AppError>
+where
+    C: ConnectionTrait,
+{
+    let record = tokens::Entity::find_by_id(principal.token_id.clone())
+        .one(db)
+        .await?
+        .ok_or_else(|| AppError::Unauthorized("Invalid token".to_string()))?;
+
+    let expires_at = Utc::now() + Duration::seconds(STREAM_TICKET_TTL_SECS);
+    let claims = format!(
+        "{}.{}.{}",
+        hex::encode(&record.id),
+        hex::encode(&principal.company_id),
+        expires_at.timestamp()
+    );
+    let signature = hex::encode(ticket_mac(vault, &record, &claims).finalize().into_bytes());
+
+    Ok((
+        format!("{}{}.{}", STREAM_TICKET_PREFIX, claims, signature),
+        expires_at,
+    ))
+}
+
+/// Resolves a stream ticket from [`issue_stream_ticket`] like a bearer token.
+pub async fn authenticate_stream_ticket<C>(
+    db: &C,
+    vault: &ApiKeyVault,
+    ticket: &str,
+    requested_company: Option<&[u8]>,
+) -> Result<Principal, AppError>
+where
+    C: ConnectionTrait,
+{
+    let invalid = || AppError::Unauthorized("Invalid stream ticket".to_string());
+
+    let (claims, signature) = ticket
+        .strip_prefix(STREAM_TICKET_PREFIX)
+        .and_then(|rest| rest.rsplit_once('.'))
+        .ok_or_else(invalid)?;
+    let mut parts = claims.split('.');
+    let (Some(token_id), Some(company_id), Some(expires_at), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return Err(invalid());
+    };
+    let token_id = hex::decode(token_id).map_err(|_| invalid())?;
+    let company_id = hex::decode(company_id).map_err(|_| invalid())?;
+    let signature = hex::decode(signature).map_err(|_| invalid())?;
+    let expires_at = expires_at.parse::<i64>().map_err(|_| invalid())?;
+
+    let record = tokens::Entity::find_by_id(token_id)
+        .one(db)
+        .await?
+        .ok_or_else(invalid)?;
+    ticket_mac(vault, &record, claims)
+        .verify_slice(&signature)
+        .map_err(|_| invalid())?;
+
+    if expires_at <= Utc::now().timestamp() {
+        return Err(AppError::Unauthorized("Stream ticket expired".to_string()));
+    }
+    if requested_company.is_some_and(|requested| requested != company_id.as_slice()) {
+        return Err(AppError::Forbidden(
+            "Stream ticket is not valid for this company".to_string(),
+        ));
+    }
+
+    principal_of(db, record, Some(&company_id)).await
+}
+
+fn ticket_mac(vault: &ApiKeyVault, record: &tokens::Model, claims: &str) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(vault.ticket_key())
+        .expect("HMAC accepts keys of any length");
+    mac.update(&record.id);
+    mac.update(claims.as_bytes());
+    mac
+}
+
+/// Checks that the token is still valid for the requested company, that the
+/// company is active and loads the identity it acts for.
+async fn principal_of<C>(
+    db: &C,
+    record: tokens::Model,
+    requested_company: Option<&[u8]>,
+) -> Result<Principal, AppError>
+where
+    C: ConnectionTrait,
+{
+    let now = Utc::now();
+    if record.expires_at <= now {
+        return Err(AppError::Unauthorized("Token expired".to_string()));
+    }
+
+    let company_id = match (&record.company_id, requested_company) {
+        (Some(bound), Some(requested)) if bound.as_slice() != requested => {
+            return Err(AppError::Forbidden(
+                "Token is not valid for this company".to_string(),
+            ));
+        }
+        (Some(bound), _) => bound.clone(),
+        (None, Some(requested)) => requested.to_vec(),
+        (None, None) => {
+            return Err(AppError::Unauthorized(
+                "Token is not bound to a company".to_string(),
+            ));
+        }
+    };
+
+    let membership =
+        company_users::Entity::find_by_id((company_id.clone(), record.user_id.clone()))
+            .one(db)
+            .await?
+            .ok_or_else(|| {
+                AppError::Forbidden("User is not a member of the company".to_string())
+            })?;
+
+    let company = companies::Entity::find_by_id(company_id.clone())
+        .one(db)
+        .await?;
+    if company.is_none_or(|company| company.is_active == Some(0)) {
+        return Err(AppError::Forbidden("Company is deactivated".to_string()));
+    }
+
+    let user = users::Entity::find_by_id(record.user_id.clone())
+        .one(db)
+        .await?
+        .ok_or_else(|| AppError::Unauthorized("Invalid token".to_string()))?;
+
+    touch(db, &record, now).await?;
+
+    Ok(Principal {
+        token_id: record.id,
+        user_id: record.user_id,
+        company_id,
+        role: membership.role.or_else(|| user.role.clone()),
+        user,
+    })
+}
+
+async fn touch<C>(db: &C, record: &tokens::Model, now: DateTimeUtc) -> Result<(), DbErr>
+where
+    C: ConnectionTrait,
+{
+    if now - record.last_used_at < Duration::seconds(LAST_USED_RESOLUTION_SECS) {
+        return Ok(());
+    }
+
+    tokens::Entity::update_many()
+        .col_expr(tokens::Column::LastUsedAt, Expr::value(now))
+        .filter(tokens::Column::Id.eq(record.id.clone()))
+        .exec(db)
+        .await?;
+
+    Ok(())
+}
+
+/// Checks login (or e-mail) and password of a user.
+pub async fn verify_credentials<C>(
+    db: &C,
+    login: &str,
+    password: &str,
+) -> Result<users::Model, AppError>
+where
+    C: ConnectionTrait,
+{
+    let invalid = || AppError::Unauthorized("Invalid login or password".to_string());
+
+    let user = users::Entity::find()
+        .filter(
+            Condition::any()
+                .add(users::Column::Login.eq(login))
+                .add(users::Column::Email.eq(login)),
+        )
+        .one(db)
+        .await?;
+
+    // bcrypt is deliberately slow; keep it off the async workers. Unknown
+    // logins are checked against a dummy hash so that they take as long as
+    // a wrong password and do not reveal which logins exist.
+    let password = password.to_string();
+    let hash = user.as_ref().map(|user| user.password.clone());
+    let verified = tokio::task::spawn_blocking(move || {
+        bcrypt::verify(password, hash.as_deref().unwrap_or(&DUMMY_PASSWORD_HASH))
+    })
+    .await
+    .map_err(|_| AppError::Internal)?;
+
+    match (user, verified) {
+        (Some(user), Ok(true)) => Ok(user),
+        (Some(_), Err(err)) => {
+            log::warn!("Unable to verify password hash of a user: {}", err);
+            Err(invalid())
+        }
+        _ => Err(invalid()),
+    }
+}
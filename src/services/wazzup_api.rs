@@ -1,29 +1,90 @@
-use crate::errors::AppError;
-use reqwest::{Client, Method};
+use std::time::Instant;
+
+use crate::{config::Config, errors::AppError, logging, metrics, telemetry};
+use reqwest::{Client, Method, RequestBuilder, Response, StatusCode};
 use serde::{Deserialize, Serialize, de::DeserializeOwned};
 use serde_json::Value;
 use utoipa::ToSchema;
 
 const WAZZUP_API_BASE_URL: &str = "https://api.wazzup24.com";
+const WAZZUP_TECH_BASE_URL: &str = "https://tech.wazzup24.com";
 
 #[derive(Clone)]
 pub struct WazzupApiService {
     client: Client,
     base_url: String,
+    /// Host of the channel management API (`tech.wazzup24.com`).
+    tech_url: String,
 }
 
 // Generic request helpers
 impl WazzupApiService {
-    pub fn new() -> Self {
+    /// Service using `WAZZUP_API_URL` / `WAZZUP_TECH_URL` when configured,
+    /// e.g. to point at a sandbox.
+    pub fn from_config(config: &Config) -> Self {
+        Self::with_base_urls(
+            config
+                .wazzup_api_url
+                .as_deref()
+                .unwrap_or(WAZZUP_API_BASE_URL),
+            config
+                .wazzup_tech_url
+                .as_deref()
+                .unwrap_or(WAZZUP_TECH_BASE_URL),
+        )
+    }
+
+    pub fn with_base_urls(api_url: &str, tech_url: &str) -> Self {
         Self {
             client: Client::builder()
                 .timeout(std::time::Duration::from_secs(15))
                 .build()
                 .unwrap(),
-            base_url: WAZZUP_API_BASE_URL.to_string(),
+            base_url: api_url.trim_end_matches('/').to_string(),
+            tech_url: tech_url.trim_end_matches('/').to_string(),
         }
     }
 
+    /// Sends the request and records it in the Wazzup API metrics.
+    async fn send(
+        &self,
+        method: &Method,
+        path: &str,
+        request_builder: RequestBuilder,
+    ) -> Result<Response, reqwest::Error> {
+        let started = Instant::now();
+        let (client, request) = request_builder.build_split();
+        let result = match request {
+            Ok(mut request) => {
+                let span = telemetry::ClientSpan::start(
+                    format!("wazzup {} {}", method, metrics::wazzup_endpoint(path)),
+                    method.as_str(),
+                    request.url(),
+                );
+                span.propagate(request.headers_mut());
+                let result = client.execute(request).await;
+                span.end(
+                    result
+                        .as_ref()
+                        .ok()
+                        .map(|response| response.status().as_u16()),
+                );
+                result
+            }
+            Err(err) => Err(err),
+        };
+        metrics::observe_wazzup(
+            method.as_str(),
+            path,
+            result
+                .as_ref()
+                .ok()
+                .map(|response| response.status().as_u16()),
+            started.elapsed(),
+        );
+        result
+    }
+
     async fn request<T: Serialize, R: DeserializeOwned>(
         &self,
         api_key: &str,
@@ -38,15 +99,14 @@ impl WazzupApiService {
             .bearer_auth(api_key);
 
         if let Some(body_data) = body {
-            let body_json = serde_json::to_string(body_data)
-                .unwrap_or_else(|_| "Failed to serialize body".to_string());
-            log::info!("Sending {} request to {}: {}", method, url, body_json);
+            log::info!("Sending {} request to {}", method, url);
+            log::debug!("Request body: {}", redacted_json(body_data));
             request_builder = request_builder.json(body_data);
         } else {
             log::info!("Sending {} request to {} (no body)", method, url);
         }
 
-        let response = match request_builder.send().await {
+        let response = match self.send(&method, path, request_builder).await {
             Ok(resp) => resp,
             Err(e) => {
                 log::error!("Failed to send request to {}: {}", url, e);
@@ -65,7 +125,7 @@ impl WazzupApiService {
                 "Wazzup API Error on path {}: {} - {}",
                 path,
                 status,
-                error_text
+                logging::redact_body(&error_text)
             );
 
             return Err(AppError::InvalidInput(format!(
@@ -91,20 +151,12 @@ impl WazzupApiService {
         path: &str,
         body: &T,
     ) -> Result<String, AppError> {
-        let url = format!("https://api.wazzup24.com{}", path);
+        let url = format!("{}{}", self.base_url, path);
         log::info!("Making webhook PATCH request to: {}", url);
-        log::info!(
-            "Request body: {:?}",
-            serde_json::to_string(body).unwrap_or_default()
-        );
+        log::debug!("Request body: {}", redacted_json(body));
 
-        let response = self
-            .client
-            .patch(&url)
-            .bearer_auth(api_key)
-            .json(body)
-            .send()
-            .await?;
+        let request_builder = self.client.patch(&url).bearer_auth(api_key).json(body);
+        let response = self.send(&Method::PATCH, path, request_builder).await?;
 
         if !response.status().is_success() {
             let status = response.status();
@@ -115,7 +167,7 @@ impl WazzupApiService {
             log::error!(
                 "Wazzup API Error on webhook patch: {} - {}",
                 status,
-                error_text
+                logging::redact_body(&error_text)
             );
             return Err(AppError::InvalidInput(format!(
                 "API request failed with status {}: {}",
@@ -128,7 +180,10 @@ impl WazzupApiService {
             response.status()
         );
         let response_text = response.text().await?;
-        log::info!("Webhook PATCH response body: {}", response_text);
+        log::debug!(
+            "Webhook PATCH response body: {}",
+            logging::redact_body(&response_text)
+        );
 
         Ok(response_text)
     }
@@ -141,8 +196,11 @@ impl WazzupApiService {
         path: &str,
         body: Option<&T>,
     ) -> Result<R, AppError> {
-        let url = format!("https://api.wazzup24.com{}", path);
-        let mut request_builder = self.client.request(method, &url).bearer_auth(api_key);
+        let url = format!("{}{}", self.base_url, path);
+        let mut request_builder = self
+            .client
+            .request(method.clone(), &url)
+            .bearer_auth(api_key);
 
         if let Some(body_data) = body {
             request_builder = request_builder.json(body_data);
@@ -150,13 +208,10 @@ impl WazzupApiService {
 
         log::info!("Making contacts API request to: {}", url);
         if let Some(body_data) = body {
-            log::info!(
-                "Request body: {:?}",
-                serde_json::to_string(body_data).unwrap_or_default()
-            );
+            log::debug!("Request body: {}", redacted_json(body_data));
         }
 
-        let response = request_builder.send().await?;
+        let response = self.send(&method, path, request_builder).await?;
 
         // Проверяем статус, потом обрабатываем тело.
         if !response.status().is_success() {
@@ -169,7 +224,7 @@ impl WazzupApiService {
                 "Wazzup Contacts API Error on path {}: {} - {}",
                 path,
                 status,
-                error_text
+                logging::redact_body(&error_text)
             );
             return Err(AppError::InvalidInput(format!(
                 "Contacts API request failed with status {}: {}",
@@ -184,14 +239,20 @@ impl WazzupApiService {
 
         // Получаем текст ответа для логирования
         let response_text = response.text().await?;
-        log::info!("Contacts API response body: {}", response_text);
+        log::debug!(
+            "Contacts API response body: {}",
+            logging::redact_body(&response_text)
+        );
 
         // Пробуем парсить JSON из текста
         match serde_json::from_str::<R>(&response_text) {
             Ok(result) => Ok(result),
             Err(e) => {
                 log::error!("Failed to parse response JSON: {}", e);
-                log::error!("Response text was: {}", response_text);
+                log::error!(
+                    "Response text was: {}",
+                    logging::redact_body(&response_text)
+                );
                 Err(AppError::InvalidInput(format!(
                     "Failed to parse API response: {}",
                     e
@@ -208,22 +269,21 @@ impl WazzupApiService {
         path: &str,
         body: Option<&T>,
     ) -> Result<R, AppError> {
-        let url = format!("https://tech.wazzup24.com{}", path);
+        let url = format!("{}{}", self.tech_url, path);
         let mut request_builder = self
             .client
             .request(method.clone(), &url)
             .bearer_auth(api_key);
 
         if let Some(body_data) = body {
-            let body_json = serde_json::to_string(body_data)
-                .unwrap_or_else(|_| "Failed to serialize body".to_string());
-            log::info!("Sending {} request to {}: {}", method, url, body_json);
+            log::info!("Sending {} request to {}", method, url);
+            log::debug!("Request body: {}", redacted_json(body_data));
             request_builder = request_builder.json(body_data);
         } else {
             log::info!("Sending {} request to {} (no body)", method, url);
         }
 
-        let response = match request_builder.send().await {
+        let response = match self.send(&method, path, request_builder).await {
             Ok(resp) => resp,
             Err(e) => {
                 log::error!("Failed to send request to {}: {}", url, e);
@@ -242,7 +302,7 @@ impl WazzupApiService {
                 "Wazzup Channels API Error on path {}: {} - {}",
                 path,
                 status,
-                error_text
+                logging::redact_body(&error_text)
             );
 
             return Err(AppError::InvalidInput(format!(
@@ -258,14 +318,20 @@ impl WazzupApiService {
 
         // Получаем текст ответа для логирования
         let response_text = response.text().await?;
-        log::info!("Channels API response body: {}", response_text);
+        log::debug!(
+            "Channels API response body: {}",
+            logging::redact_body(&response_text)
+        );
 
         // Пробуем парсить JSON из текста
         match serde_json::from_str::<R>(&response_text) {
             Ok(result) => Ok(result),
             Err(e) => {
                 log::error!("Failed to parse channels response JSON: {}", e);
-                log::error!("Response text was: {}", response_text);
+                log::error!(
+                    "Response text was: {}",
+                    logging::redact_body(&response_text)
+                );
                 Err(AppError::InvalidInput(format!(
                     "Failed to parse channels API response: {}",
                     e
@@ -275,6 +341,17 @@ impl WazzupApiService {
     }
 }
 
+/// Тело запроса для логов, без текста сообщений и контактных данных.
+fn redacted_json<T: Serialize>(body: &T) -> String {
+    match serde_json::to_value(body) {
+        Ok(mut value) => {
+            logging::redact_json(&mut value);
+            value.to_string()
+        }
+        Err(_) => "Failed to serialize body".to_string(),
+    }
+}
+
 // API method implementations
 impl WazzupApiService {
     pub async fn generate_channel_iframe_link(
@@ -455,8 +532,36 @@ impl WazzupApiService {
         .await
     }
 
+    // --- API key ---
+
+    /// Checks whether Wazzup accepts `api_key`.
+    ///
+    /// Returns `Ok(false)` when Wazzup rejects the key and an error when the
+    /// answer says nothing about the key (network failure, 5xx).
+    pub async fn check_api_key(&self, api_key: &str) -> Result<bool, AppError> {
+        let path = "/v3/channels";
+        let url = format!("{}{}", self.base_url, path);
+        let request_builder = self.client.get(&url).bearer_auth(api_key);
+        let response = self.send(&Method::GET, path, request_builder).await?;
+
+        match response.status() {
+            status if status.is_success() => Ok(true),
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Ok(false),
+            status => Err(AppError::ExternalApiError(format!(
+                "Wazzup API responded with status {} while checking the API key",
+                status
+            ))),
+        }
+    }
+
     // --- Webhooks ---
 
+    /// Current webhook settings of the account behind `api_key`.
+    pub async fn get_webhooks(&self, api_key: &str) -> Result<WebhookSettings, AppError> {
+        self.request(api_key, Method::GET, "/v3/webhooks", None::<&()>)
+            .await
+    }
+
     pub async fn connect_webhooks(
         &self,
         api_key: &str,
@@ -650,6 +755,15 @@ pub struct WebhookSubscriptions {
     pub template_status: bool,
 }
 
+/// Answer of `GET /v3/webhooks`; `webhooksUri` is missing when no webhooks
+/// are set.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookSettings {
+    pub webhooks_uri: Option<String>,
+    pub subscriptions: Option<WebhookSubscriptions>,
+}
+
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct WebhookSubscriptionResponse {
     pub ok: bool,
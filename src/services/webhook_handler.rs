@@ -1,5 +1,8 @@
 use chrono::{DateTime, Utc};
-use sea_orm::{ActiveModelTrait, DatabaseConnection, EntityTrait, IntoActiveModel, Set};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, IntoActiveModel, JoinType,
+    QueryFilter, QuerySelect, RelationTrait, Set,
+};
 use serde::{Deserialize, Serialize};
 use serde_json::{Value, json};
 use utoipa::ToSchema;
@@ -8,9 +11,13 @@ use uuid::Uuid;
 use crate::{
     api::helpers::uuid_to_bytes,
     api::validation,
-    database::models::{channels, chats, clients, companies, messages},
+    database::models::{channels, chats, clients, companies, company_users, messages},
     errors::AppError,
+    logging, metrics,
     services::bot_service::BotService,
+    services::events::{EventBus, EventKind, client_payload},
+    services::outgoing_webhooks,
+    services::search,
     services::wazzup_api::WazzupApiService,
 };
 
@@ -52,6 +59,24 @@ pub struct WebhookContactEvent {
     pub email: Option<String>,
     pub chat_id: Option<String>,
     pub channel_id: Option<String>,
+    /// Ответственный пользователь CRM; по умолчанию — первый пользователь компании
+    pub responsible_user_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookStatus {
+    pub message_id: String,
+    pub status: String,
+    pub timestamp: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookChannelUpdate {
+    pub channel_id: String,
+    pub state: Option<String>,
+    pub transport: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Serialize, ToSchema)]
@@ -59,7 +84,9 @@ pub struct WebhookContactEvent {
 pub struct WebhookRequest {
     pub test: Option<bool>,
     pub messages: Option<Vec<WebhookMessage>>,
+    pub statuses: Option<Vec<WebhookStatus>>,
     pub contacts: Option<Vec<WebhookContactEvent>>,
+    pub channels_updates: Option<Vec<WebhookChannelUpdate>>,
 }
 
 pub fn determine_message_direction(msg: &WebhookMessage) -> (bool, String) {
@@ -87,7 +114,7 @@ fn parse_uuid(value: &str) -> Result<Uuid, AppError> {
 ///
 /// UUID v5 гарантирует, что один и тот же input всегда даст один и тот же UUID,
 /// что важно для идемпотентности обработки webhook'ов.
-fn parse_flexible_uuid(value: &str) -> Uuid {
+pub(crate) fn parse_flexible_uuid(value: &str) -> Uuid {
     // Пытаемся парсить как UUID
     if let Ok(uuid) = Uuid::parse_str(value) {
         return uuid;
@@ -98,8 +125,33 @@ fn parse_flexible_uuid(value: &str) -> Uuid {
     Uuid::new_v5(&Uuid::NAMESPACE_DNS, value.as_bytes())
 }
 
-fn parse_uuid_bytes(value: &str) -> Result<Vec<u8>, AppError> {
-    Ok(uuid_to_bytes(&parse_uuid(value)?))
+/// Id хранимого чата Wazzup. UUID сохраняется как есть, остальные id (обычно
+/// номер телефона) выводятся из канала: с одним номером переписываются каналы
+/// разных компаний.
+pub(crate) fn chat_uuid(channel_uuid: &Uuid, chat_id: &str) -> Uuid {
+    Uuid::parse_str(chat_id).unwrap_or_else(|_| Uuid::new_v5(channel_uuid, chat_id.as_bytes()))
+}
+
+/// Id чата для сообщения канала. Чаты, созданные до привязки id к каналу,
+/// хранятся под [`parse_flexible_uuid`] и продолжают использоваться, если
+/// принадлежат тому же каналу.
+async fn resolve_chat_uuid(
+    db: &DatabaseConnection,
+    channel_uuid: &Uuid,
+    chat_id: &str,
+) -> Result<Uuid, AppError> {
+    let scoped = chat_uuid(channel_uuid, chat_id);
+    let legacy = parse_flexible_uuid(chat_id);
+    if legacy != scoped
+        && chats::Entity::find_by_id(legacy.to_string())
+            .filter(chats::Column::ChannelId.eq(uuid_to_bytes(channel_uuid)))
+            .one(db)
+            .await?
+            .is_some()
+    {
+        return Ok(legacy);
+    }
+    Ok(scoped)
 }
 
 fn parse_optional_uuid_bytes(value: Option<&String>) -> Option<Vec<u8>> {
@@ -141,26 +193,58 @@ fn build_message_content(message: &WebhookMessage) -> Value {
     Value::Array(parts)
 }
 
-async fn ensure_channel(
+/// Payload of `message.created` events.
+fn message_event_payload(message: &messages::Model, external_id: &str, channel_id: &str) -> Value {
+    let message_uuid = Uuid::from_slice(&message.id).unwrap_or_default();
+    let created_at: DateTime<Utc> = message.created_at;
+
+    json!({
+        "messageId": message_uuid.to_string(),
+        "externalId": external_id,
+        "chatId": message.chat_id,
+        "channelId": channel_id,
+        "isInbound": message.is_inbound == Some(1),
+        "status": message.direction_status,
+        "content": message.content,
+        "createdAt": created_at.to_rfc3339(),
+    })
+}
+
+/// Канал компании из вебхука. Владельца каналу назначает только синхронизация
+/// каналов (`sync_channels_to_db`): вебхук приходит без авторизации, поэтому
+/// данные неизвестных, ничьих и чужих каналов отклоняются. `transport`
+/// обновляет тип канала.
+async fn owned_channel(
     db: &DatabaseConnection,
+    company_bytes: &[u8],
     channel_bytes: Vec<u8>,
-    chat_type: &str,
+    transport: Option<&str>,
 ) -> Result<(), AppError> {
-    if let Some(existing) = channels::Entity::find_by_id(channel_bytes.clone())
-        .one(db)
-        .await?
-    {
-        if existing.r#type != chat_type {
-            let mut active = existing.into_active_model();
-            active.r#type = Set(chat_type.to_string());
-            active.update(db).await?;
+    let Some(existing) = channels::Entity::find_by_id(channel_bytes).one(db).await? else {
+        return Err(AppError::Forbidden(
+            "Channel is not synced for the company yet".to_string(),
+        ));
+    };
+    match existing.company_id.as_deref() {
+        Some(owner) if owner == company_bytes => {}
+        Some(_) => {
+            return Err(AppError::Forbidden(
+                "Channel belongs to another company".to_string(),
+            ));
         }
-    } else {
-        let record = channels::ActiveModel {
-            id: Set(channel_bytes),
-            r#type: Set(chat_type.to_string()),
-        };
-        record.insert(db).await?;
+        None => {
+            return Err(AppError::Forbidden(
+                "Channel is not synced for the company yet".to_string(),
+            ));
+        }
+    }
+
+    if let Some(transport) = transport
+        && existing.r#type != transport
+    {
+        let mut active = existing.into_active_model();
+        active.r#type = Set(transport.to_string());
+        active.update(db).await?;
     }
 
     Ok(())
@@ -179,6 +263,12 @@ async fn ensure_chat(
         .one(db)
         .await?
     {
+        if existing.channel_id != channel_bytes {
+            return Err(AppError::Forbidden(
+                "Chat belongs to another channel".to_string(),
+            ));
+        }
+
         let mut needs_update = false;
         let mut active = existing.clone().into_active_model();
 
@@ -237,9 +327,11 @@ async fn ensure_chat(
 /// Создаёт или обновляет клиента на основе данных из webhook сообщения
 async fn ensure_client_from_message(
     db: &DatabaseConnection,
-    company_bytes: &[u8],
+    company_uuid: &Uuid,
     message: &WebhookMessage,
+    events: &EventBus,
 ) -> Result<Option<Vec<u8>>, AppError> {
+    let company_bytes = uuid_to_bytes(company_uuid);
     use sea_orm::{ColumnTrait, QueryFilter};
 
     // Извлекаем информацию о клиенте
@@ -271,8 +363,9 @@ async fn ensure_client_from_message(
         }
     };
 
-    // Ищем существующего клиента по телефону
+    // Ищем существующего клиента компании по телефону
     if let Some(existing) = clients::Entity::find()
+        .filter(clients::Column::CompanyId.eq(company_bytes.clone()))
         .filter(clients::Column::Phone.eq(&sanitized_phone))
         .one(db)
         .await?
@@ -297,15 +390,7 @@ async fn ensure_client_from_message(
     let email = format!("{}@wazzup.local", client_uuid);
 
     // Нужен responsible_user_id - возьмём первого пользователя компании
-    use crate::database::models::company_users;
-
-    let responsible_user_id = if let Some(company_user) = company_users::Entity::find()
-        .filter(company_users::Column::CompanyId.eq(company_bytes))
-        .one(db)
-        .await?
-    {
-        company_user.user_id
-    } else {
+    let Some(responsible_user_id) = responsible_user(db, &company_bytes, None).await? else {
         log::warn!(
             "No users found for company, cannot create client without responsible_user_id"
         );
@@ -314,7 +399,7 @@ async fn ensure_client_from_message(
 
     let new_client = clients::ActiveModel {
         id: Set(client_id_bytes.clone()),
-        company_id: Set(Some(company_bytes.to_vec())),
+        company_id: Set(Some(company_bytes)),
         full_name: Set(full_name.clone()),
         email: Set(Some(email)),
         phone: Set(Some(sanitized_phone.clone())),
@@ -323,13 +408,22 @@ async fn ensure_client_from_message(
     };
 
     match new_client.insert(db).await {
-        Ok(_) => {
+        Ok(created) => {
             log::info!(
                 "Created new client: id={}, name={}, phone={}",
                 client_uuid,
                 full_name,
                 sanitized_phone
             );
+            outgoing_webhooks::publish(
+                db,
+                events,
+                company_uuid,
+                None,
+                EventKind::ClientCreated,
+                client_payload(&created),
+            )
+            .await;
             Ok(Some(client_id_bytes))
         }
         Err(err) => {
@@ -339,11 +433,35 @@ async fn ensure_client_from_message(
     }
 }
 
-async fn process_contact(
+/// `requested`, если это пользователь компании, иначе первый пользователь компании
+async fn responsible_user(
+    db: &DatabaseConnection,
     company_bytes: &[u8],
+    requested: Option<Vec<u8>>,
+) -> Result<Option<Vec<u8>>, AppError> {
+    use sea_orm::{ColumnTrait, QueryFilter};
+
+    let members =
+        company_users::Entity::find().filter(company_users::Column::CompanyId.eq(company_bytes));
+    if let Some(requested) = &requested
+        && let Some(member) = members
+            .clone()
+            .filter(company_users::Column::UserId.eq(requested.clone()))
+            .one(db)
+            .await?
+    {
+        return Ok(Some(member.user_id));
+    }
+    Ok(members.one(db).await?.map(|member| member.user_id))
+}
+
+async fn process_contact(
+    company_uuid: &Uuid,
     contact: WebhookContactEvent,
     db: &DatabaseConnection,
+    events: &EventBus,
 ) -> Result<(), AppError> {
+    let company_bytes = uuid_to_bytes(company_uuid);
     let contact_uuid = match parse_uuid(&contact.contact_id) {
         Ok(uuid) => uuid,
         Err(err) => {
@@ -357,10 +475,8 @@ async fn process_contact(
     };
 
     let id_bytes = uuid_to_bytes(&contact_uuid);
-    let email = contact
-        .email
-        .filter(|value| !value.trim().is_empty())
-        .unwrap_or_else(|| format!("{}@wazzup.local", contact_uuid));
+    let email = contact.email.filter(|value| !value.trim().is_empty());
+    let placeholder_email = || format!("{}@wazzup.local", contact_uuid);
 
     let sanitized_phone = contact
         .phone
@@ -370,27 +486,67 @@ async fn process_contact(
         .one(db)
         .await?
     {
+        // Вебхук не проверяет отправителя: клиента чужой компании не трогаем
+        if existing.company_id.as_deref() != Some(company_bytes.as_slice()) {
+            log::warn!(
+                "Skipping contact {} for company {}: it belongs to another company",
+                contact_uuid,
+                company_uuid
+            );
+            return Ok(());
+        }
+        // Контакты из API Wazzup приходят без e-mail, а иногда и без телефона:
+        // сохранённые значения не затираем
+        let email = email.or(existing.email.clone());
+        let phone = sanitized_phone.or(existing.phone.clone());
         let mut active = existing.into_active_model();
         active.full_name = Set(contact
             .name
             .unwrap_or_else(|| "Unnamed contact".to_string()));
-        active.email = Set(Some(email));
-        active.phone = Set(sanitized_phone);
-        active.company_id = Set(Some(company_bytes.to_vec()));
-        active.update(db).await?;
+        active.email = Set(Some(email.unwrap_or_else(placeholder_email)));
+        active.phone = Set(phone);
+        let updated = active.update(db).await?;
+        outgoing_webhooks::publish(
+            db,
+            events,
+            company_uuid,
+            None,
+            EventKind::ClientUpdated,
+            client_payload(&updated),
+        )
+        .await;
     } else {
+        let requested = parse_optional_uuid_bytes(contact.responsible_user_id.as_ref());
+        let Some(responsible_user_id) = responsible_user(db, &company_bytes, requested).await?
+        else {
+            log::warn!(
+                "No users found for company {}, skipping contact {}",
+                company_uuid,
+                contact_uuid
+            );
+            return Ok(());
+        };
         let record = clients::ActiveModel {
             id: Set(id_bytes),
-            company_id: Set(Some(company_bytes.to_vec())),
+            company_id: Set(Some(company_bytes)),
             full_name: Set(contact
                 .name
                 .unwrap_or_else(|| "Unnamed contact".to_string())),
-            email: Set(Some(email)),
+            email: Set(Some(email.unwrap_or_else(placeholder_email))),
             phone: Set(sanitized_phone),
-            responsible_user_id: Set(Uuid::nil().as_bytes().to_vec()),
+            responsible_user_id: Set(responsible_user_id),
             created_at: Set(Utc::now().into()),
         };
-        record.insert(db).await?;
+        let created = record.insert(db).await?;
+        outgoing_webhooks::publish(
+            db,
+            events,
+            company_uuid,
+            None,
+            EventKind::ClientCreated,
+            client_payload(&created),
+        )
+        .await;
     }
 
     Ok(())
@@ -400,8 +556,8 @@ async fn handle_contacts(
     company_uuid: &Uuid,
     contacts: Vec<WebhookContactEvent>,
     db: &DatabaseConnection,
+    events: &EventBus,
 ) -> Result<(), AppError> {
-    let company_bytes = uuid_to_bytes(company_uuid);
     let total = contacts.len();
     log::info!(
         "Processing {} contact(s) for company {}",
@@ -411,7 +567,7 @@ async fn handle_contacts(
 
     for (idx, contact) in contacts.into_iter().enumerate() {
         let contact_id = contact.contact_id.clone();
-        if let Err(err) = process_contact(&company_bytes, contact, db).await {
+        if let Err(err) = process_contact(company_uuid, contact, db, events).await {
             log::error!(
                 "Failed to process contact #{} (id={}) for company {}: {}",
                 idx + 1,
@@ -431,6 +587,7 @@ async fn process_message(
     db: &DatabaseConnection,
     bot_service: &BotService,
     wazzup_api: &WazzupApiService,
+    events: &EventBus,
 ) -> Result<(), AppError> {
     let _ = (bot_service, wazzup_api);
 
@@ -442,14 +599,21 @@ async fn process_message(
     );
 
     // Channel ID должен быть UUID
-    let channel_bytes = parse_uuid_bytes(&message.channel_id).map_err(|e| {
+    let channel_uuid = parse_uuid(&message.channel_id).map_err(|e| {
         log::error!("Invalid channel_id '{}': {}", message.channel_id, e);
         e
     })?;
-    ensure_channel(db, channel_bytes.clone(), &message.chat_type).await?;
+    let channel_bytes = uuid_to_bytes(&channel_uuid);
+    owned_channel(
+        db,
+        &uuid_to_bytes(company_uuid),
+        channel_bytes.clone(),
+        Some(&message.chat_type),
+    )
+    .await?;
 
-    // Chat ID может быть числом или UUID - используем гибкий парсинг
-    let chat_uuid = parse_flexible_uuid(&message.chat_id);
+    // Chat ID может быть числом или UUID
+    let chat_uuid = resolve_chat_uuid(db, &channel_uuid, &message.chat_id).await?;
     log::debug!(
         "Chat ID '{}' converted to UUID: {}",
         message.chat_id,
@@ -457,8 +621,7 @@ async fn process_message(
     );
 
     // Создаём или находим клиента из сообщения
-    let client_id = ensure_client_from_message(db, uuid_to_bytes(company_uuid).as_slice(), &message)
-        .await?;
+    let client_id = ensure_client_from_message(db, company_uuid, &message, events).await?;
 
     ensure_chat(
         db,
@@ -478,12 +641,23 @@ async fn process_message(
     );
 
     let message_bytes = uuid_to_bytes(&message_uuid);
+    if let Some(existing) =
+        find_company_message(db, &uuid_to_bytes(company_uuid), message_bytes.clone()).await?
+    {
+        // Повторная доставка сообщения может нести новый статус
+        if let Some(status) = message.status.as_deref() {
+            update_message_status(company_uuid, existing, status, db, events).await?;
+        }
+        return Ok(());
+    }
     if messages::Entity::find_by_id(message_bytes.clone())
         .one(db)
         .await?
         .is_some()
     {
-        return Ok(());
+        return Err(AppError::Forbidden(
+            "Message belongs to another company".to_string(),
+        ));
     }
 
     let (is_inbound, direction_status) = determine_message_direction(&message);
@@ -501,13 +675,189 @@ async fn process_message(
         created_at: Set(created_at.into()),
     };
 
-    if let Err(err) = record.insert(db).await {
-        log::error!(
-            "Failed to store message {} for company {}: {}",
-            message_uuid,
+    match record.insert(db).await {
+        Ok(stored) => {
+            if let Err(err) =
+                search::index_message(db, Some(uuid_to_bytes(company_uuid)), &stored).await
+            {
+                log::warn!(
+                    "Failed to index message {} for search: {}",
+                    message_uuid,
+                    err
+                );
+            }
+            outgoing_webhooks::publish(
+                db,
+                events,
+                company_uuid,
+                Some(stored.chat_id.clone()),
+                EventKind::MessageCreated,
+                message_event_payload(&stored, &message.message_id, &message.channel_id),
+            )
+            .await;
+        }
+        Err(err) => {
+            log::error!(
+                "Failed to store message {} for company {}: {}",
+                message_uuid,
+                company_uuid,
+                err
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Сообщение из чата канала компании; сообщения других компаний не находятся
+async fn find_company_message(
+    db: &DatabaseConnection,
+    company_bytes: &[u8],
+    message_bytes: Vec<u8>,
+) -> Result<Option<messages::Model>, AppError> {
+    Ok(messages::Entity::find_by_id(message_bytes)
+        .inner_join(chats::Entity)
+        .join(JoinType::InnerJoin, chats::Relation::Channels.def())
+        .filter(channels::Column::CompanyId.eq(company_bytes))
+        .one(db)
+        .await?)
+}
+
+/// Сохраняет новый статус исходящего сообщения и публикует событие
+async fn update_message_status(
+    company_uuid: &Uuid,
+    existing: messages::Model,
+    status: &str,
+    db: &DatabaseConnection,
+    events: &EventBus,
+) -> Result<(), AppError> {
+    let status = status.trim();
+    if status.is_empty()
+        || existing.is_inbound == Some(1)
+        || existing.direction_status.as_deref() == Some(status)
+    {
+        return Ok(());
+    }
+
+    let message_uuid = Uuid::from_slice(&existing.id).unwrap_or_default();
+    let chat_id = existing.chat_id.clone();
+    let mut active = existing.into_active_model();
+    active.direction_status = Set(Some(status.to_string()));
+    active.update(db).await?;
+
+    outgoing_webhooks::publish(
+        db,
+        events,
+        company_uuid,
+        Some(chat_id.clone()),
+        EventKind::MessageStatusChanged,
+        json!({
+            "messageId": message_uuid.to_string(),
+            "chatId": chat_id,
+            "status": status,
+        }),
+    )
+    .await;
+
+    Ok(())
+}
+
+async fn handle_statuses(
+    company_uuid: &Uuid,
+    statuses: Vec<WebhookStatus>,
+    db: &DatabaseConnection,
+    events: &EventBus,
+) -> Result<(), AppError> {
+    log::info!(
+        "Processing {} status update(s) for company {}",
+        statuses.len(),
+        company_uuid
+    );
+
+    let company_bytes = uuid_to_bytes(company_uuid);
+    for update in statuses {
+        let message_uuid = parse_flexible_uuid(&update.message_id);
+        let existing =
+            match find_company_message(db, &company_bytes, uuid_to_bytes(&message_uuid)).await? {
+                Some(existing) => existing,
+                None => {
+                    log::warn!(
+                        "Skipping status update for message {} not found in company {}",
+                        update.message_id,
+                        company_uuid
+                    );
+                    continue;
+                }
+            };
+
+        if let Err(err) =
+            update_message_status(company_uuid, existing, &update.status, db, events).await
+        {
+            log::error!(
+                "Failed to update status of message {} for company {}: {}",
+                update.message_id,
+                company_uuid,
+                err
+            );
+        }
+    }
+
+    Ok(())
+}
+
+async fn handle_channel_updates(
+    company_uuid: &Uuid,
+    updates: Vec<WebhookChannelUpdate>,
+    db: &DatabaseConnection,
+    events: &EventBus,
+) -> Result<(), AppError> {
+    let company_bytes = uuid_to_bytes(company_uuid);
+    for update in updates {
+        let channel_uuid = match parse_uuid(&update.channel_id) {
+            Ok(uuid) => uuid,
+            Err(_) => {
+                log::warn!(
+                    "Skipping channel update with invalid ID {}",
+                    update.channel_id
+                );
+                continue;
+            }
+        };
+
+        match owned_channel(
+            db,
+            &company_bytes,
+            uuid_to_bytes(&channel_uuid),
+            update.transport.as_deref(),
+        )
+        .await
+        {
+            Ok(()) => {}
+            Err(AppError::Forbidden(reason)) => {
+                log::warn!(
+                    "Skipping update of channel {} for company {}: {}",
+                    channel_uuid,
+                    company_uuid,
+                    reason
+                );
+                continue;
+            }
+            Err(err) => return Err(err),
+        }
+
+        outgoing_webhooks::publish(
+            db,
+            events,
             company_uuid,
-            err
-        );
+            None,
+            EventKind::ChannelStateChanged,
+            json!({
+                "channelId": channel_uuid.to_string(),
+                "state": update.state,
+                "transport": update.transport,
+            }),
+        )
+        .await;
     }
 
     Ok(())
@@ -519,6 +869,7 @@ async fn handle_messages(
     db: &DatabaseConnection,
     bot_service: &BotService,
     wazzup_api: &WazzupApiService,
+    events: &EventBus,
 ) -> Result<(), AppError> {
     let total = messages.len();
     log::info!(
@@ -531,8 +882,10 @@ async fn handle_messages(
         let msg_id = message.message_id.clone();
         log::debug!("Processing message {}/{}: id={}", idx + 1, total, msg_id);
 
-        if let Err(err) = process_message(company_uuid, message, db, bot_service, wazzup_api).await
-        {
+        let result =
+            process_message(company_uuid, message, db, bot_service, wazzup_api, events).await;
+        metrics::record_webhook_message(company_uuid, result.is_ok());
+        if let Err(err) = result {
             log::error!(
                 "Failed to process message #{} (id={}) for company {}: {}",
                 idx + 1,
@@ -552,7 +905,9 @@ pub async fn handle_webhook(
     db: &DatabaseConnection,
     bot_service: &BotService,
     wazzup_api: &WazzupApiService,
+    events: &EventBus,
 ) -> Result<(), AppError> {
+    logging::set_company_id(company_uuid);
     let company_bytes = uuid_to_bytes(&company_uuid);
     let company = companies::Entity::find_by_id(company_bytes)
         .one(db)
@@ -564,17 +919,42 @@ pub async fn handle_webhook(
         return Ok(());
     }
 
+    let mut company = company.into_active_model();
+    company.last_webhook_at = Set(Some(Utc::now()));
+    company.update(db).await?;
+
     if webhook.test == Some(true) {
         log::info!("Test webhook received for company {}", company_uuid);
         return Ok(());
     }
 
+    process_webhook(company_uuid, webhook, db, bot_service, wazzup_api, events).await
+}
+
+/// Обрабатывает содержимое вебхука, не проверяя компанию и не отмечая время
+/// получения. Используется для повторной обработки и загрузки истории из CLI.
+pub async fn process_webhook(
+    company_uuid: Uuid,
+    webhook: WebhookRequest,
+    db: &DatabaseConnection,
+    bot_service: &BotService,
+    wazzup_api: &WazzupApiService,
+    events: &EventBus,
+) -> Result<(), AppError> {
     if let Some(contacts) = webhook.contacts {
-        handle_contacts(&company_uuid, contacts, db).await?;
+        handle_contacts(&company_uuid, contacts, db, events).await?;
     }
 
     if let Some(messages) = webhook.messages {
-        handle_messages(&company_uuid, messages, db, bot_service, wazzup_api).await?;
+        handle_messages(&company_uuid, messages, db, bot_service, wazzup_api, events).await?;
+    }
+
+    if let Some(statuses) = webhook.statuses {
+        handle_statuses(&company_uuid, statuses, db, events).await?;
+    }
+
+    if let Some(updates) = webhook.channels_updates {
+        handle_channel_updates(&company_uuid, updates, db, events).await?;
     }
 
     Ok(())
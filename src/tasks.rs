@@ -0,0 +1,119 @@
+//! Maintenance subcommands; the work itself is in
+//! [`crate::services::maintenance`].
+
+use std::io;
+
+use chrono::{Duration, Utc};
+
+use crate::{
+    api::{diagnostics::company_diagnostics, webhooks::configured_webhook_uri},
+    app_state::AppState,
+    cli::Command,
+    errors::AppError,
+    services::{maintenance, search},
+};
+
+/// Runs a command for which [`Command::is_task`] is true.
+pub async fn run(command: Command, app_state: AppState) -> io::Result<()> {
+    let to_io = |err: AppError| io::Error::other(err.to_string());
+
+    match command {
+        Command::ConnectWebhooks {
+            company: Some(company),
+            ..
+        } => {
+            let uri = maintenance::connect_webhooks(&app_state, &company)
+                .await
+                .map_err(to_io)?;
+            log::info!("Webhooks of company {} connected to {}", company, uri);
+        }
+        Command::ConnectWebhooks { company: None, .. } => {
+            let results = maintenance::connect_all_webhooks(&app_state)
+                .await
+                .map_err(to_io)?;
+            let mut failed = 0;
+            for (company, result) in &results {
+                match result {
+                    Ok(uri) => log::info!("Webhooks of company {} connected to {}", company, uri),
+                    Err(err) => {
+                        failed += 1;
+                        log::error!("Webhooks of company {} not connected: {}", company, err);
+                    }
+                }
+            }
+            log::info!(
+                "Connected webhooks of {} of {} companies",
+                results.len() - failed,
+                results.len()
+            );
+            if failed > 0 {
+                return Err(io::Error::other(format!(
+                    "{} company(ies) could not be connected",
+                    failed
+                )));
+            }
+        }
+        Command::Sync { company } => {
+            let report = maintenance::sync_company(&app_state, &company)
+                .await
+                .map_err(to_io)?;
+            log::info!(
+                "Synced {} channel(s) and {} contact(s) of company {}",
+                report.channels,
+                report.contacts,
+                company
+            );
+        }
+        Command::ReplayWebhook { company, file } => {
+            maintenance::replay_webhook(&app_state, &company, &file)
+                .await
+                .map_err(to_io)?;
+            log::info!("Replayed {} for company {}", file.display(), company);
+        }
+        Command::BackfillMessages { company, chat } => {
+            let report = maintenance::backfill_chat(&app_state, &company, &chat)
+                .await
+                .map_err(to_io)?;
+            log::info!(
+                "Fetched {} message(s) of chat {}, {} new",
+                report.fetched,
+                chat,
+                report.stored
+            );
+        }
+        Command::IndexMessages => {
+            let indexed = search::backfill(&app_state.db)
+                .await
+                .map_err(|err| to_io(err.into()))?;
+            log::info!("Indexed {} message(s) for search", indexed);
+        }
+        Command::Purge {
+            older_than_days,
+            messages,
+            dry_run,
+        } => {
+            let cutoff = Utc::now() - Duration::days(i64::from(older_than_days));
+            let report = maintenance::purge(&app_state.db, cutoff, messages, dry_run)
+                .await
+                .map_err(to_io)?;
+            log::info!(
+                "{}{} webhook delivery(ies), {} job(s), {} expired token(s) and {} message(s) older than {} deleted",
+                if dry_run { "Dry run: " } else { "" },
+                report.deliveries,
+                report.jobs,
+                report.tokens,
+                report.messages,
+                cutoff.to_rfc3339()
+            );
+        }
+        Command::Diagnose { company } => {
+            let expected_uri = configured_webhook_uri(&app_state.config, &company);
+            let report = company_diagnostics(&app_state, &company, expected_uri)
+                .await
+                .map_err(to_io)?;
+            println!("{}", serde_json::to_string_pretty(&report)?);
+        }
+        _ => unreachable!("{:?} is not a maintenance task", command),
+    }
+    Ok(())
+}
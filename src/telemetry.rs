@@ -0,0 +1,237 @@
+//! OpenTelemetry tracing.
+//!
+//! Every request gets a server span (continuing an incoming `traceparent`),
+//! calls to Wazzup and bot hooks get client spans, and database queries made
+//! while a request is traced get a span each. Spans are exported over
+//! OTLP/HTTP to `OTEL_EXPORTER_OTLP_ENDPOINT`, e.g. a local collector on
+//! `http://localhost:4318`; without it spans are created but dropped.
+//!
+//! Outgoing calls also carry the request id in `X-Request-Id`, so a request
+//! can be followed in the logs of downstream services without a tracing
+//! backend.
+
+use std::{collections::HashMap, time::SystemTime};
+
+use actix_web::http::header::HeaderMap;
+use opentelemetry::{
+    Context, KeyValue, global,
+    propagation::{Extractor, TextMapPropagator},
+    trace::{Span, SpanKind, Status, TraceContextExt, Tracer},
+};
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::{Resource, propagation::TraceContextPropagator, trace::SdkTracerProvider};
+use reqwest::{
+    Url,
+    header::{HeaderName, HeaderValue},
+};
+use sea_orm::{DatabaseBackend, metric};
+
+use crate::{config::Config, logging};
+
+const TRACER_NAME: &str = "wazzup";
+const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Keeps the exporter alive; [`Telemetry::shutdown`] flushes pending spans.
+pub struct Telemetry {
+    provider: Option<SdkTracerProvider>,
+}
+
+impl Telemetry {
+    pub fn shutdown(self) {
+        if let Some(provider) = self.provider
+            && let Err(err) = provider.shutdown()
+        {
+            log::warn!("Failed to flush traces: {}", err);
+        }
+    }
+}
+
+/// Installs the W3C trace context propagator and, when an OTLP endpoint is
+/// configured, the exporting tracer provider.
+pub fn init(config: &Config) -> Telemetry {
+    global::set_text_map_propagator(TraceContextPropagator::new());
+
+    let Some(endpoint) = config.otel_exporter_otlp_endpoint.as_deref() else {
+        return Telemetry { provider: None };
+    };
+
+    let exporter = match opentelemetry_otlp::SpanExporter::builder()
+        .with_http()
+        .with_endpoint(format!("{}/v1/traces", endpoint.trim_end_matches('/')))
+        .build()
+    {
+        Ok(exporter) => exporter,
+        Err(err) => {
+            log::error!("Tracing disabled, OTLP exporter failed to start: {}", err);
+            return Telemetry { provider: None };
+        }
+    };
+
+    let provider = SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(
+            Resource::builder()
+                .with_service_name(config.otel_service_name().to_string())
+                .build(),
+        )
+        .build();
+    global::set_tracer_provider(provider.clone());
+    log::info!("Exporting traces to {}", endpoint);
+
+    Telemetry {
+        provider: Some(provider),
+    }
+}
+
+/// Span of a request served by one of the listeners.
+pub struct ServerSpan {
+    cx: Context,
+}
+
+impl ServerSpan {
+    /// `route` is the matched route pattern, so span names stay bounded.
+    pub fn start(headers: &HeaderMap, method: &str, route: &str, request_id: &str) -> Self {
+        let parent =
+            TraceContextPropagator::new().extract_with_context(&Context::new(), &Headers(headers));
+        let tracer = global::tracer(TRACER_NAME);
+        let span = tracer
+            .span_builder(format!("{} {}", method, route))
+            .with_kind(SpanKind::Server)
+            .with_attributes([
+                KeyValue::new("http.request.method", method.to_string()),
+                KeyValue::new("http.route", route.to_string()),
+                KeyValue::new("request_id", request_id.to_string()),
+            ])
+            .start_with_context(&tracer, &parent);
+
+        Self {
+            cx: parent.with_span(span),
+        }
+    }
+
+    /// Context to run the handler in, so nested spans become children.
+    pub fn context(&self) -> Context {
+        self.cx.clone()
+    }
+
+    pub fn end(self, status: u16) {
+        let span = self.cx.span();
+        span.set_attribute(KeyValue::new("http.response.status_code", status as i64));
+        if status >= 500 {
+            span.set_status(Status::error(format!("HTTP {}", status)));
+        }
+        span.end();
+    }
+}
+
+/// Span of a call to an external HTTP service.
+pub struct ClientSpan {
+    cx: Context,
+}
+
+impl ClientSpan {
+    /// `name` must not contain ids, e.g. `wazzup GET /v3/contacts/{param}`.
+    /// Only the host of `url` is recorded: hook URLs may carry secrets.
+    pub fn start(name: String, method: &str, url: &Url) -> Self {
+        let parent = Context::current();
+        let tracer = global::tracer(TRACER_NAME);
+        let mut attributes = vec![KeyValue::new("http.request.method", method.to_string())];
+        if let Some(host) = url.host_str() {
+            attributes.push(KeyValue::new("server.address", host.to_string()));
+        }
+        let span = tracer
+            .span_builder(name)
+            .with_kind(SpanKind::Client)
+            .with_attributes(attributes)
+            .start_with_context(&tracer, &parent);
+
+        Self {
+            cx: parent.with_span(span),
+        }
+    }
+
+    /// Adds `traceparent` and the current request id to outgoing headers.
+    pub fn propagate(&self, headers: &mut reqwest::header::HeaderMap) {
+        let mut injected = HashMap::new();
+        TraceContextPropagator::new().inject_context(&self.cx, &mut injected);
+        if let Some(request_id) = logging::current_request_id() {
+            injected.insert(REQUEST_ID_HEADER.to_string(), request_id);
+        }
+        for (name, value) in injected {
+            if let (Ok(name), Ok(value)) = (
+                HeaderName::from_bytes(name.as_bytes()),
+                HeaderValue::from_str(&value),
+            ) {
+                headers.insert(name, value);
+            }
+        }
+    }
+
+    /// `status` is `None` when no response arrived.
+    pub fn end(self, status: Option<u16>) {
+        let span = self.cx.span();
+        match status {
+            Some(status) => {
+                span.set_attribute(KeyValue::new("http.response.status_code", status as i64));
+                if status >= 400 {
+                    span.set_status(Status::error(format!("HTTP {}", status)));
+                }
+            }
+            None => span.set_status(Status::error("no response")),
+        }
+        span.end();
+    }
+}
+
+/// Database metric callback: records a finished query as a span of the
+/// current request. Queries outside traced requests (background jobs) are
+/// skipped.
+pub fn record_db_query(info: &metric::Info<'_>) {
+    let cx = Context::current();
+    if !cx.has_active_span() {
+        return;
+    }
+
+    let end = SystemTime::now();
+    let start = end.checked_sub(info.elapsed).unwrap_or(end);
+    let sql = &info.statement.sql;
+    let operation = sql
+        .split_whitespace()
+        .next()
+        .unwrap_or("QUERY")
+        .to_ascii_uppercase();
+    let system = match info.statement.db_backend {
+        DatabaseBackend::MySql => "mysql",
+        DatabaseBackend::Postgres => "postgresql",
+        DatabaseBackend::Sqlite => "sqlite",
+    };
+
+    let tracer = global::tracer(TRACER_NAME);
+    // Statements are parameterized: values are bound separately and never recorded.
+    let mut span = tracer
+        .span_builder(operation.clone())
+        .with_kind(SpanKind::Client)
+        .with_start_time(start)
+        .with_attributes([
+            KeyValue::new("db.system.name", system),
+            KeyValue::new("db.operation.name", operation),
+            KeyValue::new("db.query.text", sql.clone()),
+        ])
+        .start_with_context(&tracer, &cx);
+    if info.failed {
+        span.set_status(Status::error("query failed"));
+    }
+    span.end_with_timestamp(end);
+}
+
+struct Headers<'a>(&'a HeaderMap);
+
+impl Extractor for Headers<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|name| name.as_str()).collect()
+    }
+}
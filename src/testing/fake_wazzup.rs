@@ -0,0 +1,140 @@
+//! In-process stand-in for the Wazzup HTTP APIs.
+//!
+//! Every request is recorded. Unstubbed requests are answered with `200 {}`,
+//! which is enough for calls whose response is ignored; stub the others with
+//! [`FakeWazzup::respond`].
+
+use std::{
+    collections::HashMap,
+    net::TcpListener,
+    sync::{Arc, Mutex},
+};
+
+use actix_web::{App, HttpRequest, HttpResponse, HttpServer, http::StatusCode, web};
+use serde_json::{Value, json};
+
+use crate::services::wazzup_api::WazzupApiService;
+
+/// A request the service sent to Wazzup.
+#[derive(Debug, Clone)]
+pub struct RecordedRequest {
+    pub method: String,
+    /// Path with the query string, e.g. `/v3/contacts?offset=0`.
+    pub path: String,
+    pub authorization: Option<String>,
+    /// `X-Request-Id` of the request being served when the call was made.
+    pub request_id: Option<String>,
+    /// JSON body, `Null` when the request had none.
+    pub body: Value,
+}
+
+#[derive(Default)]
+struct FakeState {
+    requests: Vec<RecordedRequest>,
+    responses: HashMap<(String, String), (u16, Value)>,
+}
+
+/// Serves both the main and the channel (`tech`) API on one local port.
+#[derive(Clone)]
+pub struct FakeWazzup {
+    url: String,
+    state: Arc<Mutex<FakeState>>,
+}
+
+impl FakeWazzup {
+    /// Starts the server on a free local port; it runs until the test's
+    /// actix system stops.
+    pub async fn start() -> Self {
+        let state = Arc::new(Mutex::new(FakeState::default()));
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+
+        let shared = web::Data::from(state.clone());
+        let server = HttpServer::new(move || {
+            App::new()
+                .app_data(shared.clone())
+                .default_service(web::to(handle))
+        })
+        .workers(1)
+        .disable_signals()
+        .listen(listener)
+        .unwrap()
+        .run();
+        actix_web::rt::spawn(server);
+
+        Self { url, state }
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Client of this server for [`crate::app_state::AppState::wazzup_api`].
+    pub fn service(&self) -> WazzupApiService {
+        WazzupApiService::with_base_urls(&self.url, &self.url)
+    }
+
+    /// Answers `method path` with `status` and `body` from now on. `path`
+    /// matches with the query string first, then without it.
+    pub fn respond(&self, method: &str, path: &str, status: u16, body: Value) {
+        self.state
+            .lock()
+            .unwrap()
+            .responses
+            .insert((method.to_uppercase(), path.to_string()), (status, body));
+    }
+
+    /// Every request received so far, oldest first.
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    /// Requests to `method path`, with `path` compared without the query.
+    pub fn requests_to(&self, method: &str, path: &str) -> Vec<RecordedRequest> {
+        self.requests()
+            .into_iter()
+            .filter(|request| {
+                request.method.eq_ignore_ascii_case(method)
+                    && request.path.split('?').next() == Some(path)
+            })
+            .collect()
+    }
+}
+
+async fn handle(
+    req: HttpRequest,
+    body: web::Bytes,
+    state: web::Data<Mutex<FakeState>>,
+) -> HttpResponse {
+    let method = req.method().as_str().to_string();
+    let path = req
+        .uri()
+        .path_and_query()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| req.path().to_string());
+
+    let mut state = state.lock().unwrap();
+    state.requests.push(RecordedRequest {
+        method: method.clone(),
+        path: path.clone(),
+        authorization: header(&req, "authorization"),
+        request_id: header(&req, "x-request-id"),
+        body: serde_json::from_slice(&body).unwrap_or(Value::Null),
+    });
+
+    let (status, body) = state
+        .responses
+        .get(&(method.clone(), path))
+        .or_else(|| state.responses.get(&(method, req.path().to_string())))
+        .cloned()
+        .unwrap_or((200, json!({})));
+
+    HttpResponse::build(StatusCode::from_u16(status).unwrap()).json(body)
+}
+
+fn header(req: &HttpRequest, name: &str) -> Option<String> {
+    req.headers()
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+}
@@ -0,0 +1,213 @@
+//! Rows the integration tests build on. Fixtures panic on database errors.
+
+use chrono::{DateTime, Duration, Utc};
+use sea_orm::{ActiveModelTrait, DatabaseConnection, Set};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::{
+    database::{
+        connect_from_url, migrations,
+        models::{channels, chats, clients, companies, company_users, messages, users},
+    },
+    services::tokens::issue_token,
+};
+
+/// API key stored for companies created by [`create_company`].
+pub const TEST_API_KEY: &str = "test-key";
+
+pub struct Tenant {
+    pub company: Uuid,
+    pub channel: Uuid,
+    pub chat: Uuid,
+    /// Admin of the company.
+    pub admin: Uuid,
+    /// Token of [`Tenant::admin`].
+    pub token: String,
+}
+
+/// Fresh in-memory SQLite database with every migration applied.
+pub async fn setup_db() -> DatabaseConnection {
+    let db = connect_from_url("sqlite::memory:").await.unwrap();
+    migrations::run(&db).await.unwrap();
+    db
+}
+
+pub async fn create_company(db: &DatabaseConnection) -> Uuid {
+    let company = Uuid::new_v4();
+    companies::ActiveModel {
+        id: Set(company.as_bytes().to_vec()),
+        name: Set(Some(format!("Company {}", company))),
+        description: Set(None),
+        email: Set(None),
+        phone: Set(None),
+        wazzup_api_key: Set(Some(TEST_API_KEY.to_string())),
+        is_active: Set(Some(1)),
+        subscription_tier: Set(None),
+        created_at: Set(Some(Utc::now())),
+        updated_at: Set(Some(Utc::now())),
+        last_webhook_at: Set(None),
+    }
+    .insert(db)
+    .await
+    .unwrap();
+    company
+}
+
+/// A company with one owned channel, one chat and one message in it.
+pub async fn create_tenant(db: &DatabaseConnection) -> Tenant {
+    let company = create_company(db).await;
+    let channel = create_channel(db, company, "whatsapp").await;
+    let chat = create_chat(db, channel, None).await;
+
+    create_message(db, chat, "hello", true, Utc::now()).await;
+
+    let (admin, token) = create_user(db, company, "admin", None).await;
+
+    Tenant {
+        company,
+        channel,
+        chat,
+        admin,
+        token,
+    }
+}
+
+/// Channel of `transport` (e.g. `whatsapp`) owned by the company.
+pub async fn create_channel(db: &DatabaseConnection, company: Uuid, transport: &str) -> Uuid {
+    let channel = Uuid::new_v4();
+    channels::ActiveModel {
+        id: Set(channel.as_bytes().to_vec()),
+        r#type: Set(transport.to_string()),
+        company_id: Set(Some(company.as_bytes().to_vec())),
+    }
+    .insert(db)
+    .await
+    .unwrap();
+    channel
+}
+
+pub async fn create_chat(db: &DatabaseConnection, channel: Uuid, client: Option<Uuid>) -> Uuid {
+    let chat = Uuid::new_v4();
+    chats::ActiveModel {
+        id: Set(chat.to_string()),
+        channel_id: Set(channel.as_bytes().to_vec()),
+        client_id: Set(client.map(|client| client.as_bytes().to_vec())),
+        name: Set(format!("Chat {}", chat)),
+    }
+    .insert(db)
+    .await
+    .unwrap();
+    chat
+}
+
+/// Text message in the chat, inbound from the client or outgoing from it.
+pub async fn create_message(
+    db: &DatabaseConnection,
+    chat: Uuid,
+    text: &str,
+    inbound: bool,
+    created_at: DateTime<Utc>,
+) -> Uuid {
+    let message = Uuid::new_v4();
+    messages::ActiveModel {
+        id: Set(message.as_bytes().to_vec()),
+        content: Set(json!([{ "type": "text", "content": text }])),
+        chat_id: Set(chat.to_string()),
+        is_inbound: Set(Some(inbound as i8)),
+        is_echo: Set(Some(0)),
+        direction_status: Set(Some(if inbound { "incoming" } else { "sent" }.to_string())),
+        author_user_id: Set(None),
+        created_at: Set(created_at),
+    }
+    .insert(db)
+    .await
+    .unwrap();
+    message
+}
+
+/// Client of the company that `responsible` handles.
+pub async fn create_client(
+    db: &DatabaseConnection,
+    company: Uuid,
+    responsible: Uuid,
+    phone: Option<&str>,
+) -> Uuid {
+    let client = Uuid::new_v4();
+    clients::ActiveModel {
+        id: Set(client.as_bytes().to_vec()),
+        company_id: Set(Some(company.as_bytes().to_vec())),
+        full_name: Set(format!("Client {}", client)),
+        email: Set(Some(format!("{}@example.com", client))),
+        phone: Set(phone.map(str::to_string)),
+        responsible_user_id: Set(responsible.as_bytes().to_vec()),
+        created_at: Set(Utc::now()),
+    }
+    .insert(db)
+    .await
+    .unwrap();
+    client
+}
+
+/// Adds a user with `role` to the company and returns a token of that user.
+pub async fn create_member(db: &DatabaseConnection, company: Uuid, role: &str) -> String {
+    create_user(db, company, role, None).await.1
+}
+
+/// Like [`create_member`], also returning the id of the user.
+pub async fn create_member_user(
+    db: &DatabaseConnection,
+    company: Uuid,
+    role: &str,
+) -> (Uuid, String) {
+    create_user(db, company, role, None).await
+}
+
+/// Adds a user whose own `users.role` is `admin` to the company.
+pub async fn create_platform_admin(db: &DatabaseConnection, company: Uuid) -> String {
+    create_user(db, company, "admin", Some("admin")).await.1
+}
+
+async fn create_user(
+    db: &DatabaseConnection,
+    company: Uuid,
+    role: &str,
+    user_role: Option<&str>,
+) -> (Uuid, String) {
+    let user = Uuid::new_v4();
+    users::ActiveModel {
+        id: Set(user.as_bytes().to_vec()),
+        name: Set(Some(format!("{} {}", role, user))),
+        login: Set(user.to_string()),
+        email: Set(format!("{}@example.com", user)),
+        password: Set(String::new()),
+        role: Set(user_role.map(str::to_string)),
+        resource_id: Set(None),
+        bot_hook: Set(None),
+    }
+    .insert(db)
+    .await
+    .unwrap();
+
+    company_users::ActiveModel {
+        company_id: Set(company.as_bytes().to_vec()),
+        user_id: Set(user.as_bytes().to_vec()),
+        role: Set(Some(role.to_string())),
+    }
+    .insert(db)
+    .await
+    .unwrap();
+
+    let token = issue_token(
+        db,
+        user.as_bytes(),
+        company.as_bytes(),
+        None,
+        Duration::days(1),
+    )
+    .await
+    .unwrap()
+    .token;
+
+    (user, token)
+}
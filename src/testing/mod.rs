@@ -0,0 +1,152 @@
+//! Test harness for this crate and for crates built on top of it, enabled by
+//! the `testing` feature.
+//!
+//! Tests get an in-memory SQLite database with every migration applied
+//! ([`setup_db`]), fixtures for companies, channels, chats, clients and
+//! members, a fake Wazzup server ([`FakeWazzup`]) and [`api_app!`], which
+//! mounts route configurations under `/api` the way the server does.
+//!
+//! ```ignore
+//! let db = setup_db().await;
+//! let tenant = create_tenant(&db).await;
+//! let wazzup = FakeWazzup::start().await;
+//! let app = api_app!(app_state_with(&db, &wazzup), chats::init_routes);
+//! ```
+
+mod fake_wazzup;
+mod fixtures;
+
+pub use fake_wazzup::{FakeWazzup, RecordedRequest};
+pub use fixtures::{
+    TEST_API_KEY, Tenant, create_channel, create_chat, create_client, create_company,
+    create_member, create_member_user, create_message, create_platform_admin, create_tenant,
+    setup_db,
+};
+
+use actix_web::test::TestRequest;
+use sea_orm::DatabaseConnection;
+use serde_json::json;
+
+use crate::{
+    app_state::AppState,
+    config::Config,
+    services::{
+        api_keys::ApiKeyVault, background::TaskManager, bot_service::BotService, events::EventBus,
+        outgoing_webhooks::OutgoingWebhookService, wazzup_api::WazzupApiService,
+    },
+};
+
+#[doc(hidden)]
+pub use actix_web as __actix_web;
+
+/// Nothing listens here, so tests that do not set up [`FakeWazzup`] fail fast
+/// instead of reaching the real Wazzup.
+const OFFLINE_WAZZUP_URL: &str = "http://127.0.0.1:9";
+
+pub fn test_config() -> Config {
+    Config {
+        host: "127.0.0.1".to_string(),
+        port: 8080,
+        webhook_port: None,
+        public_url: None,
+        timezone: Some("UTC".to_string()),
+        max_body_bytes: None,
+        manager_own_chats_only: None,
+        api_key_master_keys: Some(
+            serde_json::from_value(json!("1:BwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwc="))
+                .unwrap(),
+        ),
+        api_key_master_key_file: None,
+        api_key_master_key_version: None,
+        wazzup_api_url: None,
+        wazzup_tech_url: None,
+        migrate_on_startup: None,
+        log_format: None,
+        log_redact: None,
+        metrics_token: None,
+        otel_exporter_otlp_endpoint: None,
+        otel_service_name: None,
+        database_url: None,
+        database_max_connections: None,
+        database_min_connections: None,
+        database_connect_timeout_secs: None,
+        database_acquire_timeout_secs: None,
+        database_idle_timeout_secs: None,
+        database_sql_log: None,
+        webhook_host: None,
+        workers: None,
+        cors_allowed_origins: None,
+        cors_max_age_secs: None,
+        cors_allowed_methods: None,
+        cors_allowed_headers: None,
+        cors_expose_headers: None,
+        tls_cert_file: None,
+        tls_key_file: None,
+        webhook_tls_cert_file: None,
+        webhook_tls_key_file: None,
+        shutdown_timeout_secs: None,
+        company_sync_schedule: None,
+        rate_limit_token_per_minute: None,
+        rate_limit_company_per_minute: None,
+        rate_limit_ip_per_minute: None,
+        rate_limit_expensive_token_per_minute: None,
+        rate_limit_expensive_company_per_minute: None,
+        webhook_rate_limit_ip_per_minute: None,
+        trusted_proxies: None,
+        outgoing_webhooks_allow_private_networks: None,
+    }
+}
+
+/// State whose Wazzup client cannot reach any server.
+pub fn app_state(db: &DatabaseConnection) -> AppState {
+    state(
+        db,
+        WazzupApiService::with_base_urls(OFFLINE_WAZZUP_URL, OFFLINE_WAZZUP_URL),
+    )
+}
+
+/// State talking to `wazzup`.
+pub fn app_state_with(db: &DatabaseConnection, wazzup: &FakeWazzup) -> AppState {
+    state(db, wazzup.service())
+}
+
+fn state(db: &DatabaseConnection, wazzup_api: WazzupApiService) -> AppState {
+    let config = test_config();
+    let outgoing_webhooks = OutgoingWebhookService::from_config(&config).unwrap();
+    AppState {
+        db: db.clone(),
+        api_keys: ApiKeyVault::from_config(&config).unwrap(),
+        config,
+        wazzup_api,
+        bot_service: BotService::new(),
+        events: EventBus::new(),
+        outgoing_webhooks,
+        tasks: TaskManager::new(),
+    }
+}
+
+pub fn authorized(request: TestRequest, token: &str) -> TestRequest {
+    request.insert_header(("Authorization", format!("Bearer {}", token)))
+}
+
+/// Test service with the given route configurations mounted under `/api`
+/// behind [`crate::api::middleware::BearerAuth`], with request ids as in `main`.
+#[macro_export]
+macro_rules! api_app {
+    ($state:expr, $($routes:expr),+ $(,)?) => {
+        $crate::testing::__actix_web::test::init_service(
+            $crate::testing::__actix_web::App::new()
+                .app_data($crate::testing::__actix_web::web::Data::new($state))
+                .app_data($crate::errors::json_config())
+                .app_data($crate::errors::query_config())
+                .app_data($crate::errors::path_config())
+                .wrap($crate::api::middleware::RequestId)
+                .service(
+                    $crate::testing::__actix_web::web::scope("/api")
+                        .wrap($crate::api::middleware::BearerAuth)
+                        $(.configure($routes))+,
+                ),
+        )
+        .await
+    };
+}
@@ -0,0 +1,127 @@
+//! HTTPS for the API and webhook listeners.
+//!
+//! A listener's certificate is read from PEM files into a [`Certificate`]
+//! that rustls asks for the certificate on every handshake, so a reload
+//! (`SIGHUP`, see [`reload_on_hangup`]) applies to new connections without
+//! restarting the listener. When the files cannot be read or do not match,
+//! the error is logged and the previous certificate stays in use.
+
+use std::{
+    fmt::Display,
+    io,
+    path::Path,
+    sync::{Arc, RwLock},
+};
+
+use rustls::{
+    ServerConfig,
+    crypto::{CryptoProvider, ring},
+    pki_types::{CertificateDer, PrivateKeyDer, pem::PemObject},
+    server::{ClientHello, ResolvesServerCert},
+    sign::CertifiedKey,
+};
+
+use crate::{config::TlsFiles, errors::AppError, services::background::Shutdown};
+
+#[derive(Debug)]
+pub struct Certificate {
+    files: TlsFiles,
+    provider: Arc<CryptoProvider>,
+    current: RwLock<Arc<CertifiedKey>>,
+}
+
+impl Certificate {
+    /// Reads the certificate chain and the private key; fails when either
+    /// is missing or they do not belong together.
+    pub fn load(files: TlsFiles) -> io::Result<Arc<Self>> {
+        let provider = Arc::new(ring::default_provider());
+        let key = read(&files, &provider)?;
+        Ok(Arc::new(Self {
+            files,
+            provider,
+            current: RwLock::new(Arc::new(key)),
+        }))
+    }
+
+    /// Reads the files again; on error the current certificate is kept.
+    pub fn reload(&self) -> io::Result<()> {
+        let key = read(&self.files, &self.provider)?;
+        *self.current.write().unwrap() = Arc::new(key);
+        Ok(())
+    }
+
+    /// The certificate chain presented to clients right now.
+    pub fn chain(&self) -> Vec<CertificateDer<'static>> {
+        self.current.read().unwrap().cert.clone()
+    }
+
+    pub fn files(&self) -> &TlsFiles {
+        &self.files
+    }
+
+    /// rustls configuration of a listener that presents this certificate.
+    pub fn server_config(self: &Arc<Self>) -> io::Result<ServerConfig> {
+        Ok(ServerConfig::builder_with_provider(self.provider.clone())
+            .with_safe_default_protocol_versions()
+            .map_err(io::Error::other)?
+            .with_no_client_auth()
+            .with_cert_resolver(self.clone()))
+    }
+}
+
+impl ResolvesServerCert for Certificate {
+    fn resolve(&self, _client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        Some(self.current.read().unwrap().clone())
+    }
+}
+
+/// Reloads `certificates` on every `SIGHUP` until shutdown.
+#[cfg(unix)]
+pub async fn reload_on_hangup(
+    certificates: Vec<Arc<Certificate>>,
+    mut shutdown: Shutdown,
+) -> Result<(), AppError> {
+    use tokio::signal::unix::{SignalKind, signal};
+
+    let mut hangup = signal(SignalKind::hangup()).map_err(|err| {
+        log::error!("Unable to listen for SIGHUP: {}", err);
+        AppError::Internal
+    })?;
+    loop {
+        tokio::select! {
+            _ = hangup.recv() => {
+                for certificate in &certificates {
+                    let cert = certificate.files().cert.display();
+                    match certificate.reload() {
+                        Ok(()) => log::info!("Reloaded TLS certificate {}", cert),
+                        Err(err) => log::error!(
+                            "Keeping the previous TLS certificate {}: {}",
+                            cert,
+                            err
+                        ),
+                    }
+                }
+            }
+            _ = shutdown.requested() => return Ok(()),
+        }
+    }
+}
+
+fn read(files: &TlsFiles, provider: &CryptoProvider) -> io::Result<CertifiedKey> {
+    let chain = CertificateDer::pem_file_iter(&files.cert)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .map_err(|err| invalid(&files.cert, err))?;
+    if chain.is_empty() {
+        return Err(invalid(&files.cert, "no certificate found"));
+    }
+    let key = PrivateKeyDer::from_pem_file(&files.key).map_err(|err| invalid(&files.key, err))?;
+
+    CertifiedKey::from_der(chain, key, provider).map_err(|err| invalid(&files.key, err))
+}
+
+fn invalid(path: &Path, err: impl Display) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("{}: {}", path.display(), err),
+    )
+}
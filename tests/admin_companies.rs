@@ -0,0 +1,107 @@
+//! Platform administrators manage companies through `/api/admin/companies`.
+
+use actix_web::{http::StatusCode, test};
+use serde_json::{Value, json};
+
+use wazzup::api::admin;
+
+use wazzup::{
+    api_app,
+    testing::{
+        app_state, authorized, create_company, create_member, create_platform_admin, setup_db,
+    },
+};
+
+#[actix_web::test]
+async fn platform_admins_manage_companies() {
+    let db = setup_db().await;
+    let home = create_company(&db).await;
+    let token = create_platform_admin(&db, home).await;
+    let app = api_app!(app_state(&db), admin::init_routes);
+
+    let create = authorized(
+        test::TestRequest::post()
+            .uri("/api/admin/companies")
+            .set_json(json!({ "name": "  Acme  ", "subscriptionTier": "pro" })),
+        &token,
+    )
+    .to_request();
+    let response = test::call_service(&app, create).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let body: Value = test::read_body_json(response).await;
+    let company = body["company"].clone();
+    assert_eq!(company["name"], "Acme");
+    assert_eq!(company["isActive"], true);
+    assert_eq!(company["hasApiKey"], false);
+    assert!(body.get("webhooks").is_none());
+    let id = company["id"].as_str().unwrap().to_string();
+
+    let update = authorized(
+        test::TestRequest::patch()
+            .uri(&format!("/api/admin/companies/{}", id))
+            .set_json(json!({ "isActive": false, "subscriptionTier": "" })),
+        &token,
+    )
+    .to_request();
+    let body: Value = test::call_and_read_body_json(&app, update).await;
+    assert_eq!(body["isActive"], false);
+    assert!(body.get("subscriptionTier").is_none());
+
+    let status = authorized(
+        test::TestRequest::get().uri(&format!("/api/admin/companies/{}/status", id)),
+        &token,
+    )
+    .to_request();
+    let body: Value = test::call_and_read_body_json(&app, status).await;
+    assert_eq!(body["channelCount"], 0);
+    assert_eq!(body["apiKey"], "missing");
+    assert_eq!(body["isActive"], false);
+
+    let list =
+        authorized(test::TestRequest::get().uri("/api/admin/companies"), &token).to_request();
+    let body: Value = test::call_and_read_body_json(&app, list).await;
+    assert_eq!(body["data"].as_array().unwrap().len(), 2);
+
+    let delete = authorized(
+        test::TestRequest::delete().uri(&format!("/api/admin/companies/{}", id)),
+        &token,
+    )
+    .to_request();
+    assert_eq!(
+        test::call_service(&app, delete).await.status(),
+        StatusCode::NO_CONTENT
+    );
+}
+
+#[actix_web::test]
+async fn companies_with_members_are_not_deleted() {
+    let db = setup_db().await;
+    let home = create_company(&db).await;
+    let token = create_platform_admin(&db, home).await;
+    let app = api_app!(app_state(&db), admin::init_routes);
+
+    let delete = authorized(
+        test::TestRequest::delete().uri(&format!("/api/admin/companies/{}", home)),
+        &token,
+    )
+    .to_request();
+    assert_eq!(
+        test::call_service(&app, delete).await.status(),
+        StatusCode::BAD_REQUEST
+    );
+}
+
+#[actix_web::test]
+async fn company_admins_are_not_platform_admins() {
+    let db = setup_db().await;
+    let home = create_company(&db).await;
+    let token = create_member(&db, home, "admin").await;
+    let app = api_app!(app_state(&db), admin::init_routes);
+
+    let list =
+        authorized(test::TestRequest::get().uri("/api/admin/companies"), &token).to_request();
+    assert_eq!(
+        test::call_service(&app, list).await.status(),
+        StatusCode::FORBIDDEN
+    );
+}
@@ -0,0 +1,136 @@
+//! Wazzup API keys are stored sealed with a versioned master key.
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use sea_orm::{ActiveModelTrait, DatabaseConnection, EntityTrait, IntoActiveModel, Set};
+use serde_json::json;
+use uuid::Uuid;
+
+use wazzup::{
+    config::{Config, Secret},
+    database::models::companies,
+    services::api_keys::ApiKeyVault,
+};
+
+use wazzup::testing::{create_company, setup_db, test_config};
+
+fn vault(keys: &str, version: Option<u32>) -> ApiKeyVault {
+    let config = Config {
+        api_key_master_keys: Some(serde_json::from_value::<Secret>(json!(keys)).unwrap()),
+        api_key_master_key_version: version,
+        ..test_config()
+    };
+    ApiKeyVault::from_config(&config).unwrap()
+}
+
+fn master_key(byte: u8) -> String {
+    STANDARD.encode([byte; 32])
+}
+
+async fn stored_key(db: &DatabaseConnection, company: Uuid) -> String {
+    companies::Entity::find_by_id(company.as_bytes().to_vec())
+        .one(db)
+        .await
+        .unwrap()
+        .unwrap()
+        .wazzup_api_key
+        .unwrap()
+}
+
+async fn store_key(db: &DatabaseConnection, company: Uuid, value: &str) {
+    let mut active = companies::Entity::find_by_id(company.as_bytes().to_vec())
+        .one(db)
+        .await
+        .unwrap()
+        .unwrap()
+        .into_active_model();
+    active.wazzup_api_key = Set(Some(value.to_string()));
+    active.update(db).await.unwrap();
+}
+
+#[actix_web::test]
+async fn plain_keys_are_sealed_and_rewrapped_on_rotation() {
+    let db = setup_db().await;
+    let company = create_company(&db).await;
+    store_key(&db, company, "plain-wazzup-key").await;
+
+    let first = vault(&format!("1:{}", master_key(1)), None);
+    assert_eq!(
+        first.company_api_key(&db, &company).await.unwrap(),
+        "plain-wazzup-key"
+    );
+
+    let report = first.seal_stored_keys(&db, false).await.unwrap();
+    assert_eq!(
+        (report.sealed, report.rewrapped, report.unchanged),
+        (1, 0, 0)
+    );
+    let sealed = stored_key(&db, company).await;
+    assert!(sealed.starts_with("wzk:v1:"));
+    assert!(!sealed.contains("plain-wazzup-key"));
+    assert_eq!(
+        first.company_api_key(&db, &company).await.unwrap(),
+        "plain-wazzup-key"
+    );
+
+    let rotated = vault(&format!("1:{},2:{}", master_key(1), master_key(2)), None);
+    let report = rotated.seal_stored_keys(&db, false).await.unwrap();
+    assert_eq!(
+        (report.sealed, report.rewrapped, report.unchanged),
+        (0, 1, 0)
+    );
+    assert!(stored_key(&db, company).await.starts_with("wzk:v2:"));
+
+    let second_only = vault(&format!("2:{}", master_key(2)), None);
+    assert_eq!(
+        second_only.company_api_key(&db, &company).await.unwrap(),
+        "plain-wazzup-key"
+    );
+    assert!(first.company_api_key(&db, &company).await.is_err());
+}
+
+#[actix_web::test]
+async fn dry_run_leaves_rows_untouched() {
+    let db = setup_db().await;
+    let company = create_company(&db).await;
+
+    let report = vault(&format!("1:{}", master_key(1)), None)
+        .seal_stored_keys(&db, true)
+        .await
+        .unwrap();
+    assert_eq!(report.sealed, 1);
+    assert_eq!(stored_key(&db, company).await, "test-key");
+}
+
+#[actix_web::test]
+async fn sealed_keys_are_bound_to_their_company() {
+    let db = setup_db().await;
+    let owner = create_company(&db).await;
+    let other = create_company(&db).await;
+    let vault = vault(&format!("1:{},2:{}", master_key(1), master_key(2)), Some(1));
+
+    let sealed = vault.seal(owner.as_bytes(), "owner-key").unwrap();
+    assert!(sealed.starts_with("wzk:v1:"));
+    store_key(&db, owner, &sealed).await;
+    store_key(&db, other, &sealed).await;
+
+    assert_eq!(
+        vault.company_api_key(&db, &owner).await.unwrap(),
+        "owner-key"
+    );
+    assert!(vault.company_api_key(&db, &other).await.is_err());
+}
+
+#[test]
+fn master_keys_are_validated() {
+    let config = |keys: &str| Config {
+        api_key_master_keys: Some(serde_json::from_value::<Secret>(json!(keys)).unwrap()),
+        ..test_config()
+    };
+
+    assert!(ApiKeyVault::from_config(&config("1:c2hvcnQ=")).is_err());
+    assert!(
+        ApiKeyVault::from_config(&config(&format!("1:{},1:{}", master_key(1), master_key(2))))
+            .is_err()
+    );
+    assert!(format!("{:?}", config(&format!("1:{}", master_key(1)))).contains("Secret(***)"));
+}
@@ -0,0 +1,160 @@
+//! Supervised background tasks and their status report.
+
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicU32, Ordering},
+    },
+    time::Duration,
+};
+
+use actix_web::{http::StatusCode, test};
+use serde_json::Value;
+
+use wazzup::{
+    api::admin,
+    api_app,
+    errors::AppError,
+    services::background::{TaskKind, TaskManager, TaskState, TaskStatus},
+    testing::{
+        app_state, authorized, create_company, create_member, create_platform_admin, setup_db,
+    },
+};
+
+fn status(tasks: &TaskManager, name: &str) -> TaskStatus {
+    tasks
+        .statuses()
+        .into_iter()
+        .find(|status| status.name == name)
+        .unwrap_or_else(|| panic!("no task named {}", name))
+}
+
+async fn settle() {
+    tokio::time::sleep(Duration::from_millis(50)).await;
+}
+
+#[actix_web::test]
+async fn one_off_tasks_record_their_outcome() {
+    let tasks = TaskManager::new();
+    tasks.spawn("ok", async { Ok(()) });
+    tasks.spawn("error", async {
+        Err(AppError::InvalidInput("bad input".to_string()))
+    });
+    tasks.spawn("panic", async {
+        panic!("boom");
+    });
+    settle().await;
+
+    let ok = status(&tasks, "ok");
+    assert_eq!(
+        (ok.kind, ok.state),
+        (TaskKind::OneOff, TaskState::Succeeded)
+    );
+    assert!(ok.finished_at.is_some());
+
+    let error = status(&tasks, "error");
+    assert_eq!(error.state, TaskState::Failed);
+    assert!(error.last_error.unwrap().contains("bad input"));
+
+    let panicked = status(&tasks, "panic");
+    assert_eq!(panicked.state, TaskState::Failed);
+    assert_eq!(panicked.last_error.as_deref(), Some("panicked: boom"));
+}
+
+#[actix_web::test]
+async fn crashed_periodic_tasks_are_restarted() {
+    let tasks = TaskManager::new();
+    let runs = Arc::new(AtomicU32::new(0));
+    let counter = runs.clone();
+    tasks.spawn_periodic("sweep", Duration::from_millis(20), move || {
+        let counter = counter.clone();
+        async move {
+            if counter.fetch_add(1, Ordering::SeqCst) == 0 {
+                panic!("first run crashed");
+            }
+            Ok(())
+        }
+    });
+    settle().await;
+
+    let crashed = status(&tasks, "sweep");
+    assert_eq!(
+        (crashed.kind, crashed.state),
+        (TaskKind::Periodic, TaskState::Restarting)
+    );
+    assert_eq!(crashed.restarts, 1);
+    assert_eq!(
+        crashed.last_error.as_deref(),
+        Some("panicked: first run crashed")
+    );
+
+    tokio::time::sleep(Duration::from_millis(1200)).await;
+    assert_eq!(status(&tasks, "sweep").state, TaskState::Running);
+    assert!(runs.load(Ordering::SeqCst) > 2);
+
+    tasks.shutdown(Duration::from_secs(1)).await;
+    let stopped = status(&tasks, "sweep");
+    assert_eq!((stopped.state, stopped.restarts), (TaskState::Stopped, 1));
+}
+
+#[actix_web::test]
+async fn shutdown_drains_tasks_and_aborts_stragglers() {
+    let tasks = TaskManager::new();
+    tasks.spawn("short", async {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        Ok(())
+    });
+    tasks.spawn("stuck", async {
+        tokio::time::sleep(Duration::from_secs(3600)).await;
+        Ok(())
+    });
+    tasks.spawn_service("listener", |mut shutdown| async move {
+        shutdown.requested().await;
+        Ok(())
+    });
+
+    tasks.shutdown(Duration::from_millis(200)).await;
+
+    assert!(tasks.is_shutting_down());
+    assert_eq!(status(&tasks, "short").state, TaskState::Succeeded);
+    assert_eq!(status(&tasks, "stuck").state, TaskState::Aborted);
+    assert_eq!(status(&tasks, "listener").state, TaskState::Stopped);
+
+    // Nothing new starts once shutdown began.
+    tasks.spawn("late", async { Ok(()) });
+    assert!(tasks.statuses().iter().all(|status| status.name != "late"));
+}
+
+#[actix_web::test]
+async fn platform_admins_see_background_tasks() {
+    let db = setup_db().await;
+    let home = create_company(&db).await;
+    let admin_token = create_platform_admin(&db, home).await;
+    let member_token = create_member(&db, home, "admin").await;
+    let state = app_state(&db);
+    state.tasks.spawn("search.backfill", async { Ok(()) });
+    settle().await;
+    let app = api_app!(state, admin::init_routes);
+
+    let forbidden = authorized(
+        test::TestRequest::get().uri("/api/admin/tasks"),
+        &member_token,
+    )
+    .to_request();
+    assert_eq!(
+        test::call_service(&app, forbidden).await.status(),
+        StatusCode::FORBIDDEN
+    );
+
+    let list = authorized(
+        test::TestRequest::get().uri("/api/admin/tasks"),
+        &admin_token,
+    )
+    .to_request();
+    let body: Value = test::call_and_read_body_json(&app, list).await;
+    let task = &body["data"][0];
+    assert_eq!(task["name"], "search.backfill");
+    assert_eq!(task["kind"], "oneOff");
+    assert_eq!(task["state"], "succeeded");
+    assert!(task["finishedAt"].is_string());
+}
@@ -0,0 +1,450 @@
+//! Chats and channels are only visible to the company that owns the channel.
+
+use actix_web::{http::StatusCode, test};
+use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, PaginatorTrait, QueryFilter, Set};
+use serde_json::{Value, json};
+use uuid::Uuid;
+
+use wazzup::{
+    api::{channels::functions::sync_channels_to_db, chats},
+    database::models::{channels, chats as chat_models, clients as client_models, messages},
+    services::{
+        bot_service::BotService,
+        events::EventBus,
+        wazzup_api::ChannelListResponse,
+        webhook_handler::{WebhookRequest, handle_webhook},
+    },
+};
+
+use wazzup::{
+    api_app,
+    testing::{app_state, authorized, create_client, create_company, create_tenant, setup_db},
+};
+
+macro_rules! chats_app {
+    ($db:expr) => {
+        api_app!(app_state($db), chats::init_routes)
+    };
+}
+
+#[actix_web::test]
+async fn previews_only_list_chats_of_owned_channels() {
+    let db = setup_db().await;
+    let first = create_tenant(&db).await;
+    let second = create_tenant(&db).await;
+    let app = chats_app!(&db);
+
+    let request = authorized(
+        test::TestRequest::get().uri(&format!("/api/chats/{}/previews", first.company)),
+        &first.token,
+    )
+    .to_request();
+    let body: Value = test::call_and_read_body_json(&app, request).await;
+
+    let ids: Vec<&str> = body["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|preview| preview["id"].as_str().unwrap())
+        .collect();
+    assert_eq!(ids, vec![first.chat.to_string()]);
+    assert!(!ids.contains(&second.chat.to_string().as_str()));
+}
+
+#[actix_web::test]
+async fn chats_of_other_companies_are_not_found() {
+    let db = setup_db().await;
+    let first = create_tenant(&db).await;
+    let second = create_tenant(&db).await;
+    let app = chats_app!(&db);
+
+    let own = authorized(
+        test::TestRequest::get().uri(&format!("/api/chats/{}/{}", first.company, first.chat)),
+        &first.token,
+    )
+    .to_request();
+    assert_eq!(test::call_service(&app, own).await.status(), StatusCode::OK);
+
+    for uri in [
+        format!("/api/chats/{}/{}", first.company, second.chat),
+        format!("/api/chats/{}/{}/messages", first.company, second.chat),
+    ] {
+        let request = authorized(test::TestRequest::get().uri(&uri), &first.token).to_request();
+        let response = test::call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND, "GET {}", uri);
+    }
+
+    let send = authorized(
+        test::TestRequest::post()
+            .uri(&format!(
+                "/api/chats/{}/{}/send",
+                first.company, second.chat
+            ))
+            .set_json(json!({ "message": { "content": [{ "type": "text", "content": "hi" }] } })),
+        &first.token,
+    )
+    .to_request();
+    assert_eq!(
+        test::call_service(&app, send).await.status(),
+        StatusCode::NOT_FOUND
+    );
+}
+
+#[actix_web::test]
+async fn chats_of_unowned_channels_are_hidden() {
+    let db = setup_db().await;
+    let tenant = create_tenant(&db).await;
+
+    let mut channel: channels::ActiveModel =
+        channels::Entity::find_by_id(tenant.channel.as_bytes().to_vec())
+            .one(&db)
+            .await
+            .unwrap()
+            .unwrap()
+            .into();
+    channel.company_id = Set(None);
+    channel.update(&db).await.unwrap();
+
+    let app = chats_app!(&db);
+    let request = authorized(
+        test::TestRequest::get().uri(&format!("/api/chats/{}/{}", tenant.company, tenant.chat)),
+        &tenant.token,
+    )
+    .to_request();
+    assert_eq!(
+        test::call_service(&app, request).await.status(),
+        StatusCode::NOT_FOUND
+    );
+}
+
+#[actix_web::test]
+async fn channel_sync_assigns_ownership() {
+    let db = setup_db().await;
+    let company = create_company(&db).await;
+    let channel = Uuid::new_v4();
+
+    let response: ChannelListResponse = serde_json::from_value(json!({
+        "channels": [{
+            "deleted": false,
+            "guid": channel.to_string(),
+            "hasAccess": true,
+            "transport": "telegram",
+            "visible": true,
+        }],
+        "count": 1,
+    }))
+    .unwrap();
+    sync_channels_to_db(&company, &response, &db).await.unwrap();
+
+    let stored = channels::Entity::find_by_id(channel.as_bytes().to_vec())
+        .one(&db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(stored.company_id, Some(company.as_bytes().to_vec()));
+    assert_eq!(stored.r#type, "telegram");
+}
+
+#[actix_web::test]
+async fn webhooks_only_store_messages_of_channels_synced_for_the_company() {
+    let db = setup_db().await;
+    let owner = create_company(&db).await;
+    let intruder = create_company(&db).await;
+    let channel = Uuid::new_v4();
+
+    let webhook = |company_message: &str| -> WebhookRequest {
+        serde_json::from_value(json!({
+            "messages": [{
+                "messageId": Uuid::new_v4().to_string(),
+                "channelId": channel.to_string(),
+                "chatType": "whatsapp",
+                "chatId": "79990001122",
+                "type": "text",
+                "text": company_message,
+                "isEcho": false,
+            }]
+        }))
+        .unwrap()
+    };
+    let stored_messages = || async {
+        messages::Entity::find()
+            .inner_join(chat_models::Entity)
+            .filter(chat_models::Column::ChannelId.eq(channel.as_bytes().to_vec()))
+            .count(&db)
+            .await
+            .unwrap()
+    };
+
+    let events = EventBus::new();
+    let bot_service = BotService::new();
+    let wazzup_api = app_state(&db).wazzup_api;
+
+    // Whoever posts first does not get an unknown channel.
+    handle_webhook(
+        intruder,
+        webhook("before sync"),
+        &db,
+        &bot_service,
+        &wazzup_api,
+        &events,
+    )
+    .await
+    .unwrap();
+    assert!(
+        channels::Entity::find_by_id(channel.as_bytes().to_vec())
+            .one(&db)
+            .await
+            .unwrap()
+            .is_none()
+    );
+    assert_eq!(stored_messages().await, 0);
+
+    let response: ChannelListResponse = serde_json::from_value(json!({
+        "channels": [{
+            "deleted": false,
+            "guid": channel.to_string(),
+            "hasAccess": true,
+            "transport": "whatsapp",
+            "visible": true,
+        }],
+        "count": 1,
+    }))
+    .unwrap();
+    sync_channels_to_db(&owner, &response, &db).await.unwrap();
+
+    handle_webhook(
+        owner,
+        webhook("from owner"),
+        &db,
+        &bot_service,
+        &wazzup_api,
+        &events,
+    )
+    .await
+    .unwrap();
+    handle_webhook(
+        intruder,
+        webhook("from intruder"),
+        &db,
+        &bot_service,
+        &wazzup_api,
+        &events,
+    )
+    .await
+    .unwrap();
+
+    let stored = channels::Entity::find_by_id(channel.as_bytes().to_vec())
+        .one(&db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(stored.company_id, Some(owner.as_bytes().to_vec()));
+    assert_eq!(stored_messages().await, 1);
+}
+
+#[actix_web::test]
+async fn status_webhooks_only_touch_messages_of_the_company() {
+    let db = setup_db().await;
+    let first = create_tenant(&db).await;
+    let second = create_tenant(&db).await;
+    let events = EventBus::new();
+    let bot_service = BotService::new();
+    let wazzup_api = app_state(&db).wazzup_api;
+    let message_id = Uuid::new_v4();
+
+    let sent: WebhookRequest = serde_json::from_value(json!({
+        "messages": [{
+            "messageId": message_id.to_string(),
+            "channelId": second.channel.to_string(),
+            "chatType": "whatsapp",
+            "chatId": second.chat.to_string(),
+            "type": "text",
+            "text": "Ваш заказ готов",
+            "isEcho": true,
+        }]
+    }))
+    .unwrap();
+    handle_webhook(
+        second.company,
+        sent,
+        &db,
+        &bot_service,
+        &wazzup_api,
+        &events,
+    )
+    .await
+    .unwrap();
+
+    let mut published = events.subscribe();
+
+    let status = |company_status: &str| -> WebhookRequest {
+        serde_json::from_value(json!({
+            "statuses": [{ "messageId": message_id.to_string(), "status": company_status }]
+        }))
+        .unwrap()
+    };
+    handle_webhook(
+        first.company,
+        status("read"),
+        &db,
+        &bot_service,
+        &wazzup_api,
+        &events,
+    )
+    .await
+    .unwrap();
+
+    let stored = messages::Entity::find_by_id(message_id.as_bytes().to_vec())
+        .one(&db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(stored.direction_status.as_deref(), Some("outgoing"));
+    assert!(published.try_recv().is_err());
+
+    handle_webhook(
+        second.company,
+        status("delivered"),
+        &db,
+        &bot_service,
+        &wazzup_api,
+        &events,
+    )
+    .await
+    .unwrap();
+    let stored = messages::Entity::find_by_id(message_id.as_bytes().to_vec())
+        .one(&db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(stored.direction_status.as_deref(), Some("delivered"));
+}
+
+#[actix_web::test]
+async fn the_same_number_gets_a_chat_per_company() {
+    let db = setup_db().await;
+    let first = create_tenant(&db).await;
+    let second = create_tenant(&db).await;
+    let events = EventBus::new();
+    let bot_service = BotService::new();
+    let wazzup_api = app_state(&db).wazzup_api;
+
+    let webhook = |channel: Uuid, chat: &str| -> WebhookRequest {
+        serde_json::from_value(json!({
+            "messages": [{
+                "messageId": Uuid::new_v4().to_string(),
+                "channelId": channel.to_string(),
+                "chatType": "whatsapp",
+                "chatId": chat,
+                "type": "text",
+                "text": "Здравствуйте",
+                "isEcho": false,
+                "clientName": "Анна",
+                "clientPhone": "79990001122",
+            }]
+        }))
+        .unwrap()
+    };
+    for tenant in [&first, &second] {
+        handle_webhook(
+            tenant.company,
+            webhook(tenant.channel, "79990001122"),
+            &db,
+            &bot_service,
+            &wazzup_api,
+            &events,
+        )
+        .await
+        .unwrap();
+    }
+    // A chat id of another company's chat does not move it to this channel.
+    handle_webhook(
+        first.company,
+        webhook(first.channel, &second.chat.to_string()),
+        &db,
+        &bot_service,
+        &wazzup_api,
+        &events,
+    )
+    .await
+    .unwrap();
+
+    for (tenant, other) in [(&first, &second), (&second, &first)] {
+        let chats = chat_models::Entity::find()
+            .filter(chat_models::Column::ChannelId.eq(tenant.channel.as_bytes().to_vec()))
+            .filter(chat_models::Column::Id.ne(tenant.chat.to_string()))
+            .all(&db)
+            .await
+            .unwrap();
+        assert_eq!(chats.len(), 1);
+        let chat = &chats[0];
+        assert_ne!(chat.id, other.chat.to_string());
+        let client = client_models::Entity::find_by_id(chat.client_id.clone().unwrap())
+            .one(&db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(client.company_id, Some(tenant.company.as_bytes().to_vec()));
+        assert_eq!(
+            messages::Entity::find()
+                .filter(messages::Column::ChatId.eq(chat.id.clone()))
+                .count(&db)
+                .await
+                .unwrap(),
+            1
+        );
+    }
+    let foreign_chat = chat_models::Entity::find_by_id(second.chat.to_string())
+        .one(&db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(foreign_chat.channel_id, second.channel.as_bytes().to_vec());
+    assert_eq!(
+        messages::Entity::find()
+            .filter(messages::Column::ChatId.eq(second.chat.to_string()))
+            .count(&db)
+            .await
+            .unwrap(),
+        1
+    );
+}
+
+#[actix_web::test]
+async fn contact_webhooks_do_not_take_over_clients_of_other_companies() {
+    let db = setup_db().await;
+    let owner = create_tenant(&db).await;
+    let intruder = create_tenant(&db).await;
+    let client = create_client(&db, owner.company, owner.admin, Some("79990001122")).await;
+    let events = EventBus::new();
+    let mut published = events.subscribe();
+
+    let contact: WebhookRequest = serde_json::from_value(json!({
+        "contacts": [{
+            "contactId": client.to_string(),
+            "name": "Подменённое имя",
+            "phone": "70000000000",
+        }]
+    }))
+    .unwrap();
+    handle_webhook(
+        intruder.company,
+        contact,
+        &db,
+        &BotService::new(),
+        &app_state(&db).wazzup_api,
+        &events,
+    )
+    .await
+    .unwrap();
+
+    let stored = client_models::Entity::find_by_id(client.as_bytes().to_vec())
+        .one(&db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(stored.company_id, Some(owner.company.as_bytes().to_vec()));
+    assert_eq!(stored.full_name, format!("Client {}", client));
+    assert_eq!(stored.phone.as_deref(), Some("79990001122"));
+    assert!(published.try_recv().is_err());
+}
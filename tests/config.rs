@@ -0,0 +1,123 @@
+//! Layered configuration: TOML file, then environment, then flags.
+
+use std::{env, fs, path::PathBuf};
+
+use uuid::Uuid;
+use wazzup::config::Config;
+
+struct TempFile(PathBuf);
+
+impl TempFile {
+    fn toml(contents: &str) -> Self {
+        let path = env::temp_dir().join(format!("wazzup-{}.toml", Uuid::new_v4()));
+        fs::write(&path, contents).unwrap();
+        Self(path)
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+const FILE: &str = r#"
+host = "127.0.0.1"
+port = 8080
+webhook_port = 8081
+database_url = "sqlite::memory:"
+database_max_connections = 7
+database_min_connections = 2
+cors_allowed_origins = "https://crm.example.com, https://admin.example.com"
+"#;
+
+#[test]
+fn file_values_are_overridden_by_environment_and_flags() {
+    let file = TempFile::toml(FILE);
+    // SAFETY: other tests read the environment only through `std::env`, which
+    // serializes access with `set_var`.
+    unsafe { env::set_var("DATABASE_MIN_CONNECTIONS", "3") };
+
+    let config = Config::load(
+        Some(&file.0),
+        &[
+            ("port", "9090".to_string()),
+            ("host", "0.0.0.0".to_string()),
+        ],
+    )
+    .unwrap();
+    unsafe { env::remove_var("DATABASE_MIN_CONNECTIONS") };
+
+    assert_eq!(config.host, "0.0.0.0");
+    assert_eq!(config.port, 9090);
+    assert_eq!(config.effective_webhook_port(), 8081);
+    assert_eq!(config.effective_webhook_host(), "0.0.0.0");
+    assert_eq!(
+        config.cors_origins(),
+        vec!["https://crm.example.com", "https://admin.example.com"]
+    );
+
+    let database = config.database_settings().unwrap();
+    assert_eq!(database.url, "sqlite::memory:");
+    assert_eq!(database.max_connections, Some(7));
+    assert_eq!(database.min_connections, Some(3));
+}
+
+#[test]
+fn invalid_values_are_rejected() {
+    let file = TempFile::toml(FILE);
+
+    let same_ports = Config::load(Some(&file.0), &[("webhook_port", "8080".to_string())]);
+    assert!(same_ports.is_err());
+
+    let bad_origin = Config::load(
+        Some(&file.0),
+        &[("cors_allowed_origins", "crm.example.com".to_string())],
+    );
+    assert!(
+        bad_origin
+            .unwrap_err()
+            .to_string()
+            .contains("crm.example.com")
+    );
+
+    let missing = Config::load(Some(&env::temp_dir().join("no-such-wazzup.toml")), &[]);
+    assert!(missing.is_err());
+
+    let bad_method = Config::load(
+        Some(&file.0),
+        &[("cors_allowed_methods", "GET, PO ST".to_string())],
+    );
+    assert!(bad_method.unwrap_err().to_string().contains("PO ST"));
+
+    let bad_proxy = Config::load(
+        Some(&file.0),
+        &[("trusted_proxies", "10.0.0.0/8, 10.0.0.1/33".to_string())],
+    );
+    assert!(bad_proxy.unwrap_err().to_string().contains("10.0.0.1/33"));
+
+    let key_without_cert = Config::load(
+        Some(&file.0),
+        &[("webhook_tls_key_file", "/etc/wazzup/tls.key".to_string())],
+    );
+    assert!(key_without_cert.is_err());
+}
+
+#[test]
+fn tls_files_are_per_listener() {
+    let file = TempFile::toml(FILE);
+
+    let config = Config::load(
+        Some(&file.0),
+        &[
+            ("tls_cert_file", "/etc/wazzup/api.crt".to_string()),
+            ("tls_key_file", "/etc/wazzup/api.key".to_string()),
+        ],
+    )
+    .unwrap();
+
+    let api = config.api_tls().unwrap();
+    assert_eq!(api.cert, PathBuf::from("/etc/wazzup/api.crt"));
+    assert_eq!(api.key, PathBuf::from("/etc/wazzup/api.key"));
+    assert_eq!(config.webhook_tls(), None);
+}
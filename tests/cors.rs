@@ -0,0 +1,62 @@
+//! CORS of the API follows the `cors_*` settings.
+
+use actix_web::{
+    App, HttpResponse,
+    http::{StatusCode, header},
+    test, web,
+};
+
+use wazzup::{api::middleware::cors, config::Config, testing::test_config};
+
+fn preflight(method: &str, headers: &str) -> test::TestRequest {
+    test::TestRequest::default()
+        .method(actix_web::http::Method::OPTIONS)
+        .uri("/api/ping")
+        .insert_header((header::ORIGIN, "https://crm.example.com"))
+        .insert_header((header::ACCESS_CONTROL_REQUEST_METHOD, method))
+        .insert_header((header::ACCESS_CONTROL_REQUEST_HEADERS, headers))
+}
+
+#[actix_web::test]
+async fn configured_origins_methods_and_headers_are_enforced() {
+    let config = Config {
+        cors_allowed_origins: Some("https://crm.example.com".to_string()),
+        cors_allowed_methods: Some("GET, POST".to_string()),
+        cors_allowed_headers: Some("Authorization, Content-Type".to_string()),
+        cors_expose_headers: Some("X-Request-Id".to_string()),
+        ..test_config()
+    };
+    let app = test::init_service(App::new().wrap(cors(&config)).route(
+        "/api/ping",
+        web::route().to(|| async { HttpResponse::Ok().finish() }),
+    ))
+    .await;
+
+    let allowed = test::call_service(&app, preflight("POST", "authorization").to_request()).await;
+    assert_eq!(allowed.status(), StatusCode::OK);
+    assert_eq!(
+        allowed
+            .headers()
+            .get(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+            .unwrap(),
+        "https://crm.example.com"
+    );
+
+    for (method, headers) in [("DELETE", "authorization"), ("GET", "x-debug")] {
+        let rejected = test::call_service(&app, preflight(method, headers).to_request()).await;
+        assert_eq!(rejected.status(), StatusCode::BAD_REQUEST, "{}", method);
+    }
+
+    let request = test::TestRequest::get()
+        .uri("/api/ping")
+        .insert_header((header::ORIGIN, "https://crm.example.com"))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(
+        response
+            .headers()
+            .get(header::ACCESS_CONTROL_EXPOSE_HEADERS)
+            .unwrap(),
+        "x-request-id"
+    );
+}
@@ -0,0 +1,182 @@
+//! Whole request flows through the API against a fake Wazzup.
+
+use actix_web::{http::StatusCode, test};
+use sea_orm::EntityTrait;
+use serde_json::{Value, json};
+use uuid::Uuid;
+
+use wazzup::{
+    api::{channels, chats, contacts, webhooks},
+    database::models::channels as channel_models,
+};
+
+use wazzup::{
+    api_app,
+    testing::{
+        FakeWazzup, TEST_API_KEY, app_state_with, authorized, create_channel, create_client,
+        create_tenant, setup_db,
+    },
+};
+
+#[actix_web::test]
+async fn webhook_messages_show_up_in_chat_previews() {
+    let db = setup_db().await;
+    let tenant = create_tenant(&db).await;
+    let wazzup = FakeWazzup::start().await;
+    let app = api_app!(
+        app_state_with(&db, &wazzup),
+        webhooks::init_routes,
+        chats::init_routes
+    );
+
+    let webhook = test::TestRequest::post()
+        .uri(&format!("/api/webhook/{}", tenant.company))
+        .set_json(json!({
+            "messages": [{
+                "messageId": Uuid::new_v4().to_string(),
+                "channelId": tenant.channel.to_string(),
+                "chatType": "whatsapp",
+                "chatId": "79990001122",
+                "type": "text",
+                "text": "Где мой заказ?",
+                "isEcho": false,
+            }]
+        }))
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, webhook).await.status(),
+        StatusCode::OK
+    );
+
+    let request = authorized(
+        test::TestRequest::get().uri(&format!("/api/chats/{}/previews", tenant.company)),
+        &tenant.token,
+    )
+    .to_request();
+    let body: Value = test::call_and_read_body_json(&app, request).await;
+
+    let previews = body["data"].as_array().unwrap();
+    assert_eq!(previews.len(), 2);
+    let incoming = previews
+        .iter()
+        .find(|preview| preview["id"] != tenant.chat.to_string())
+        .unwrap();
+    assert_eq!(incoming["channel"]["id"], tenant.channel.to_string());
+    assert_eq!(
+        incoming["lastMessage"]["content"][0]["content"],
+        "Где мой заказ?"
+    );
+}
+
+#[actix_web::test]
+async fn sent_messages_name_the_sending_user() {
+    let db = setup_db().await;
+    let tenant = create_tenant(&db).await;
+    let wazzup = FakeWazzup::start().await;
+    let app = api_app!(app_state_with(&db, &wazzup), chats::init_routes);
+
+    let request = authorized(
+        test::TestRequest::post()
+            .uri(&format!(
+                "/api/chats/{}/{}/send",
+                tenant.company, tenant.chat
+            ))
+            .set_json(json!({
+                "message": { "content": [{ "type": "text", "content": "Добрый день" }] }
+            })),
+        &tenant.token,
+    )
+    .to_request();
+    assert_eq!(
+        test::call_service(&app, request).await.status(),
+        StatusCode::OK
+    );
+
+    let sent = wazzup.requests_to("POST", "/v3/message");
+    assert_eq!(sent.len(), 1);
+    assert_eq!(sent[0].body["text"], "Добрый день");
+    assert_eq!(sent[0].body["crmUserId"], tenant.admin.to_string());
+}
+
+#[actix_web::test]
+async fn contact_updates_are_pushed_to_wazzup() {
+    let db = setup_db().await;
+    let tenant = create_tenant(&db).await;
+    let wazzup = FakeWazzup::start().await;
+    let app = api_app!(app_state_with(&db, &wazzup), contacts::init_routes);
+
+    let known = create_client(&db, tenant.company, tenant.admin, None).await;
+    let update = |client: Uuid| {
+        authorized(
+            test::TestRequest::put()
+                .uri(&format!("/api/contacts/{}/{}", tenant.company, client))
+                .set_json(json!({
+                    "fullName": "Иван Петров",
+                    "email": format!("{}@example.com", client),
+                    "phone": "+7 999 000-11-22",
+                })),
+            &tenant.token,
+        )
+        .to_request()
+    };
+
+    let response = test::call_service(&app, update(known)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let pushed = wazzup.requests_to("PUT", &format!("/v3/contacts/{}", known));
+    assert_eq!(pushed.len(), 1);
+    assert_eq!(
+        pushed[0].authorization.as_deref(),
+        Some(format!("Bearer {}", TEST_API_KEY).as_str())
+    );
+    assert_eq!(pushed[0].body["name"], "Иван Петров");
+    assert_eq!(pushed[0].body["contactData"][0]["chatId"], "79990001122");
+    assert!(wazzup.requests_to("POST", "/v3/contacts").is_empty());
+
+    let unknown = create_client(&db, tenant.company, tenant.admin, None).await;
+    wazzup.respond(
+        "PUT",
+        &format!("/v3/contacts/{}", unknown),
+        404,
+        json!({ "error": "contactNotFound" }),
+    );
+
+    let response = test::call_service(&app, update(unknown)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let created = wazzup.requests_to("POST", "/v3/contacts");
+    assert_eq!(created.len(), 1);
+    assert_eq!(created[0].body[0]["id"], unknown.to_string());
+}
+
+#[actix_web::test]
+async fn deleted_channels_are_removed_from_wazzup_and_the_database() {
+    let db = setup_db().await;
+    let tenant = create_tenant(&db).await;
+    let wazzup = FakeWazzup::start().await;
+    let app = api_app!(app_state_with(&db, &wazzup), channels::init_routes);
+    let channel = create_channel(&db, tenant.company, "whatsapp").await;
+
+    let request = authorized(
+        test::TestRequest::delete().uri(&format!(
+            "/api/channels/{}/{}?delete_chats=false",
+            tenant.company, channel
+        )),
+        &tenant.token,
+    )
+    .to_request();
+    assert_eq!(
+        test::call_service(&app, request).await.status(),
+        StatusCode::OK
+    );
+
+    let deleted = wazzup.requests_to("DELETE", &format!("/channels/whatsapp/{}", channel));
+    assert_eq!(deleted.len(), 1);
+    assert_eq!(deleted[0].body, json!({ "deleteChats": false }));
+
+    let stored = channel_models::Entity::find_by_id(channel.as_bytes().to_vec())
+        .one(&db)
+        .await
+        .unwrap();
+    assert!(stored.is_none());
+}
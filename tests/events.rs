@@ -0,0 +1,349 @@
+//! Server-sent event stream: filtering, resuming and stream tickets.
+
+use std::{future::poll_fn, pin::Pin, time::Duration};
+
+use actix_web::{
+    body::{BoxBody, MessageBody},
+    http::StatusCode,
+    test,
+};
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+use serde_json::{Value, json};
+use sha2::Sha256;
+use uuid::Uuid;
+
+use wazzup::{
+    api::{events, webhooks},
+    api_app,
+    database::models::tokens as token_models,
+    services::{events::EventKind, tokens},
+    testing::{
+        app_state, authorized, create_chat, create_client, create_member_user, create_message,
+        create_tenant, setup_db,
+    },
+};
+
+/// Next chunk written to the stream.
+async fn next_frame(body: &mut BoxBody) -> String {
+    let chunk = tokio::time::timeout(
+        Duration::from_secs(5),
+        poll_fn(|cx| Pin::new(&mut *body).poll_next(cx)),
+    )
+    .await
+    .expect("no frame within 5 s")
+    .expect("stream ended")
+    .unwrap();
+    String::from_utf8(chunk.to_vec()).unwrap()
+}
+
+fn event_id(frame: &str) -> u64 {
+    frame
+        .lines()
+        .find_map(|line| line.strip_prefix("id: "))
+        .unwrap()
+        .parse()
+        .unwrap()
+}
+
+fn event_data(frame: &str) -> Value {
+    let data = frame
+        .lines()
+        .find_map(|line| line.strip_prefix("data: "))
+        .unwrap();
+    serde_json::from_str(data).unwrap()
+}
+
+#[actix_web::test]
+async fn streams_deliver_events_of_the_company_and_the_chosen_chats() {
+    let db = setup_db().await;
+    let tenant = create_tenant(&db).await;
+    let other = create_tenant(&db).await;
+    let state = app_state(&db);
+    let bus = state.events.clone();
+    let app = api_app!(state, events::init_routes);
+
+    let request = authorized(
+        test::TestRequest::get().uri(&format!(
+            "/api/events/{}?chatId={}",
+            tenant.company, tenant.chat
+        )),
+        &tenant.token,
+    )
+    .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers().get("content-type").unwrap(),
+        "text/event-stream"
+    );
+    let mut body = response.into_body().boxed();
+    assert_eq!(next_frame(&mut body).await, "retry: 3000\n\n");
+
+    let chat = |uuid: Uuid| Some(uuid.to_string());
+    bus.publish(
+        Uuid::new_v4(),
+        Utc::now(),
+        &other.company,
+        chat(other.chat),
+        EventKind::ChatRead,
+        json!({}),
+    );
+    bus.publish(
+        Uuid::new_v4(),
+        Utc::now(),
+        &tenant.company,
+        chat(Uuid::new_v4()),
+        EventKind::ChatRead,
+        json!({}),
+    );
+    let channel = bus.publish(
+        Uuid::new_v4(),
+        Utc::now(),
+        &tenant.company,
+        None,
+        EventKind::ChannelStateChanged,
+        json!({ "state": "active" }),
+    );
+    let message = bus.publish(
+        Uuid::new_v4(),
+        Utc::now(),
+        &tenant.company,
+        chat(tenant.chat),
+        EventKind::MessageCreated,
+        json!({ "chatId": tenant.chat.to_string() }),
+    );
+
+    let frame = next_frame(&mut body).await;
+    assert!(frame.contains("event: channel.state\n"), "{}", frame);
+    assert_eq!(event_id(&frame), channel.id);
+    let frame = next_frame(&mut body).await;
+    assert!(frame.contains("event: message.created\n"), "{}", frame);
+    assert_eq!(event_id(&frame), message.id);
+    assert_eq!(event_data(&frame)["chatId"], tenant.chat.to_string());
+}
+
+#[actix_web::test]
+async fn streams_resume_after_the_last_event_id() {
+    let db = setup_db().await;
+    let tenant = create_tenant(&db).await;
+    let state = app_state(&db);
+    let bus = state.events.clone();
+    let app = api_app!(state, events::init_routes);
+    let published: Vec<u64> = (0..3)
+        .map(|index| {
+            bus.publish(
+                Uuid::new_v4(),
+                Utc::now(),
+                &tenant.company,
+                None,
+                EventKind::ChannelStateChanged,
+                json!({ "index": index }),
+            )
+            .id
+        })
+        .collect();
+    let resume = |last_event_id: String| {
+        authorized(
+            test::TestRequest::get()
+                .uri(&format!("/api/events/{}", tenant.company))
+                .insert_header(("Last-Event-ID", last_event_id)),
+            &tenant.token,
+        )
+        .to_request()
+    };
+
+    let response = test::call_service(&app, resume(published[0].to_string())).await;
+    let mut body = response.into_body().boxed();
+    assert_eq!(next_frame(&mut body).await, "retry: 3000\n\n");
+    for id in &published[1..] {
+        assert_eq!(event_id(&next_frame(&mut body).await), *id);
+    }
+
+    // Ids from before a restart or from another instance are unknown here:
+    // the client is told to refetch instead of silently missing events.
+    for unknown in [1, published[2] + 1_000] {
+        let response = test::call_service(&app, resume(unknown.to_string())).await;
+        let mut body = response.into_body().boxed();
+        assert_eq!(next_frame(&mut body).await, "retry: 3000\n\n");
+        assert_eq!(
+            next_frame(&mut body).await,
+            "event: stream.reset\ndata: {}\n\n"
+        );
+
+        let live = bus.publish(
+            Uuid::new_v4(),
+            Utc::now(),
+            &tenant.company,
+            None,
+            EventKind::ChannelStateChanged,
+            json!({}),
+        );
+        assert_eq!(event_id(&next_frame(&mut body).await), live.id);
+    }
+}
+
+#[actix_web::test]
+async fn status_webhooks_are_streamed() {
+    let db = setup_db().await;
+    let tenant = create_tenant(&db).await;
+    let message = create_message(&db, tenant.chat, "Ваш заказ готов", false, Utc::now()).await;
+    let app = api_app!(app_state(&db), events::init_routes, webhooks::init_routes);
+
+    let request = authorized(
+        test::TestRequest::get().uri(&format!("/api/events/{}", tenant.company)),
+        &tenant.token,
+    )
+    .to_request();
+    let mut body = test::call_service(&app, request).await.into_body().boxed();
+    assert_eq!(next_frame(&mut body).await, "retry: 3000\n\n");
+
+    let webhook = test::TestRequest::post()
+        .uri(&format!("/api/webhook/{}", tenant.company))
+        .set_json(json!({
+            "statuses": [{ "messageId": message.to_string(), "status": "read" }]
+        }))
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, webhook).await.status(),
+        StatusCode::OK
+    );
+
+    let frame = next_frame(&mut body).await;
+    assert!(frame.contains("event: message.status\n"), "{}", frame);
+    let event = event_data(&frame);
+    assert_eq!(event["chatId"], tenant.chat.to_string());
+    assert_eq!(event["payload"]["messageId"], message.to_string());
+    assert_eq!(event["payload"]["status"], "read");
+}
+
+#[actix_web::test]
+async fn streams_take_short_lived_tickets_instead_of_tokens_in_the_url() {
+    let db = setup_db().await;
+    let tenant = create_tenant(&db).await;
+    let other = create_tenant(&db).await;
+    let app = api_app!(app_state(&db), events::init_routes);
+    let open = |company: Uuid, query: String| {
+        test::TestRequest::get()
+            .uri(&format!("/api/events/{}?{}", company, query))
+            .to_request()
+    };
+    let status = |result: Result<_, actix_web::Error>| match result {
+        Ok(response) => actix_web::dev::ServiceResponse::status(&response),
+        Err(err) => err.as_response_error().status_code(),
+    };
+
+    let issue = authorized(
+        test::TestRequest::post().uri(&format!("/api/events/{}/ticket", tenant.company)),
+        &tenant.token,
+    )
+    .to_request();
+    let issued: Value = test::call_and_read_body_json(&app, issue).await;
+    let ticket = issued["ticket"].as_str().unwrap().to_string();
+    assert!(ticket.starts_with("wzs_"));
+    assert!(!ticket.contains(&tenant.token));
+
+    let response =
+        test::call_service(&app, open(tenant.company, format!("ticket={}", ticket))).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let tampered = format!("{}0", ticket.trim_end_matches(|c| c != '.'));
+    // The stored token hash is not the signing key.
+    let record = token_models::Entity::find()
+        .filter(token_models::Column::TokenHash.eq(tokens::hash_token(&tenant.token)))
+        .one(&db)
+        .await
+        .unwrap()
+        .unwrap();
+    let claims = format!(
+        "{}.{}.{}",
+        hex::encode(&record.id),
+        hex::encode(tenant.company.as_bytes()),
+        Utc::now().timestamp() + 60
+    );
+    let signature = <Hmac<Sha256> as Mac>::new_from_slice(record.token_hash.as_bytes())
+        .unwrap()
+        .chain_update(claims.as_bytes())
+        .finalize()
+        .into_bytes();
+    let forged = format!("wzs_{}.{}", claims, hex::encode(signature));
+    for query in [
+        format!("access_token={}", tenant.token),
+        format!("ticket={}", tampered),
+        format!("ticket={}", forged),
+    ] {
+        assert_eq!(
+            status(test::try_call_service(&app, open(tenant.company, query)).await),
+            StatusCode::UNAUTHORIZED
+        );
+    }
+    assert_eq!(
+        status(
+            test::try_call_service(&app, open(other.company, format!("ticket={}", ticket))).await
+        ),
+        StatusCode::FORBIDDEN
+    );
+}
+
+#[actix_web::test]
+async fn restricted_managers_only_receive_events_of_their_own_chats() {
+    let db = setup_db().await;
+    let tenant = create_tenant(&db).await;
+    let (manager, token) = create_member_user(&db, tenant.company, "manager").await;
+    let client = create_client(&db, tenant.company, manager, None).await;
+    let own_chat = create_chat(&db, tenant.channel, Some(client)).await;
+    let mut state = app_state(&db);
+    state.config.manager_own_chats_only = Some(true);
+    let bus = state.events.clone();
+    let app = api_app!(state, events::init_routes);
+
+    let publish = |chat: Option<Uuid>, kind: EventKind, payload: Value| {
+        bus.publish(
+            Uuid::new_v4(),
+            Utc::now(),
+            &tenant.company,
+            chat.map(|chat| chat.to_string()),
+            kind,
+            payload,
+        )
+        .id
+    };
+    let publish_mixed = || {
+        publish(Some(tenant.chat), EventKind::MessageCreated, json!({}));
+        publish(
+            None,
+            EventKind::ClientUpdated,
+            json!({ "responsibleUserId": tenant.admin.to_string() }),
+        );
+        [
+            publish(Some(own_chat), EventKind::MessageCreated, json!({})),
+            publish(
+                None,
+                EventKind::ClientUpdated,
+                json!({ "responsibleUserId": manager.to_string() }),
+            ),
+            publish(None, EventKind::ChannelStateChanged, json!({})),
+        ]
+    };
+
+    // Replayed events are filtered like live ones.
+    let start = publish(None, EventKind::ChannelStateChanged, json!({}));
+    let replayed = publish_mixed();
+    let request = authorized(
+        test::TestRequest::get()
+            .uri(&format!("/api/events/{}", tenant.company))
+            .insert_header(("Last-Event-ID", start.to_string())),
+        &token,
+    )
+    .to_request();
+    let mut body = test::call_service(&app, request).await.into_body().boxed();
+    assert_eq!(next_frame(&mut body).await, "retry: 3000\n\n");
+    for id in replayed {
+        assert_eq!(event_id(&next_frame(&mut body).await), id);
+    }
+
+    for id in publish_mixed() {
+        assert_eq!(event_id(&next_frame(&mut body).await), id);
+    }
+}
@@ -0,0 +1,147 @@
+//! Probes of both listeners and the per-company integration diagnostics.
+
+use actix_web::{App, http::StatusCode, test, web};
+use sea_orm::Database;
+use serde_json::{Value, json};
+
+use wazzup::api::{diagnostics, health};
+
+use wazzup::{
+    api_app,
+    testing::{
+        FakeWazzup, app_state, app_state_with, authorized, create_member, create_tenant, setup_db,
+    },
+};
+
+#[actix_web::test]
+async fn probes_report_liveness_and_readiness() {
+    let db = setup_db().await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state(&db)))
+            .configure(health::init_routes),
+    )
+    .await;
+
+    let response =
+        test::call_service(&app, test::TestRequest::get().uri("/healthz").to_request()).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let request = test::TestRequest::get().uri("/readyz").to_request();
+    let body: Value = test::call_and_read_body_json(&app, request).await;
+    assert_eq!(body["status"], "ready");
+    assert_eq!(body["database"], "ok");
+    assert_eq!(body["migrations"], "ok");
+}
+
+#[actix_web::test]
+async fn pending_migrations_make_the_service_unready() {
+    let db = Database::connect("sqlite::memory:").await.unwrap();
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state(&db)))
+            .configure(health::init_routes),
+    )
+    .await;
+
+    let response =
+        test::call_service(&app, test::TestRequest::get().uri("/readyz").to_request()).await;
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(body["status"], "unavailable");
+    assert_eq!(body["database"], "ok");
+    assert_eq!(body["migrations"], "failed");
+    assert_eq!(body["pendingMigrations"], json!([1, 2, 3, 4, 5]));
+}
+
+#[actix_web::test]
+async fn diagnostics_report_webhooks_and_channel_states() {
+    let db = setup_db().await;
+    let tenant = create_tenant(&db).await;
+    let wazzup = FakeWazzup::start().await;
+    wazzup.respond(
+        "GET",
+        "/v3/webhooks",
+        200,
+        json!({ "webhooksUri": "https://old-crm.example.com/hook" }),
+    );
+    wazzup.respond(
+        "GET",
+        "/channels/list",
+        200,
+        json!({
+            "count": 2,
+            "channels": [
+                {
+                    "guid": tenant.channel.to_string(),
+                    "transport": "whatsapp",
+                    "state": "active",
+                    "deleted": false,
+                    "hasAccess": true,
+                    "visible": true,
+                },
+                {
+                    "guid": "0d1f6a3e-5b7c-4e8d-9a2b-3c4d5e6f7a8b",
+                    "transport": "telegram",
+                    "state": "qr",
+                    "deleted": false,
+                    "hasAccess": true,
+                    "visible": true,
+                },
+            ],
+        }),
+    );
+    let app = api_app!(app_state_with(&db, &wazzup), diagnostics::init_routes);
+    let uri = format!("/api/diagnostics/{}", tenant.company);
+
+    let request = authorized(test::TestRequest::get().uri(&uri), &tenant.token).to_request();
+    let body: Value = test::call_and_read_body_json(&app, request).await;
+
+    assert_eq!(body["apiKey"], "valid");
+    assert_eq!(body["webhooks"]["state"], "mismatch");
+    assert_eq!(
+        body["webhooks"]["registeredUri"],
+        "https://old-crm.example.com/hook"
+    );
+    let expected_uri = body["webhooks"]["expectedUri"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    assert!(expected_uri.ends_with(&format!("/api/webhook/{}", tenant.company)));
+    assert_eq!(
+        body["channels"],
+        json!([
+            {
+                "channelId": tenant.channel.to_string(),
+                "transport": "whatsapp",
+                "state": "active",
+                "known": true,
+            },
+            {
+                "channelId": "0d1f6a3e-5b7c-4e8d-9a2b-3c4d5e6f7a8b",
+                "transport": "telegram",
+                "state": "qr",
+                "known": false,
+            },
+        ])
+    );
+    assert!(body.get("lastWebhookAt").is_none());
+
+    wazzup.respond(
+        "GET",
+        "/v3/webhooks",
+        200,
+        json!({ "webhooksUri": expected_uri }),
+    );
+    let request = authorized(test::TestRequest::get().uri(&uri), &tenant.token).to_request();
+    let body: Value = test::call_and_read_body_json(&app, request).await;
+    assert_eq!(body["webhooks"]["state"], "registered");
+
+    let manager = create_member(&db, tenant.company, "manager").await;
+    let request = authorized(test::TestRequest::get().uri(&uri), &manager).to_request();
+    assert_eq!(
+        test::call_service(&app, request).await.status(),
+        StatusCode::FORBIDDEN
+    );
+}
@@ -0,0 +1,267 @@
+//! Persistent job queue: enqueueing, claiming, retries and cron schedules.
+
+use std::sync::{
+    Arc,
+    atomic::{AtomicU32, Ordering},
+};
+
+use chrono::{Duration, TimeZone, Utc};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, IntoActiveModel, QueryFilter,
+    Set,
+};
+use serde_json::json;
+use uuid::Uuid;
+
+use wazzup::{
+    database::models::{jobs, recurring_jobs},
+    errors::AppError,
+    services::{
+        cron::CronSchedule,
+        jobs::{self as queue, JobQueue, NewJob, STATUS_FAILED, STATUS_PENDING, STATUS_SUCCEEDED},
+    },
+    testing::{app_state, create_company, setup_db},
+};
+
+async fn job(db: &DatabaseConnection, id: Uuid) -> jobs::Model {
+    jobs::Entity::find_by_id(id.as_bytes().to_vec())
+        .one(db)
+        .await
+        .unwrap()
+        .unwrap()
+}
+
+#[test]
+fn cron_schedules_find_the_next_run() {
+    let at = |y, mo, d, h, mi| Utc.with_ymd_and_hms(y, mo, d, h, mi, 0).unwrap();
+    let next = |expression: &str, after| CronSchedule::parse(expression).unwrap().next_after(after);
+
+    let monday_noon = at(2024, 5, 6, 12, 7);
+    assert_eq!(
+        next("*/15 * * * *", monday_noon),
+        Some(at(2024, 5, 6, 12, 15))
+    );
+    assert_eq!(next("0 3 * * *", monday_noon), Some(at(2024, 5, 7, 3, 0)));
+    // Saturday 2024-05-11 is skipped for Monday.
+    assert_eq!(
+        next("30 9 * * 1-5", at(2024, 5, 10, 10, 0)),
+        Some(at(2024, 5, 13, 9, 30))
+    );
+    assert_eq!(next("0 0 * * 7", monday_noon), Some(at(2024, 5, 12, 0, 0)));
+    // Either day field matches when both are restricted.
+    assert_eq!(next("0 0 15 * 0", monday_noon), Some(at(2024, 5, 12, 0, 0)));
+    assert_eq!(next("0 0 29 2 *", monday_noon), Some(at(2028, 2, 29, 0, 0)));
+    assert_eq!(next("0 0 31 2 *", monday_noon), None);
+
+    for invalid in [
+        "",
+        "* * * *",
+        "60 * * * *",
+        "* * * 13 *",
+        "5-1 * * * *",
+        "*/0 * * * *",
+    ] {
+        assert!(CronSchedule::parse(invalid).is_err(), "{:?}", invalid);
+    }
+}
+
+#[actix_web::test]
+async fn unique_keys_deduplicate_unfinished_jobs() {
+    let db = setup_db().await;
+    let app_state = app_state(&db);
+    let work = JobQueue::new().register("noop", |_, _| async { Ok(()) });
+
+    let first = queue::enqueue(&db, NewJob::new("noop", json!({})).unique_key("k"))
+        .await
+        .unwrap();
+    let again = queue::enqueue(&db, NewJob::new("noop", json!({})).unique_key("k"))
+        .await
+        .unwrap();
+    assert_eq!(first, again);
+
+    assert_eq!(work.run_due(&app_state).await.unwrap(), 1);
+    let finished = job(&db, first).await;
+    assert_eq!(finished.status, STATUS_SUCCEEDED);
+    assert_eq!(finished.unique_key, None);
+    assert!(finished.finished_at.is_some());
+
+    // The key is free again once the job finished.
+    let next = queue::enqueue(&db, NewJob::new("noop", json!({})).unique_key("k"))
+        .await
+        .unwrap();
+    assert_ne!(next, first);
+}
+
+#[actix_web::test]
+async fn failed_jobs_are_retried_with_backoff_until_attempts_run_out() {
+    let db = setup_db().await;
+    let app_state = app_state(&db);
+    let work = JobQueue::new()
+        .register("flaky", |_, payload| async move {
+            Err(AppError::InvalidInput(format!("rejected {}", payload["n"])))
+        })
+        .register("crash", |_, _| async { panic!("handler crashed") });
+
+    let retried = queue::enqueue(&db, NewJob::new("flaky", json!({ "n": 1 })))
+        .await
+        .unwrap();
+    let last_try = queue::enqueue(
+        &db,
+        NewJob {
+            max_attempts: 1,
+            ..NewJob::new("crash", json!({}))
+        },
+    )
+    .await
+    .unwrap();
+    let unknown = queue::enqueue(&db, NewJob::new("unknown", json!({})))
+        .await
+        .unwrap();
+    let later = queue::enqueue(
+        &db,
+        NewJob {
+            run_at: Some(Utc::now() + Duration::hours(1)),
+            ..NewJob::new("flaky", json!({ "n": 2 }))
+        },
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(work.run_due(&app_state).await.unwrap(), 3);
+
+    let retried = job(&db, retried).await;
+    assert_eq!(
+        (retried.status.as_str(), retried.attempts),
+        (STATUS_PENDING, 1)
+    );
+    assert!(retried.last_error.unwrap().contains("rejected 1"));
+    assert!(retried.run_at > Utc::now() + Duration::seconds(20));
+    assert_eq!(retried.locked_until, None);
+
+    let crashed = job(&db, last_try).await;
+    assert_eq!(crashed.status, STATUS_FAILED);
+    assert_eq!(
+        crashed.last_error.as_deref(),
+        Some("panicked: handler crashed")
+    );
+
+    let unknown = job(&db, unknown).await;
+    assert_eq!(unknown.status, STATUS_PENDING);
+    assert!(unknown.last_error.unwrap().contains("No handler"));
+
+    assert_eq!(job(&db, later).await.attempts, 0);
+    // Nothing is due any more.
+    assert_eq!(work.run_due(&app_state).await.unwrap(), 0);
+}
+
+#[actix_web::test]
+async fn concurrent_workers_run_each_job_once_and_take_over_expired_leases() {
+    let db = setup_db().await;
+    let app_state = app_state(&db);
+    let runs = Arc::new(AtomicU32::new(0));
+    let counter = runs.clone();
+    let work = JobQueue::new().register("count", move |_, _| {
+        let counter = counter.clone();
+        async move {
+            counter.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    });
+    for _ in 0..15 {
+        queue::enqueue(&db, NewJob::new("count", json!({})))
+            .await
+            .unwrap();
+    }
+
+    let (first, second) = tokio::join!(work.run_due(&app_state), work.run_due(&app_state));
+    let third = work.run_due(&app_state).await.unwrap();
+    assert_eq!(first.unwrap() + second.unwrap() + third, 15);
+    assert_eq!(runs.load(Ordering::SeqCst), 15);
+
+    // A worker died while running this job: the lease expired.
+    let abandoned = queue::enqueue(&db, NewJob::new("count", json!({})))
+        .await
+        .unwrap();
+    let mut record = job(&db, abandoned).await.into_active_model();
+    record.status = Set("running".to_string());
+    record.attempts = Set(1);
+    record.locked_until = Set(Some(Utc::now() - Duration::seconds(1)));
+    record.update(&db).await.unwrap();
+
+    assert_eq!(work.run_due(&app_state).await.unwrap(), 1);
+    let recovered = job(&db, abandoned).await;
+    assert_eq!(
+        (recovered.status.as_str(), recovered.attempts),
+        (STATUS_SUCCEEDED, 2)
+    );
+}
+
+#[actix_web::test]
+async fn recurring_jobs_enqueue_once_per_due_run() {
+    let db = setup_db().await;
+    let first = create_company(&db).await;
+    let second = create_company(&db).await;
+
+    queue::set_recurring(
+        &db,
+        queue::COMPANY_SYNC_SCHEDULE,
+        "0 3 * * *",
+        queue::SYNC_ALL_COMPANIES,
+        json!({}),
+    )
+    .await
+    .unwrap();
+    assert!(
+        queue::set_recurring(&db, "broken", "0 3 * *", "noop", json!({}))
+            .await
+            .is_err()
+    );
+    assert_eq!(queue::enqueue_recurring(&db).await.unwrap(), 0);
+
+    let due = Utc::now() - Duration::minutes(5);
+    let mut schedule = recurring_jobs::Entity::find_by_id(queue::COMPANY_SYNC_SCHEDULE)
+        .one(&db)
+        .await
+        .unwrap()
+        .unwrap()
+        .into_active_model();
+    schedule.next_run_at = Set(due);
+    schedule.update(&db).await.unwrap();
+
+    assert_eq!(queue::enqueue_recurring(&db).await.unwrap(), 1);
+    assert_eq!(queue::enqueue_recurring(&db).await.unwrap(), 0);
+    let schedule = recurring_jobs::Entity::find_by_id(queue::COMPANY_SYNC_SCHEDULE)
+        .one(&db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(schedule.next_run_at > Utc::now());
+    assert_eq!(schedule.next_run_at.format("%H:%M").to_string(), "03:00");
+
+    // The fan-out job enqueues one sync per company, deduplicated by company.
+    let work = JobQueue::standard();
+    assert_eq!(work.run_due(&app_state(&db)).await.unwrap(), 1);
+    let mut synced: Vec<Uuid> = jobs::Entity::find()
+        .filter(jobs::Column::JobType.eq(queue::SYNC_COMPANY))
+        .all(&db)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|job| serde_json::from_value(job.payload["companyId"].clone()).unwrap())
+        .collect();
+    synced.sort();
+    let mut expected = vec![first, second];
+    expected.sort();
+    assert_eq!(synced, expected);
+
+    queue::remove_recurring(&db, queue::COMPANY_SYNC_SCHEDULE)
+        .await
+        .unwrap();
+    assert!(
+        recurring_jobs::Entity::find()
+            .all(&db)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+}
@@ -0,0 +1,76 @@
+//! Personal data is masked in logs and every response carries its request id.
+
+use actix_web::{App, HttpResponse, test::TestRequest, web};
+use serde_json::{Value, json};
+
+use wazzup::{
+    api::middleware::RequestId,
+    logging::{redact, redact_body},
+};
+
+#[test]
+fn free_text_is_redacted() {
+    let line = redact(
+        "Invalid phone +79261234567 from ivan.petrov@example.com, \
+         Authorization: Bearer wzt_abc.def, chat 5f0c2d1e-8a7b-4c3d-9e2f-446655440000, \
+         stream /api/events/1?ticket=wzs_0a1b.2c3d.1700000000.9f8e",
+    );
+
+    assert!(!line.contains("79261234567"));
+    assert!(line.contains("+*********67"));
+    assert!(line.contains("***@example.com"));
+    assert!(!line.contains("ivan.petrov"));
+    assert!(!line.contains("wzt_abc"));
+    assert!(!line.contains("0a1b"));
+    assert!(!line.contains("9f8e"));
+    assert!(line.contains("5f0c2d1e-8a7b-4c3d-9e2f-446655440000"));
+}
+
+#[test]
+fn json_bodies_lose_message_text_and_contacts() {
+    let body = json!({
+        "messages": [{
+            "messageId": "b0e7a7c5",
+            "chatId": "79261234567",
+            "text": "Мой адрес: Тверская, 1",
+            "contact": { "name": "Иван", "phone": "+79261234567" },
+            "status": "sent"
+        }],
+        "apiKey": "secret"
+    });
+
+    let redacted: Value = serde_json::from_str(&redact_body(&body.to_string())).unwrap();
+    let message = &redacted["messages"][0];
+    assert_eq!(message["messageId"], "b0e7a7c5");
+    assert_eq!(message["status"], "sent");
+    assert_eq!(message["chatId"], "***");
+    assert_eq!(message["text"], "***");
+    assert_eq!(message["contact"]["name"], "***");
+    assert_eq!(message["contact"]["phone"], "***");
+    assert_eq!(redacted["apiKey"], "***");
+
+    assert_eq!(
+        redact_body("upstream failed for client@example.com"),
+        "upstream failed for ***@example.com"
+    );
+}
+
+#[actix_web::test]
+async fn responses_carry_the_request_id() {
+    let app = actix_web::test::init_service(
+        App::new()
+            .wrap(RequestId)
+            .route("/", web::get().to(HttpResponse::Ok)),
+    )
+    .await;
+
+    let response = actix_web::test::call_service(&app, TestRequest::get().to_request()).await;
+    let generated = response.headers().get("x-request-id").unwrap();
+    assert_eq!(generated.to_str().unwrap().len(), 36);
+
+    let request = TestRequest::get()
+        .insert_header(("x-request-id", "edge-42"))
+        .to_request();
+    let response = actix_web::test::call_service(&app, request).await;
+    assert_eq!(response.headers().get("x-request-id").unwrap(), "edge-42");
+}
@@ -0,0 +1,268 @@
+//! Operator tasks behind the maintenance subcommands.
+
+use std::{env, fs};
+
+use chrono::{Duration, Utc};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, EntityTrait, IntoActiveModel, PaginatorTrait, QueryFilter, Set,
+};
+use serde_json::json;
+use uuid::Uuid;
+
+use wazzup::{
+    database::models::{channels, clients, companies, messages, tokens},
+    services::maintenance,
+    testing::{FakeWazzup, app_state_with, create_client, create_company, create_tenant, setup_db},
+};
+
+#[actix_web::test]
+async fn sync_pulls_channels_and_contacts_without_erasing_stored_emails() {
+    let db = setup_db().await;
+    let tenant = create_tenant(&db).await;
+    let client = create_client(&db, tenant.company, tenant.admin, None).await;
+    let wazzup = FakeWazzup::start().await;
+    let new_channel = Uuid::new_v4();
+    wazzup.respond(
+        "GET",
+        "/channels/list",
+        200,
+        json!({
+            "count": 1,
+            "channels": [{
+                "guid": new_channel.to_string(),
+                "transport": "telegram",
+                "deleted": false,
+                "hasAccess": true,
+                "visible": true,
+            }],
+        }),
+    );
+    wazzup.respond(
+        "GET",
+        "/v3/contacts?offset=0",
+        200,
+        json!({
+            "count": 2,
+            "data": [{
+                "id": client.to_string(),
+                "responsibleUserId": tenant.admin.to_string(),
+                "name": "Иван Петров",
+                "contactData": [{ "chatType": "whatsapp", "chatId": "79990001122" }],
+            }],
+        }),
+    );
+    let new_contact = Uuid::new_v4();
+    wazzup.respond(
+        "GET",
+        "/v3/contacts?offset=1",
+        200,
+        json!({
+            "count": 2,
+            "data": [{
+                "id": new_contact.to_string(),
+                "responsibleUserId": tenant.admin.to_string(),
+                "name": "Мария",
+                "contactData": [],
+            }],
+        }),
+    );
+
+    let report = maintenance::sync_company(&app_state_with(&db, &wazzup), &tenant.company)
+        .await
+        .unwrap();
+    assert_eq!((report.channels, report.contacts), (1, 2));
+
+    let channel = channels::Entity::find_by_id(new_channel.as_bytes().to_vec())
+        .one(&db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(channel.company_id, Some(tenant.company.as_bytes().to_vec()));
+    assert_eq!(channel.r#type, "telegram");
+
+    let updated = clients::Entity::find_by_id(client.as_bytes().to_vec())
+        .one(&db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(updated.full_name, "Иван Петров");
+    assert_eq!(updated.phone.as_deref(), Some("79990001122"));
+    assert_eq!(updated.email, Some(format!("{}@example.com", client)));
+    let created = clients::Entity::find_by_id(new_contact.as_bytes().to_vec())
+        .one(&db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(created.company_id, Some(tenant.company.as_bytes().to_vec()));
+}
+
+#[actix_web::test]
+async fn replay_and_backfill_store_messages_once() {
+    let db = setup_db().await;
+    let tenant = create_tenant(&db).await;
+    let wazzup = FakeWazzup::start().await;
+    let app_state = app_state_with(&db, &wazzup);
+    let chat = tenant.chat.to_string();
+    let stored = || {
+        messages::Entity::find()
+            .filter(messages::Column::ChatId.eq(chat.clone()))
+            .count(&db)
+    };
+
+    let replayed = Uuid::new_v4();
+    let file = env::temp_dir().join(format!("wazzup-webhook-{}.json", Uuid::new_v4()));
+    fs::write(
+        &file,
+        json!({
+            "messages": [{
+                "messageId": replayed.to_string(),
+                "channelId": tenant.channel.to_string(),
+                "chatType": "whatsapp",
+                "chatId": chat,
+                "type": "text",
+                "text": "replayed",
+                "isEcho": false,
+            }],
+        })
+        .to_string(),
+    )
+    .unwrap();
+    let result = maintenance::replay_webhook(&app_state, &tenant.company, &file).await;
+    fs::remove_file(&file).unwrap();
+    result.unwrap();
+    assert_eq!(stored().await.unwrap(), 2);
+    let company = companies::Entity::find_by_id(tenant.company.as_bytes().to_vec())
+        .one(&db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(company.last_webhook_at, None);
+
+    wazzup.respond(
+        "GET",
+        &format!("/messages?chatId={}", chat),
+        200,
+        json!({
+            "count": 2,
+            "messages": [
+                { "id": replayed.to_string(), "text": "replayed", "is_inbound": true },
+                {
+                    "id": Uuid::new_v4().to_string(),
+                    "text": "from history",
+                    "created_at": "2024-05-01T10:00:00Z",
+                    "is_inbound": false,
+                },
+            ],
+        }),
+    );
+    let report = maintenance::backfill_chat(&app_state, &tenant.company, &chat)
+        .await
+        .unwrap();
+    assert_eq!((report.fetched, report.stored), (2, 1));
+    assert_eq!(stored().await.unwrap(), 3);
+
+    let other = create_company(&db).await;
+    assert!(
+        maintenance::backfill_chat(&app_state, &other, &chat)
+            .await
+            .is_err()
+    );
+}
+
+#[actix_web::test]
+async fn purge_removes_expired_tokens_and_only_then_old_messages() {
+    let db = setup_db().await;
+    let tenant = create_tenant(&db).await;
+    let long_ago = Utc::now() - Duration::days(400);
+    let user = tokens::Entity::find()
+        .one(&db)
+        .await
+        .unwrap()
+        .unwrap()
+        .user_id;
+    tokens::ActiveModel {
+        id: Set(Uuid::new_v4().as_bytes().to_vec()),
+        name: Set(None),
+        token_hash: Set(Uuid::new_v4().to_string()),
+        user_id: Set(user),
+        created_at: Set(long_ago),
+        last_used_at: Set(long_ago),
+        expires_at: Set(long_ago + Duration::days(30)),
+        company_id: Set(None),
+    }
+    .insert(&db)
+    .await
+    .unwrap();
+    messages::ActiveModel {
+        id: Set(Uuid::new_v4().as_bytes().to_vec()),
+        content: Set(json!([{ "type": "text", "content": "old" }])),
+        chat_id: Set(tenant.chat.to_string()),
+        is_inbound: Set(Some(1)),
+        is_echo: Set(Some(0)),
+        direction_status: Set(Some("incoming".to_string())),
+        author_user_id: Set(None),
+        created_at: Set(long_ago),
+    }
+    .insert(&db)
+    .await
+    .unwrap();
+    let cutoff = Utc::now() - Duration::days(90);
+
+    let dry_run = maintenance::purge(&db, cutoff, true, true).await.unwrap();
+    assert_eq!((dry_run.tokens, dry_run.messages), (1, 1));
+    assert_eq!(tokens::Entity::find().count(&db).await.unwrap(), 2);
+
+    let report = maintenance::purge(&db, cutoff, false, false).await.unwrap();
+    assert_eq!((report.tokens, report.messages), (1, 0));
+    assert_eq!(tokens::Entity::find().count(&db).await.unwrap(), 1);
+    assert_eq!(messages::Entity::find().count(&db).await.unwrap(), 2);
+
+    maintenance::purge(&db, cutoff, true, false).await.unwrap();
+    assert_eq!(messages::Entity::find().count(&db).await.unwrap(), 1);
+}
+
+#[actix_web::test]
+async fn connect_all_subscribes_every_active_company() {
+    let db = setup_db().await;
+    let first = create_company(&db).await;
+    let second = create_company(&db).await;
+    let inactive = create_company(&db).await;
+    let mut record = companies::Entity::find_by_id(inactive.as_bytes().to_vec())
+        .one(&db)
+        .await
+        .unwrap()
+        .unwrap()
+        .into_active_model();
+    record.is_active = Set(Some(0));
+    record.update(&db).await.unwrap();
+    let wazzup = FakeWazzup::start().await;
+    let mut app_state = app_state_with(&db, &wazzup);
+
+    // Without a public URL there is no address to give Wazzup.
+    assert!(
+        maintenance::connect_webhooks(&app_state, &first)
+            .await
+            .is_err()
+    );
+    assert!(wazzup.requests().is_empty());
+
+    app_state.config.public_url = Some("https://crm.example.com".to_string());
+    app_state.config.webhook_port = Some(8443);
+    let results = maintenance::connect_all_webhooks(&app_state).await.unwrap();
+
+    let mut connected: Vec<Uuid> = results
+        .into_iter()
+        .map(|(company, result)| {
+            assert_eq!(
+                result.unwrap(),
+                format!("https://crm.example.com:8443/api/webhook/{}", company)
+            );
+            company
+        })
+        .collect();
+    connected.sort();
+    let mut expected = vec![first, second];
+    expected.sort();
+    assert_eq!(connected, expected);
+    assert_eq!(wazzup.requests_to("PATCH", "/v3/webhooks").len(), 2);
+}
@@ -0,0 +1,189 @@
+//! Keyset pagination of chat history: `before`, `after` and `around`.
+
+use actix_web::{http::StatusCode, test};
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use chrono::{Duration, Utc};
+use serde_json::Value;
+use uuid::Uuid;
+
+use wazzup::{
+    api::chats,
+    api_app,
+    testing::{app_state, authorized, create_chat, create_message, create_tenant, setup_db},
+};
+
+fn ids(page: &Value) -> Vec<String> {
+    page["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|message| message["id"].as_str().unwrap().to_string())
+        .collect()
+}
+
+#[actix_web::test]
+async fn cursors_walk_messages_sent_at_the_same_time() {
+    let db = setup_db().await;
+    let tenant = create_tenant(&db).await;
+    let chat = create_chat(&db, tenant.channel, None).await;
+    let at = Utc::now();
+    let mut sent = Vec::new();
+    for _ in 0..5 {
+        sent.push(
+            create_message(&db, chat, "same time", true, at)
+                .await
+                .to_string(),
+        );
+    }
+    // Equal times are ordered by id, newest first.
+    sent.sort();
+    sent.reverse();
+    let app = api_app!(app_state(&db), chats::init_routes);
+    let page = |query: String| {
+        authorized(
+            test::TestRequest::get().uri(&format!(
+                "/api/chats/{}/{}/messages?count=2{}",
+                tenant.company, chat, query
+            )),
+            &tenant.token,
+        )
+        .to_request()
+    };
+
+    let first: Value = test::call_and_read_body_json(&app, page(String::new())).await;
+    assert_eq!(ids(&first), sent[0..2]);
+    assert_eq!(first["hasMore"], true);
+    assert_eq!(first["hasNewer"], false);
+    assert!(first.get("prevCursor").is_none());
+
+    let next = |page: &Value| format!("&before={}", page["nextCursor"].as_str().unwrap());
+    let second: Value = test::call_and_read_body_json(&app, page(next(&first))).await;
+    assert_eq!(ids(&second), sent[2..4]);
+    assert_eq!(second["hasNewer"], true);
+    let last: Value = test::call_and_read_body_json(&app, page(next(&second))).await;
+    assert_eq!(ids(&last), sent[4..]);
+    assert_eq!(last["hasMore"], false);
+    assert!(last.get("nextCursor").is_none());
+
+    let back = format!("&after={}", last["prevCursor"].as_str().unwrap());
+    let back: Value = test::call_and_read_body_json(&app, page(back)).await;
+    assert_eq!(ids(&back), sent[2..4]);
+    assert_eq!(back["hasMore"], true);
+    assert_eq!(back["hasNewer"], true);
+}
+
+#[actix_web::test]
+async fn malformed_cursors_and_anchors_are_rejected() {
+    let db = setup_db().await;
+    let tenant = create_tenant(&db).await;
+    let app = api_app!(app_state(&db), chats::init_routes);
+    let messages = |query: &str| {
+        authorized(
+            test::TestRequest::get().uri(&format!(
+                "/api/chats/{}/{}/messages?{}",
+                tenant.company, tenant.chat, query
+            )),
+            &tenant.token,
+        )
+        .to_request()
+    };
+    let valid: Value = test::call_and_read_body_json(&app, messages("")).await;
+    let message = ids(&valid).remove(0);
+    let encode = |raw: &[u8]| URL_SAFE_NO_PAD.encode(raw);
+
+    for cursor in [
+        "not.a.cursor".to_string(),
+        encode(b"1700000000000000"),
+        encode(b"soon:00000000000000000000000000000000"),
+        encode(b"1700000000000000:not-a-uuid"),
+        encode(format!("{}0:{}", i64::MAX, Uuid::new_v4().simple()).as_bytes()),
+        encode(&[0xff, 0xfe, b':']),
+    ] {
+        for direction in ["before", "after"] {
+            let response =
+                test::call_service(&app, messages(&format!("{}={}", direction, cursor))).await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", cursor);
+            let body: Value = test::read_body_json(response).await;
+            assert_eq!(body["message"], "Invalid input: Invalid cursor");
+        }
+    }
+
+    assert_eq!(
+        test::call_service(&app, messages("around=42"))
+            .await
+            .status(),
+        StatusCode::BAD_REQUEST
+    );
+    assert_eq!(
+        test::call_service(&app, messages(&format!("around={}", Uuid::new_v4())))
+            .await
+            .status(),
+        StatusCode::NOT_FOUND
+    );
+    assert_eq!(
+        test::call_service(&app, messages(&format!("around={}&offset=1", message)))
+            .await
+            .status(),
+        StatusCode::BAD_REQUEST
+    );
+}
+
+#[actix_web::test]
+async fn pages_around_the_first_and_the_last_message_stop_at_the_ends() {
+    let db = setup_db().await;
+    let tenant = create_tenant(&db).await;
+    let chat = create_chat(&db, tenant.channel, None).await;
+    let now = Utc::now();
+    // Newest first, as pages list them.
+    let mut sent = Vec::new();
+    for minutes in 0..5 {
+        let at = now - Duration::minutes(minutes);
+        sent.push(
+            create_message(&db, chat, "message", true, at)
+                .await
+                .to_string(),
+        );
+    }
+    let app = api_app!(app_state(&db), chats::init_routes);
+    let around = |message: &str| {
+        authorized(
+            test::TestRequest::get().uri(&format!(
+                "/api/chats/{}/{}/messages?count=3&around={}",
+                tenant.company, chat, message
+            )),
+            &tenant.token,
+        )
+        .to_request()
+    };
+
+    let middle: Value = test::call_and_read_body_json(&app, around(&sent[2])).await;
+    assert_eq!(ids(&middle), sent[1..4]);
+    assert_eq!(middle["hasMore"], true);
+    assert_eq!(middle["hasNewer"], true);
+
+    let oldest: Value = test::call_and_read_body_json(&app, around(&sent[4])).await;
+    assert_eq!(ids(&oldest), sent[3..]);
+    assert_eq!(oldest["hasMore"], false);
+    assert!(oldest.get("nextCursor").is_none());
+    assert_eq!(oldest["hasNewer"], true);
+
+    let newest: Value = test::call_and_read_body_json(&app, around(&sent[0])).await;
+    assert_eq!(ids(&newest), sent[..2]);
+    assert_eq!(newest["hasNewer"], false);
+    assert!(newest.get("prevCursor").is_none());
+    assert_eq!(newest["hasMore"], true);
+
+    // The cursors of an edge page lead on from it.
+    let older = authorized(
+        test::TestRequest::get().uri(&format!(
+            "/api/chats/{}/{}/messages?count=3&before={}",
+            tenant.company,
+            chat,
+            newest["nextCursor"].as_str().unwrap()
+        )),
+        &tenant.token,
+    )
+    .to_request();
+    let older: Value = test::call_and_read_body_json(&app, older).await;
+    assert_eq!(ids(&older), sent[2..]);
+}
@@ -0,0 +1,193 @@
+//! `/metrics` exposes HTTP, Wazzup and webhook metrics with bounded labels.
+
+use actix_web::{App, http::StatusCode, test, web};
+use serde_json::json;
+use uuid::Uuid;
+
+use wazzup::{
+    api::{
+        chats, health,
+        middleware::{BearerAuth, RequestId},
+        webhooks,
+    },
+    app_state::AppState,
+};
+
+use wazzup::testing::{
+    FakeWazzup, TEST_API_KEY, app_state, app_state_with, authorized, create_tenant, setup_db,
+};
+
+macro_rules! metrics_app {
+    ($state:expr) => {
+        test::init_service(
+            App::new()
+                .app_data(web::Data::new($state))
+                .wrap(RequestId)
+                .configure(health::init_metrics_routes)
+                .service(
+                    web::scope("/api")
+                        .wrap(BearerAuth)
+                        .configure(chats::init_routes)
+                        .configure(webhooks::init_routes),
+                ),
+        )
+        .await
+    };
+}
+
+/// Line of `metric` whose labels include every one of `labels`.
+fn sample<'a>(body: &'a str, metric: &str, labels: &[&str]) -> Option<&'a str> {
+    body.lines().find(|line| {
+        line.starts_with(&format!("{}{{", metric))
+            && labels.iter().all(|label| line.contains(label))
+    })
+}
+
+#[actix_web::test]
+async fn requests_are_counted_per_route_pattern() {
+    let db = setup_db().await;
+    let tenant = create_tenant(&db).await;
+    let app = metrics_app!(app_state(&db));
+
+    let request = authorized(
+        test::TestRequest::get().uri(&format!("/api/chats/{}/previews", tenant.company)),
+        &tenant.token,
+    )
+    .to_request();
+    assert_eq!(
+        test::call_service(&app, request).await.status(),
+        StatusCode::OK
+    );
+    let request = test::TestRequest::get()
+        .uri(&format!("/no-such-page/{}", Uuid::new_v4()))
+        .to_request();
+    test::call_service(&app, request).await;
+
+    let request = test::TestRequest::get().uri("/metrics").to_request();
+    let body = String::from_utf8(test::call_and_read_body(&app, request).await.to_vec()).unwrap();
+
+    assert!(
+        sample(
+            &body,
+            "http_requests_total",
+            &[
+                r#"route="/api/chats/{companyId}/previews""#,
+                r#"status="200""#,
+                r#"method="GET""#,
+            ],
+        )
+        .is_some()
+    );
+    assert!(
+        sample(
+            &body,
+            "http_request_duration_seconds_count",
+            &[r#"route="/api/chats/{companyId}/previews""#],
+        )
+        .is_some()
+    );
+    assert!(sample(&body, "http_requests_total", &[r#"route="unmatched""#]).is_some());
+    assert!(!body.contains("no-such-page"));
+    assert!(sample(&body, "db_pool_connections", &[r#"state="max""#]).is_some());
+    assert!(
+        sample(
+            &body,
+            "background_queue_depth",
+            &[r#"queue="outgoing_webhooks""#]
+        )
+        .is_some()
+    );
+}
+
+#[actix_web::test]
+async fn wazzup_calls_and_webhook_messages_are_counted() {
+    let db = setup_db().await;
+    let tenant = create_tenant(&db).await;
+    let wazzup = FakeWazzup::start().await;
+    let state = app_state_with(&db, &wazzup);
+
+    let contact = Uuid::new_v4().to_string();
+    wazzup.respond(
+        "DELETE",
+        &format!("/v3/contacts/{}", contact),
+        503,
+        json!({}),
+    );
+    assert!(
+        state
+            .wazzup_api
+            .delete_contact(TEST_API_KEY, &contact)
+            .await
+            .is_err()
+    );
+
+    let app = metrics_app!(state);
+    let webhook = test::TestRequest::post()
+        .uri(&format!("/api/webhook/{}", tenant.company))
+        .set_json(json!({
+            "messages": [{
+                "messageId": Uuid::new_v4().to_string(),
+                "channelId": tenant.channel.to_string(),
+                "chatType": "whatsapp",
+                "chatId": "79990001122",
+                "type": "text",
+                "text": "hello",
+                "isEcho": false,
+            }]
+        }))
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, webhook).await.status(),
+        StatusCode::OK
+    );
+
+    let request = test::TestRequest::get().uri("/metrics").to_request();
+    let body = String::from_utf8(test::call_and_read_body(&app, request).await.to_vec()).unwrap();
+
+    let failed = sample(
+        &body,
+        "wazzup_api_requests_total",
+        &[
+            r#"endpoint="/v3/contacts/{param}""#,
+            r#"method="DELETE""#,
+            r#"outcome="5xx""#,
+        ],
+    )
+    .unwrap();
+    assert!(failed.ends_with(" 1"));
+    assert!(!body.contains(&contact));
+
+    let processed = sample(
+        &body,
+        "webhook_messages_total",
+        &[
+            &format!(r#"company_id="{}""#, tenant.company),
+            r#"outcome="processed""#,
+        ],
+    )
+    .unwrap();
+    assert!(processed.ends_with(" 1"));
+}
+
+#[actix_web::test]
+async fn metrics_token_is_required_when_configured() {
+    let db = setup_db().await;
+    let mut state: AppState = app_state(&db);
+    state.config.metrics_token = Some(serde_json::from_value(json!("scrape-secret")).unwrap());
+    let app = metrics_app!(state);
+
+    let request = test::TestRequest::get().uri("/metrics").to_request();
+    assert_eq!(
+        test::call_service(&app, request).await.status(),
+        StatusCode::UNAUTHORIZED
+    );
+
+    let request = test::TestRequest::get()
+        .uri("/metrics")
+        .insert_header(("Authorization", "Bearer scrape-secret"))
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, request).await.status(),
+        StatusCode::OK
+    );
+}
@@ -0,0 +1,158 @@
+//! Migrations bootstrap an empty database and upgrade one restored from the
+//! legacy dump.
+
+use chrono::Utc;
+use sea_orm::{
+    ActiveModelTrait, ConnectionTrait, Database, DatabaseConnection, DbBackend, Set, Statement,
+};
+use uuid::Uuid;
+
+use wazzup::database::{
+    migrations,
+    models::{clients, users},
+};
+
+use wazzup::testing::{create_company, setup_db};
+
+async fn count(db: &DatabaseConnection, sql: &str) -> i64 {
+    db.query_one(Statement::from_string(DbBackend::Sqlite, sql))
+        .await
+        .unwrap()
+        .unwrap()
+        .try_get::<i64>("", "cnt")
+        .unwrap()
+}
+
+#[actix_web::test]
+async fn migrations_are_applied_once() {
+    let db = Database::connect("sqlite::memory:").await.unwrap();
+
+    let applied = migrations::run(&db).await.unwrap();
+    assert_eq!(applied, migrations::MIGRATIONS);
+    assert!(migrations::run(&db).await.unwrap().is_empty());
+    assert!(migrations::pending(&db).await.unwrap().is_empty());
+
+    let recorded: Vec<i32> = migrations::applied(&db)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|record| record.version)
+        .collect();
+    assert_eq!(recorded, vec![1, 2, 3, 4, 5]);
+
+    assert_eq!(
+        count(
+            &db,
+            "SELECT COUNT(*) AS cnt FROM sqlite_master \
+             WHERE type = 'index' AND name = 'idx_messages_chat_created_at'",
+        )
+        .await,
+        1
+    );
+    // Deliveries refer to events by UUID rather than by stream position.
+    for (column, expected) in [("event_uid", 1), ("event_id", 0)] {
+        let sql = format!(
+            "SELECT COUNT(*) AS cnt FROM pragma_table_info('outgoing_webhook_deliveries') \
+             WHERE name = '{}'",
+            column
+        );
+        assert_eq!(count(&db, &sql).await, expected, "{}", column);
+    }
+}
+
+#[actix_web::test]
+async fn legacy_tables_get_service_columns() {
+    let db = Database::connect("sqlite::memory:").await.unwrap();
+    db.execute_unprepared("CREATE TABLE companies (id BLOB PRIMARY KEY, name TEXT)")
+        .await
+        .unwrap();
+    db.execute_unprepared("CREATE TABLE channels (id BLOB PRIMARY KEY, type TEXT NOT NULL)")
+        .await
+        .unwrap();
+
+    migrations::run(&db).await.unwrap();
+
+    assert_eq!(
+        count(
+            &db,
+            "SELECT COUNT(*) AS cnt FROM pragma_table_info('channels') WHERE name = 'company_id'",
+        )
+        .await,
+        1
+    );
+    assert_eq!(
+        count(
+            &db,
+            "SELECT COUNT(*) AS cnt FROM pragma_table_info('companies') \
+             WHERE name = 'last_webhook_at'",
+        )
+        .await,
+        1
+    );
+}
+
+#[actix_web::test]
+async fn a_failed_migration_is_rolled_back_as_a_whole() {
+    let db = Database::connect("sqlite::memory:").await.unwrap();
+    // A leftover `jobs` table whose keys the unique index of the queue rejects.
+    db.execute_unprepared(
+        "CREATE TABLE jobs (id INTEGER PRIMARY KEY, status TEXT, run_at TEXT, unique_key TEXT); \
+         INSERT INTO jobs (unique_key) VALUES ('sync'), ('sync')",
+    )
+    .await
+    .unwrap();
+
+    assert!(migrations::run(&db).await.is_err());
+
+    let recorded: Vec<i32> = migrations::applied(&db)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|record| record.version)
+        .collect();
+    assert_eq!(recorded, vec![1, 2, 3]);
+    assert_eq!(
+        count(
+            &db,
+            "SELECT COUNT(*) AS cnt FROM sqlite_master \
+             WHERE type = 'table' AND name = 'recurring_jobs'",
+        )
+        .await,
+        0
+    );
+}
+
+#[actix_web::test]
+async fn client_emails_are_unique_within_a_company() {
+    let db = setup_db().await;
+    let company = create_company(&db).await;
+    let other = create_company(&db).await;
+    let responsible = Uuid::new_v4().as_bytes().to_vec();
+    users::ActiveModel {
+        id: Set(responsible.clone()),
+        name: Set(None),
+        login: Set("owner".to_string()),
+        email: Set("owner@example.com".to_string()),
+        password: Set(String::new()),
+        role: Set(None),
+        resource_id: Set(None),
+        bot_hook: Set(None),
+    }
+    .insert(&db)
+    .await
+    .unwrap();
+
+    let client = |company: Uuid| clients::ActiveModel {
+        id: Set(Uuid::new_v4().as_bytes().to_vec()),
+        company_id: Set(Some(company.as_bytes().to_vec())),
+        full_name: Set("Client".to_string()),
+        email: Set(Some("client@example.com".to_string())),
+        phone: Set(None),
+        responsible_user_id: Set(responsible.clone()),
+        created_at: Set(Utc::now()),
+    };
+
+    client(company).insert(&db).await.unwrap();
+    client(other).insert(&db).await.unwrap();
+    assert!(client(company).insert(&db).await.is_err());
+}
@@ -0,0 +1,272 @@
+//! Delivery of outgoing webhooks to company endpoints.
+
+use std::{
+    net::SocketAddr,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+use actix_web::{http::StatusCode, test};
+use chrono::Utc;
+use sea_orm::{ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, Set};
+use serde_json::json;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+};
+use uuid::Uuid;
+
+use wazzup::{
+    api::{contacts, helpers::uuid_to_bytes},
+    api_app,
+    config::Config,
+    database::models::{outgoing_webhook_deliveries, outgoing_webhooks},
+    services::{
+        background::TaskManager,
+        outgoing_webhooks::{OutgoingWebhookService, STATUS_PENDING, STATUS_SUCCEEDED},
+    },
+    testing::{
+        FakeWazzup, app_state_with, authorized, create_client, create_tenant, setup_db, test_config,
+    },
+};
+
+async fn webhook(db: &DatabaseConnection, company: Uuid, url: &str) -> outgoing_webhooks::Model {
+    let now = Utc::now();
+    outgoing_webhooks::ActiveModel {
+        id: Set(uuid_to_bytes(&Uuid::new_v4())),
+        company_id: Set(uuid_to_bytes(&company)),
+        url: Set(url.to_string()),
+        secret: Set("secret".to_string()),
+        event_types: Set(json!([])),
+        description: Set(None),
+        is_active: Set(1),
+        created_at: Set(now),
+        updated_at: Set(now),
+    }
+    .insert(db)
+    .await
+    .unwrap()
+}
+
+async fn webhook_with_delivery(
+    db: &DatabaseConnection,
+    company: Uuid,
+    url: &str,
+) -> (outgoing_webhooks::Model, outgoing_webhook_deliveries::Model) {
+    let now = Utc::now();
+    let webhook = webhook(db, company, url).await;
+    let delivery = outgoing_webhook_deliveries::ActiveModel {
+        id: Set(uuid_to_bytes(&Uuid::new_v4())),
+        webhook_id: Set(webhook.id.clone()),
+        company_id: Set(uuid_to_bytes(&company)),
+        event_uid: Set(Some(uuid_to_bytes(&Uuid::new_v4()))),
+        event_type: Set("message.created".to_string()),
+        payload: Set(json!({ "id": 1 })),
+        status: Set(STATUS_PENDING.to_string()),
+        attempts: Set(0),
+        response_status: Set(None),
+        last_error: Set(None),
+        next_attempt_at: Set(Some(now)),
+        created_at: Set(now),
+        delivered_at: Set(None),
+    }
+    .insert(db)
+    .await
+    .unwrap();
+    (webhook, delivery)
+}
+
+/// Local endpoint answering every request with `respond(its address)`;
+/// counts the requests it gets.
+async fn receiver(respond: fn(SocketAddr) -> String) -> (SocketAddr, Arc<AtomicUsize>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let requests = Arc::new(AtomicUsize::new(0));
+    let counter = requests.clone();
+    tokio::spawn(async move {
+        while let Ok((mut socket, _)) = listener.accept().await {
+            counter.fetch_add(1, Ordering::SeqCst);
+            let mut buffer = [0; 4096];
+            let _ = socket.read(&mut buffer).await;
+            let _ = socket.write_all(respond(address).as_bytes()).await;
+        }
+    });
+    (address, requests)
+}
+
+fn allowing_private_networks() -> OutgoingWebhookService {
+    OutgoingWebhookService::from_config(&Config {
+        outgoing_webhooks_allow_private_networks: Some(true),
+        ..test_config()
+    })
+    .unwrap()
+}
+
+#[actix_web::test]
+async fn deliveries_to_internal_addresses_are_refused() {
+    let db = setup_db().await;
+    let tenant = create_tenant(&db).await;
+    let service = OutgoingWebhookService::from_config(&test_config()).unwrap();
+    let (webhook, delivery) =
+        webhook_with_delivery(&db, tenant.company, "http://127.0.0.1:9/hook").await;
+
+    let delivery = service.redeliver(&db, &webhook, delivery).await.unwrap();
+
+    assert_eq!(delivery.status, STATUS_PENDING);
+    assert_eq!(delivery.attempts, 1);
+    assert_eq!(delivery.response_status, None);
+    assert!(
+        delivery
+            .last_error
+            .as_deref()
+            .is_some_and(|error| error.starts_with("Target refused")),
+        "{:?}",
+        delivery.last_error
+    );
+}
+
+#[actix_web::test]
+async fn redirects_are_not_followed() {
+    let db = setup_db().await;
+    let tenant = create_tenant(&db).await;
+    let service = allowing_private_networks();
+    let (address, requests) = receiver(|address| {
+        format!(
+            "HTTP/1.1 307 Temporary Redirect\r\nLocation: http://{}/elsewhere\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            address
+        )
+    })
+    .await;
+
+    let (webhook, delivery) =
+        webhook_with_delivery(&db, tenant.company, &format!("http://{}/hook", address)).await;
+    let delivery = service.redeliver(&db, &webhook, delivery).await.unwrap();
+
+    assert_eq!(delivery.status, STATUS_PENDING);
+    assert_eq!(delivery.response_status, Some(307));
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+}
+
+#[actix_web::test]
+async fn events_are_recorded_for_delivery_before_the_request_returns() {
+    let db = setup_db().await;
+    let tenant = create_tenant(&db).await;
+    let wazzup = FakeWazzup::start().await;
+    let (address, requests) = receiver(|_| {
+        "HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string()
+    })
+    .await;
+    let webhook = webhook(&db, tenant.company, &format!("http://{}/hook", address)).await;
+    // No dispatcher listens to the bus, as after a restart.
+    let state = app_state_with(&db, &wazzup);
+    let mut bus = state.events.subscribe();
+    let app = api_app!(state, contacts::init_routes);
+
+    let client = create_client(&db, tenant.company, tenant.admin, None).await;
+    let request = authorized(
+        test::TestRequest::put()
+            .uri(&format!("/api/contacts/{}/{}", tenant.company, client))
+            .set_json(json!({ "fullName": "Иван Петров", "email": "ivan@example.com" })),
+        &tenant.token,
+    )
+    .to_request();
+    assert_eq!(
+        test::call_service(&app, request).await.status(),
+        StatusCode::OK
+    );
+
+    let recorded = outgoing_webhook_deliveries::Entity::find()
+        .filter(outgoing_webhook_deliveries::Column::WebhookId.eq(webhook.id.clone()))
+        .all(&db)
+        .await
+        .unwrap();
+    assert_eq!(recorded.len(), 1);
+    assert_eq!(recorded[0].event_type, "client.updated");
+    assert_eq!(recorded[0].status, STATUS_PENDING);
+    assert_eq!(recorded[0].attempts, 0);
+    // Deliveries and their body refer to the event by its stable id.
+    let event = bus.try_recv().unwrap();
+    assert_eq!(recorded[0].event_uid, Some(uuid_to_bytes(&event.uid)));
+    assert_eq!(recorded[0].payload["id"], event.uid.to_string());
+
+    allowing_private_networks().deliver_due(&db).await.unwrap();
+
+    let delivered = outgoing_webhook_deliveries::Entity::find_by_id(recorded[0].id.clone())
+        .one(&db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(delivered.status, STATUS_SUCCEEDED);
+    assert_eq!(delivered.response_status, Some(200));
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+}
+
+#[actix_web::test]
+async fn shutdown_waits_for_deliveries_the_dispatcher_is_sending() {
+    let db = setup_db().await;
+    let tenant = create_tenant(&db).await;
+    let wazzup = FakeWazzup::start().await;
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let requests = Arc::new(AtomicUsize::new(0));
+    let counter = requests.clone();
+    tokio::spawn(async move {
+        while let Ok((mut socket, _)) = listener.accept().await {
+            counter.fetch_add(1, Ordering::SeqCst);
+            let mut buffer = [0; 4096];
+            let _ = socket.read(&mut buffer).await;
+            tokio::time::sleep(Duration::from_millis(300)).await;
+            let _ = socket
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+                .await;
+        }
+    });
+    let webhook = webhook(&db, tenant.company, &format!("http://{}/hook", address)).await;
+
+    let state = app_state_with(&db, &wazzup);
+    let tasks = TaskManager::new();
+    {
+        let (db, events) = (db.clone(), state.events.clone());
+        tasks.spawn_service("outgoing-webhooks.dispatch", move |shutdown| {
+            allowing_private_networks().listen(db.clone(), events.clone(), shutdown)
+        });
+    }
+    let app = api_app!(state, contacts::init_routes);
+    // Lets the dispatcher subscribe to the bus.
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    let client = create_client(&db, tenant.company, tenant.admin, None).await;
+    let request = authorized(
+        test::TestRequest::put()
+            .uri(&format!("/api/contacts/{}/{}", tenant.company, client))
+            .set_json(json!({ "fullName": "Иван Петров", "email": "ivan@example.com" })),
+        &tenant.token,
+    )
+    .to_request();
+    assert_eq!(
+        test::call_service(&app, request).await.status(),
+        StatusCode::OK
+    );
+    let sending = async {
+        while requests.load(Ordering::SeqCst) == 0 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    };
+    tokio::time::timeout(Duration::from_secs(5), sending)
+        .await
+        .expect("the dispatcher sends the delivery");
+
+    tasks.shutdown(Duration::from_secs(5)).await;
+
+    let delivery = outgoing_webhook_deliveries::Entity::find()
+        .filter(outgoing_webhook_deliveries::Column::WebhookId.eq(webhook.id.clone()))
+        .one(&db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(delivery.status, STATUS_SUCCEEDED);
+    assert_eq!(delivery.attempts, 1);
+}
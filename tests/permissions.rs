@@ -0,0 +1,254 @@
+//! Routes are guarded by the permissions of the user's role in the company.
+
+use actix_web::{http::StatusCode, test};
+use chrono::Utc;
+use sea_orm::{ActiveModelTrait, DatabaseConnection, EntityTrait, Set};
+use serde_json::{Value, json};
+use uuid::Uuid;
+
+use wazzup::{
+    api::{chats, contacts, webhooks},
+    database::models::{channels, chats as chat_models, clients, company_users},
+};
+
+use wazzup::{
+    api_app,
+    testing::{
+        app_state, authorized, create_client, create_member, create_member_user, create_tenant,
+        setup_db,
+    },
+};
+
+/// Moves the tenant's chat to a client `responsible` is responsible for.
+async fn assign_client(db: &DatabaseConnection, company: Uuid, chat: Uuid, responsible: &[u8]) {
+    let client = Uuid::new_v4();
+    clients::ActiveModel {
+        id: Set(client.as_bytes().to_vec()),
+        company_id: Set(Some(company.as_bytes().to_vec())),
+        full_name: Set(format!("Client {}", client)),
+        email: Set(None),
+        phone: Set(None),
+        responsible_user_id: Set(responsible.to_vec()),
+        created_at: Set(Utc::now()),
+    }
+    .insert(db)
+    .await
+    .unwrap();
+
+    let mut record: chat_models::ActiveModel = chat_models::Entity::find_by_id(chat.to_string())
+        .one(db)
+        .await
+        .unwrap()
+        .unwrap()
+        .into();
+    record.client_id = Set(Some(client.as_bytes().to_vec()));
+    record.update(db).await.unwrap();
+}
+
+async fn member_id(db: &DatabaseConnection, company: Uuid, role: &str) -> Vec<u8> {
+    company_users::Entity::find()
+        .all(db)
+        .await
+        .unwrap()
+        .into_iter()
+        .find(|member| {
+            member.company_id == company.as_bytes().to_vec() && member.role.as_deref() == Some(role)
+        })
+        .unwrap()
+        .user_id
+}
+
+#[actix_web::test]
+async fn employees_can_read_but_not_reassign_chats() {
+    let db = setup_db().await;
+    let tenant = create_tenant(&db).await;
+    let employee = create_member(&db, tenant.company, "employee").await;
+    let app = api_app!(app_state(&db), chats::init_routes);
+
+    let previews = authorized(
+        test::TestRequest::get().uri(&format!("/api/chats/{}/previews", tenant.company)),
+        &employee,
+    )
+    .to_request();
+    assert_eq!(
+        test::call_service(&app, previews).await.status(),
+        StatusCode::OK
+    );
+
+    let assign = authorized(
+        test::TestRequest::post()
+            .uri(&format!(
+                "/api/chats/{}/{}/assign",
+                tenant.company, tenant.chat
+            ))
+            .set_json(json!({ "userId": Uuid::new_v4().to_string() })),
+        &employee,
+    )
+    .to_request();
+    assert_eq!(
+        test::call_service(&app, assign).await.status(),
+        StatusCode::FORBIDDEN
+    );
+}
+
+#[actix_web::test]
+async fn members_without_a_role_are_forbidden() {
+    let db = setup_db().await;
+    let tenant = create_tenant(&db).await;
+    let guest = create_member(&db, tenant.company, "guest").await;
+    let app = api_app!(app_state(&db), chats::init_routes);
+
+    let request = authorized(
+        test::TestRequest::get().uri(&format!("/api/chats/{}/previews", tenant.company)),
+        &guest,
+    )
+    .to_request();
+    assert_eq!(
+        test::call_service(&app, request).await.status(),
+        StatusCode::FORBIDDEN
+    );
+}
+
+#[actix_web::test]
+async fn managers_can_be_limited_to_their_own_chats() {
+    let db = setup_db().await;
+    let own = create_tenant(&db).await;
+    let manager = create_member(&db, own.company, "manager").await;
+    let manager_id = member_id(&db, own.company, "manager").await;
+    let admin_id = member_id(&db, own.company, "admin").await;
+    assign_client(&db, own.company, own.chat, &manager_id).await;
+
+    // A second chat of the same company, handled by the admin.
+    let other = create_tenant(&db).await;
+    let mut channel: channels::ActiveModel =
+        channels::Entity::find_by_id(other.channel.as_bytes().to_vec())
+            .one(&db)
+            .await
+            .unwrap()
+            .unwrap()
+            .into();
+    channel.company_id = Set(Some(own.company.as_bytes().to_vec()));
+    channel.update(&db).await.unwrap();
+    assign_client(&db, own.company, other.chat, &admin_id).await;
+
+    let preview_ids = |body: Value| -> Vec<String> {
+        body["data"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|preview| preview["id"].as_str().unwrap().to_string())
+            .collect()
+    };
+
+    let unrestricted = api_app!(app_state(&db), chats::init_routes);
+    let request = authorized(
+        test::TestRequest::get().uri(&format!("/api/chats/{}/previews", own.company)),
+        &manager,
+    )
+    .to_request();
+    let body: Value = test::call_and_read_body_json(&unrestricted, request).await;
+    assert_eq!(preview_ids(body).len(), 2);
+
+    let mut state = app_state(&db);
+    state.config.manager_own_chats_only = Some(true);
+    let restricted = api_app!(state, chats::init_routes);
+
+    let request = authorized(
+        test::TestRequest::get().uri(&format!("/api/chats/{}/previews", own.company)),
+        &manager,
+    )
+    .to_request();
+    let body: Value = test::call_and_read_body_json(&restricted, request).await;
+    assert_eq!(preview_ids(body), vec![own.chat.to_string()]);
+
+    let request = authorized(
+        test::TestRequest::get().uri(&format!("/api/chats/{}/{}", own.company, other.chat)),
+        &manager,
+    )
+    .to_request();
+    assert_eq!(
+        test::call_service(&restricted, request).await.status(),
+        StatusCode::NOT_FOUND
+    );
+}
+
+#[actix_web::test]
+async fn restricted_managers_only_see_and_edit_their_own_clients() {
+    let db = setup_db().await;
+    let tenant = create_tenant(&db).await;
+    let (manager, token) = create_member_user(&db, tenant.company, "manager").await;
+    let own = create_client(&db, tenant.company, manager, None).await;
+    let foreign = create_client(&db, tenant.company, tenant.admin, None).await;
+
+    let mut state = app_state(&db);
+    state.config.manager_own_chats_only = Some(true);
+    let app = api_app!(state, contacts::init_routes);
+
+    let request = authorized(
+        test::TestRequest::get().uri(&format!("/api/contacts/{}", tenant.company)),
+        &token,
+    )
+    .to_request();
+    let body: Value = test::call_and_read_body_json(&app, request).await;
+    let ids: Vec<&str> = body
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|contact| contact["id"].as_str().unwrap())
+        .collect();
+    assert_eq!(ids, [own.to_string()]);
+
+    let uri = format!("/api/contacts/{}/{}", tenant.company, foreign);
+    for request in [
+        test::TestRequest::get().uri(&uri),
+        test::TestRequest::put().uri(&uri).set_json(json!({
+            "fullName": "Taken over",
+            "email": "taken@example.com"
+        })),
+        test::TestRequest::delete().uri(&uri),
+    ] {
+        let response = test::call_service(&app, authorized(request, &token).to_request()).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+    let client = clients::Entity::find_by_id(foreign.as_bytes().to_vec())
+        .one(&db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(client.full_name, format!("Client {}", foreign));
+}
+
+#[actix_web::test]
+async fn only_webhook_callbacks_are_public() {
+    let db = setup_db().await;
+    let tenant = create_tenant(&db).await;
+    let manager = create_member(&db, tenant.company, "manager").await;
+    let app = api_app!(app_state(&db), webhooks::init_routes);
+
+    let callback = test::TestRequest::get()
+        .uri(&format!("/api/webhook/{}", tenant.company))
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, callback).await.status(),
+        StatusCode::OK
+    );
+
+    let anonymous = test::TestRequest::post()
+        .uri(&format!("/api/webhook/{}/test", tenant.company))
+        .to_request();
+    let error = test::try_call_service(&app, anonymous).await.unwrap_err();
+    assert_eq!(
+        error.as_response_error().status_code(),
+        StatusCode::UNAUTHORIZED
+    );
+
+    let request = authorized(
+        test::TestRequest::post().uri(&format!("/api/webhook/{}/test", tenant.company)),
+        &manager,
+    )
+    .to_request();
+    assert_eq!(
+        test::call_service(&app, request).await.status(),
+        StatusCode::FORBIDDEN
+    );
+}
@@ -0,0 +1,138 @@
+//! Chat previews: ordering, last messages, pagination and unread counters.
+
+use actix_web::test;
+use chrono::{Duration, Utc};
+use sea_orm::{ActiveModelTrait, EntityTrait, IntoActiveModel, Set};
+use serde_json::{Value, json};
+use uuid::Uuid;
+
+use wazzup::{
+    api::chats,
+    api_app,
+    database::models::chats as chat_models,
+    testing::{app_state, authorized, create_chat, create_message, create_tenant, setup_db},
+};
+
+#[actix_web::test]
+async fn previews_list_the_most_recently_active_chats_first() {
+    let db = setup_db().await;
+    let tenant = create_tenant(&db).await;
+    let now = Utc::now();
+
+    let earlier = create_chat(&db, tenant.channel, None).await;
+    let read = create_message(&db, earlier, "first", true, now - Duration::minutes(10)).await;
+    let unread = create_message(&db, earlier, "second", true, now - Duration::minutes(5)).await;
+
+    // Two messages at the same time: the one with the higher id is the last.
+    let tied = create_chat(&db, tenant.channel, None).await;
+    let at = now - Duration::minutes(1);
+    let mut tie = [
+        create_message(&db, tied, "tie", true, at).await,
+        create_message(&db, tied, "tie", false, at).await,
+    ];
+    tie.sort();
+
+    // Chats without messages come last, by name.
+    let mut quiet = [
+        create_chat(&db, tenant.channel, None).await,
+        create_chat(&db, tenant.channel, None).await,
+    ];
+    quiet.sort_by_key(|chat| format!("Chat {}", chat));
+
+    let app = api_app!(app_state(&db), chats::init_routes);
+    let mark_read = authorized(
+        test::TestRequest::post()
+            .uri(&format!("/api/chats/{}/{}/read", tenant.company, earlier))
+            .set_json(json!({ "messageId": read.to_string() })),
+        &tenant.token,
+    )
+    .to_request();
+    test::call_service(&app, mark_read).await;
+    let previews = |query: &str| {
+        authorized(
+            test::TestRequest::get()
+                .uri(&format!("/api/chats/{}/previews{}", tenant.company, query)),
+            &tenant.token,
+        )
+        .to_request()
+    };
+    let ids = |page: &Value| -> Vec<String> {
+        page["data"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|preview| preview["id"].as_str().unwrap().to_string())
+            .collect()
+    };
+
+    let all: Value = test::call_and_read_body_json(&app, previews("")).await;
+    let order: Vec<String> = [tenant.chat, tied, earlier, quiet[0], quiet[1]]
+        .iter()
+        .map(Uuid::to_string)
+        .collect();
+    assert_eq!(ids(&all), order);
+
+    let data = all["data"].as_array().unwrap();
+    assert_eq!(data[0]["unreadCount"], 1);
+    assert_eq!(data[1]["lastMessage"]["id"], tie[1].to_string());
+    assert_eq!(data[1]["unreadCount"], 1);
+    assert_eq!(data[2]["lastMessage"]["id"], unread.to_string());
+    assert_eq!(data[2]["unreadCount"], 1);
+    for preview in &data[3..] {
+        assert!(preview.get("lastMessage").is_none());
+        assert_eq!(preview["unreadCount"], 0);
+    }
+
+    let mut paged = Vec::new();
+    for offset in [0, 2, 4] {
+        let page: Value =
+            test::call_and_read_body_json(&app, previews(&format!("?offset={}&count=2", offset)))
+                .await;
+        paged.extend(ids(&page));
+    }
+    assert_eq!(paged, order);
+
+    let page: Value = test::call_and_read_body_json(&app, previews("?offset=1&count=2")).await;
+    assert_eq!(page["data"][1]["lastMessage"]["id"], unread.to_string());
+    assert_eq!(page["data"][1]["unreadCount"], 1);
+}
+
+#[actix_web::test]
+async fn name_filters_match_wildcard_characters_literally() {
+    let db = setup_db().await;
+    let tenant = create_tenant(&db).await;
+    let sale = create_chat(&db, tenant.channel, None).await;
+    let mut chat = chat_models::Entity::find_by_id(sale.to_string())
+        .one(&db)
+        .await
+        .unwrap()
+        .unwrap()
+        .into_active_model();
+    chat.name = Set("Sale 50% off".to_string());
+    chat.update(&db).await.unwrap();
+
+    let app = api_app!(app_state(&db), chats::init_routes);
+    for (filter, expected) in [
+        ("50%25", vec![sale.to_string()]),
+        ("%25", vec![sale.to_string()]),
+        ("_", vec![]),
+        ("%5C", vec![]),
+    ] {
+        let request = authorized(
+            test::TestRequest::get().uri(&format!(
+                "/api/chats/{}/previews?filter={}",
+                tenant.company, filter
+            )),
+            &tenant.token,
+        )
+        .to_request();
+        let page: Value = test::call_and_read_body_json(&app, request).await;
+        let ids: Vec<String> = page["data"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|preview| preview["id"].as_str().unwrap().to_string())
+            .collect();
+        assert_eq!(ids, expected, "filter {}", filter);
+    }
+}
@@ -0,0 +1,271 @@
+//! Inbound rate limits: per IP, token and company, with separate budgets for
+//! expensive routes and the webhook listener.
+
+use std::net::SocketAddr;
+
+use actix_web::{App, HttpResponse, body::to_bytes, http::StatusCode, test, web};
+use serde_json::Value;
+use uuid::Uuid;
+
+use wazzup::{
+    api::{
+        chats, contacts,
+        middleware::{BearerAuth, RateLimit, RequestId},
+        webhooks,
+    },
+    config::RateLimits,
+    services::rate_limit::RateLimiter,
+    testing::{app_state, authorized, create_member, create_tenant, setup_db},
+};
+
+const UNLIMITED: RateLimits = RateLimits {
+    token: 0,
+    company: 0,
+    ip: 0,
+    expensive_token: 0,
+    expensive_company: 0,
+    webhook_ip: 0,
+};
+
+/// Test service with the API scope wrapped as in `main`.
+macro_rules! limited_api {
+    ($db:expr, $limits:expr) => {
+        limited_api!($db, $limits, Vec::new())
+    };
+    ($db:expr, $limits:expr, $trusted_proxies:expr) => {{
+        let limiter = RateLimiter::new();
+        test::init_service(
+            App::new()
+                .app_data(web::Data::new(app_state($db)))
+                .wrap(RequestId)
+                .service(
+                    web::scope("/api")
+                        .wrap(RateLimit::per_principal(limiter.clone(), $limits))
+                        .wrap(BearerAuth)
+                        .wrap(
+                            RateLimit::per_ip(limiter.clone(), $limits).trusting($trusted_proxies),
+                        )
+                        .configure(contacts::init_routes)
+                        .configure(chats::init_routes),
+                ),
+        )
+        .await
+    }};
+}
+
+/// Response to `request`, also when a middleware rejected it.
+macro_rules! call {
+    ($app:expr, $request:expr) => {
+        match test::try_call_service($app, $request.to_request()).await {
+            Ok(response) => response.map_into_boxed_body().into_parts().1,
+            Err(err) => err.error_response(),
+        }
+    };
+}
+
+fn header(response: &HttpResponse, name: &str) -> Option<String> {
+    response
+        .headers()
+        .get(name)
+        .map(|value| value.to_str().unwrap().to_string())
+}
+
+fn from(ip: &str) -> test::TestRequest {
+    test::TestRequest::default().peer_addr(SocketAddr::new(ip.parse().unwrap(), 40000))
+}
+
+#[actix_web::test]
+async fn token_budget_rejects_with_retry_after() {
+    let db = setup_db().await;
+    let tenant = create_tenant(&db).await;
+    let other = create_member(&db, tenant.company, "manager").await;
+    let app = limited_api!(
+        &db,
+        RateLimits {
+            token: 2,
+            ..UNLIMITED
+        }
+    );
+    let contacts = |token: &str| {
+        authorized(
+            test::TestRequest::get().uri(&format!("/api/contacts/{}", tenant.company)),
+            token,
+        )
+    };
+
+    let first = call!(&app, contacts(&tenant.token));
+    assert_eq!(first.status(), StatusCode::OK);
+    assert_eq!(header(&first, "x-ratelimit-limit").as_deref(), Some("2"));
+    assert_eq!(
+        header(&first, "x-ratelimit-remaining").as_deref(),
+        Some("1")
+    );
+    assert_eq!(header(&first, "x-ratelimit-reset").as_deref(), Some("30"));
+    assert_eq!(
+        call!(&app, contacts(&tenant.token)).status(),
+        StatusCode::OK
+    );
+
+    let rejected = call!(&app, contacts(&tenant.token));
+    assert_eq!(rejected.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(header(&rejected, "retry-after").as_deref(), Some("30"));
+    assert_eq!(
+        header(&rejected, "x-ratelimit-remaining").as_deref(),
+        Some("0")
+    );
+    assert!(header(&rejected, "x-request-id").is_some());
+    let body = to_bytes(rejected.into_body()).await.unwrap();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["code"], "RATE_LIMITED");
+
+    // Another token of the same company has its own budget.
+    assert_eq!(call!(&app, contacts(&other)).status(), StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn expensive_routes_and_companies_have_their_own_budgets() {
+    let db = setup_db().await;
+    let tenant = create_tenant(&db).await;
+    let other = create_member(&db, tenant.company, "manager").await;
+    let app = limited_api!(
+        &db,
+        RateLimits {
+            company: 4,
+            expensive_token: 1,
+            ..UNLIMITED
+        }
+    );
+    let get = |path: &str, token: &str| {
+        authorized(
+            test::TestRequest::get().uri(&format!("/api/{}", path)),
+            token,
+        )
+    };
+    let previews = format!("chats/{}/previews", tenant.company);
+    let search = format!("chats/{}/search?q=order", tenant.company);
+    let contacts = format!("contacts/{}", tenant.company);
+
+    assert_eq!(
+        call!(&app, get(&previews, &tenant.token)).status(),
+        StatusCode::OK
+    );
+    let rejected = call!(&app, get(&previews, &tenant.token));
+    assert_eq!(rejected.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(header(&rejected, "retry-after").as_deref(), Some("60"));
+    // Search shares the budget of heavy routes.
+    assert_eq!(
+        call!(&app, get(&search, &tenant.token)).status(),
+        StatusCode::TOO_MANY_REQUESTS
+    );
+    // Cheap routes are still open to the same token.
+    assert_eq!(
+        call!(&app, get(&contacts, &tenant.token)).status(),
+        StatusCode::OK
+    );
+
+    // The rejected request took nothing: two of four company requests used.
+    assert_eq!(call!(&app, get(&previews, &other)).status(), StatusCode::OK);
+    assert_eq!(call!(&app, get(&contacts, &other)).status(), StatusCode::OK);
+    for token in [&tenant.token, &other] {
+        assert_eq!(
+            call!(&app, get(&contacts, token)).status(),
+            StatusCode::TOO_MANY_REQUESTS
+        );
+    }
+}
+
+#[actix_web::test]
+async fn ip_budgets_count_requests_before_authentication() {
+    let db = setup_db().await;
+    let app = limited_api!(&db, RateLimits { ip: 2, ..UNLIMITED });
+    let company = Uuid::new_v4();
+    let anonymous = |ip: &str| from(ip).uri(&format!("/api/contacts/{}", company));
+
+    for _ in 0..2 {
+        assert_eq!(
+            call!(&app, anonymous("203.0.113.7")).status(),
+            StatusCode::UNAUTHORIZED
+        );
+    }
+    assert_eq!(
+        call!(&app, anonymous("203.0.113.7")).status(),
+        StatusCode::TOO_MANY_REQUESTS
+    );
+    assert_eq!(
+        call!(&app, anonymous("203.0.113.8")).status(),
+        StatusCode::UNAUTHORIZED
+    );
+}
+
+#[actix_web::test]
+async fn forwarded_addresses_are_only_taken_from_trusted_proxies() {
+    let db = setup_db().await;
+    let app = limited_api!(
+        &db,
+        RateLimits { ip: 1, ..UNLIMITED },
+        vec!["10.0.0.0/8".parse().unwrap()]
+    );
+    let company = Uuid::new_v4();
+    let request = |peer: &str, forwarded_for: &str| {
+        from(peer)
+            .insert_header(("X-Forwarded-For", forwarded_for))
+            .uri(&format!("/api/contacts/{}", company))
+    };
+
+    // Behind the proxy the rightmost untrusted hop is the client, whatever
+    // the client put in front of it.
+    assert_eq!(
+        call!(&app, request("10.0.0.1", "1.1.1.1, 203.0.113.7")).status(),
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        call!(&app, request("10.0.0.2", "2.2.2.2, 203.0.113.7, 10.0.0.3")).status(),
+        StatusCode::TOO_MANY_REQUESTS
+    );
+    assert_eq!(
+        call!(&app, request("10.0.0.1", "203.0.113.8")).status(),
+        StatusCode::UNAUTHORIZED
+    );
+
+    // A client connecting directly cannot pick its address.
+    assert_eq!(
+        call!(&app, request("198.51.100.5", "203.0.113.9")).status(),
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        call!(&app, request("198.51.100.5", "203.0.113.10")).status(),
+        StatusCode::TOO_MANY_REQUESTS
+    );
+}
+
+#[actix_web::test]
+async fn webhook_listener_has_its_own_ip_budget() {
+    let db = setup_db().await;
+    let tenant = create_tenant(&db).await;
+    let limiter = RateLimiter::new();
+    let limits = RateLimits {
+        ip: 100,
+        webhook_ip: 1,
+        ..UNLIMITED
+    };
+    let app = test::init_service(
+        App::new().app_data(web::Data::new(app_state(&db))).service(
+            web::scope("/api")
+                .wrap(RateLimit::webhooks(limiter.clone(), limits))
+                .configure(webhooks::init_routes),
+        ),
+    )
+    .await;
+    let webhook = || {
+        from("198.51.100.1")
+            .method(actix_web::http::Method::POST)
+            .uri(&format!("/api/webhook/{}", tenant.company))
+            .set_json(serde_json::json!({ "test": true }))
+    };
+
+    assert_eq!(call!(&app, webhook()).status(), StatusCode::OK);
+    assert_eq!(
+        call!(&app, webhook()).status(),
+        StatusCode::TOO_MANY_REQUESTS
+    );
+}
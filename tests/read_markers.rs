@@ -0,0 +1,128 @@
+//! Read markers and unread counters of chats, per reading user.
+
+use actix_web::{http::StatusCode, test};
+use chrono::{Duration, Utc};
+use serde_json::{Value, json};
+
+use wazzup::{
+    api::chats,
+    api_app,
+    testing::{
+        app_state, authorized, create_chat, create_member, create_message, create_tenant, setup_db,
+    },
+};
+
+#[actix_web::test]
+async fn read_markers_belong_to_the_requesting_user() {
+    let db = setup_db().await;
+    let tenant = create_tenant(&db).await;
+    let manager = create_member(&db, tenant.company, "manager").await;
+    let app = api_app!(app_state(&db), chats::init_routes);
+    let unread = |token: &str| {
+        authorized(
+            test::TestRequest::get().uri(&format!("/api/chats/{}/unread", tenant.company)),
+            token,
+        )
+        .to_request()
+    };
+
+    let read = authorized(
+        test::TestRequest::post()
+            .uri(&format!(
+                "/api/chats/{}/{}/read",
+                tenant.company, tenant.chat
+            ))
+            .set_json(json!({})),
+        &manager,
+    )
+    .to_request();
+    let response = test::call_service(&app, read).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let marker: Value = test::read_body_json(response).await;
+    assert_eq!(marker["unreadCount"], 0);
+    assert_ne!(marker["userId"], tenant.admin.to_string());
+
+    let summary: Value = test::call_and_read_body_json(&app, unread(&manager)).await;
+    assert_eq!(summary["totalUnread"], 0);
+
+    // The admin's own marker is untouched, whatever user it asks for.
+    let summary: Value = test::call_and_read_body_json(&app, unread(&tenant.token)).await;
+    assert_eq!(summary["totalUnread"], 1);
+    let foreign = authorized(
+        test::TestRequest::get().uri(&format!(
+            "/api/chats/{}/unread?userId={}",
+            tenant.company,
+            marker["userId"].as_str().unwrap()
+        )),
+        &tenant.token,
+    )
+    .to_request();
+    let summary: Value = test::call_and_read_body_json(&app, foreign).await;
+    assert_eq!(summary["totalUnread"], 1);
+}
+
+#[actix_web::test]
+async fn unread_counts_follow_the_marker_and_never_move_back() {
+    let db = setup_db().await;
+    let tenant = create_tenant(&db).await;
+    let chat = create_chat(&db, tenant.channel, None).await;
+    let now = Utc::now();
+    let mut inbound = Vec::new();
+    for minutes in [3, 2, 1] {
+        let at = now - Duration::minutes(minutes);
+        inbound.push(create_message(&db, chat, "question", true, at).await);
+    }
+    // Own replies are never unread.
+    create_message(&db, chat, "answer", false, now - Duration::seconds(30)).await;
+    let app = api_app!(app_state(&db), chats::init_routes);
+    let summary = || {
+        authorized(
+            test::TestRequest::get().uri(&format!("/api/chats/{}/unread", tenant.company)),
+            &tenant.token,
+        )
+        .to_request()
+    };
+    let read = |body: Value| {
+        authorized(
+            test::TestRequest::post()
+                .uri(&format!("/api/chats/{}/{}/read", tenant.company, chat))
+                .set_json(body),
+            &tenant.token,
+        )
+        .to_request()
+    };
+
+    let unread: Value = test::call_and_read_body_json(&app, summary()).await;
+    assert_eq!(unread["totalUnread"], 4);
+    assert_eq!(unread["chatsWithUnread"], 2);
+    assert_eq!(unread["data"][0]["chatId"], chat.to_string());
+    assert_eq!(unread["data"][0]["unreadCount"], 3);
+    assert_eq!(unread["data"][1]["chatId"], tenant.chat.to_string());
+    assert_eq!(unread["data"][1]["unreadCount"], 1);
+
+    let marker: Value =
+        test::call_and_read_body_json(&app, read(json!({ "messageId": inbound[1].to_string() })))
+            .await;
+    assert_eq!(marker["lastReadMessageId"], inbound[1].to_string());
+    assert_eq!(marker["unreadCount"], 1);
+
+    // An older message does not move the marker back.
+    let marker: Value =
+        test::call_and_read_body_json(&app, read(json!({ "messageId": inbound[0].to_string() })))
+            .await;
+    assert_eq!(marker["lastReadMessageId"], inbound[1].to_string());
+    assert_eq!(marker["unreadCount"], 1);
+
+    let unread: Value = test::call_and_read_body_json(&app, summary()).await;
+    assert_eq!(unread["totalUnread"], 2);
+    assert_eq!(unread["chatsWithUnread"], 2);
+
+    // Without a message the chat is read up to its latest one.
+    let marker: Value = test::call_and_read_body_json(&app, read(json!({}))).await;
+    assert_eq!(marker["unreadCount"], 0);
+
+    let unread: Value = test::call_and_read_body_json(&app, summary()).await;
+    assert_eq!(unread["totalUnread"], 1);
+    assert_eq!(unread["chatsWithUnread"], 1);
+    assert_eq!(unread["data"][0]["chatId"], tenant.chat.to_string());
+}
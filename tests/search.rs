@@ -0,0 +1,82 @@
+//! Message search on the `LIKE` fallback of SQLite.
+
+use actix_web::test;
+use chrono::{Duration, Utc};
+use serde_json::Value;
+
+use wazzup::{
+    api::chats,
+    api_app,
+    services::search,
+    testing::{app_state, authorized, create_chat, create_message, create_tenant, setup_db},
+};
+
+#[actix_web::test]
+async fn messages_of_the_company_are_found_by_every_word() {
+    let db = setup_db().await;
+    let tenant = create_tenant(&db).await;
+    let other = create_tenant(&db).await;
+    let chat = create_chat(&db, tenant.channel, None).await;
+    let now = Utc::now();
+    let both = create_message(
+        &db,
+        chat,
+        "Where is my <b>Order</b> & its delivery?",
+        true,
+        now - Duration::minutes(2),
+    )
+    .await;
+    let one = create_message(&db, tenant.chat, "ORDER cancelled", false, now).await;
+    create_message(&db, other.chat, "order delivery", true, now).await;
+
+    // The fixture greetings of both tenants and the three messages above.
+    assert_eq!(search::backfill(&db).await.unwrap(), 5);
+    assert_eq!(search::backfill(&db).await.unwrap(), 0);
+
+    let app = api_app!(app_state(&db), chats::init_routes);
+    let search = |q: &str| {
+        authorized(
+            test::TestRequest::get().uri(&format!("/api/chats/{}/search?q={}", tenant.company, q)),
+            &tenant.token,
+        )
+        .to_request()
+    };
+
+    let found: Value = test::call_and_read_body_json(&app, search("order%20deliv")).await;
+    let hits = found["messages"].as_array().unwrap();
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0]["messageId"], both.to_string());
+    assert_eq!(hits[0]["chatId"], chat.to_string());
+    assert_eq!(
+        hits[0]["snippet"],
+        "Where is my &lt;b&gt;<mark>Order</mark>&lt;/b&gt; &amp; its <mark>deliv</mark>ery?"
+    );
+
+    // Newest first when the fallback cannot rank.
+    let found: Value = test::call_and_read_body_json(&app, search("order")).await;
+    let ids: Vec<&str> = found["messages"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|hit| hit["messageId"].as_str().unwrap())
+        .collect();
+    assert_eq!(ids, [one.to_string(), both.to_string()]);
+
+    let found: Value = test::call_and_read_body_json(&app, search("refund")).await;
+    assert_eq!(found["messages"], Value::Array(Vec::new()));
+}
+
+#[actix_web::test]
+async fn snippets_are_cut_around_the_first_match() {
+    let text = format!("{}needle{}", "a".repeat(100), "b".repeat(200));
+    let snippet = search::highlight_snippet(&text, &["needle".to_string()]);
+
+    assert_eq!(
+        snippet,
+        format!("…{}<mark>needle</mark>{}…", "a".repeat(60), "b".repeat(114))
+    );
+    assert_eq!(
+        search::highlight_snippet("x < y", &["z".to_string()]),
+        "x &lt; y"
+    );
+}
@@ -0,0 +1,23 @@
+//! The service runs on an in-memory SQLite database.
+
+use sea_orm::EntityTrait;
+
+use wazzup::database::{self, migrations, models::companies};
+
+use wazzup::testing::create_company;
+
+#[actix_web::test]
+async fn in_memory_database_outlives_single_queries() {
+    let db = database::connect_from_url("sqlite::memory:").await.unwrap();
+    migrations::run(&db).await.unwrap();
+    database::ping(&db).await.unwrap();
+
+    // Every query must see the same database, not a fresh one per connection.
+    let company = create_company(&db).await;
+    let stored = companies::Entity::find_by_id(company.as_bytes().to_vec())
+        .one(&db)
+        .await
+        .unwrap();
+    assert!(stored.is_some());
+    assert!(migrations::pending(&db).await.unwrap().is_empty());
+}
@@ -0,0 +1,121 @@
+//! HTTPS listeners and certificate reloads.
+//!
+//! `fixtures/tls` holds two self-signed P-256 certificates for `localhost`:
+//! `openssl req -x509 -newkey ec -pkeyopt ec_paramgen_curve:prime256v1 -nodes
+//! -days 36500 -subj /CN=localhost -addext subjectAltName=DNS:localhost,IP:127.0.0.1`.
+
+use std::{env, fs, net::TcpListener, path::PathBuf};
+
+use actix_web::{App, HttpResponse, HttpServer, web};
+use rustls::pki_types::{CertificateDer, pem::PemObject};
+use uuid::Uuid;
+
+use wazzup::{config::TlsFiles, tls::Certificate};
+
+const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/tls");
+
+/// Certificate files in a scratch directory, so tests can replace them.
+struct Scratch(PathBuf);
+
+impl Scratch {
+    fn with(name: &str) -> Self {
+        let dir = env::temp_dir().join(format!("wazzup-tls-{}", Uuid::new_v4()));
+        fs::create_dir(&dir).unwrap();
+        let scratch = Self(dir);
+        scratch.install(name);
+        scratch
+    }
+
+    fn install(&self, name: &str) {
+        for extension in ["crt", "key"] {
+            fs::copy(
+                format!("{}/{}.{}", FIXTURES, name, extension),
+                self.0.join(format!("server.{}", extension)),
+            )
+            .unwrap();
+        }
+    }
+
+    fn files(&self) -> TlsFiles {
+        TlsFiles {
+            cert: self.0.join("server.crt"),
+            key: self.0.join("server.key"),
+        }
+    }
+}
+
+impl Drop for Scratch {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+fn fixture(name: &str) -> Vec<u8> {
+    CertificateDer::from_pem_file(format!("{}/{}.crt", FIXTURES, name))
+        .unwrap()
+        .to_vec()
+}
+
+async fn served_certificate(url: &str) -> Vec<u8> {
+    let client = reqwest::Client::builder()
+        .danger_accept_invalid_certs(true)
+        .tls_info(true)
+        .pool_max_idle_per_host(0)
+        .build()
+        .unwrap();
+    let response = client.get(url).send().await.unwrap();
+    assert!(response.status().is_success());
+    response
+        .extensions()
+        .get::<reqwest::tls::TlsInfo>()
+        .and_then(|info| info.peer_certificate())
+        .unwrap()
+        .to_vec()
+}
+
+#[actix_web::test]
+async fn listeners_serve_https_and_pick_up_reloaded_certificates() {
+    let scratch = Scratch::with("first");
+    let certificate = Certificate::load(scratch.files()).unwrap();
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("https://{}/ping", listener.local_addr().unwrap());
+    let server = HttpServer::new(|| {
+        App::new().route(
+            "/ping",
+            web::get().to(|| async { HttpResponse::Ok().finish() }),
+        )
+    })
+    .workers(1)
+    .disable_signals()
+    .listen_rustls_0_23(listener, certificate.server_config().unwrap())
+    .unwrap()
+    .run();
+    actix_web::rt::spawn(server);
+
+    assert_eq!(served_certificate(&url).await, fixture("first"));
+
+    scratch.install("second");
+    certificate.reload().unwrap();
+    assert_eq!(served_certificate(&url).await, fixture("second"));
+}
+
+#[test]
+fn broken_files_are_rejected_and_keep_the_current_certificate() {
+    let scratch = Scratch::with("first");
+    let certificate = Certificate::load(scratch.files()).unwrap();
+
+    // Key of another certificate.
+    fs::copy(
+        format!("{}/second.key", FIXTURES),
+        scratch.0.join("server.key"),
+    )
+    .unwrap();
+    assert!(certificate.reload().is_err());
+    assert!(Certificate::load(scratch.files()).is_err());
+
+    fs::write(scratch.0.join("server.crt"), "not a certificate").unwrap();
+    let err = certificate.reload().unwrap_err();
+    assert!(err.to_string().contains("server.crt"));
+
+    assert_eq!(certificate.chain()[0].to_vec(), fixture("first"));
+}
@@ -0,0 +1,292 @@
+//! Bearer tokens: issuing, listing, revoking and their lifetime.
+
+use actix_web::{http::StatusCode, test};
+use chrono::{Duration, Utc};
+use sea_orm::{ActiveModelTrait, DatabaseConnection, EntityTrait, IntoActiveModel, Set};
+use serde_json::{Value, json};
+use uuid::Uuid;
+
+use wazzup::{
+    api::{contacts, tokens},
+    api_app,
+    database::models::{companies, tokens as token_models, users},
+    testing::{app_state, authorized, create_member, create_tenant, setup_db},
+};
+
+const PASSWORD: &str = "correct horse battery staple";
+
+async fn set_password(db: &DatabaseConnection, user: Uuid) {
+    let mut record = users::Entity::find_by_id(user.as_bytes().to_vec())
+        .one(db)
+        .await
+        .unwrap()
+        .unwrap()
+        .into_active_model();
+    record.password = Set(bcrypt::hash(PASSWORD, 4).unwrap());
+    record.update(db).await.unwrap();
+}
+
+async fn token_record(db: &DatabaseConnection, id: &str) -> token_models::Model {
+    token_models::Entity::find_by_id(Uuid::parse_str(id).unwrap().as_bytes().to_vec())
+        .one(db)
+        .await
+        .unwrap()
+        .unwrap()
+}
+
+async fn update_token(
+    db: &DatabaseConnection,
+    id: &str,
+    change: impl FnOnce(&mut token_models::ActiveModel),
+) {
+    let mut record = token_record(db, id).await.into_active_model();
+    change(&mut record);
+    record.update(db).await.unwrap();
+}
+
+#[actix_web::test]
+async fn tokens_are_issued_listed_and_revoked() {
+    let db = setup_db().await;
+    let tenant = create_tenant(&db).await;
+    set_password(&db, tenant.admin).await;
+    let app = api_app!(app_state(&db), tokens::init_routes, contacts::init_routes);
+    let contacts = |token: &str| {
+        authorized(
+            test::TestRequest::get().uri(&format!("/api/contacts/{}", tenant.company)),
+            token,
+        )
+        .to_request()
+    };
+
+    // A wrong password and an unknown login are rejected alike.
+    for login in [
+        format!("{}@example.com", tenant.admin),
+        "nobody@example.com".to_string(),
+    ] {
+        let rejected = test::TestRequest::post()
+            .uri("/api/auth/token")
+            .set_json(json!({
+                "login": login,
+                "password": "wrong",
+                "companyId": tenant.company.to_string(),
+            }))
+            .to_request();
+        let response = test::call_service(&app, rejected).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let body: Value = test::read_body_json(response).await;
+        assert_eq!(body["message"], "Unauthorized: Invalid login or password");
+    }
+
+    let login = test::TestRequest::post()
+        .uri("/api/auth/token")
+        .set_json(json!({
+            "login": format!("{}@example.com", tenant.admin),
+            "password": PASSWORD,
+            "companyId": tenant.company.to_string(),
+            "name": "CRM",
+            "expiresInDays": 7,
+        }))
+        .to_request();
+    let response = test::call_service(&app, login).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let issued: Value = test::read_body_json(response).await;
+    let token = issued["token"].as_str().unwrap().to_string();
+    assert!(token.starts_with("wzt_"));
+    assert_eq!(issued["name"], "CRM");
+    assert_eq!(issued["companyId"], tenant.company.to_string());
+    assert_eq!(
+        test::call_service(&app, contacts(&token)).await.status(),
+        StatusCode::OK
+    );
+
+    let create = authorized(
+        test::TestRequest::post()
+            .uri(&format!("/api/tokens/{}", tenant.company))
+            .set_json(json!({ "name": "script" })),
+        &token,
+    )
+    .to_request();
+    let response = test::call_service(&app, create).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let created: Value = test::read_body_json(response).await;
+
+    let list = authorized(
+        test::TestRequest::get().uri(&format!("/api/tokens/{}", tenant.company)),
+        &token,
+    )
+    .to_request();
+    let listed: Value = test::call_and_read_body_json(&app, list).await;
+    let listed = listed["data"].as_array().unwrap();
+    // The fixture token of the admin, the login token and the created one.
+    assert_eq!(listed.len(), 3);
+    assert!(listed.iter().all(|view| view.get("token").is_none()));
+    let current: Vec<&Value> = listed
+        .iter()
+        .filter(|view| view["current"] == true)
+        .collect();
+    assert_eq!(current.len(), 1);
+    assert_eq!(current[0]["id"], issued["id"]);
+
+    let revoke = authorized(
+        test::TestRequest::delete().uri(&format!(
+            "/api/tokens/{}/{}",
+            tenant.company,
+            created["id"].as_str().unwrap()
+        )),
+        &token,
+    )
+    .to_request();
+    assert_eq!(
+        test::call_service(&app, revoke).await.status(),
+        StatusCode::NO_CONTENT
+    );
+    let revoked = test::try_call_service(&app, contacts(created["token"].as_str().unwrap())).await;
+    assert_eq!(
+        revoked.unwrap_err().as_response_error().status_code(),
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        test::call_service(&app, contacts(&token)).await.status(),
+        StatusCode::OK
+    );
+}
+
+#[actix_web::test]
+async fn expired_tokens_are_rejected() {
+    let db = setup_db().await;
+    let tenant = create_tenant(&db).await;
+    let app = api_app!(app_state(&db), tokens::init_routes);
+    let list = || {
+        authorized(
+            test::TestRequest::get().uri(&format!("/api/tokens/{}", tenant.company)),
+            &tenant.token,
+        )
+        .to_request()
+    };
+    let listed: Value = test::call_and_read_body_json(&app, list()).await;
+    let id = listed["data"][0]["id"].as_str().unwrap().to_string();
+
+    update_token(&db, &id, |record| {
+        record.expires_at = Set(Utc::now() - Duration::seconds(1))
+    })
+    .await;
+
+    let err = test::try_call_service(&app, list()).await.unwrap_err();
+    assert_eq!(
+        err.as_response_error().status_code(),
+        StatusCode::UNAUTHORIZED
+    );
+}
+
+#[actix_web::test]
+async fn tokens_of_deactivated_companies_are_refused() {
+    let db = setup_db().await;
+    let tenant = create_tenant(&db).await;
+    let app = api_app!(app_state(&db), tokens::init_routes);
+    let list = || {
+        authorized(
+            test::TestRequest::get().uri(&format!("/api/tokens/{}", tenant.company)),
+            &tenant.token,
+        )
+        .to_request()
+    };
+    assert_eq!(
+        test::call_service(&app, list()).await.status(),
+        StatusCode::OK
+    );
+
+    let mut company = companies::Entity::find_by_id(tenant.company.as_bytes().to_vec())
+        .one(&db)
+        .await
+        .unwrap()
+        .unwrap()
+        .into_active_model();
+    company.is_active = Set(Some(0));
+    company.update(&db).await.unwrap();
+
+    let err = test::try_call_service(&app, list()).await.unwrap_err();
+    assert_eq!(err.as_response_error().status_code(), StatusCode::FORBIDDEN);
+}
+
+#[actix_web::test]
+async fn last_use_is_only_recorded_once_a_minute() {
+    let db = setup_db().await;
+    let tenant = create_tenant(&db).await;
+    let app = api_app!(app_state(&db), tokens::init_routes);
+    let list = || {
+        authorized(
+            test::TestRequest::get().uri(&format!("/api/tokens/{}", tenant.company)),
+            &tenant.token,
+        )
+        .to_request()
+    };
+    let listed: Value = test::call_and_read_body_json(&app, list()).await;
+    let id = listed["data"][0]["id"].as_str().unwrap().to_string();
+
+    let recent = Utc::now() - Duration::seconds(30);
+    update_token(&db, &id, |record| record.last_used_at = Set(recent)).await;
+    test::call_service(&app, list()).await;
+    assert_eq!(token_record(&db, &id).await.last_used_at, recent);
+
+    let stale = Utc::now() - Duration::minutes(5);
+    update_token(&db, &id, |record| record.last_used_at = Set(stale)).await;
+    test::call_service(&app, list()).await;
+    assert!(token_record(&db, &id).await.last_used_at > Utc::now() - Duration::seconds(30));
+}
+
+#[actix_web::test]
+async fn members_only_manage_their_own_tokens() {
+    let db = setup_db().await;
+    let tenant = create_tenant(&db).await;
+    let employee = create_member(&db, tenant.company, "employee").await;
+    let without_role = create_member(&db, tenant.company, "guest").await;
+    let app = api_app!(app_state(&db), tokens::init_routes);
+    let list = |token: &str| {
+        authorized(
+            test::TestRequest::get().uri(&format!("/api/tokens/{}", tenant.company)),
+            token,
+        )
+        .to_request()
+    };
+    let revoke = |id: &Value, token: &str| {
+        authorized(
+            test::TestRequest::delete().uri(&format!(
+                "/api/tokens/{}/{}",
+                tenant.company,
+                id.as_str().unwrap()
+            )),
+            token,
+        )
+        .to_request()
+    };
+
+    let own: Value = test::call_and_read_body_json(&app, list(&employee)).await;
+    let admin: Value = test::call_and_read_body_json(&app, list(&tenant.token)).await;
+    assert_eq!(own["data"].as_array().unwrap().len(), 1);
+    assert_eq!(admin["data"].as_array().unwrap().len(), 1);
+    let employee_token = &own["data"][0]["id"];
+    let admin_token = &admin["data"][0]["id"];
+
+    // Neither an employee nor the company admin can revoke someone else's token.
+    assert_eq!(
+        test::call_service(&app, revoke(admin_token, &employee))
+            .await
+            .status(),
+        StatusCode::NOT_FOUND
+    );
+    assert_eq!(
+        test::call_service(&app, revoke(employee_token, &tenant.token))
+            .await
+            .status(),
+        StatusCode::NOT_FOUND
+    );
+    assert_eq!(
+        test::call_service(&app, list(&employee)).await.status(),
+        StatusCode::OK
+    );
+
+    assert_eq!(
+        test::call_service(&app, list(&without_role)).await.status(),
+        StatusCode::FORBIDDEN
+    );
+}
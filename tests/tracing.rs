@@ -0,0 +1,153 @@
+//! Request ids in error bodies and on calls to Wazzup.
+
+use actix_web::{
+    body::to_bytes,
+    http::{StatusCode, header::HeaderMap},
+    test,
+};
+use serde_json::{Value, json};
+use uuid::Uuid;
+
+use wazzup::api::{chats, contacts};
+
+use wazzup::{
+    api_app,
+    testing::{
+        FakeWazzup, TEST_API_KEY, app_state_with, authorized, create_client, create_tenant,
+        setup_db,
+    },
+};
+
+fn request_id(headers: &HeaderMap) -> String {
+    headers
+        .get("x-request-id")
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .to_string()
+}
+
+#[actix_web::test]
+async fn error_bodies_carry_the_request_id() {
+    let db = setup_db().await;
+    let tenant = create_tenant(&db).await;
+    let wazzup = FakeWazzup::start().await;
+    let app = api_app!(
+        app_state_with(&db, &wazzup),
+        chats::init_routes,
+        contacts::init_routes
+    );
+
+    // Rejected by the auth middleware, before any handler runs.
+    let request = test::TestRequest::get()
+        .uri(&format!("/api/chats/{}/previews", tenant.company))
+        .to_request();
+    let response = test::try_call_service(&app, request)
+        .await
+        .unwrap_err()
+        .error_response();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let id = request_id(response.headers());
+    let body = to_bytes(response.into_body()).await.unwrap();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["trace_id"], id);
+
+    // Returned by a handler; an id passed by the proxy is kept.
+    let request = authorized(
+        test::TestRequest::get()
+            .uri(&format!(
+                "/api/contacts/{}/{}",
+                tenant.company,
+                Uuid::new_v4()
+            ))
+            .insert_header(("X-Request-Id", "edge-42")),
+        &tenant.token,
+    )
+    .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_eq!(request_id(response.headers()), "edge-42");
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(body["trace_id"], "edge-42");
+}
+
+#[actix_web::test]
+async fn malformed_bodies_and_queries_get_the_common_error_body() {
+    let db = setup_db().await;
+    let tenant = create_tenant(&db).await;
+    let wazzup = FakeWazzup::start().await;
+    let app = api_app!(
+        app_state_with(&db, &wazzup),
+        chats::init_routes,
+        contacts::init_routes
+    );
+    let client = create_client(&db, tenant.company, tenant.admin, None).await;
+
+    for (request, status) in [
+        (
+            test::TestRequest::put()
+                .uri(&format!("/api/contacts/{}/{}", tenant.company, client))
+                .insert_header(("Content-Type", "application/json"))
+                .set_payload("{\"fullName\":"),
+            StatusCode::BAD_REQUEST,
+        ),
+        (
+            test::TestRequest::put()
+                .uri(&format!("/api/contacts/{}/{}", tenant.company, client))
+                .insert_header(("Content-Type", "text/plain"))
+                .set_payload("fullName"),
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+        ),
+        (
+            test::TestRequest::get().uri(&format!(
+                "/api/chats/{}/{}/messages?count=many",
+                tenant.company, tenant.chat
+            )),
+            StatusCode::BAD_REQUEST,
+        ),
+    ] {
+        let response =
+            test::call_service(&app, authorized(request, &tenant.token).to_request()).await;
+        assert_eq!(response.status(), status);
+        let id = request_id(response.headers());
+        let body: Value = test::read_body_json(response).await;
+        assert_eq!(body["trace_id"], id);
+    }
+}
+
+#[actix_web::test]
+async fn wazzup_calls_carry_the_request_id() {
+    let db = setup_db().await;
+    let tenant = create_tenant(&db).await;
+    let wazzup = FakeWazzup::start().await;
+    let app = api_app!(app_state_with(&db, &wazzup), contacts::init_routes);
+    let client = create_client(&db, tenant.company, tenant.admin, None).await;
+
+    let request = authorized(
+        test::TestRequest::put()
+            .uri(&format!("/api/contacts/{}/{}", tenant.company, client))
+            .set_json(json!({
+                "fullName": "Иван Петров",
+                "email": "ivan@example.com",
+                "phone": "+7 999 000-11-22",
+            })),
+        &tenant.token,
+    )
+    .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let pushed = wazzup.requests_to("PUT", &format!("/v3/contacts/{}", client));
+    assert_eq!(pushed.len(), 1);
+    assert_eq!(pushed[0].request_id, Some(request_id(response.headers())));
+
+    // Calls made outside a request (background jobs) carry no id.
+    let contact = Uuid::new_v4().to_string();
+    app_state_with(&db, &wazzup)
+        .wazzup_api
+        .delete_contact(TEST_API_KEY, &contact)
+        .await
+        .unwrap();
+    let deleted = wazzup.requests_to("DELETE", &format!("/v3/contacts/{}", contact));
+    assert_eq!(deleted[0].request_id, None);
+}
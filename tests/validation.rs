@@ -0,0 +1,274 @@
+//! Invalid request bodies are rejected with every field error listed.
+
+use actix_web::{http::StatusCode, test};
+use serde_json::{Value, json};
+
+use wazzup::api::{chats, contacts, outgoing_webhooks, tokens};
+
+use wazzup::{
+    api_app,
+    testing::{FakeWazzup, app_state_with, authorized, create_client, create_tenant, setup_db},
+};
+
+/// `(field, code)` pairs of a `VALIDATION_ERROR` body.
+fn field_errors(body: &Value) -> Vec<(String, String)> {
+    assert_eq!(body["code"], "VALIDATION_ERROR");
+    body["details"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|error| {
+            assert!(error["message"].as_str().is_some_and(|m| !m.is_empty()));
+            (
+                error["field"].as_str().unwrap().to_string(),
+                error["code"].as_str().unwrap().to_string(),
+            )
+        })
+        .collect()
+}
+
+fn pairs(expected: &[(&str, &str)]) -> Vec<(String, String)> {
+    expected
+        .iter()
+        .map(|(field, code)| (field.to_string(), code.to_string()))
+        .collect()
+}
+
+#[actix_web::test]
+async fn contact_updates_report_every_invalid_field() {
+    let db = setup_db().await;
+    let tenant = create_tenant(&db).await;
+    let wazzup = FakeWazzup::start().await;
+    let app = api_app!(app_state_with(&db, &wazzup), contacts::init_routes);
+    let client = create_client(&db, tenant.company, tenant.admin, None).await;
+
+    let request = authorized(
+        test::TestRequest::put()
+            .uri(&format!("/api/contacts/{}/{}", tenant.company, client))
+            .set_json(json!({
+                "fullName": "x".repeat(201),
+                "email": "not-an-email",
+                "phone": "12",
+            })),
+        &tenant.token,
+    )
+    .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body: Value = test::read_body_json(response).await;
+
+    assert_eq!(
+        field_errors(&body),
+        pairs(&[
+            ("fullName", "too_long"),
+            ("email", "invalid_email"),
+            ("phone", "invalid_phone"),
+        ])
+    );
+    assert!(wazzup.requests().is_empty());
+}
+
+#[actix_web::test]
+async fn message_parts_are_validated_by_position() {
+    let db = setup_db().await;
+    let tenant = create_tenant(&db).await;
+    let wazzup = FakeWazzup::start().await;
+    let app = api_app!(app_state_with(&db, &wazzup), chats::init_routes);
+    let uri = format!("/api/chats/{}/{}/send", tenant.company, tenant.chat);
+
+    let send = |content: Value| {
+        authorized(
+            test::TestRequest::post()
+                .uri(&uri)
+                .set_json(json!({ "message": { "content": content } })),
+            &tenant.token,
+        )
+        .to_request()
+    };
+
+    let body: Value = test::call_and_read_body_json(
+        &app,
+        send(json!([
+            { "type": "image", "content": "https://example.com/a.png" },
+            { "type": "video", "content": "https://example.com/a.mp4" },
+            { "type": "image", "content": "https://example.com/b.png" },
+        ])),
+    )
+    .await;
+    assert_eq!(
+        field_errors(&body),
+        pairs(&[
+            ("message.content[1].type", "unsupported"),
+            ("message.content[2]", "too_many"),
+        ])
+    );
+
+    let body: Value = test::call_and_read_body_json(&app, send(json!([]))).await;
+    assert_eq!(
+        field_errors(&body),
+        pairs(&[("message.content", "required")])
+    );
+    assert!(wazzup.requests().is_empty());
+}
+
+#[actix_web::test]
+async fn fields_that_do_not_deserialize_are_reported_by_path() {
+    let db = setup_db().await;
+    let tenant = create_tenant(&db).await;
+    let wazzup = FakeWazzup::start().await;
+    let app = api_app!(
+        app_state_with(&db, &wazzup),
+        chats::init_routes,
+        contacts::init_routes
+    );
+    let client = create_client(&db, tenant.company, tenant.admin, None).await;
+    let send = format!("/api/chats/{}/{}/send", tenant.company, tenant.chat);
+    let contact = format!("/api/contacts/{}/{}", tenant.company, client);
+
+    for (request, expected) in [
+        (
+            test::TestRequest::post().uri(&send).set_json(json!({
+                "message": { "content": [{ "type": "text", "content": 5 }] }
+            })),
+            ("message.content[0].content", "invalid_value"),
+        ),
+        (
+            test::TestRequest::post().uri(&send).set_json(json!({
+                "message": { "content": [{ "type": "text" }] }
+            })),
+            ("message.content[0].content", "required"),
+        ),
+        (
+            test::TestRequest::put()
+                .uri(&contact)
+                .set_json(json!({ "fullName": "Иван" })),
+            ("email", "required"),
+        ),
+        (
+            test::TestRequest::get().uri(&format!(
+                "/api/chats/{}/{}/messages?count=many",
+                tenant.company, tenant.chat
+            )),
+            ("count", "invalid_value"),
+        ),
+    ] {
+        let response =
+            test::call_service(&app, authorized(request, &tenant.token).to_request()).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body: Value = test::read_body_json(response).await;
+        assert_eq!(field_errors(&body), pairs(&[expected]));
+    }
+
+    // A body that is not JSON at all has no field to point at.
+    let request = authorized(
+        test::TestRequest::put()
+            .uri(&contact)
+            .insert_header(("Content-Type", "application/json"))
+            .set_payload("{"),
+        &tenant.token,
+    )
+    .to_request();
+    let body: Value = test::call_and_read_body_json(&app, request).await;
+    assert_eq!(body["code"], "INVALID_INPUT");
+    assert!(wazzup.requests().is_empty());
+}
+
+#[actix_web::test]
+async fn outgoing_webhooks_report_url_and_event_type_errors() {
+    let db = setup_db().await;
+    let tenant = create_tenant(&db).await;
+    let wazzup = FakeWazzup::start().await;
+    let app = api_app!(app_state_with(&db, &wazzup), outgoing_webhooks::init_routes);
+
+    let request = authorized(
+        test::TestRequest::post()
+            .uri(&format!("/api/outgoing-webhooks/{}", tenant.company))
+            .set_json(json!({
+                "url": "ftp://example.com/hook",
+                "eventTypes": ["message.created", "no.such.event"],
+            })),
+        &tenant.token,
+    )
+    .to_request();
+    let body: Value = test::call_and_read_body_json(&app, request).await;
+
+    assert_eq!(
+        field_errors(&body),
+        pairs(&[("url", "invalid_url"), ("eventTypes[1]", "unsupported")])
+    );
+}
+
+#[actix_web::test]
+async fn outgoing_webhooks_refuse_internal_targets() {
+    let db = setup_db().await;
+    let tenant = create_tenant(&db).await;
+    let wazzup = FakeWazzup::start().await;
+    let app = api_app!(app_state_with(&db, &wazzup), outgoing_webhooks::init_routes);
+
+    for url in [
+        "http://127.0.0.1:8080/hook",
+        "http://169.254.169.254/latest/meta-data",
+        "https://10.0.0.5/hook",
+        "http://[::1]/hook",
+        "http://[::ffff:192.168.1.1]/hook",
+        "http://localhost/hook",
+    ] {
+        let request = authorized(
+            test::TestRequest::post()
+                .uri(&format!("/api/outgoing-webhooks/{}", tenant.company))
+                .set_json(json!({ "url": url })),
+            &tenant.token,
+        )
+        .to_request();
+        let response = test::call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", url);
+        let body: Value = test::read_body_json(response).await;
+        assert_eq!(
+            field_errors(&body),
+            pairs(&[("url", "forbidden_host")]),
+            "{}",
+            url
+        );
+    }
+}
+
+#[actix_web::test]
+async fn token_requests_report_company_and_lifetime_errors() {
+    let db = setup_db().await;
+    let tenant = create_tenant(&db).await;
+    let wazzup = FakeWazzup::start().await;
+    let app = api_app!(app_state_with(&db, &wazzup), tokens::init_routes);
+
+    let login = test::TestRequest::post()
+        .uri("/api/auth/token")
+        .set_json(json!({
+            "login": "nobody@example.com",
+            "password": "secret",
+            "companyId": "not-a-uuid",
+            "name": "x".repeat(256),
+            "expiresInDays": 0,
+        }))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, login).await;
+    assert_eq!(
+        field_errors(&body),
+        pairs(&[
+            ("companyId", "invalid_uuid"),
+            ("name", "too_long"),
+            ("expiresInDays", "out_of_range"),
+        ])
+    );
+
+    let create = authorized(
+        test::TestRequest::post()
+            .uri(&format!("/api/tokens/{}", tenant.company))
+            .set_json(json!({ "expiresInDays": i64::MAX })),
+        &tenant.token,
+    )
+    .to_request();
+    let body: Value = test::call_and_read_body_json(&app, create).await;
+    assert_eq!(
+        field_errors(&body),
+        pairs(&[("expiresInDays", "out_of_range")])
+    );
+}